@@ -0,0 +1,35 @@
+import { error } from '@sveltejs/kit';
+import type { Paginated } from 'bindings/Paginated';
+
+/**
+ * Load every page of a paginated endpoint by following `next_cursor`
+ *
+ * `url` must not have a `cursor` param, `limit` is kept for every page
+ */
+export async function fetchAll<T>(
+  fetch: typeof globalThis.fetch,
+  url: string,
+  init?: RequestInit,
+): Promise<T[]> {
+  const items: T[] = [];
+  let cursor: string | null = null;
+
+  do {
+    const pageUrl = new URL(url);
+    if (cursor !== null) {
+      pageUrl.searchParams.set("cursor", cursor);
+    }
+
+    const res = await fetch(pageUrl, init);
+    if (res.status != 200) {
+      const resError = await res.json().catch(() => ({ error: res.statusText }));
+      throw error(res.status, resError);
+    }
+
+    const page: Paginated<T> = await res.json();
+    items.push(...page.items);
+    cursor = page.next_cursor;
+  } while (cursor !== null);
+
+  return items;
+}
@@ -2,6 +2,7 @@ import { error } from '@sveltejs/kit';
 import type { PageServerLoad } from './$types';
 import type { ComicResponseBrief } from 'bindings/ComicResponseBrief';
 import type { UserResponse } from 'bindings/UserResponse';
+import { fetchAll } from '$lib/helpers/pagination';
 
 export const load = (async ({ fetch, params }) => {
 
@@ -16,14 +17,9 @@ export const load = (async ({ fetch, params }) => {
   }
   const user: UserResponse = await userRes.json();
 
-  const comicsRes = await fetch(`http://localhost:6060/api/v1/users/comics/${user.id}`, {
+  const comics = await fetchAll<ComicResponseBrief>(fetch, `http://localhost:6060/api/v1/users/comics/${user.id}?limit=50`, {
     credentials: "include",
   });
-  if (comicsRes.status != 200) {
-    const resError = await comicsRes.json().catch(() => ({ error: comicsRes.statusText }));
-    throw error(comicsRes.status, resError);
-  }
-  const comics: ComicResponseBrief[] = await comicsRes.json();
 
 
   return {
@@ -2,6 +2,7 @@ import { error } from '@sveltejs/kit';
 import type { PageServerLoad } from './$types';
 import type { ComicResponse } from 'bindings/ComicResponse';
 import type { ComicCommentResponse } from 'bindings/ComicCommentResponse';
+import { fetchAll } from '$lib/helpers/pagination';
 
 export const load = (async ({ fetch, params }) => {
     const { comic_slug, username } = params;
@@ -15,14 +16,8 @@ export const load = (async ({ fetch, params }) => {
 
     const comic: ComicResponse = await comicRes.json();
 
-    const commentRes = await fetch(`http://localhost:6060/api/v1/comics/${comic.id}/comments`);
-
-    if (commentRes.status != 200) {
-        const resError = await commentRes.json().catch(() => ({ error: commentRes.statusText }));
-        throw error(commentRes.status, resError);
-    }
-
-    let comments: ComicCommentResponse[] = await commentRes.json();
+    // replies can be on a later page than their parent, so every page is loaded
+    const comments = await fetchAll<ComicCommentResponse>(fetch, `http://localhost:6060/api/v1/comics/${comic.id}/comments?limit=50`);
 
     let top_level_comments: ComicCommentResponse[] = comments.filter((comment) => {
         return comment.parent_comment === null;
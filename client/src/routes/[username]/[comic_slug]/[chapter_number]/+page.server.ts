@@ -2,6 +2,7 @@ import { error } from '@sveltejs/kit';
 import type { PageServerLoad } from './$types';
 import type { ChapterCommentResponse } from 'bindings/ChapterCommentResponse';
 import type { ChapterResponse } from 'bindings/ChapterResponse';
+import { fetchAll } from '$lib/helpers/pagination';
 
 export const load = (async ({ fetch, params }) => {
     const { comic_slug, username, chapter_number } = params;
@@ -15,14 +16,8 @@ export const load = (async ({ fetch, params }) => {
 
     const chapter: ChapterResponse = await chapterRes.json();
 
-    const commentRes = await fetch(`http://localhost:6060/api/v1/comics/chapters/${chapter.id}/comments`);
-
-    if (commentRes.status != 200) {
-        const resError = await commentRes.json().catch(() => ({ error: commentRes.statusText }));
-        throw error(commentRes.status, resError);
-    }
-
-    let comments: ChapterCommentResponse[] = await commentRes.json();
+    // replies can be on a later page than their parent, so every page is loaded
+    const comments = await fetchAll<ChapterCommentResponse>(fetch, `http://localhost:6060/api/v1/comics/chapters/${chapter.id}/comments?limit=50`);
 
     let top_level_comments: ChapterCommentResponse[] = comments.filter((comment) => {
         return comment.parent_comment === null;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER comics_storage_deletion ON comics;
+DROP TRIGGER profile_images_storage_deletion ON profile_images;
+DROP TRIGGER chapter_pages_storage_deletion ON chapter_pages;
+DROP FUNCTION enqueue_storage_poster_deletion();
+DROP FUNCTION enqueue_storage_path_deletion();
+DROP TABLE storage_deletions;
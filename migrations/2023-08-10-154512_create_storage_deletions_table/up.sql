@@ -0,0 +1,40 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS storage_deletions (
+    id BIGSERIAL PRIMARY KEY,
+    path TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- enqueue the old object whenever a row that owns a storage path is deleted
+-- (including through ON DELETE CASCADE) or its path is replaced
+CREATE OR REPLACE FUNCTION enqueue_storage_path_deletion() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' OR OLD.path IS DISTINCT FROM NEW.path THEN
+        INSERT INTO storage_deletions(path) VALUES (OLD.path);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION enqueue_storage_poster_deletion() RETURNS trigger AS $$
+BEGIN
+    IF OLD.poster_path IS NOT NULL
+        AND (TG_OP = 'DELETE' OR OLD.poster_path IS DISTINCT FROM NEW.poster_path) THEN
+        INSERT INTO storage_deletions(path) VALUES (OLD.poster_path);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER chapter_pages_storage_deletion
+    AFTER DELETE OR UPDATE OF path ON chapter_pages
+    FOR EACH ROW EXECUTE PROCEDURE enqueue_storage_path_deletion();
+
+CREATE TRIGGER profile_images_storage_deletion
+    AFTER DELETE OR UPDATE OF path ON profile_images
+    FOR EACH ROW EXECUTE PROCEDURE enqueue_storage_path_deletion();
+
+CREATE TRIGGER comics_storage_deletion
+    AFTER DELETE OR UPDATE OF poster_path ON comics
+    FOR EACH ROW EXECUTE PROCEDURE enqueue_storage_poster_deletion();
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS storage_uploads (
+    id UUID PRIMARY KEY,
+    staging_path TEXT UNIQUE NOT NULL,
+    path TEXT UNIQUE NOT NULL,
+    content_type TEXT NOT NULL,
+    -- set in the same transaction that inserts the rows referencing `staging_path`,
+    -- they are pointed at `path` once the object is promoted
+    committed BOOLEAN NOT NULL,
+    -- set while the object is being promoted or discarded, the upload can be claimed again
+    -- once it's passed
+    claimed_until TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    user_id UUID NOT NULL,
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
@@ -0,0 +1,34 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS chapter_page_uploads (
+    id UUID PRIMARY KEY,
+    number INTEGER NOT NULL,
+    description TEXT,
+    content_type TEXT NOT NULL,
+    content_length BIGINT NOT NULL,
+    comic_id UUID NOT NULL,
+    chapter_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    storage_upload_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(storage_upload_id)
+        REFERENCES storage_uploads(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
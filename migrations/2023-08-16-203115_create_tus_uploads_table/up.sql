@@ -0,0 +1,36 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS tus_uploads (
+    id UUID PRIMARY KEY,
+    upload_length BIGINT NOT NULL,
+    upload_offset BIGINT NOT NULL DEFAULT 0,
+    -- what gets created once the upload is complete
+    target TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    description TEXT,
+    comic_id UUID NOT NULL,
+    chapter_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    -- set while a request writes a chunk, the upload can be claimed again once it's passed
+    locked_until TIMESTAMPTZ,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CHECK (upload_offset <= upload_length)
+);
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS chapter_pages_path_idx;
+
+DROP TABLE chapter_entitlements;
+
+ALTER TABLE comic_chapters
+    DROP COLUMN price,
+    DROP COLUMN unlocks_at;
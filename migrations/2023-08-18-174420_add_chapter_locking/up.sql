@@ -0,0 +1,26 @@
+-- Your SQL goes here
+ALTER TABLE comic_chapters
+    ADD COLUMN price INTEGER NOT NULL DEFAULT 0 CHECK (price >= 0),
+    -- the chapter is free for everyone after this date
+    ADD COLUMN unlocks_at TIMESTAMPTZ;
+
+CREATE TABLE IF NOT EXISTS chapter_entitlements (
+    user_id UUID NOT NULL,
+    chapter_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY (user_id, chapter_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+-- used by get_image to find the chapter of a page
+CREATE INDEX IF NOT EXISTS chapter_pages_path_idx ON chapter_pages (path);
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE provider_refunds;
+
+DROP TABLE payout_requests;
+
+DROP TABLE ledger_entries;
+
+DROP TABLE ledger_transactions;
+
+DROP FUNCTION IF EXISTS check_ledger_transaction_balanced;
+
+DROP FUNCTION IF EXISTS forbid_ledger_changes;
+
+DROP TABLE wallets;
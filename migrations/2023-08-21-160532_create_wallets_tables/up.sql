@@ -0,0 +1,145 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS wallets (
+    id UUID PRIMARY KEY,
+    -- system wallets don't belong to a user
+    user_id UUID UNIQUE,
+    kind TEXT NOT NULL,
+    balance BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    -- ledger history has to be kept, users with a wallet can't be deleted
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE RESTRICT
+        ON UPDATE CASCADE,
+
+    CHECK ((kind = 'user') = (user_id IS NOT NULL)),
+
+    -- the external wallet stands for the money outside of the platform,
+    -- it's the only one that can go negative
+    CONSTRAINT wallets_balance_check CHECK (kind = 'external' OR balance >= 0)
+);
+
+INSERT INTO wallets (id, user_id, kind, balance) VALUES
+    ('00000000-0000-0000-0000-000000000001', NULL, 'external', 0),
+    ('00000000-0000-0000-0000-000000000002', NULL, 'pending_payouts', 0)
+ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS ledger_transactions (
+    id UUID PRIMARY KEY,
+    kind TEXT NOT NULL,
+    -- prefixed with the user id, retrying a request with the same key returns the same transaction
+    idempotency_key TEXT UNIQUE NOT NULL,
+    -- user who made the transaction
+    user_id UUID NOT NULL,
+    -- not a foreign key, the record is kept when the chapter is deleted
+    chapter_id UUID,
+    -- set on refunds, a transaction can only be refunded once
+    reverses_id UUID UNIQUE,
+    -- reference of the charge, refund or payout at the payment provider
+    provider_reference TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE RESTRICT
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(reverses_id)
+        REFERENCES ledger_transactions(id)
+        ON DELETE RESTRICT
+);
+
+CREATE TABLE IF NOT EXISTS ledger_entries (
+    id BIGSERIAL PRIMARY KEY,
+    transaction_id UUID NOT NULL,
+    wallet_id UUID NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount <> 0),
+    -- balance of the wallet right after this entry
+    balance_after BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    FOREIGN KEY(transaction_id)
+        REFERENCES ledger_transactions(id)
+        ON DELETE RESTRICT,
+
+    FOREIGN KEY(wallet_id)
+        REFERENCES wallets(id)
+        ON DELETE RESTRICT
+);
+
+CREATE INDEX IF NOT EXISTS ledger_entries_wallet_id_idx ON ledger_entries (wallet_id, id);
+CREATE INDEX IF NOT EXISTS ledger_entries_transaction_id_idx ON ledger_entries (transaction_id);
+
+-- the ledger is never changed, mistakes are fixed with new transactions
+CREATE OR REPLACE FUNCTION forbid_ledger_changes() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER ledger_transactions_append_only
+    BEFORE UPDATE OR DELETE ON ledger_transactions
+    FOR EACH ROW EXECUTE PROCEDURE forbid_ledger_changes();
+
+CREATE TRIGGER ledger_entries_append_only
+    BEFORE UPDATE OR DELETE ON ledger_entries
+    FOR EACH ROW EXECUTE PROCEDURE forbid_ledger_changes();
+
+-- the entries of a transaction must add up to zero,
+-- checked on commit so the entries can be inserted one by one
+CREATE OR REPLACE FUNCTION check_ledger_transaction_balanced() RETURNS trigger AS $$
+BEGIN
+    IF (SELECT SUM(amount) FROM ledger_entries WHERE transaction_id = NEW.transaction_id) <> 0 THEN
+        RAISE EXCEPTION 'ledger transaction % is not balanced', NEW.transaction_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER ledger_entries_balanced
+    AFTER INSERT ON ledger_entries
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE PROCEDURE check_ledger_transaction_balanced();
+
+CREATE TABLE IF NOT EXISTS payout_requests (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    -- pending, processing while the payment provider pays it out, paid or rejected
+    status TEXT NOT NULL,
+    -- moved the coins to the pending payouts wallet
+    request_transaction_id UUID NOT NULL,
+    -- paid the coins out or returned them to the user
+    resolution_transaction_id UUID,
+    created_at TIMESTAMPTZ NOT NULL,
+    resolved_at TIMESTAMPTZ,
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE RESTRICT
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(request_transaction_id)
+        REFERENCES ledger_transactions(id)
+        ON DELETE RESTRICT,
+
+    FOREIGN KEY(resolution_transaction_id)
+        REFERENCES ledger_transactions(id)
+        ON DELETE RESTRICT
+);
+
+-- refunds of top-ups, the coins are taken back first and the money is returned
+-- through the payment provider afterwards
+CREATE TABLE IF NOT EXISTS provider_refunds (
+    transaction_id UUID PRIMARY KEY,
+    -- pending, refunded or failed
+    status TEXT NOT NULL,
+    provider_reference TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    resolved_at TIMESTAMPTZ,
+
+    FOREIGN KEY(transaction_id)
+        REFERENCES ledger_transactions(id)
+        ON DELETE RESTRICT
+);
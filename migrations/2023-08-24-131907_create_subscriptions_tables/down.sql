@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE comic_chapters
+    DROP CONSTRAINT comic_chapters_early_access_check,
+    DROP COLUMN early_access_at,
+    DROP COLUMN early_access_level;
+
+DROP TABLE subscription_payments;
+
+DROP INDEX IF EXISTS subscriptions_renewal_idx;
+
+DROP TABLE subscriptions;
+
+DROP TABLE subscription_tiers;
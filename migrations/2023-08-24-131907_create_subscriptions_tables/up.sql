@@ -0,0 +1,101 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS subscription_tiers (
+    id UUID PRIMARY KEY,
+    -- author offering the tier
+    user_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    -- charged every month
+    price BIGINT NOT NULL CHECK (price > 0),
+    -- a tier gets the early access of every tier with a lower or equal level
+    level INTEGER NOT NULL CHECK (level > 0),
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ,
+
+    UNIQUE (user_id, level),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id UUID PRIMARY KEY,
+    -- subscriber
+    user_id UUID NOT NULL,
+    author_id UUID NOT NULL,
+    tier_id UUID NOT NULL,
+    -- cheaper tier the subscription switches to at the next renewal
+    next_tier_id UUID,
+    -- the subscription gives early access until the end of the current period
+    current_period_start TIMESTAMPTZ NOT NULL,
+    current_period_end TIMESTAMPTZ NOT NULL,
+    -- canceled subscriptions aren't renewed, they end with the current period
+    canceled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ,
+
+    -- switching tiers or subscribing again reuses the same subscription
+    UNIQUE (user_id, author_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(author_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(tier_id)
+        REFERENCES subscription_tiers(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(next_tier_id)
+        REFERENCES subscription_tiers(id)
+        ON DELETE SET NULL
+        ON UPDATE CASCADE
+);
+
+-- used by the renewal worker
+CREATE INDEX IF NOT EXISTS subscriptions_renewal_idx
+    ON subscriptions (current_period_end)
+    WHERE canceled_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS subscription_payments (
+    id UUID PRIMARY KEY,
+    subscription_id UUID NOT NULL,
+    tier_id UUID NOT NULL,
+    amount BIGINT NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    -- reference of the charge at the payment provider
+    provider_reference TEXT NOT NULL,
+    -- key of the charge at the payment provider, retries with the same key aren't charged twice
+    idempotency_key TEXT UNIQUE NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    -- a period is only paid once, upgrades pay for the rest of the period from the switch
+    UNIQUE (subscription_id, period_start),
+
+    FOREIGN KEY(subscription_id)
+        REFERENCES subscriptions(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(tier_id)
+        REFERENCES subscription_tiers(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+ALTER TABLE comic_chapters
+    -- subscribers can read the chapter from this date until it's published
+    ADD COLUMN early_access_at TIMESTAMPTZ,
+    -- lowest tier level of the author that gets the early access
+    ADD COLUMN early_access_level INTEGER CHECK (early_access_level > 0),
+    ADD CONSTRAINT comic_chapters_early_access_check
+        CHECK ((early_access_at IS NULL) = (early_access_level IS NULL));
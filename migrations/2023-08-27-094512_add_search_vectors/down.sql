@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS users_search_vector_idx;
+DROP INDEX IF EXISTS comic_chapters_search_vector_idx;
+DROP INDEX IF EXISTS comics_search_vector_idx;
+
+ALTER TABLE users DROP COLUMN search_vector;
+ALTER TABLE comic_chapters DROP COLUMN search_vector;
+ALTER TABLE comics DROP COLUMN search_vector;
+
+DROP FUNCTION IF EXISTS search_headline(TEXT, TSQUERY);
+DROP FUNCTION IF EXISTS normalize_arabic(TEXT);
@@ -0,0 +1,44 @@
+-- Your SQL goes here
+
+-- makes differently written arabic words match:
+-- strips tashkeel and tatweel, unifies the alef and hamza forms, ta marbuta and alef maksura
+CREATE OR REPLACE FUNCTION normalize_arabic(input TEXT) RETURNS TEXT AS $$
+    SELECT translate(
+        regexp_replace(lower(input), E'[\\u064B-\\u065F\\u0670\\u06D6-\\u06ED\\u0640]', '', 'g'),
+        'أإآٱؤئةى',
+        'ااااويهي'
+    );
+$$ LANGUAGE SQL IMMUTABLE STRICT PARALLEL SAFE;
+
+-- the 'simple' configuration doesn't stem, arabic words are matched after normalization only
+ALTER TABLE comics
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', normalize_arabic(title)), 'A') ||
+        setweight(to_tsvector('simple', normalize_arabic(coalesce(description, ''))), 'B')
+    ) STORED;
+
+ALTER TABLE comic_chapters
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', normalize_arabic(title))
+    ) STORED;
+
+ALTER TABLE users
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', normalize_arabic(displayname)), 'A') ||
+        setweight(to_tsvector('simple', normalize_arabic(username)), 'B')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS comics_search_vector_idx ON comics USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS comic_chapters_search_vector_idx ON comic_chapters USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS users_search_vector_idx ON users USING GIN (search_vector);
+
+-- highlights the matches of a search in a normalized document, the document is html escaped
+-- so only the <mark> tags are markup
+CREATE OR REPLACE FUNCTION search_headline(document TEXT, query TSQUERY) RETURNS TEXT AS $$
+    SELECT ts_headline(
+        'simple',
+        replace(replace(replace(normalize_arabic(document), '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+        query,
+        'StartSel=<mark>, StopSel=</mark>, MinWords=15, MaxWords=35'
+    );
+$$ LANGUAGE SQL STABLE STRICT PARALLEL SAFE;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX IF EXISTS comic_genres_mapping_genre_id_idx;
+
+ALTER TABLE comics DROP COLUMN status;
+
+DROP TYPE ComicStatus;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+CREATE TYPE ComicStatus AS ENUM (
+    'ongoing', 'completed', 'hiatus', 'cancelled'
+);
+
+ALTER TABLE comics ADD COLUMN status ComicStatus NOT NULL DEFAULT 'ongoing';
+
+CREATE INDEX IF NOT EXISTS comic_genres_mapping_genre_id_idx ON comic_genres_mapping (genre_id);
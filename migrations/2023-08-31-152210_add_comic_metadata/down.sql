@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE users DROP COLUMN birth_date;
+
+ALTER TABLE comics
+    DROP COLUMN original_language,
+    DROP COLUMN reading_direction,
+    DROP COLUMN content_rating;
+
+DROP TYPE ReadingDirection;
+
+DROP TYPE ContentRating;
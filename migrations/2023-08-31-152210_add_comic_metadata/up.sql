@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TYPE ContentRating AS ENUM (
+    'all_ages', 'teen', 'mature'
+);
+
+CREATE TYPE ReadingDirection AS ENUM (
+    'rtl', 'ltr', 'vertical'
+);
+
+ALTER TABLE comics
+    ADD COLUMN content_rating ContentRating NOT NULL DEFAULT 'all_ages',
+    ADD COLUMN reading_direction ReadingDirection NOT NULL DEFAULT 'rtl',
+    -- ISO 639 language code
+    ADD COLUMN original_language TEXT NOT NULL DEFAULT 'ar';
+
+ALTER TABLE users ADD COLUMN birth_date DATE;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS comic_tags_mapping;
+
+DROP TABLE IF EXISTS comic_tags;
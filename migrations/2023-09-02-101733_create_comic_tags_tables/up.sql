@@ -0,0 +1,41 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS comic_tags (
+    id UUID PRIMARY KEY,
+    -- normalized, see comic_tags::utils::normalize_tag
+    name TEXT UNIQUE NOT NULL,
+    -- synonyms are merged into a single tag, merged tags have no comics
+    merged_into UUID,
+    banned_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    FOREIGN KEY(merged_into)
+        REFERENCES comic_tags(id)
+        ON DELETE SET NULL
+        ON UPDATE CASCADE,
+
+    CONSTRAINT comic_tags_merged_into_check CHECK (merged_into <> id)
+);
+
+-- prefix matching for autocomplete
+CREATE INDEX IF NOT EXISTS comic_tags_name_pattern_idx ON comic_tags (name text_pattern_ops);
+
+CREATE TABLE IF NOT EXISTS comic_tags_mapping (
+    comic_id UUID NOT NULL,
+    tag_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(comic_id, tag_id),
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(tag_id)
+        REFERENCES comic_tags(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS comic_tags_mapping_tag_id_idx ON comic_tags_mapping (tag_id);
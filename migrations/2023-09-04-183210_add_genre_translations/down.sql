@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS comic_genre_translations;
+
+DROP INDEX IF EXISTS comic_genres_parent_id_idx;
+
+ALTER TABLE comic_genres DROP CONSTRAINT IF EXISTS comic_genres_parent_id_check;
+ALTER TABLE comic_genres DROP CONSTRAINT IF EXISTS comic_genres_parent_id_fkey;
+ALTER TABLE comic_genres DROP CONSTRAINT IF EXISTS comic_genres_slug_key;
+
+ALTER TABLE comic_genres DROP COLUMN IF EXISTS parent_id;
+ALTER TABLE comic_genres DROP COLUMN IF EXISTS slug;
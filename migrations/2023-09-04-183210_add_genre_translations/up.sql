@@ -0,0 +1,68 @@
+-- Your SQL goes here
+ALTER TABLE comic_genres ADD COLUMN IF NOT EXISTS slug TEXT;
+ALTER TABLE comic_genres ADD COLUMN IF NOT EXISTS parent_id INTEGER;
+
+-- same format as comics::utils::slugify, genres with the same name get their id appended
+UPDATE comic_genres
+    SET slug = slugs.slug
+    FROM (
+        SELECT
+            id,
+            base || CASE
+                WHEN row_number() OVER (PARTITION BY base ORDER BY id) > 1 THEN '_' || id
+                ELSE ''
+            END AS slug
+        FROM (
+            SELECT
+                id,
+                coalesce(
+                    nullif(trim(BOTH '_' FROM lower(regexp_replace(name, '[^[:alnum:]]+', '_', 'g'))), ''),
+                    'genre'
+                ) AS base
+            FROM comic_genres
+        ) AS bases
+    ) AS slugs
+    WHERE comic_genres.id = slugs.id AND comic_genres.slug IS NULL;
+
+ALTER TABLE comic_genres ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE comic_genres ADD CONSTRAINT comic_genres_slug_key UNIQUE (slug);
+
+-- genres are only nested one level deep, sub-genres can't have their own sub-genres
+ALTER TABLE comic_genres
+    ADD CONSTRAINT comic_genres_parent_id_fkey
+    FOREIGN KEY(parent_id)
+        REFERENCES comic_genres(id)
+        ON DELETE SET NULL
+        ON UPDATE CASCADE;
+
+ALTER TABLE comic_genres ADD CONSTRAINT comic_genres_parent_id_check CHECK (parent_id <> id);
+
+CREATE INDEX IF NOT EXISTS comic_genres_parent_id_idx ON comic_genres (parent_id);
+
+-- `comic_genres.name` is the fallback for locales without a translation
+CREATE TABLE IF NOT EXISTS comic_genre_translations (
+    genre_id INTEGER NOT NULL,
+    locale TEXT NOT NULL,
+    name TEXT NOT NULL,
+
+    PRIMARY KEY(genre_id, locale),
+
+    FOREIGN KEY(genre_id)
+        REFERENCES comic_genres(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+INSERT INTO comic_genre_translations(genre_id, locale, name)
+    SELECT id, 'en', name FROM comic_genres
+    ON CONFLICT DO NOTHING;
+
+INSERT INTO comic_genre_translations(genre_id, locale, name)
+    SELECT id, 'ar', CASE name
+        WHEN 'Action' THEN 'أكشن'
+        WHEN 'Adventure' THEN 'مغامرة'
+        WHEN 'Romance' THEN 'رومانسي'
+    END
+    FROM comic_genres
+    WHERE name IN ('Action', 'Adventure', 'Romance')
+    ON CONFLICT DO NOTHING;
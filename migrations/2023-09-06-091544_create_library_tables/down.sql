@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS library_shelf_comics;
+
+DROP TABLE IF EXISTS library_shelves;
+
+DROP TABLE IF EXISTS library_entries;
+
+DROP TYPE IF EXISTS LibraryStatus;
@@ -0,0 +1,70 @@
+-- Your SQL goes here
+CREATE TYPE LibraryStatus AS ENUM ('reading', 'plan_to_read', 'completed', 'dropped');
+
+CREATE TABLE IF NOT EXISTS library_entries (
+    user_id UUID NOT NULL,
+    comic_id UUID NOT NULL,
+    status LibraryStatus NOT NULL DEFAULT 'plan_to_read',
+    -- chapters released after this are unread, it's when the comic was added if it's null
+    last_read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(user_id, comic_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS library_entries_comic_id_idx ON library_entries (comic_id);
+
+CREATE INDEX IF NOT EXISTS library_entries_updated_at_idx
+    ON library_entries (user_id, updated_at DESC, comic_id DESC);
+
+-- custom shelves, on top of the reading statuses
+CREATE TABLE IF NOT EXISTS library_shelves (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ,
+
+    UNIQUE (user_id, name),
+    -- lets shelf comics make sure the shelf and the library entry belong to the same user
+    UNIQUE (id, user_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS library_shelf_comics (
+    shelf_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    comic_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(shelf_id, comic_id),
+
+    FOREIGN KEY(shelf_id, user_id)
+        REFERENCES library_shelves(id, user_id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    -- removing a comic from the library removes it from its shelves
+    FOREIGN KEY(user_id, comic_id)
+        REFERENCES library_entries(user_id, comic_id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS library_shelf_comics_user_id_comic_id_idx
+    ON library_shelf_comics (user_id, comic_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS reading_progress;
+
+DROP TABLE IF EXISTS chapter_reads;
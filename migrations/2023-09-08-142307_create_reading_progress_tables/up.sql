@@ -0,0 +1,61 @@
+-- Your SQL goes here
+-- chapters the user finished or marked as read
+CREATE TABLE IF NOT EXISTS chapter_reads (
+    user_id UUID NOT NULL,
+    chapter_id UUID NOT NULL,
+    comic_id UUID NOT NULL,
+    read_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(user_id, chapter_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS chapter_reads_user_id_comic_id_idx
+    ON chapter_reads (user_id, comic_id);
+
+CREATE INDEX IF NOT EXISTS chapter_reads_chapter_id_idx ON chapter_reads (chapter_id);
+
+-- last chapter and page the user read of each comic
+CREATE TABLE IF NOT EXISTS reading_progress (
+    user_id UUID NOT NULL,
+    comic_id UUID NOT NULL,
+    chapter_id UUID NOT NULL,
+    page INTEGER NOT NULL CHECK (page > 0),
+    updated_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(user_id, comic_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS reading_progress_updated_at_idx
+    ON reading_progress (user_id, updated_at DESC, comic_id DESC);
+
+CREATE INDEX IF NOT EXISTS reading_progress_chapter_id_idx ON reading_progress (chapter_id);
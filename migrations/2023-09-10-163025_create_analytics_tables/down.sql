@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS chapter_daily_views;
+
+DROP TABLE IF EXISTS comic_daily_views;
+
+DROP TABLE IF EXISTS chapter_view_windows;
+
+DROP TABLE IF EXISTS comic_view_windows;
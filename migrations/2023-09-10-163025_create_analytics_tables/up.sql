@@ -0,0 +1,70 @@
+-- Your SQL goes here
+-- a view is counted once per viewer per window, rows of expired windows are cleaned up
+CREATE TABLE IF NOT EXISTS comic_view_windows (
+    comic_id UUID NOT NULL,
+    -- the user's id, or a keyed hash of the anonymous viewer's address and user agent
+    viewer TEXT NOT NULL,
+    window_start TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(comic_id, viewer, window_start),
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS comic_view_windows_window_start_idx
+    ON comic_view_windows (window_start);
+
+CREATE TABLE IF NOT EXISTS chapter_view_windows (
+    chapter_id UUID NOT NULL,
+    viewer TEXT NOT NULL,
+    window_start TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(chapter_id, viewer, window_start),
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS chapter_view_windows_window_start_idx
+    ON chapter_view_windows (window_start);
+
+-- daily rollups of the counted views, days are in UTC
+CREATE TABLE IF NOT EXISTS comic_daily_views (
+    comic_id UUID NOT NULL,
+    day DATE NOT NULL,
+    views BIGINT NOT NULL DEFAULT 0,
+
+    PRIMARY KEY(comic_id, day),
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS chapter_daily_views (
+    chapter_id UUID NOT NULL,
+    comic_id UUID NOT NULL,
+    day DATE NOT NULL,
+    views BIGINT NOT NULL DEFAULT 0,
+
+    PRIMARY KEY(chapter_id, day),
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS chapter_daily_views_comic_id_day_idx
+    ON chapter_daily_views (comic_id, day);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+-- precomputed periodically by the rankings worker so listing comics by them stays cheap
+CREATE TABLE IF NOT EXISTS comic_rankings (
+    comic_id UUID PRIMARY KEY,
+    -- time-decayed score of the recent views, ratings, library adds and comments
+    trending_score DOUBLE PRECISION NOT NULL DEFAULT 0,
+    -- views of the comic and its chapters of all time
+    views BIGINT NOT NULL DEFAULT 0,
+    computed_at TIMESTAMPTZ NOT NULL,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS comic_rankings_trending_score_idx
+    ON comic_rankings (trending_score DESC, comic_id DESC);
+
+CREATE INDEX IF NOT EXISTS comic_rankings_views_idx
+    ON comic_rankings (views DESC, comic_id DESC);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE comic_chapters DROP COLUMN IF EXISTS rating_count;
+ALTER TABLE comic_chapters DROP COLUMN IF EXISTS rating_sum;
+
+ALTER TABLE comics DROP COLUMN IF EXISTS chapters_count;
+ALTER TABLE comics DROP COLUMN IF EXISTS rating_count;
+ALTER TABLE comics DROP COLUMN IF EXISTS rating_sum;
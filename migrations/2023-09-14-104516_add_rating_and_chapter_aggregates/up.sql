@@ -0,0 +1,36 @@
+-- Your SQL goes here
+-- kept in sync by the routes that rate comics and chapters and create or delete chapters,
+-- `src/bin/repair_aggregates.rs` recomputes them if they ever drift
+ALTER TABLE comics ADD COLUMN IF NOT EXISTS rating_sum DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE comics ADD COLUMN IF NOT EXISTS rating_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE comics ADD COLUMN IF NOT EXISTS chapters_count BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE comic_chapters ADD COLUMN IF NOT EXISTS rating_sum DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE comic_chapters ADD COLUMN IF NOT EXISTS rating_count BIGINT NOT NULL DEFAULT 0;
+
+UPDATE comics
+    SET rating_sum = ratings.rating_sum, rating_count = ratings.rating_count
+    FROM (
+        SELECT comic_id, sum(rating) AS rating_sum, count(*) AS rating_count
+        FROM comic_ratings
+        GROUP BY comic_id
+    ) AS ratings
+    WHERE comics.id = ratings.comic_id;
+
+UPDATE comics
+    SET chapters_count = chapters.chapters_count
+    FROM (
+        SELECT comic_id, count(*) AS chapters_count
+        FROM comic_chapters
+        GROUP BY comic_id
+    ) AS chapters
+    WHERE comics.id = chapters.comic_id;
+
+UPDATE comic_chapters
+    SET rating_sum = ratings.rating_sum, rating_count = ratings.rating_count
+    FROM (
+        SELECT chapter_id, sum(rating) AS rating_sum, count(*) AS rating_count
+        FROM chapter_ratings
+        GROUP BY chapter_id
+    ) AS ratings
+    WHERE comic_chapters.id = ratings.chapter_id;
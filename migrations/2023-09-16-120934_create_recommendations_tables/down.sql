@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS user_recommendations;
+
+DROP TABLE IF EXISTS comic_similarities;
+
+DROP VIEW IF EXISTS comic_interactions;
@@ -0,0 +1,67 @@
+-- Your SQL goes here
+-- how much each user is into each comic, from 0 to 1, by their strongest signal
+CREATE OR REPLACE VIEW comic_interactions AS
+    SELECT user_id, comic_id, max(weight) AS weight
+    FROM (
+        -- only ratings of at least 3 count as liking the comic
+        SELECT user_id, comic_id, rating / 5 AS weight
+        FROM comic_ratings
+        WHERE rating >= 3
+
+        UNION ALL
+
+        SELECT user_id, comic_id, 0.8
+        FROM library_entries
+        WHERE status <> 'dropped'
+
+        UNION ALL
+
+        SELECT DISTINCT user_id, comic_id, 0.5
+        FROM chapter_reads
+    ) AS signals
+    GROUP BY user_id, comic_id;
+
+-- computed periodically by the recommendations worker
+CREATE TABLE IF NOT EXISTS comic_similarities (
+    comic_id UUID NOT NULL,
+    similar_comic_id UUID NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(comic_id, similar_comic_id),
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(similar_comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS comic_similarities_score_idx
+    ON comic_similarities (comic_id, score DESC);
+
+CREATE TABLE IF NOT EXISTS user_recommendations (
+    user_id UUID NOT NULL,
+    comic_id UUID NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(user_id, comic_id),
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS user_recommendations_score_idx
+    ON user_recommendations (user_id, score DESC);
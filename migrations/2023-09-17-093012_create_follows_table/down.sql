@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS comics_user_id_released_at_idx;
+
+DROP INDEX IF EXISTS comic_chapters_released_at_idx;
+
+DROP TABLE IF EXISTS follows;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS follows (
+    follower_id UUID NOT NULL,
+    followed_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(follower_id, followed_id),
+    CHECK (follower_id <> followed_id),
+
+    FOREIGN KEY(follower_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(followed_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS follows_followed_id_idx ON follows (followed_id);
+
+-- the feed goes through the chapters of followed authors and library comics by release date
+CREATE INDEX IF NOT EXISTS comic_chapters_released_at_idx
+    ON comic_chapters (comic_id, (coalesce(published_at, created_at)) DESC);
+
+CREATE INDEX IF NOT EXISTS comics_user_id_released_at_idx
+    ON comics (user_id, (coalesce(published_at, created_at)) DESC);
@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS comic_chapters_unnotified_idx;
+
+ALTER TABLE comic_chapters DROP COLUMN IF EXISTS notified_at;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS notify_new_chapters,
+    DROP COLUMN IF EXISTS notify_comment_replies,
+    DROP COLUMN IF EXISTS notify_comic_comments,
+    DROP COLUMN IF EXISTS notify_rating_milestones,
+    DROP COLUMN IF EXISTS notify_moderation;
+
+DROP TABLE IF EXISTS notifications;
+
+DROP TYPE IF EXISTS NotificationKind;
@@ -0,0 +1,69 @@
+-- Your SQL goes here
+CREATE TYPE NotificationKind AS ENUM ('new_chapter', 'comment_reply', 'comic_comment', 'rating_milestone', 'moderation');
+
+CREATE TABLE IF NOT EXISTS notifications (
+    id UUID PRIMARY KEY,
+    -- who gets notified
+    user_id UUID NOT NULL,
+    kind NotificationKind NOT NULL,
+    -- who caused it, if it was a user
+    actor_id UUID,
+    comic_id UUID,
+    chapter_id UUID,
+    -- a comic or a chapter comment, depending on whether chapter_id is set
+    comment_id UUID,
+    -- number of ratings reached by rating milestones
+    milestone BIGINT,
+    -- what the moderators did for moderation notifications
+    details TEXT,
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+
+    FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(actor_id)
+        REFERENCES users(id)
+        ON DELETE SET NULL
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(comic_id)
+        REFERENCES comics(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    FOREIGN KEY(chapter_id)
+        REFERENCES comic_chapters(id)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS notifications_user_id_idx ON notifications (user_id, id DESC);
+
+CREATE INDEX IF NOT EXISTS notifications_unread_idx
+    ON notifications (user_id)
+    WHERE read_at IS NULL;
+
+-- a comic reaches each milestone once, even if it loses ratings and gets them back
+CREATE UNIQUE INDEX IF NOT EXISTS notifications_rating_milestone_idx
+    ON notifications (comic_id, milestone)
+    WHERE kind = 'rating_milestone';
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS notify_new_chapters BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS notify_comment_replies BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS notify_comic_comments BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS notify_rating_milestones BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS notify_moderation BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- readers are notified about chapters once they're released, chapters released before
+-- notifications existed are skipped, scheduled ones are notified when they come out
+ALTER TABLE comic_chapters ADD COLUMN IF NOT EXISTS notified_at TIMESTAMPTZ;
+
+UPDATE comic_chapters SET notified_at = now() WHERE coalesce(published_at, created_at) <= now();
+
+CREATE INDEX IF NOT EXISTS comic_chapters_unnotified_idx
+    ON comic_chapters (published_at)
+    WHERE notified_at IS NULL;
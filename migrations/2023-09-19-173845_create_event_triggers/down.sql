@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS chapter_comments_chapter_id_idx;
+
+DROP TRIGGER IF EXISTS chapter_comments_publish_event ON chapter_comments;
+
+DROP FUNCTION IF EXISTS publish_chapter_comment_event;
+
+DROP TRIGGER IF EXISTS notifications_publish_event ON notifications;
+
+DROP FUNCTION IF EXISTS publish_notification_event;
@@ -0,0 +1,35 @@
+-- Your SQL goes here
+-- new notifications and chapter comments are published to every app instance listening
+-- on the events channel, NOTIFY is only delivered once the transaction commits
+CREATE OR REPLACE FUNCTION publish_notification_event() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('musawarah_events', json_build_object(
+        'kind', 'notification',
+        'id', NEW.id,
+        'user_id', NEW.user_id
+    )::TEXT);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notifications_publish_event
+    AFTER INSERT ON notifications
+    FOR EACH ROW EXECUTE PROCEDURE publish_notification_event();
+
+CREATE OR REPLACE FUNCTION publish_chapter_comment_event() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('musawarah_events', json_build_object(
+        'kind', 'chapter_comment',
+        'id', NEW.id,
+        'chapter_id', NEW.chapter_id
+    )::TEXT);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER chapter_comments_publish_event
+    AFTER INSERT ON chapter_comments
+    FOR EACH ROW EXECUTE PROCEDURE publish_chapter_comment_event();
+
+-- comments a reconnecting client missed are replayed by id
+CREATE INDEX IF NOT EXISTS chapter_comments_chapter_id_idx ON chapter_comments (chapter_id, id);
@@ -0,0 +1,53 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, Utc};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use tokio::time::interval;
+
+use crate::{
+    schema::{chapter_view_windows, comic_view_windows},
+    InnerAppState,
+};
+
+use super::{AnalyticsError, VIEW_WINDOW_MINUTES};
+
+const VIEW_WINDOWS_CLEANUP_INTERVAL_SECS: u64 = 15 * 60;
+
+/// Delete the view windows that ended, their views are already in the daily rollups
+///
+/// returns the number of removed windows
+pub async fn process_expired_view_windows(state: &InnerAppState) -> Result<usize, AnalyticsError> {
+    let mut db = state.pool.get().await?;
+
+    let expired_before = Utc::now() - Duration::minutes(VIEW_WINDOW_MINUTES);
+
+    let comic_windows = diesel::delete(
+        comic_view_windows::table.filter(comic_view_windows::window_start.lt(expired_before)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    let chapter_windows = diesel::delete(
+        chapter_view_windows::table.filter(chapter_view_windows::window_start.lt(expired_before)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    Ok(comic_windows + chapter_windows)
+}
+
+/// Background task that removes the view windows that ended
+pub async fn view_windows_cleanup_worker(state: Arc<InnerAppState>) {
+    let mut cleanup_interval = interval(StdDuration::from_secs(VIEW_WINDOWS_CLEANUP_INTERVAL_SECS));
+
+    loop {
+        cleanup_interval.tick().await;
+
+        match process_expired_view_windows(&state).await {
+            Ok(0) => {}
+            Ok(removed) => tracing::debug!("removed {removed} expired view windows"),
+            Err(err) => tracing::error!("failed to clean up view windows: {:#?}", err),
+        }
+    }
+}
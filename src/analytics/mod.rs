@@ -0,0 +1,67 @@
+use axum::{http::StatusCode, response::IntoResponse};
+
+use crate::ErrorResponse;
+
+pub mod cleanup;
+pub mod models;
+pub mod routes;
+pub mod utils;
+
+/// A viewer's views of the same comic or chapter are counted once per window
+pub const VIEW_WINDOW_MINUTES: i64 = 30;
+
+/// Days covered by the analytics when no range is given
+pub const DEFAULT_ANALYTICS_DAYS: i64 = 30;
+
+pub const MAX_ANALYTICS_DAYS: i64 = 366;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnalyticsError {
+    #[error("comic not found")]
+    ComicNotFound,
+
+    #[error("chapter not found")]
+    ChapterNotFound,
+
+    #[error("invalid date range, it can cover up to {MAX_ANALYTICS_DAYS} days")]
+    InvalidRange,
+
+    #[error("internal server error")]
+    InternalServerError,
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for AnalyticsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let (status, error) = match &self {
+            AnalyticsError::ComicNotFound | AnalyticsError::ChapterNotFound => {
+                (StatusCode::NOT_FOUND, self.to_string())
+            }
+            AnalyticsError::InvalidRange => (StatusCode::BAD_REQUEST, self.to_string()),
+            AnalyticsError::Diesel(diesel::result::Error::NotFound) => {
+                (StatusCode::NOT_FOUND, String::from("not found"))
+            }
+            AnalyticsError::InternalServerError
+            | AnalyticsError::Diesel(_)
+            | AnalyticsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error,
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
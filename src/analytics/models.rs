@@ -0,0 +1,118 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::{
+    prelude::*,
+    sql_types::{BigInt, Date, Nullable, Text, Uuid as SqlUuid},
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::schema::{
+    chapter_daily_views, chapter_view_windows, comic_daily_views, comic_view_windows,
+};
+
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = comic_view_windows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ComicViewWindow {
+    pub comic_id: Uuid,
+    pub viewer: String,
+    pub window_start: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = chapter_view_windows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterViewWindow {
+    pub chapter_id: Uuid,
+    pub viewer: String,
+    pub window_start: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = comic_daily_views)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ComicDailyViews {
+    pub comic_id: Uuid,
+    pub day: NaiveDate,
+    pub views: i64,
+}
+
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = chapter_daily_views)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterDailyViews {
+    pub chapter_id: Uuid,
+    pub comic_id: Uuid,
+    pub day: NaiveDate,
+    pub views: i64,
+}
+
+/// One day of one metric of a comic, or of one of its chapters if `chapter_id` is set
+#[derive(QueryableByName, Debug)]
+pub struct AnalyticsRow {
+    #[diesel(sql_type = Text)]
+    pub metric: String,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    pub chapter_id: Option<Uuid>,
+    #[diesel(sql_type = Date)]
+    pub day: NaiveDate,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct RecordView {
+    pub comic_id: Uuid,
+    /// the view is of this chapter of the comic rather than the comic's page
+    pub chapter_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct RecordViewResponse {
+    /// false if the viewer's view was already counted in the current window
+    pub counted: bool,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub struct AnalyticsParams {
+    /// first day of the range (UTC), defaults to 30 days before `to`
+    pub from: Option<NaiveDate>,
+    /// last day of the range (UTC), defaults to today
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct DailyCount {
+    pub day: String,
+    pub count: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct ChapterAnalyticsResponse {
+    pub chapter_id: Uuid,
+    pub number: i32,
+    pub title: String,
+    pub views: Vec<DailyCount>,
+    pub ratings: Vec<DailyCount>,
+    pub comments: Vec<DailyCount>,
+}
+
+/// Time series of a comic's metrics, days without any activity are left out
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct ComicAnalyticsResponse {
+    pub comic_id: Uuid,
+    pub from: String,
+    pub to: String,
+    pub views: Vec<DailyCount>,
+    pub ratings: Vec<DailyCount>,
+    pub comments: Vec<DailyCount>,
+    pub library_adds: Vec<DailyCount>,
+    pub chapters: Vec<ChapterAnalyticsResponse>,
+}
@@ -0,0 +1,327 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use diesel::{
+    prelude::*,
+    result::Error::NotFound,
+    sql_types::{Date, Uuid as SqlUuid},
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    comics::chapters::{entitlements::is_chapter_visible, models::Chapter},
+    schema::{comic_chapters, comics},
+    users::models::UserRole,
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{
+        AnalyticsParams, AnalyticsRow, ChapterAnalyticsResponse, ComicAnalyticsResponse,
+        DailyCount, RecordView, RecordViewResponse,
+    },
+    utils::{record_chapter_view, record_comic_view, view_window_start, viewer_key},
+    AnalyticsError, DEFAULT_ANALYTICS_DAYS, MAX_ANALYTICS_DAYS,
+};
+
+pub fn analytics_router() -> Router<AppState> {
+    Router::new()
+        .route("/views", post(record_view))
+        .route("/comics/:comic_id", get(get_comic_analytics))
+}
+
+// every metric of the comic and its chapters per day, days are in UTC
+//
+// $1 is the comic, $2 and $3 are the first and last days of the range
+const ANALYTICS_QUERY: &str = "
+    SELECT 'views' AS metric, NULL::uuid AS chapter_id, day, views AS count
+    FROM comic_daily_views
+    WHERE comic_id = $1 AND day BETWEEN $2 AND $3
+
+    UNION ALL
+
+    SELECT 'views', chapter_id, day, views
+    FROM chapter_daily_views
+    WHERE comic_id = $1 AND day BETWEEN $2 AND $3
+
+    UNION ALL
+
+    SELECT 'ratings', NULL, (created_at AT TIME ZONE 'UTC')::date, count(*)
+    FROM comic_ratings
+    WHERE comic_id = $1 AND (created_at AT TIME ZONE 'UTC')::date BETWEEN $2 AND $3
+    GROUP BY 3
+
+    UNION ALL
+
+    SELECT 'ratings', chapter_ratings.chapter_id, (chapter_ratings.created_at AT TIME ZONE 'UTC')::date, count(*)
+    FROM chapter_ratings
+    INNER JOIN comic_chapters ON comic_chapters.id = chapter_ratings.chapter_id
+    WHERE comic_chapters.comic_id = $1
+        AND (chapter_ratings.created_at AT TIME ZONE 'UTC')::date BETWEEN $2 AND $3
+    GROUP BY 2, 3
+
+    UNION ALL
+
+    SELECT 'comments', NULL, (created_at AT TIME ZONE 'UTC')::date, count(*)
+    FROM comic_comments
+    WHERE comic_id = $1 AND (created_at AT TIME ZONE 'UTC')::date BETWEEN $2 AND $3
+    GROUP BY 3
+
+    UNION ALL
+
+    SELECT 'comments', chapter_comments.chapter_id, (chapter_comments.created_at AT TIME ZONE 'UTC')::date, count(*)
+    FROM chapter_comments
+    INNER JOIN comic_chapters ON comic_chapters.id = chapter_comments.chapter_id
+    WHERE comic_chapters.comic_id = $1
+        AND (chapter_comments.created_at AT TIME ZONE 'UTC')::date BETWEEN $2 AND $3
+    GROUP BY 2, 3
+
+    UNION ALL
+
+    SELECT 'library_adds', NULL, (created_at AT TIME ZONE 'UTC')::date, count(*)
+    FROM library_entries
+    WHERE comic_id = $1 AND (created_at AT TIME ZONE 'UTC')::date BETWEEN $2 AND $3
+    GROUP BY 3
+
+    ORDER BY day
+";
+
+/// Count a view of a comic's page or one of its chapters, called by the client when it's shown
+///
+/// views are counted once per user, or per anonymous viewer, in a 30 minutes window,
+/// authors' views of their own comics aren't counted
+#[utoipa::path(
+    post,
+    path = "/api/v1/analytics/views",
+    request_body(content = RecordView, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Whether the view was counted", body = RecordViewResponse),
+        (status = StatusCode::NOT_FOUND, description = "Comic or chapter not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Analytics API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn record_view(
+    auth: Option<AuthExtractor<{ UserRole::User as u32 }>>,
+    State(state): State<Arc<InnerAppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<RecordView>,
+) -> Result<Json<RecordViewResponse>, AnalyticsError> {
+    let mut db = state.pool.get().await?;
+
+    let user = auth.as_ref().map(|auth| &auth.current_user);
+
+    let author_id = comics::table
+        .find(payload.comic_id)
+        .select(comics::user_id)
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => AnalyticsError::ComicNotFound,
+            e => e.into(),
+        })?;
+
+    let chapter = match payload.chapter_id {
+        Some(chapter_id) => {
+            let chapter = comic_chapters::table
+                .find(chapter_id)
+                .filter(comic_chapters::comic_id.eq(payload.comic_id))
+                .select(Chapter::as_select())
+                .first::<Chapter>(&mut db)
+                .await
+                .map_err(|e| match e {
+                    NotFound => AnalyticsError::ChapterNotFound,
+                    e => e.into(),
+                })?;
+
+            if !is_chapter_visible(&mut db, &chapter, user).await? {
+                return Err(AnalyticsError::ChapterNotFound);
+            }
+
+            Some(chapter)
+        }
+        None => None,
+    };
+
+    if user.is_some_and(|user| user.id == author_id) {
+        return Ok(Json(RecordViewResponse { counted: false }));
+    }
+
+    let now = Utc::now();
+    let window_start = view_window_start(now).ok_or(AnalyticsError::InternalServerError)?;
+    let viewer = viewer_key(
+        user.map(|user| user.id),
+        &headers,
+        addr,
+        &state.trusted_proxies,
+        state.cookies_secret.master(),
+        now,
+    );
+    let comic_id = payload.comic_id;
+
+    let counted = db
+        .transaction::<_, AnalyticsError, _>(|conn| {
+            async move {
+                let counted = match chapter {
+                    Some(chapter) => {
+                        record_chapter_view(conn, chapter.id, comic_id, viewer, now, window_start)
+                            .await?
+                    }
+                    None => record_comic_view(conn, comic_id, viewer, now, window_start).await?,
+                };
+
+                Ok(counted)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(Json(RecordViewResponse { counted }))
+}
+
+/// Get the daily views, new ratings, comments and library adds of a comic and its chapters
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/comics/:comic_id",
+    params(
+        AnalyticsParams,
+    ),
+    responses(
+        (status = 200, description = "Time series of the comic's metrics", body = ComicAnalyticsResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid date range", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Comic not found or caller isn't its author", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Analytics API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_comic_analytics(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(comic_id): Path<Uuid>,
+    Query(params): Query<AnalyticsParams>,
+) -> Result<Json<ComicAnalyticsResponse>, AnalyticsError> {
+    let to = params.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = params
+        .from
+        .unwrap_or(to - Duration::days(DEFAULT_ANALYTICS_DAYS - 1));
+
+    if from > to || (to - from).num_days() >= MAX_ANALYTICS_DAYS {
+        return Err(AnalyticsError::InvalidRange);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    let mut comic_query = comics::table
+        .filter(comics::id.eq(comic_id))
+        .select(comics::id)
+        .into_boxed();
+
+    // only the author and staff can see how a comic is doing
+    if !matches!(auth.current_user.role, UserRole::Admin | UserRole::Staff) {
+        comic_query = comic_query.filter(comics::user_id.eq(auth.current_user.id));
+    }
+
+    comic_query
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => AnalyticsError::ComicNotFound,
+            e => e.into(),
+        })?;
+
+    let mut chapters = comic_chapters::table
+        .filter(comic_chapters::comic_id.eq(comic_id))
+        .order((comic_chapters::number.asc(), comic_chapters::id.asc()))
+        .select((
+            comic_chapters::id,
+            comic_chapters::number,
+            comic_chapters::title,
+        ))
+        .load::<(Uuid, i32, String)>(&mut db)
+        .await?
+        .into_iter()
+        .map(|(chapter_id, number, title)| ChapterAnalyticsResponse {
+            chapter_id,
+            number,
+            title,
+            views: vec![],
+            ratings: vec![],
+            comments: vec![],
+        })
+        .collect::<Vec<ChapterAnalyticsResponse>>();
+
+    let chapter_indices = chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| (chapter.chapter_id, index))
+        .collect::<HashMap<Uuid, usize>>();
+
+    let rows = diesel::sql_query(ANALYTICS_QUERY)
+        .bind::<SqlUuid, _>(comic_id)
+        .bind::<Date, _>(from)
+        .bind::<Date, _>(to)
+        .load::<AnalyticsRow>(&mut db)
+        .await?;
+
+    let mut analytics = ComicAnalyticsResponse {
+        comic_id,
+        from: from.to_string(),
+        to: to.to_string(),
+        views: vec![],
+        ratings: vec![],
+        comments: vec![],
+        library_adds: vec![],
+        chapters: vec![],
+    };
+
+    for row in rows {
+        let point = DailyCount {
+            day: row.day.to_string(),
+            count: row.count,
+        };
+
+        match row.chapter_id {
+            None => match row.metric.as_str() {
+                "views" => analytics.views.push(point),
+                "ratings" => analytics.ratings.push(point),
+                "comments" => analytics.comments.push(point),
+                "library_adds" => analytics.library_adds.push(point),
+                _ => {}
+            },
+            Some(chapter_id) => {
+                let Some(chapter) = chapter_indices
+                    .get(&chapter_id)
+                    .map(|index| &mut chapters[*index])
+                else {
+                    continue;
+                };
+
+                match row.metric.as_str() {
+                    "views" => chapter.views.push(point),
+                    "ratings" => chapter.ratings.push(point),
+                    "comments" => chapter.comments.push(point),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    analytics.chapters = chapters;
+
+    Ok(Json(analytics))
+}
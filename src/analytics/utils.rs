@@ -0,0 +1,190 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::{header::USER_AGENT, HeaderMap};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, TimeZone, Utc};
+use diesel::{ExpressionMethods, QueryResult};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::schema::{
+    chapter_daily_views, chapter_view_windows, comic_daily_views, comic_view_windows,
+};
+
+use super::{
+    models::{ChapterDailyViews, ChapterViewWindow, ComicDailyViews, ComicViewWindow},
+    VIEW_WINDOW_MINUTES,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Address of the client that sent the request
+///
+/// `X-Forwarded-For` is only believed when the request comes from a trusted proxy. Every proxy
+/// appends the address it got the request from, so the right-most address that isn't a trusted
+/// proxy is the client's, anything left of it could have been sent by the client
+pub fn client_address(headers: &HeaderMap, addr: SocketAddr, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let mut address = addr.ip();
+
+    if !trusted_proxies.contains(&address) {
+        return address;
+    }
+
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<&str>>();
+
+    for hop in forwarded.into_iter().rev() {
+        let Ok(hop) = hop.parse::<IpAddr>() else {
+            break;
+        };
+
+        address = hop;
+
+        if !trusted_proxies.contains(&address) {
+            break;
+        }
+    }
+
+    address
+}
+
+/// Identifies a viewer, users by their id and anonymous viewers by an HMAC of their address
+/// and user agent
+///
+/// the HMAC key is derived from `secret` and changes every UTC day, so the fingerprints can't be
+/// reversed without the secret and a viewer's fingerprints of different days can't be linked.
+/// Days start on a view window boundary, a window never spans two keys
+pub fn viewer_key(
+    user_id: Option<Uuid>,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+    trusted_proxies: &[IpAddr],
+    secret: &[u8],
+    now: DateTime<Utc>,
+) -> String {
+    if let Some(user_id) = user_id {
+        return user_id.to_string();
+    }
+
+    let address = client_address(headers, addr, trusted_proxies).to_string();
+
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let day_key = HmacSha256::new_from_slice(secret)
+        .expect("hmac accepts keys of any size")
+        .chain_update(b"anonymous viewers\n")
+        .chain_update(now.date_naive().to_string())
+        .finalize()
+        .into_bytes();
+
+    let digest = HmacSha256::new_from_slice(&day_key)
+        .expect("hmac accepts keys of any size")
+        .chain_update(address)
+        .chain_update(b"\n")
+        .chain_update(user_agent)
+        .finalize()
+        .into_bytes();
+
+    format!("anon:{}", URL_SAFE_NO_PAD.encode(digest))
+}
+
+/// Start of the view window `now` falls in
+pub fn view_window_start(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let timestamp = now.timestamp();
+
+    Utc.timestamp_opt(
+        timestamp - timestamp.rem_euclid(VIEW_WINDOW_MINUTES * 60),
+        0,
+    )
+    .single()
+}
+
+/// Counts a view of the comic's page unless the viewer's view was already counted in the window
+///
+/// returns whether the view was counted
+pub async fn record_comic_view(
+    db: &mut AsyncPgConnection,
+    comic_id: Uuid,
+    viewer: String,
+    now: DateTime<Utc>,
+    window_start: DateTime<Utc>,
+) -> QueryResult<bool> {
+    let inserted = diesel::insert_into(comic_view_windows::table)
+        .values(&ComicViewWindow {
+            comic_id,
+            viewer,
+            window_start,
+        })
+        .on_conflict_do_nothing()
+        .execute(db)
+        .await?;
+
+    if inserted == 0 {
+        return Ok(false);
+    }
+
+    diesel::insert_into(comic_daily_views::table)
+        .values(&ComicDailyViews {
+            comic_id,
+            day: now.date_naive(),
+            views: 1,
+        })
+        .on_conflict((comic_daily_views::comic_id, comic_daily_views::day))
+        .do_update()
+        .set(comic_daily_views::views.eq(comic_daily_views::views + 1))
+        .execute(db)
+        .await?;
+
+    Ok(true)
+}
+
+/// Counts a view of the chapter unless the viewer's view was already counted in the window
+///
+/// returns whether the view was counted
+pub async fn record_chapter_view(
+    db: &mut AsyncPgConnection,
+    chapter_id: Uuid,
+    comic_id: Uuid,
+    viewer: String,
+    now: DateTime<Utc>,
+    window_start: DateTime<Utc>,
+) -> QueryResult<bool> {
+    let inserted = diesel::insert_into(chapter_view_windows::table)
+        .values(&ChapterViewWindow {
+            chapter_id,
+            viewer,
+            window_start,
+        })
+        .on_conflict_do_nothing()
+        .execute(db)
+        .await?;
+
+    if inserted == 0 {
+        return Ok(false);
+    }
+
+    diesel::insert_into(chapter_daily_views::table)
+        .values(&ChapterDailyViews {
+            chapter_id,
+            comic_id,
+            day: now.date_naive(),
+            views: 1,
+        })
+        .on_conflict((chapter_daily_views::chapter_id, chapter_daily_views::day))
+        .do_update()
+        .set(chapter_daily_views::views.eq(chapter_daily_views::views + 1))
+        .execute(db)
+        .await?;
+
+    Ok(true)
+}
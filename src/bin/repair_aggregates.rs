@@ -0,0 +1,35 @@
+//! Recomputes the denormalized rating sums, rating counts and chapter counts of comics and
+//! chapters, run it with `cargo run --bin repair_aggregates` if they drifted from the rows
+//! they count
+
+use std::env;
+
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection};
+use dotenvy::dotenv;
+use musawarah::comics::aggregates::repair_aggregates;
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    if let Err(err) = dotenv() {
+        tracing::error!("Could not load .env file: {}", err);
+    }
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL env variable");
+
+    let mut db = AsyncPgConnection::establish(&database_url)
+        .await
+        .expect("db connection");
+
+    let repaired = db
+        .transaction(|conn| async move { repair_aggregates(conn).await }.scope_boxed())
+        .await
+        .expect("repair aggregates");
+
+    tracing::info!(
+        "repaired the aggregates of {} comics and {} chapters",
+        repaired.comics,
+        repaired.chapters
+    );
+}
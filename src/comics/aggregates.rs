@@ -0,0 +1,75 @@
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+// comics and chapters whose stored aggregates don't match their rows get them recomputed,
+// comics and chapters without any ratings or chapters are reset to 0
+const REPAIR_COMICS_QUERY: &str = "
+    UPDATE comics
+    SET
+        rating_sum = aggregates.rating_sum,
+        rating_count = aggregates.rating_count,
+        chapters_count = aggregates.chapters_count
+    FROM (
+        SELECT
+            comics.id,
+            coalesce(ratings.rating_sum, 0) AS rating_sum,
+            coalesce(ratings.rating_count, 0) AS rating_count,
+            coalesce(chapters.chapters_count, 0) AS chapters_count
+        FROM comics
+        LEFT JOIN (
+            SELECT comic_id, sum(rating) AS rating_sum, count(*) AS rating_count
+            FROM comic_ratings
+            GROUP BY comic_id
+        ) AS ratings ON ratings.comic_id = comics.id
+        LEFT JOIN (
+            SELECT comic_id, count(*) AS chapters_count
+            FROM comic_chapters
+            GROUP BY comic_id
+        ) AS chapters ON chapters.comic_id = comics.id
+    ) AS aggregates
+    WHERE comics.id = aggregates.id
+        AND (comics.rating_sum, comics.rating_count, comics.chapters_count)
+            IS DISTINCT FROM (aggregates.rating_sum, aggregates.rating_count, aggregates.chapters_count)
+";
+
+const REPAIR_CHAPTERS_QUERY: &str = "
+    UPDATE comic_chapters
+    SET
+        rating_sum = aggregates.rating_sum,
+        rating_count = aggregates.rating_count
+    FROM (
+        SELECT
+            comic_chapters.id,
+            coalesce(ratings.rating_sum, 0) AS rating_sum,
+            coalesce(ratings.rating_count, 0) AS rating_count
+        FROM comic_chapters
+        LEFT JOIN (
+            SELECT chapter_id, sum(rating) AS rating_sum, count(*) AS rating_count
+            FROM chapter_ratings
+            GROUP BY chapter_id
+        ) AS ratings ON ratings.chapter_id = comic_chapters.id
+    ) AS aggregates
+    WHERE comic_chapters.id = aggregates.id
+        AND (comic_chapters.rating_sum, comic_chapters.rating_count)
+            IS DISTINCT FROM (aggregates.rating_sum, aggregates.rating_count)
+";
+
+/// Number of comics and chapters whose aggregates were wrong
+#[derive(Debug, Default)]
+pub struct RepairedAggregates {
+    pub comics: usize,
+    pub chapters: usize,
+}
+
+/// Recompute the rating sums, rating counts and chapter counts of comics and chapters from
+/// their ratings and chapters
+///
+/// the routes keep them in sync, but rows removed by cascading deletes, e.g. the ratings of a
+/// deleted user, aren't subtracted from them
+pub async fn repair_aggregates(
+    db: &mut AsyncPgConnection,
+) -> Result<RepairedAggregates, diesel::result::Error> {
+    let comics = diesel::sql_query(REPAIR_COMICS_QUERY).execute(db).await?;
+    let chapters = diesel::sql_query(REPAIR_CHAPTERS_QUERY).execute(db).await?;
+
+    Ok(RepairedAggregates { comics, chapters })
+}
@@ -1,7 +1,7 @@
 use axum::{http::StatusCode, response::IntoResponse};
 use diesel::result::DatabaseErrorKind;
 
-use crate::ErrorResponse;
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
 
 pub mod models;
 pub mod routes;
@@ -13,6 +13,9 @@ pub enum ChapterCommentsError {
 
     #[error(transparent)]
     PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
 }
 
 impl IntoResponse for ChapterCommentsError {
@@ -52,6 +55,7 @@ impl IntoResponse for ChapterCommentsError {
                 }
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
+            ChapterCommentsError::InvalidCursor(err) => err.into_response(),
             _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
         }
     }
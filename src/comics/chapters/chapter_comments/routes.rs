@@ -1,21 +1,24 @@
 use chrono::Utc;
-use diesel::GroupedBy;
 use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     routing::{delete, get, post},
     Json, Router,
 };
 use diesel::BelongingToDsl;
-use diesel::{ExpressionMethods, QueryDsl};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
 use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
-use itertools::multizip;
 use uuid::Uuid;
 
 use crate::{
     auth::AuthExtractor,
     comics::chapters::{chapter_comments::models::CreateChapterComment, models::Chapter},
+    common::pagination::{Paginated, PaginationParams},
+    notifications::{
+        models::{Notification, NotificationKind},
+        utils::notify,
+    },
     schema::{chapter_comments, chapter_comments_mapping, comic_chapters, users},
     users::models::{User, UserResponseBrief, UserRole},
     AppState, InnerAppState,
@@ -36,9 +39,12 @@ pub fn chapter_comments_router() -> Router<AppState> {
 #[utoipa::path(
     get,
     path = "/api/v1/comics/chapters/:chapter_id/comments",
+    params(
+        PaginationParams,
+    ),
     responses (
-        (status = 200, body = ChapterCommentResponse),
-        (status = StatusCode::BAD_REQUEST, description = "Invalid Chapter ID", body = ErrorResponse),
+        (status = 200, body = PaginatedChapterComments),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid Chapter ID or cursor", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Chapter Comments API"
@@ -48,71 +54,85 @@ pub async fn get_comments(
     _auth: AuthExtractor<{ UserRole::User as u32 }>,
     Path(chapter_id): Path<Uuid>,
     State(state): State<Arc<InnerAppState>>,
-) -> Result<Json<Vec<ChapterCommentResponse>>, ChapterCommentsError> {
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<ChapterCommentResponse>>, ChapterCommentsError> {
+    let cursor = pagination.cursor::<Uuid>()?;
     let mut db = state.pool.get().await?;
 
     let chapter = comic_chapters::table
         .filter(comic_chapters::id.eq(chapter_id))
+        .select(Chapter::as_select())
         .get_result::<Chapter>(&mut db)
         .await?;
 
-    let (comments, users): (Vec<ChapterComment>, Vec<User>) =
-        ChapterComment::belonging_to(&chapter)
-            .inner_join(users::table)
-            .load::<(ChapterComment, User)>(&mut db)
-            .await?
-            .into_iter()
-            .unzip();
+    let mut query = ChapterComment::belonging_to(&chapter)
+        .inner_join(users::table)
+        .into_boxed();
 
-    let comment_mappings = ChapterCommentMapping::belonging_to(&comments)
-        .load::<ChapterCommentMapping>(&mut db)
+    // comment ids are v7 uuids, ordering by them lists the oldest comments first
+    if let Some(prev_id) = cursor {
+        query = query.filter(chapter_comments::id.gt(prev_id));
+    }
+
+    let mut rows = query
+        .order(chapter_comments::id.asc())
+        .limit(pagination.limit() + 1)
+        .select((ChapterComment::as_select(), User::as_select()))
+        .load::<(ChapterComment, User)>(&mut db)
         .await?;
 
-    let comment_mappings_by_parents = comment_mappings.clone().grouped_by(&comments); // 1 parent -> children
+    let next_cursor = pagination.page(&mut rows, |(comment, _)| comment.id);
+
+    let (comments, users): (Vec<ChapterComment>, Vec<User>) = rows.into_iter().unzip();
 
-    let id_indices: HashMap<_, _> = comments
+    let comment_ids = comments
         .iter()
-        .enumerate()
-        .map(|(i, u)| (u.id, i))
-        .collect();
+        .map(|comment| comment.id)
+        .collect::<Vec<Uuid>>();
+
+    // parents and replies of the comments in this page can be in other pages
+    let comment_mappings = chapter_comments_mapping::table
+        .filter(
+            chapter_comments_mapping::parent_comment_id
+                .eq_any(&comment_ids)
+                .or(chapter_comments_mapping::child_comment_id.eq_any(&comment_ids)),
+        )
+        .select(ChapterCommentMapping::as_select())
+        .load::<ChapterCommentMapping>(&mut db)
+        .await?;
 
-    let mut comment_mappings_by_children = comments.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    let mut parent_ids: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut children_ids: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
 
-    for child in comment_mappings {
-        comment_mappings_by_children[id_indices[&child.child_comment_id]].push(child);
+    for mapping in comment_mappings {
+        parent_ids.insert(mapping.child_comment_id, mapping.parent_comment_id);
+        children_ids
+            .entry(mapping.parent_comment_id)
+            .or_default()
+            .push(mapping.child_comment_id);
     }
 
-    let comments = multizip((
-        comments,
-        users,
-        comment_mappings_by_parents,
-        comment_mappings_by_children,
-    ))
-    .map(
-        |(comment, user, comment_parent_mapping, comment_children_mapping)| {
-            ChapterCommentResponse {
-                id: comment.id,
-                chapter_id: chapter.id,
-                content: comment.content,
-                user: UserResponseBrief {
-                    id: user.id,
-                    displayname: user.displayname,
-                    username: user.username,
-                    email: user.email,
-                    role: user.role,
-                },
-                parent_comment: comment_children_mapping.get(0).map(|m| m.parent_comment_id),
-                child_comments_ids: comment_parent_mapping
-                    .iter()
-                    .map(|m| m.child_comment_id)
-                    .collect(),
-                child_comments: vec![],
-            }
-        },
-    )
-    .collect();
+    let comments = comments
+        .into_iter()
+        .zip(users)
+        .map(|(comment, user)| ChapterCommentResponse {
+            id: comment.id,
+            chapter_id: chapter.id,
+            content: comment.content,
+            user: UserResponseBrief {
+                id: user.id,
+                displayname: user.displayname,
+                username: user.username,
+                email: user.email,
+                role: user.role,
+            },
+            parent_comment: parent_ids.get(&comment.id).copied(),
+            child_comments_ids: children_ids.remove(&comment.id).unwrap_or_default(),
+            child_comments: vec![],
+        })
+        .collect();
 
-    Ok(Json(comments))
+    Ok(Json(Paginated::new(comments, next_cursor)))
 }
 
 #[utoipa::path(
@@ -135,6 +155,8 @@ pub async fn create_comment(
 ) -> Result<Json<ChapterCommentResponse>, ChapterCommentsError> {
     let mut db = state.pool.get().await?;
 
+    let user_id = auth.current_user.id;
+
     let comment = db
         .transaction::<_, ChapterCommentsError, _>(|transaction| {
             async move {
@@ -144,7 +166,7 @@ pub async fn create_comment(
                     created_at: Utc::now(),
                     updated_at: None,
                     chapter_id,
-                    user_id: auth.current_user.id,
+                    user_id,
                 };
 
                 let comment = diesel::insert_into(chapter_comments::table)
@@ -162,16 +184,56 @@ pub async fn create_comment(
                     child_comments: vec![],
                 };
 
-                if let Some(parent_comment_id) = payload.parent_comment_id {
-                    diesel::insert_into(chapter_comments_mapping::table)
-                        .values((
-                            chapter_comments_mapping::parent_comment_id.eq(parent_comment_id),
-                            chapter_comments_mapping::child_comment_id.eq(comment.id),
-                        ))
-                        .execute(transaction)
-                        .await?;
+                let (comic_id, author_id) = comic_chapters::table
+                    .find(chapter_id)
+                    .select((comic_chapters::comic_id, comic_chapters::user_id))
+                    .first::<(Uuid, Uuid)>(transaction)
+                    .await?;
+
+                let mut new_notifications = vec![];
+
+                let parent_author_id = match payload.parent_comment_id {
+                    Some(parent_comment_id) => {
+                        diesel::insert_into(chapter_comments_mapping::table)
+                            .values((
+                                chapter_comments_mapping::parent_comment_id.eq(parent_comment_id),
+                                chapter_comments_mapping::child_comment_id.eq(comment.id),
+                            ))
+                            .execute(transaction)
+                            .await?;
+
+                        let parent_author_id = chapter_comments::table
+                            .find(parent_comment_id)
+                            .select(chapter_comments::user_id)
+                            .first::<Uuid>(transaction)
+                            .await?;
+
+                        new_notifications.push(
+                            Notification::new(parent_author_id, NotificationKind::CommentReply)
+                                .actor(user_id)
+                                .comic(comic_id)
+                                .chapter(chapter_id)
+                                .comment(comment.id),
+                        );
+
+                        Some(parent_author_id)
+                    }
+                    None => None,
+                };
+
+                // authors replied to on their own chapter only get the reply
+                if parent_author_id != Some(author_id) {
+                    new_notifications.push(
+                        Notification::new(author_id, NotificationKind::ComicComment)
+                            .actor(user_id)
+                            .comic(comic_id)
+                            .chapter(chapter_id)
+                            .comment(comment.id),
+                    );
                 }
 
+                notify(transaction, new_notifications).await?;
+
                 Ok(commnet_response)
             }
             .scope_boxed()
@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    schema::{chapter_entitlements, subscription_tiers, subscriptions},
+    users::models::{UserResponseBrief, UserRole},
+};
+
+use super::models::Chapter;
+
+/// Tier levels of the user's current subscriptions, by author
+pub async fn subscription_levels(
+    db: &mut AsyncPgConnection,
+    user_id: Uuid,
+    author_ids: &[Uuid],
+) -> Result<HashMap<Uuid, i32>, diesel::result::Error> {
+    Ok(subscriptions::table
+        .inner_join(subscription_tiers::table)
+        .filter(subscriptions::user_id.eq(user_id))
+        .filter(subscriptions::author_id.eq_any(author_ids))
+        .filter(subscriptions::current_period_end.gt(Utc::now()))
+        .select((subscriptions::author_id, subscription_tiers::level))
+        .load::<(Uuid, i32)>(db)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+/// Returns the ids of the chapters `user` can see
+///
+/// released chapters are visible to everyone, unreleased ones only to their author, staff
+/// and subscribers of the author whose tier has early access
+pub async fn visible_chapters(
+    db: &mut AsyncPgConnection,
+    chapters: &[&Chapter],
+    user: Option<&UserResponseBrief>,
+) -> Result<HashSet<Uuid>, diesel::result::Error> {
+    let now = Utc::now();
+
+    let (mut visible, unreleased): (Vec<&Chapter>, Vec<&Chapter>) = chapters
+        .iter()
+        .copied()
+        .partition(|chapter| chapter.is_released(now));
+
+    if let Some(user) = user {
+        if matches!(user.role, UserRole::Admin | UserRole::Staff) {
+            return Ok(chapters.iter().map(|chapter| chapter.id).collect());
+        }
+
+        let (authored, unreleased): (Vec<&Chapter>, Vec<&Chapter>) = unreleased
+            .into_iter()
+            .partition(|chapter| chapter.user_id == user.id);
+
+        visible.extend(authored);
+
+        let early_access = unreleased
+            .into_iter()
+            .filter(|chapter| chapter.early_access_at.is_some())
+            .collect::<Vec<&Chapter>>();
+
+        if !early_access.is_empty() {
+            let levels = subscription_levels(
+                db,
+                user.id,
+                &early_access
+                    .iter()
+                    .map(|chapter| chapter.user_id)
+                    .collect::<Vec<Uuid>>(),
+            )
+            .await?;
+
+            visible.extend(early_access.into_iter().filter(|chapter| {
+                levels
+                    .get(&chapter.user_id)
+                    .is_some_and(|level| chapter.has_early_access(now, *level))
+            }));
+        }
+    }
+
+    Ok(visible.into_iter().map(|chapter| chapter.id).collect())
+}
+
+/// Whether `user` can see `chapter`
+pub async fn is_chapter_visible(
+    db: &mut AsyncPgConnection,
+    chapter: &Chapter,
+    user: Option<&UserResponseBrief>,
+) -> Result<bool, diesel::result::Error> {
+    Ok(visible_chapters(db, &[chapter], user)
+        .await?
+        .contains(&chapter.id))
+}
+
+/// Returns the ids of the chapters whose pages `user` can view
+///
+/// only visible chapters are accessible, unlocked ones by everyone and locked ones
+/// only by their author, staff and users with an entitlement
+pub async fn accessible_chapters(
+    db: &mut AsyncPgConnection,
+    chapters: &[&Chapter],
+    user: Option<&UserResponseBrief>,
+) -> Result<HashSet<Uuid>, diesel::result::Error> {
+    let now = Utc::now();
+
+    let visible = visible_chapters(db, chapters, user).await?;
+
+    let chapters = chapters
+        .iter()
+        .copied()
+        .filter(|chapter| visible.contains(&chapter.id))
+        .collect::<Vec<&Chapter>>();
+
+    let (mut accessible, locked): (Vec<&Chapter>, Vec<&Chapter>) = chapters
+        .iter()
+        .copied()
+        .partition(|chapter| !chapter.is_locked(now));
+
+    let Some(user) = user else {
+        return Ok(accessible.into_iter().map(|chapter| chapter.id).collect());
+    };
+
+    if matches!(user.role, UserRole::Admin | UserRole::Staff) {
+        return Ok(chapters.iter().map(|chapter| chapter.id).collect());
+    }
+
+    let (authored, locked): (Vec<&Chapter>, Vec<&Chapter>) = locked
+        .into_iter()
+        .partition(|chapter| chapter.user_id == user.id);
+
+    accessible.extend(authored);
+
+    let mut accessible = accessible
+        .into_iter()
+        .map(|chapter| chapter.id)
+        .collect::<HashSet<Uuid>>();
+
+    if !locked.is_empty() {
+        accessible.extend(
+            chapter_entitlements::table
+                .filter(chapter_entitlements::user_id.eq(user.id))
+                .filter(
+                    chapter_entitlements::chapter_id.eq_any(
+                        locked
+                            .iter()
+                            .map(|chapter| chapter.id)
+                            .collect::<Vec<Uuid>>(),
+                    ),
+                )
+                .select(chapter_entitlements::chapter_id)
+                .load::<Uuid>(db)
+                .await?,
+        );
+    }
+
+    Ok(accessible)
+}
+
+/// Whether `user` can view the pages of `chapter`
+pub async fn has_chapter_access(
+    db: &mut AsyncPgConnection,
+    chapter: &Chapter,
+    user: Option<&UserResponseBrief>,
+) -> Result<bool, diesel::result::Error> {
+    Ok(accessible_chapters(db, &[chapter], user)
+        .await?
+        .contains(&chapter.id))
+}
@@ -1,4 +1,5 @@
 pub mod chapter_comments;
+pub mod entitlements;
 pub mod models;
 pub mod routes;
 mod utils;
@@ -7,19 +8,18 @@ use axum::{http::StatusCode, response::IntoResponse};
 use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
 use serde::Deserialize;
 use utoipa::IntoParams;
-use uuid::Uuid;
 
-use crate::{ErrorResponse, SortingOrder};
+use crate::{common::pagination::InvalidCursor, ErrorResponse, SortingOrder};
 // use tracing::debug;
 
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct ChaptersParams {
-    #[serde(default = "Uuid::nil")]
-    pub min_id: Uuid,
-    #[serde(default = "Uuid::max")]
-    pub max_id: Uuid,
+    /// order of the chapters' average ratings, chapters are ordered by number without it
     #[serde(default)]
     pub sorting: Option<SortingOrder>,
+    /// order of the chapter numbers when they aren't sorted by rating, ascending by default
+    #[serde(default)]
+    pub order: Option<SortingOrder>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -30,6 +30,9 @@ pub enum ChaptersError {
     #[error("chapter not found")]
     ChapterNotFound,
 
+    #[error("mature content is only available to verified adults")]
+    MatureContent,
+
     #[error("bad request")]
     BadRequest,
 
@@ -47,6 +50,15 @@ pub enum ChaptersError {
 
     #[error("{0}")]
     Conflict(String),
+
+    #[error("{0}")]
+    InvalidUpload(String),
+
+    #[error("upload has expired")]
+    UploadExpired,
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
 }
 
 impl IntoResponse for ChaptersError {
@@ -54,6 +66,14 @@ impl IntoResponse for ChaptersError {
         tracing::error!("{:#?}", self);
 
         match self {
+            ChaptersError::MatureContent => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
             ChaptersError::ChapterNotFound => (
                 StatusCode::NOT_FOUND,
                 ErrorResponse {
@@ -86,6 +106,22 @@ impl IntoResponse for ChaptersError {
                 },
             )
                 .into_response(),
+            ChaptersError::InvalidUpload(_) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
+            ChaptersError::UploadExpired => (
+                StatusCode::GONE,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
             ChaptersError::Diesel(diesel_error) => match diesel_error {
                 diesel::result::Error::NotFound => StatusCode::NOT_FOUND.into_response(),
                 DatabaseError(DatabaseErrorKind::UniqueViolation, message) => {
@@ -122,8 +158,30 @@ impl IntoResponse for ChaptersError {
                         )
                             .into_response();
                     }
+                    if let Some("chapter_entitlements_user_id_fkey") = constraint_name {
+                        return (
+                            StatusCode::NOT_FOUND,
+                            ErrorResponse {
+                                error: String::from("user not found"),
+                                ..Default::default()
+                            },
+                        )
+                            .into_response();
+                    }
                     StatusCode::INTERNAL_SERVER_ERROR.into_response()
                 }
+                DatabaseError(DatabaseErrorKind::CheckViolation, message)
+                    if message.constraint_name() == Some("comic_chapters_early_access_check") =>
+                {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        ErrorResponse {
+                            error: String::from("early access needs both a date and a tier level"),
+                            ..Default::default()
+                        },
+                    )
+                        .into_response()
+                }
                 diesel::result::Error::QueryBuilderError(message) => {
                     let message = message.to_string();
 
@@ -161,6 +219,7 @@ impl IntoResponse for ChaptersError {
                 },
             )
                 .into_response(),
+            ChaptersError::InvalidCursor(err) => err.into_response(),
         }
     }
 }
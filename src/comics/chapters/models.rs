@@ -10,11 +10,13 @@ use uuid::Uuid;
 
 use crate::{
     comics::models::Comic,
-    common::models::ImageMetadataResponse,
-    schema::{chapter_pages, chapter_ratings, comic_chapters},
+    common::models::{ImageMetadataResponse, RatingSummaryResponse},
+    s3::signing::ImageSigner,
+    schema::{
+        chapter_entitlements, chapter_page_uploads, chapter_pages, chapter_ratings, comic_chapters,
+    },
     users::models::User,
-    utils::average_rating,
-    Rating,
+    utils::{average_rating, double_option},
 };
 
 #[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, PartialEq)]
@@ -33,13 +35,63 @@ pub struct Chapter {
     pub is_visible: bool,
     pub user_id: Uuid,
     pub comic_id: Uuid,
+    /// price to unlock the chapter, 0 means it's free
+    pub price: i32,
+    /// the chapter is free for everyone after this date
+    pub unlocks_at: Option<DateTime<chrono::Utc>>,
+    /// subscribers can read the chapter from this date until it's published
+    pub early_access_at: Option<DateTime<chrono::Utc>>,
+    /// lowest tier level of the author that gets the early access
+    pub early_access_level: Option<i32>,
+    pub rating_sum: f64,
+    pub rating_count: i64,
+}
+
+/// Sort key of the last chapter of a page, it's encoded in the cursor with the chapter's id
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChapterOrder {
+    Number(i32),
+    Rating(f64),
 }
 
 impl Chapter {
+    /// Whether the chapter is visible to everyone
+    ///
+    /// chapters without a publish date are released right away
+    pub fn is_released(&self, now: DateTime<chrono::Utc>) -> bool {
+        self.published_at
+            .map_or(true, |published_at| published_at <= now)
+    }
+
+    /// Whether a subscriber of the author with a tier of `level` can read the chapter before its release
+    pub fn has_early_access(&self, now: DateTime<chrono::Utc>, level: i32) -> bool {
+        match (self.early_access_at, self.early_access_level) {
+            (Some(early_access_at), Some(early_access_level)) => {
+                early_access_at <= now && early_access_level <= level
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the chapter's pages can only be viewed by users who unlocked it
+    ///
+    /// a chapter with an unlock date is locked until then, even if it's free,
+    /// otherwise it's locked if it has a price
+    pub fn is_locked(&self, now: DateTime<chrono::Utc>) -> bool {
+        match self.unlocks_at {
+            Some(unlocks_at) => unlocks_at > now,
+            None => self.price > 0,
+        }
+    }
+
     pub fn into_response(
         self,
         chapter_pages: Vec<ChapterPage>,
-        chapter_ratings: Vec<ChapterRating>,
+        ratings: RatingSummaryResponse,
+        image_signer: &ImageSigner,
+        viewer: Option<Uuid>,
+        has_access: bool,
     ) -> ChapterResponse {
         ChapterResponse {
             id: self.id,
@@ -47,41 +99,53 @@ impl Chapter {
             number: self.number,
             description: self.description,
             created_at: self.created_at,
+            locked: !has_access,
+            price: self.price,
+            unlocks_at: self.unlocks_at,
+            published_at: self.published_at,
+            early_access_at: self.early_access_at,
+            early_access_level: self.early_access_level,
             pages: chapter_pages
                 .into_iter()
                 .map(|page| ChapterPageResponse {
                     id: page.id,
                     number: page.number,
                     description: page.description,
-                    image: ImageMetadataResponse {
-                        content_type: page.content_type,
-                        path: page.path,
-                    },
+                    image: image_signer.image_metadata(page.path, page.content_type, viewer),
                 })
                 .collect(),
-            rating: average_rating(chapter_ratings),
+            rating: average_rating(self.rating_sum, self.rating_count),
+            ratings,
             author_id: self.user_id,
             comic_id: self.comic_id,
+            read: false,
         }
     }
 
-    pub fn into_response_brief(self, chapter_pages: Vec<ChapterPage>) -> ChapterResponseBrief {
+    pub fn into_response_brief(
+        self,
+        chapter_pages: Vec<ChapterPage>,
+        image_signer: &ImageSigner,
+        viewer: Option<Uuid>,
+    ) -> ChapterResponseBrief {
         ChapterResponseBrief {
             id: self.id,
             title: self.title,
             number: self.number,
             description: self.description,
             created_at: self.created_at,
+            price: self.price,
+            unlocks_at: self.unlocks_at,
+            published_at: self.published_at,
+            early_access_at: self.early_access_at,
+            early_access_level: self.early_access_level,
             pages: chapter_pages
                 .into_iter()
                 .map(|page| ChapterPageResponseBrief {
                     id: page.id,
                     number: page.number,
                     description: page.description,
-                    image: ImageMetadataResponse {
-                        content_type: page.content_type,
-                        path: page.path,
-                    },
+                    image: image_signer.image_metadata(page.path, page.content_type, viewer),
                 })
                 .collect(),
         }
@@ -121,10 +185,22 @@ pub struct ChapterRating {
     pub chapter_id: Uuid,
 }
 
-impl Rating for ChapterRating {
-    fn rating(&self) -> f64 {
-        self.rating
-    }
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(Chapter))]
+#[diesel(primary_key(user_id, chapter_id))]
+#[diesel(table_name = chapter_entitlements)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterEntitlement {
+    pub user_id: Uuid,
+    pub chapter_id: Uuid,
+    pub created_at: DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateChapterEntitlement {
+    pub user_id: Uuid,
 }
 
 #[derive(Deserialize, ToSchema, Debug, TS)]
@@ -133,6 +209,12 @@ pub struct CreateChapter {
     pub title: String,
     pub description: Option<String>,
     pub number: i32,
+    pub price: Option<i32>,
+    pub unlocks_at: Option<DateTime<chrono::Utc>>,
+    /// the chapter is hidden from everyone but its author and early access subscribers until then
+    pub published_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_level: Option<i32>,
 }
 
 #[derive(AsChangeset, Deserialize, ToSchema, Debug, TS)]
@@ -143,6 +225,15 @@ pub struct UpdateChapter {
     pub title: Option<String>,
     pub description: Option<String>,
     pub number: Option<i32>,
+    pub price: Option<i32>,
+    /// `null` removes the unlock date, the chapter is then locked by its price only
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<DateTime<chrono::Utc>>)]
+    #[ts(type = "string | null")]
+    pub unlocks_at: Option<Option<DateTime<chrono::Utc>>>,
+    pub published_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_level: Option<i32>,
 }
 
 #[derive(AsChangeset, Deserialize, ToSchema, Debug, TS)]
@@ -162,10 +253,27 @@ pub struct ChapterResponse {
     pub comic_id: Uuid,
     pub title: String,
     pub rating: f64,
+    pub ratings: RatingSummaryResponse,
     pub number: i32,
     pub description: Option<String>,
     pub pages: Vec<ChapterPageResponse>,
     pub created_at: DateTime<chrono::Utc>,
+    /// the pages' images can't be viewed until the chapter is unlocked
+    pub locked: bool,
+    pub price: i32,
+    pub unlocks_at: Option<DateTime<chrono::Utc>>,
+    pub published_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_level: Option<i32>,
+    /// the current user has read the chapter
+    pub read: bool,
+}
+
+impl ChapterResponse {
+    pub fn with_read(mut self, read: bool) -> Self {
+        self.read = read;
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
@@ -177,6 +285,11 @@ pub struct ChapterResponseBrief {
     pub description: Option<String>,
     pub pages: Vec<ChapterPageResponseBrief>,
     pub created_at: DateTime<chrono::Utc>,
+    pub price: i32,
+    pub unlocks_at: Option<DateTime<chrono::Utc>>,
+    pub published_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_at: Option<DateTime<chrono::Utc>>,
+    pub early_access_level: Option<i32>,
 }
 
 #[derive(ToSchema)]
@@ -204,6 +317,50 @@ impl ChapterPageData {
     }
 }
 
+/// A chapter page waiting for its image to be uploaded directly to storage
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Comic))]
+#[diesel(belongs_to(Chapter))]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = chapter_page_uploads)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterPageUpload {
+    pub id: Uuid,
+    pub number: i32,
+    pub description: Option<String>,
+    pub content_type: String,
+    pub content_length: i64,
+    pub comic_id: Uuid,
+    pub chapter_id: Uuid,
+    pub user_id: Uuid,
+    pub storage_upload_id: Uuid,
+    pub created_at: DateTime<chrono::Utc>,
+    pub expires_at: DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateChapterPageUpload {
+    pub number: i32,
+    pub description: Option<String>,
+    pub file_name: String,
+    pub content_type: String,
+    pub content_length: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct ChapterPageUploadResponse {
+    pub upload_id: Uuid,
+    /// presigned url the image has to be `PUT` to
+    pub url: String,
+    /// headers that must be sent as is with the upload request
+    pub content_type: String,
+    pub content_length: i64,
+    /// the upload has to be completed before this, the url expires earlier
+    pub expires_at: DateTime<chrono::Utc>,
+}
+
 #[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
 #[ts(export)]
 pub struct ChapterPageResponse {
@@ -225,6 +382,6 @@ pub struct ChapterPageResponseBrief {
 #[derive(garde::Validate, Serialize, Deserialize, ToSchema, TS, Debug)]
 #[ts(export)]
 pub struct NewChapterRating {
-    #[garde(range(min = 0, max = 10))]
+    #[garde(range(min = 0, max = 5))]
     pub rating: i32,
 }
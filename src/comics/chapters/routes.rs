@@ -1,4 +1,4 @@
-use std::{io::SeekFrom, sync::Arc};
+use std::{io::SeekFrom, sync::Arc, time::Duration};
 
 use axum::{
     extract::{DefaultBodyLimit, Multipart, Path, Query, State},
@@ -6,9 +6,13 @@ use axum::{
     Json, Router,
 };
 use chrono::Utc;
+use diesel::dsl::sql;
+use diesel::result::Error::NotFound;
+use diesel::sql_types::Double;
 use diesel::BelongingToDsl;
 use diesel::GroupedBy;
 use diesel::NullableExpressionMethods;
+use diesel::OptionalExtension;
 use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
 use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use futures::TryStreamExt;
@@ -30,30 +34,50 @@ use crate::{
         models::{ChapterPage, ChapterRating, NewChapterRating, UpdateChapterPage},
         ChaptersParams,
     },
-    common::models::ImageMetadataResponse,
-    s3::Upload,
-    schema::{chapter_pages, chapter_ratings, comic_chapters, comics, users},
-    users::models::UserRole,
+    comics::models::ContentRating,
+    common::pagination::{InvalidCursor, Paginated, PaginationParams},
+    library::utils::read_chapters,
+    s3::{
+        models::StorageUpload,
+        uploads::{commit_upload, discard_upload, promote_upload, reserve_upload, stage_upload},
+        Upload,
+    },
+    schema::{
+        chapter_entitlements, chapter_page_uploads, chapter_pages, chapter_ratings, comic_chapters,
+        comics, storage_uploads, users,
+    },
+    users::{models::UserRole, utils::can_view_mature_content},
+    utils::average_rating,
     AppState, InnerAppState, SortingOrder,
 };
 
 use super::{
     chapter_comments::routes::chapter_comments_router,
+    entitlements::{
+        accessible_chapters, has_chapter_access, is_chapter_visible, subscription_levels,
+    },
     models::{
-        Chapter, ChapterPageData, ChapterPageResponse, ChapterResponse, ChapterResponseBrief,
-        CreateChapter, UpdateChapter,
+        Chapter, ChapterEntitlement, ChapterOrder, ChapterPageData, ChapterPageResponse,
+        ChapterPageUpload, ChapterPageUploadResponse, ChapterResponse, ChapterResponseBrief,
+        CreateChapter, CreateChapterEntitlement, CreateChapterPageUpload, UpdateChapter,
     },
-    utils::box_error,
+    utils::{box_error, chapter_rating_summaries},
     ChaptersError,
 };
 
 use uuid::Uuid;
 
-const ALLOWED_MIME_TYPES: [&str; 3] = ["image/jpeg", "image/jpg", "image/png"];
+pub const ALLOWED_MIME_TYPES: [&str; 3] = ["image/jpeg", "image/jpg", "image/png"];
 
 pub const FILE_SIZE_LIMIT_MB: usize = 10;
 
-const FILE_SIZE_LIMIT: usize = FILE_SIZE_LIMIT_MB * 1024 * 1024; // 10mb
+pub const FILE_SIZE_LIMIT: usize = FILE_SIZE_LIMIT_MB * 1024 * 1024; // 10mb
+
+const UPLOAD_URL_EXPIRY_MINUTES: i64 = 15;
+
+/// A `PUT` started right before the url expired still has time to finish and be completed,
+/// has to stay below `STAGED_UPLOAD_TTL_MINUTES` so the staged object isn't discarded first
+const UPLOAD_COMPLETION_EXPIRY_MINUTES: i64 = UPLOAD_URL_EXPIRY_MINUTES + 15;
 
 pub fn chapters_router() -> Router<AppState> {
     Router::new()
@@ -74,6 +98,22 @@ pub fn chapters_router() -> Router<AppState> {
             "/:comic_id/chapters/:chapter_id/pages",
             post(create_chapter_page),
         )
+        .route(
+            "/:comic_id/chapters/:chapter_id/pages/uploads",
+            post(create_chapter_page_upload),
+        )
+        .route(
+            "/chapters/pages/uploads/:upload_id/complete",
+            post(complete_chapter_page_upload),
+        )
+        .route(
+            "/chapters/:chapter_id/entitlements",
+            post(create_chapter_entitlement),
+        )
+        .route(
+            "/chapters/:chapter_id/entitlements/:user_id",
+            delete(delete_chapter_entitlement),
+        )
         .route("/chapters/pages/:chapter_page_id", put(update_chapter_page))
         .route(
             "/chapters/pages/:chapter_page_id",
@@ -100,6 +140,12 @@ pub async fn create_chapter(
     Path(comic_id): Path<Uuid>,
     Json(payload): Json<CreateChapter>,
 ) -> Result<Json<ChapterResponseBrief>, ChaptersError> {
+    if matches!(payload.price, Some(price) if price < 0)
+        || payload.early_access_at.is_some() != payload.early_access_level.is_some()
+    {
+        return Err(ChaptersError::BadRequest);
+    }
+
     let mut db = state.pool.get().await?;
 
     let chapter = Chapter {
@@ -111,17 +157,41 @@ pub async fn create_chapter(
         description: payload.description,
         created_at: Utc::now(),
         updated_at: None,
-        published_at: None,
+        published_at: payload.published_at,
         is_visible: false,
+        price: payload.price.unwrap_or(0),
+        unlocks_at: payload.unlocks_at,
+        early_access_at: payload.early_access_at,
+        early_access_level: payload.early_access_level,
+        rating_sum: 0.0,
+        rating_count: 0,
     };
 
-    let chapter = diesel::insert_into(comic_chapters::table)
-        .values(&chapter)
-        .returning(Chapter::as_returning())
-        .get_result::<Chapter>(&mut db)
+    let chapter = db
+        .transaction::<_, ChaptersError, _>(|transaction| {
+            async move {
+                let chapter = diesel::insert_into(comic_chapters::table)
+                    .values(&chapter)
+                    .returning(Chapter::as_returning())
+                    .get_result::<Chapter>(transaction)
+                    .await?;
+
+                diesel::update(comics::table.find(chapter.comic_id))
+                    .set(comics::chapters_count.eq(comics::chapters_count + 1))
+                    .execute(transaction)
+                    .await?;
+
+                Ok(chapter)
+            }
+            .scope_boxed()
+        })
         .await?;
 
-    Ok(Json(chapter.into_response_brief(vec![])))
+    Ok(Json(chapter.into_response_brief(
+        vec![],
+        &state.image_signer,
+        Some(auth.current_user.id),
+    )))
 }
 
 #[derive(Deserialize)]
@@ -149,8 +219,6 @@ pub async fn create_chapter_page(
     Path(path_params): Path<ChapterPagePathParams>,
     mut fields: Multipart,
 ) -> Result<Json<ChapterPageResponse>, ChaptersError> {
-    let mut db = state.pool.get().await?;
-
     let mut chapter_page = ChapterPageData::builder();
     let mut upload = Upload::builder();
     let mut content_length: i64 = 0;
@@ -252,8 +320,25 @@ pub async fn create_chapter_page(
         ChaptersError::BadRequest
     })?;
 
-    let new_chapter_page = {
-        let state = state.clone();
+    // upload the image to a staging key outside of the transaction,
+    // it gets promoted to its final path once the chapter page is committed
+    tracing::debug!("staging chapter page image");
+    let staged_upload = stage_upload(
+        &state,
+        StorageUpload::new(auth.current_user.id, upload.path, upload.content_type),
+        upload.stream,
+        content_length,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!("failed to stage chapter page image: {:#?}", err);
+        ChaptersError::InternalServerError
+    })?;
+
+    let mut db = state.pool.get().await?;
+
+    let transaction_result = {
+        let staged_upload = staged_upload.clone();
         db.transaction::<_, ChaptersError, _>(|transaction| {
             async move {
                 // save chapter page to db
@@ -264,8 +349,8 @@ pub async fn create_chapter_page(
                     chapter_id: path_params.chapter_id,
                     number: chapter_page.number,
                     description: chapter_page.description,
-                    path: upload.path,
-                    content_type: upload.content_type,
+                    path: staged_upload.staging_path,
+                    content_type: staged_upload.content_type,
                     created_at: Utc::now(),
                     updated_at: None,
                 };
@@ -275,47 +360,276 @@ pub async fn create_chapter_page(
                     .execute(transaction)
                     .await?;
 
-                // upload image to s3
-                tracing::debug!("uploading chapter page image");
-                if let Err(err) = state
-                    .storage
-                    .put(
-                        &chapter_page.path,
-                        upload.stream,
-                        content_length,
-                        &chapter_page.content_type,
-                    )
-                    .await
-                    .map_err(|err| {
-                        tracing::debug!("s3 error: {:#?}", err);
-                        ChaptersError::InternalServerError
-                    })
-                {
-                    tracing::debug!("failed to upload to s3 rolling back");
-                    return Err(err);
-                }
+                commit_upload(transaction, staged_upload.id).await?;
 
                 Ok(chapter_page)
             }
             .scope_boxed()
         })
-        // TODO: error handling
-        .await?
+        .await
     };
 
+    let mut new_chapter_page = match transaction_result {
+        Ok(chapter_page) => chapter_page,
+        Err(err) => {
+            if let Err(err) = discard_upload(&state, &staged_upload).await {
+                // the storage cleanup worker will discard it once it's stale
+                tracing::error!("failed to discard staged upload: {:#?}", err);
+            }
+            return Err(err);
+        }
+    };
+
+    // the page is served from the staging key until the upload is promoted
+    match promote_upload(&state, &staged_upload).await {
+        Ok(true) => new_chapter_page.path = staged_upload.path.clone(),
+        Ok(false) => {}
+        // the upload is committed, the storage cleanup worker will retry promoting it
+        Err(err) => tracing::error!("failed to promote chapter page image: {:#?}", err),
+    }
+
     let chapter_page = ChapterPageResponse {
         id: new_chapter_page.id,
         number: new_chapter_page.number,
         description: new_chapter_page.description,
-        image: ImageMetadataResponse {
-            path: new_chapter_page.path,
-            content_type: new_chapter_page.content_type,
-        },
+        image: state.image_signer.image_metadata(
+            new_chapter_page.path,
+            new_chapter_page.content_type,
+            Some(auth.current_user.id),
+        ),
     };
 
     Ok(Json(chapter_page))
 }
 
+/// Create a direct upload for a chapter page
+///
+/// Returns a presigned url the client has to `PUT` the image to,
+/// the chapter page is created once the upload is completed
+#[utoipa::path(
+    post,
+    path = "/api/v1/comics/:comic_id/chapters/:chapter_id/pages/uploads",
+    request_body(content = CreateChapterPageUpload, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Upload created", body = ChapterPageUploadResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Unsupported image type or size", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified chapter not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Chapters API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn create_chapter_page_upload(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(path_params): Path<ChapterPagePathParams>,
+    Json(payload): Json<CreateChapterPageUpload>,
+) -> Result<Json<ChapterPageUploadResponse>, ChaptersError> {
+    if !ALLOWED_MIME_TYPES.contains(&payload.content_type.as_str()) {
+        tracing::error!("wrong image type");
+        return Err(ChaptersError::BadRequest);
+    }
+
+    if payload.content_length <= 0 {
+        return Err(ChaptersError::BadRequest);
+    }
+
+    if payload.content_length > FILE_SIZE_LIMIT as i64 {
+        return Err(ChaptersError::ImageTooLarge);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    // only the author can add pages to the chapter
+    comic_chapters::table
+        .filter(comic_chapters::id.eq(path_params.chapter_id))
+        .filter(comic_chapters::comic_id.eq(path_params.comic_id))
+        .filter(comic_chapters::user_id.eq(auth.current_user.id))
+        .select(comic_chapters::id)
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => ChaptersError::ChapterNotFound,
+            e => e.into(),
+        })?;
+
+    let storage_upload = StorageUpload::new(
+        auth.current_user.id,
+        format!("{}_{}", Uuid::now_v7(), payload.file_name.replace('/', "_")),
+        payload.content_type,
+    );
+
+    let url = state
+        .storage
+        .presign_put(
+            &storage_upload.staging_path,
+            payload.content_length,
+            &storage_upload.content_type,
+            Duration::from_secs(UPLOAD_URL_EXPIRY_MINUTES as u64 * 60),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!("failed to presign chapter page upload: {:#?}", err);
+            ChaptersError::InternalServerError
+        })?;
+
+    let page_upload = db
+        .transaction::<_, ChaptersError, _>(|transaction| {
+            async move {
+                let storage_upload = reserve_upload(transaction, &storage_upload).await?;
+
+                let page_upload = ChapterPageUpload {
+                    id: Uuid::now_v7(),
+                    number: payload.number,
+                    description: payload.description,
+                    content_type: storage_upload.content_type,
+                    content_length: payload.content_length,
+                    comic_id: path_params.comic_id,
+                    chapter_id: path_params.chapter_id,
+                    user_id: auth.current_user.id,
+                    storage_upload_id: storage_upload.id,
+                    created_at: Utc::now(),
+                    expires_at: Utc::now()
+                        + chrono::Duration::minutes(UPLOAD_COMPLETION_EXPIRY_MINUTES),
+                };
+
+                let page_upload = diesel::insert_into(chapter_page_uploads::table)
+                    .values(&page_upload)
+                    .returning(ChapterPageUpload::as_returning())
+                    .get_result::<ChapterPageUpload>(transaction)
+                    .await?;
+
+                Ok(page_upload)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(Json(ChapterPageUploadResponse {
+        upload_id: page_upload.id,
+        url,
+        content_type: page_upload.content_type,
+        content_length: page_upload.content_length,
+        expires_at: page_upload.expires_at,
+    }))
+}
+
+/// Complete a direct chapter page upload
+///
+/// Verifies the uploaded image and creates the chapter page
+#[utoipa::path(
+    post,
+    path = "/api/v1/comics/chapters/pages/uploads/:upload_id/complete",
+    responses(
+        (status = 200, description = "Chapter page successfully created", body = ChapterPageResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Image is missing or doesn't match the upload", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified upload not found", body = ErrorResponse),
+        (status = StatusCode::GONE, description = "Upload has expired", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Chapters API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn complete_chapter_page_upload(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<Json<ChapterPageResponse>, ChaptersError> {
+    let (page_upload, storage_upload) = {
+        let mut db = state.pool.get().await?;
+
+        chapter_page_uploads::table
+            .inner_join(storage_uploads::table)
+            .filter(chapter_page_uploads::id.eq(upload_id))
+            .filter(chapter_page_uploads::user_id.eq(auth.current_user.id))
+            .select((ChapterPageUpload::as_select(), StorageUpload::as_select()))
+            .first::<(ChapterPageUpload, StorageUpload)>(&mut db)
+            .await?
+    };
+
+    if page_upload.expires_at < Utc::now() {
+        return Err(ChaptersError::UploadExpired);
+    }
+
+    let metadata = state
+        .storage
+        .head(&storage_upload.staging_path)
+        .await
+        .map_err(|err| {
+            tracing::error!("failed to get uploaded chapter page metadata: {:#?}", err);
+            ChaptersError::InternalServerError
+        })?
+        .ok_or_else(|| ChaptersError::InvalidUpload(String::from("image was not uploaded")))?;
+
+    if metadata.content_length != page_upload.content_length {
+        return Err(ChaptersError::InvalidUpload(String::from(
+            "uploaded image size doesn't match the upload",
+        )));
+    }
+
+    if metadata.content_type.as_deref() != Some(page_upload.content_type.as_str()) {
+        return Err(ChaptersError::InvalidUpload(String::from(
+            "uploaded image type doesn't match the upload",
+        )));
+    }
+
+    let mut db = state.pool.get().await?;
+
+    let mut new_chapter_page = {
+        let storage_upload = storage_upload.clone();
+        db.transaction::<_, ChaptersError, _>(|transaction| {
+            async move {
+                let chapter_page = ChapterPage {
+                    id: Uuid::now_v7(),
+                    user_id: page_upload.user_id,
+                    comic_id: page_upload.comic_id,
+                    chapter_id: page_upload.chapter_id,
+                    number: page_upload.number,
+                    description: page_upload.description,
+                    path: storage_upload.staging_path,
+                    content_type: page_upload.content_type,
+                    created_at: Utc::now(),
+                    updated_at: None,
+                };
+
+                diesel::insert_into(chapter_pages::table)
+                    .values(&chapter_page)
+                    .execute(transaction)
+                    .await?;
+
+                commit_upload(transaction, storage_upload.id).await?;
+
+                diesel::delete(chapter_page_uploads::table.find(page_upload.id))
+                    .execute(transaction)
+                    .await?;
+
+                Ok(chapter_page)
+            }
+            .scope_boxed()
+        })
+        .await?
+    };
+
+    // the page is served from the staging key until the upload is promoted
+    match promote_upload(&state, &storage_upload).await {
+        Ok(true) => new_chapter_page.path = storage_upload.path.clone(),
+        Ok(false) => {}
+        // the upload is committed, the storage cleanup worker will retry promoting it
+        Err(err) => tracing::error!("failed to promote chapter page image: {:#?}", err),
+    }
+
+    Ok(Json(ChapterPageResponse {
+        id: new_chapter_page.id,
+        number: new_chapter_page.number,
+        description: new_chapter_page.description,
+        image: state.image_signer.image_metadata(
+            new_chapter_page.path,
+            new_chapter_page.content_type,
+            Some(auth.current_user.id),
+        ),
+    }))
+}
+
 /// Update chapter page
 #[utoipa::path(
     put,
@@ -356,13 +670,14 @@ pub async fn update_chapter_page(
     responses(
         (status = 200, description = "Get chapter", body = ChapterResponse),
         (status = StatusCode::NOT_FOUND, description = "Specified chapter not found", body = ErrorResponse),
+        (status = StatusCode::FORBIDDEN, description = "Comic is rated mature and caller isn't a verified adult", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Chapters API"
 )]
 #[axum::debug_handler(state = AppState)]
 pub async fn get_chapter(
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
     State(state): State<Arc<InnerAppState>>,
     Path(chapter_id): Path<Uuid>,
 ) -> Result<Json<ChapterResponse>, ChaptersError> {
@@ -370,19 +685,53 @@ pub async fn get_chapter(
 
     let chapter = comic_chapters::table
         .find(chapter_id)
+        .select(Chapter::as_select())
         .first::<Chapter>(&mut db)
         .await?;
 
+    // unreleased chapters are hidden from everyone without early access
+    if !is_chapter_visible(&mut db, &chapter, Some(&auth.current_user)).await? {
+        return Err(ChaptersError::ChapterNotFound);
+    }
+
+    let content_rating = comics::table
+        .find(chapter.comic_id)
+        .select(comics::content_rating)
+        .first::<ContentRating>(&mut db)
+        .await?;
+
+    if content_rating == ContentRating::Mature
+        && chapter.user_id != auth.current_user.id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        return Err(ChaptersError::MatureContent);
+    }
+
     let chapter_pages = ChapterPage::belonging_to(&chapter)
         .order(chapter_pages::number.asc())
         .load::<ChapterPage>(&mut db)
         .await?;
 
-    let chapter_ratings = ChapterRating::belonging_to(&chapter)
-        .load::<ChapterRating>(&mut db)
-        .await?;
+    let ratings = chapter_rating_summaries(
+        &mut db,
+        &[chapter.id],
+        Some(auth.current_user.id),
+        state.rating_prior,
+    )
+    .await?
+    .remove(&chapter.id)
+    .unwrap_or_default();
+
+    // pages are listed either way, their images are refused by get_image without access
+    let has_access = has_chapter_access(&mut db, &chapter, Some(&auth.current_user)).await?;
 
-    let chapter = chapter.into_response(chapter_pages, chapter_ratings);
+    let chapter = chapter.into_response(
+        chapter_pages,
+        ratings,
+        &state.image_signer,
+        Some(auth.current_user.id),
+        has_access,
+    );
 
     Ok(Json(chapter))
 }
@@ -394,13 +743,14 @@ pub async fn get_chapter(
     responses(
         (status = 200, description = "Get chapter", body = ChapterResponse),
         (status = StatusCode::NOT_FOUND, description = "Specified chapter not found", body = ErrorResponse),
+        (status = StatusCode::FORBIDDEN, description = "Comic is rated mature and caller isn't a verified adult", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Chapters API"
 )]
 #[axum::debug_handler(state = AppState)]
 pub async fn get_chapter_by_slug(
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
     State(state): State<Arc<InnerAppState>>,
     Path((username, slug, chapter_number)): Path<(String, String, i32)>,
 ) -> Result<Json<ChapterResponse>, ChaptersError> {
@@ -416,19 +766,53 @@ pub async fn get_chapter_by_slug(
     let chapter = comic_chapters::table
         .filter(comic_chapters::comic_id.nullable().eq(comic_id))
         .filter(comic_chapters::number.eq(chapter_number))
+        .select(Chapter::as_select())
         .first::<Chapter>(&mut db)
         .await?;
 
+    // unreleased chapters are hidden from everyone without early access
+    if !is_chapter_visible(&mut db, &chapter, Some(&auth.current_user)).await? {
+        return Err(ChaptersError::ChapterNotFound);
+    }
+
+    let content_rating = comics::table
+        .find(chapter.comic_id)
+        .select(comics::content_rating)
+        .first::<ContentRating>(&mut db)
+        .await?;
+
+    if content_rating == ContentRating::Mature
+        && chapter.user_id != auth.current_user.id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        return Err(ChaptersError::MatureContent);
+    }
+
     let chapter_pages = ChapterPage::belonging_to(&chapter)
         .order(chapter_pages::number.asc())
         .load::<ChapterPage>(&mut db)
         .await?;
 
-    let chapter_ratings = ChapterRating::belonging_to(&chapter)
-        .load::<ChapterRating>(&mut db)
-        .await?;
+    let ratings = chapter_rating_summaries(
+        &mut db,
+        &[chapter.id],
+        Some(auth.current_user.id),
+        state.rating_prior,
+    )
+    .await?
+    .remove(&chapter.id)
+    .unwrap_or_default();
+
+    // pages are listed either way, their images are refused by get_image without access
+    let has_access = has_chapter_access(&mut db, &chapter, Some(&auth.current_user)).await?;
 
-    let chapter = chapter.into_response(chapter_pages, chapter_ratings);
+    let chapter = chapter.into_response(
+        chapter_pages,
+        ratings,
+        &state.image_signer,
+        Some(auth.current_user.id),
+        has_access,
+    );
 
     Ok(Json(chapter))
 }
@@ -451,6 +835,10 @@ pub async fn update_chapter(
     Path(chapter_id): Path<Uuid>,
     Json(payload): Json<UpdateChapter>,
 ) -> Result<Json<Uuid>, ChaptersError> {
+    if matches!(payload.price, Some(price) if price < 0) {
+        return Err(ChaptersError::BadRequest);
+    }
+
     let mut db = state.pool.get().await?;
 
     let chapter = diesel::update(
@@ -484,17 +872,111 @@ pub async fn delete_chapter(
 ) -> Result<Json<Uuid>, ChaptersError> {
     let mut db = state.pool.get().await?;
 
-    let chapter = diesel::delete(
-        comic_chapters::table
-            .filter(comic_chapters::id.eq(chapter_id))
-            .filter(comic_chapters::user_id.eq(auth.current_user.id)),
-    )
-    .get_result::<Chapter>(&mut db)
-    .await?;
+    let chapter = db
+        .transaction::<_, ChaptersError, _>(|transaction| {
+            async move {
+                let chapter = diesel::delete(
+                    comic_chapters::table
+                        .filter(comic_chapters::id.eq(chapter_id))
+                        .filter(comic_chapters::user_id.eq(auth.current_user.id)),
+                )
+                .returning(Chapter::as_returning())
+                .get_result::<Chapter>(transaction)
+                .await?;
+
+                diesel::update(comics::table.find(chapter.comic_id))
+                    .set(comics::chapters_count.eq(comics::chapters_count - 1))
+                    .execute(transaction)
+                    .await?;
+
+                Ok(chapter)
+            }
+            .scope_boxed()
+        })
+        .await?;
 
     Ok(Json(chapter.id))
 }
 
+/// Unlock a chapter for a user
+#[utoipa::path(
+    post,
+    path = "/api/v1/comics/chapters/:chapter_id/entitlements",
+    request_body(content = CreateChapterEntitlement, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Chapter unlocked for the user", body = Uuid),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified chapter or user not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Chapters API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn create_chapter_entitlement(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(chapter_id): Path<Uuid>,
+    Json(payload): Json<CreateChapterEntitlement>,
+) -> Result<Json<Uuid>, ChaptersError> {
+    let mut db = state.pool.get().await?;
+
+    comic_chapters::table
+        .find(chapter_id)
+        .select(comic_chapters::id)
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => ChaptersError::ChapterNotFound,
+            e => e.into(),
+        })?;
+
+    diesel::insert_into(chapter_entitlements::table)
+        .values(&ChapterEntitlement {
+            user_id: payload.user_id,
+            chapter_id,
+            created_at: Utc::now(),
+        })
+        .on_conflict_do_nothing()
+        .execute(&mut db)
+        .await?;
+
+    Ok(Json(chapter_id))
+}
+
+/// Revoke a user's access to a chapter
+#[utoipa::path(
+    delete,
+    path = "/api/v1/comics/chapters/:chapter_id/entitlements/:user_id",
+    responses(
+        (status = 200, description = "Chapter access revoked", body = Uuid),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "The user doesn't have access to the chapter", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Chapters API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn delete_chapter_entitlement(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path((chapter_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Uuid>, ChaptersError> {
+    let mut db = state.pool.get().await?;
+
+    diesel::delete(chapter_entitlements::table.find((user_id, chapter_id)))
+        .returning(ChapterEntitlement::as_returning())
+        .get_result::<ChapterEntitlement>(&mut db)
+        .await?;
+
+    Ok(Json(chapter_id))
+}
+
 /// Delete chapter page
 #[utoipa::path(
     delete,
@@ -532,7 +1014,7 @@ pub async fn delete_chapter_page(
 #[utoipa::path(
     get,
     path = "/api/v1/comics/:comic_id/chapters/:chapter_id/rate",
-    request_body(content = NewChapterRating, description = "Validation:\n- rating: 0-10", content_type = "application/json"),
+    request_body(content = NewChapterRating, description = "Validation:\n- rating: 0-5", content_type = "application/json"),
     responses(),
     security(
         ("auth" = [])
@@ -550,38 +1032,74 @@ pub async fn rate_chapter(
 
     let mut db = state.pool.get().await?;
 
-    match diesel::update(
-        chapter_ratings::table
-            .filter(chapter_ratings::user_id.eq(auth.current_user.id))
-            .filter(chapter_ratings::chapter_id.eq(chapter_id)),
-    )
-    .set((
-        chapter_ratings::updated_at.eq(Some(Utc::now())),
-        chapter_ratings::rating.eq(payload.rating as f64),
-    ))
-    .get_result::<ChapterRating>(&mut db)
-    .await
-    {
-        Err(diesel::result::Error::NotFound) => {
-            let chapter_rating = ChapterRating {
-                id: Uuid::now_v7(),
-                rating: payload.rating as f64,
-                created_at: Utc::now(),
-                updated_at: None,
-                user_id: auth.current_user.id,
-                chapter_id,
-            };
-
-            diesel::insert_into(chapter_ratings::table)
-                .values(chapter_rating)
-                .execute(&mut db)
-                .await?;
+    let user_id = auth.current_user.id;
+    let rating = payload.rating as f64;
+
+    // the chapter's rating sum and count change with its ratings so listing chapters doesn't
+    // need to load them
+    db.transaction::<_, ChaptersError, _>(|transaction| {
+        async move {
+            let previous_rating = chapter_ratings::table
+                .filter(chapter_ratings::user_id.eq(user_id))
+                .filter(chapter_ratings::chapter_id.eq(chapter_id))
+                .select(chapter_ratings::rating)
+                .for_update()
+                .first::<f64>(transaction)
+                .await
+                .optional()?;
+
+            match previous_rating {
+                Some(previous_rating) => {
+                    diesel::update(
+                        chapter_ratings::table
+                            .filter(chapter_ratings::user_id.eq(user_id))
+                            .filter(chapter_ratings::chapter_id.eq(chapter_id)),
+                    )
+                    .set((
+                        chapter_ratings::updated_at.eq(Some(Utc::now())),
+                        chapter_ratings::rating.eq(rating),
+                    ))
+                    .execute(transaction)
+                    .await?;
+
+                    diesel::update(comic_chapters::table.find(chapter_id))
+                        .set(
+                            comic_chapters::rating_sum
+                                .eq(comic_chapters::rating_sum + (rating - previous_rating)),
+                        )
+                        .execute(transaction)
+                        .await?;
+                }
+                None => {
+                    let chapter_rating = ChapterRating {
+                        id: Uuid::now_v7(),
+                        rating,
+                        created_at: Utc::now(),
+                        updated_at: None,
+                        user_id,
+                        chapter_id,
+                    };
+
+                    diesel::insert_into(chapter_ratings::table)
+                        .values(chapter_rating)
+                        .execute(transaction)
+                        .await?;
+
+                    diesel::update(comic_chapters::table.find(chapter_id))
+                        .set((
+                            comic_chapters::rating_sum.eq(comic_chapters::rating_sum + rating),
+                            comic_chapters::rating_count.eq(comic_chapters::rating_count + 1),
+                        ))
+                        .execute(transaction)
+                        .await?;
+                }
+            }
 
             Ok(())
         }
-        Err(e) => Err(e.into()),
-        Ok(_) => Ok(()),
-    }
+        .scope_boxed()
+    })
+    .await
 }
 
 /// Get chapters of a comic with pagination
@@ -589,65 +1107,201 @@ pub async fn rate_chapter(
 #[utoipa::path(
     get,
     path = "/api/v1/comics/:comic_id/chapters",
-        params(
-        ChaptersParams
+    params(
+        ChaptersParams,
+        PaginationParams,
     ),
     responses(
-        (status = 200, body = [ChapterResponse]),
+        (status = 200, body = PaginatedChapters),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
+        (status = StatusCode::FORBIDDEN, description = "Mature comic and the caller isn't a verified adult", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Chapters API"
 )]
 #[axum::debug_handler(state = AppState)]
 pub async fn get_chapters(
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
     State(state): State<Arc<InnerAppState>>,
     Query(params): Query<ChaptersParams>,
+    Query(pagination): Query<PaginationParams>,
     Path(comic_id): Path<Uuid>,
-) -> Result<Json<Vec<ChapterResponse>>, ChaptersError> {
+) -> Result<Json<Paginated<ChapterResponse>>, ChaptersError> {
+    let cursor = pagination.cursor::<(ChapterOrder, Uuid)>()?;
     let mut db = state.pool.get().await?;
 
+    let (author_id, content_rating) = comics::table
+        .find(comic_id)
+        .select((comics::user_id, comics::content_rating))
+        .first::<(Uuid, ContentRating)>(&mut db)
+        .await?;
+
+    if content_rating == ContentRating::Mature
+        && author_id != auth.current_user.id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        return Err(ChaptersError::MatureContent);
+    }
+
     let mut chapters_query = comic_chapters::table
-        .left_join(chapter_ratings::table)
-        .order(comic_chapters::number.asc())
         .filter(comic_chapters::comic_id.eq(comic_id))
-        .filter(comic_chapters::id.gt(params.min_id))
-        .filter(comic_chapters::id.lt(params.max_id))
         .into_boxed();
 
-    if let Some(sorting_order) = params.sorting {
-        match sorting_order {
-            SortingOrder::Descending => {
-                chapters_query = chapters_query.order(chapter_ratings::rating.desc())
-            }
-            SortingOrder::Ascending => {
-                chapters_query = chapters_query.order(chapter_ratings::rating.asc())
-            }
-        }
+    if !matches!(auth.current_user.role, UserRole::Admin | UserRole::Staff) {
+        let now = Utc::now();
+
+        // early access needs a subscription to the author, tier levels start at 1
+        let level = subscription_levels(&mut db, auth.current_user.id, &[author_id])
+            .await?
+            .get(&author_id)
+            .copied()
+            .unwrap_or(0);
+
+        // same rules as entitlements::visible_chapters
+        chapters_query = chapters_query.filter(
+            comic_chapters::published_at
+                .is_null()
+                .or(comic_chapters::published_at.le(now))
+                .or(comic_chapters::user_id.eq(auth.current_user.id))
+                .or(comic_chapters::user_id
+                    .eq(author_id)
+                    .and(comic_chapters::early_access_at.le(now))
+                    .and(comic_chapters::early_access_level.le(level))),
+        );
     }
 
-    let chapters = chapters_query
-        .limit(10)
+    // chapters without ratings average 0, same as `average_rating`
+    let rating = sql::<Double>(
+        "(CASE WHEN comic_chapters.rating_count = 0 THEN 0 \
+         ELSE comic_chapters.rating_sum / comic_chapters.rating_count END)",
+    );
+
+    let by_rating = params.sorting.is_some();
+
+    chapters_query = match (
+        params.sorting,
+        params.order.unwrap_or(SortingOrder::Ascending),
+        cursor,
+    ) {
+        (Some(SortingOrder::Ascending), _, None) => {
+            chapters_query.order((rating.asc(), comic_chapters::id.asc()))
+        }
+        (Some(SortingOrder::Ascending), _, Some((ChapterOrder::Rating(prev_rating), prev_id))) => {
+            chapters_query
+                .filter(
+                    rating.clone().gt(prev_rating).or(rating
+                        .clone()
+                        .eq(prev_rating)
+                        .and(comic_chapters::id.gt(prev_id))),
+                )
+                .order((rating.asc(), comic_chapters::id.asc()))
+        }
+        (Some(SortingOrder::Descending), _, None) => {
+            chapters_query.order((rating.desc(), comic_chapters::id.desc()))
+        }
+        (Some(SortingOrder::Descending), _, Some((ChapterOrder::Rating(prev_rating), prev_id))) => {
+            chapters_query
+                .filter(
+                    rating.clone().lt(prev_rating).or(rating
+                        .clone()
+                        .eq(prev_rating)
+                        .and(comic_chapters::id.lt(prev_id))),
+                )
+                .order((rating.desc(), comic_chapters::id.desc()))
+        }
+        (None, SortingOrder::Ascending, None) => {
+            chapters_query.order((comic_chapters::number.asc(), comic_chapters::id.asc()))
+        }
+        (None, SortingOrder::Ascending, Some((ChapterOrder::Number(prev_number), prev_id))) => {
+            chapters_query
+                .filter(
+                    comic_chapters::number
+                        .gt(prev_number)
+                        .or(comic_chapters::number
+                            .eq(prev_number)
+                            .and(comic_chapters::id.gt(prev_id))),
+                )
+                .order((comic_chapters::number.asc(), comic_chapters::id.asc()))
+        }
+        (None, SortingOrder::Descending, None) => {
+            chapters_query.order((comic_chapters::number.desc(), comic_chapters::id.desc()))
+        }
+        (None, SortingOrder::Descending, Some((ChapterOrder::Number(prev_number), prev_id))) => {
+            chapters_query
+                .filter(
+                    comic_chapters::number
+                        .lt(prev_number)
+                        .or(comic_chapters::number
+                            .eq(prev_number)
+                            .and(comic_chapters::id.lt(prev_id))),
+                )
+                .order((comic_chapters::number.desc(), comic_chapters::id.desc()))
+        }
+        // the cursor belongs to a differently ordered list
+        _ => return Err(InvalidCursor.into()),
+    };
+
+    let mut chapters = chapters_query
+        .limit(pagination.limit() + 1)
         .select(Chapter::as_select())
         .load::<Chapter>(&mut db)
         .await?;
 
+    let next_cursor = pagination.page(&mut chapters, |chapter| {
+        let key = if by_rating {
+            ChapterOrder::Rating(average_rating(chapter.rating_sum, chapter.rating_count))
+        } else {
+            ChapterOrder::Number(chapter.number)
+        };
+
+        (key, chapter.id)
+    });
+
     let chapter_pages = ChapterPage::belonging_to(&chapters)
         .select(ChapterPage::as_select())
         .load::<ChapterPage>(&mut db)
         .await?;
 
-    let chapters_ratings = ChapterRating::belonging_to(&chapters)
-        .select(ChapterRating::as_select())
-        .load::<ChapterRating>(&mut db)
-        .await?;
-
     let chapter_pages = chapter_pages.grouped_by(&chapters);
-    let chapters_ratings = chapters_ratings.grouped_by(&chapters);
 
-    let chapters = multizip((chapters, chapter_pages, chapters_ratings))
-        .map(|(chapter, pages, chapter_ratings)| chapter.into_response(pages, chapter_ratings))
+    let accessible = accessible_chapters(
+        &mut db,
+        &chapters.iter().collect::<Vec<&Chapter>>(),
+        Some(&auth.current_user),
+    )
+    .await?;
+
+    let chapter_ids = chapters
+        .iter()
+        .map(|chapter| chapter.id)
+        .collect::<Vec<Uuid>>();
+
+    let read = read_chapters(&mut db, auth.current_user.id, &chapter_ids).await?;
+
+    let mut ratings = chapter_rating_summaries(
+        &mut db,
+        &chapter_ids,
+        Some(auth.current_user.id),
+        state.rating_prior,
+    )
+    .await?;
+
+    let chapters = multizip((chapters, chapter_pages))
+        .map(|(chapter, pages)| {
+            let has_access = accessible.contains(&chapter.id);
+            let is_read = read.contains(&chapter.id);
+            let chapter_ratings = ratings.remove(&chapter.id).unwrap_or_default();
+            chapter
+                .into_response(
+                    pages,
+                    chapter_ratings,
+                    &state.image_signer,
+                    Some(auth.current_user.id),
+                    has_access,
+                )
+                .with_read(is_read)
+        })
         .collect();
 
-    Ok(Json(chapters))
+    Ok(Json(Paginated::new(chapters, next_cursor)))
 }
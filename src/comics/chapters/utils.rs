@@ -1,7 +1,69 @@
-use std::error::Error;
+use std::{collections::HashMap, error::Error};
+
+use diesel::{dsl::count, prelude::*};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    common::models::RatingSummaryResponse,
+    schema::chapter_ratings,
+    utils::{rating_summary, RatingPrior},
+};
 
 use super::ChaptersError;
 
 pub fn box_error(err: ChaptersError) -> Box<dyn Error + Sync + Send + 'static> {
     Box::new(err)
 }
+
+/// Rating summaries of the chapters, with `viewer`'s own ratings if any
+pub async fn chapter_rating_summaries(
+    db: &mut AsyncPgConnection,
+    chapter_ids: &[Uuid],
+    viewer: Option<Uuid>,
+    prior: RatingPrior,
+) -> QueryResult<HashMap<Uuid, RatingSummaryResponse>> {
+    let counts = chapter_ratings::table
+        .filter(chapter_ratings::chapter_id.eq_any(chapter_ids))
+        .group_by((chapter_ratings::chapter_id, chapter_ratings::rating))
+        .select((
+            chapter_ratings::chapter_id,
+            chapter_ratings::rating,
+            count(chapter_ratings::id),
+        ))
+        .load::<(Uuid, f64, i64)>(db)
+        .await?;
+
+    let user_ratings = match viewer {
+        Some(viewer) => chapter_ratings::table
+            .filter(chapter_ratings::chapter_id.eq_any(chapter_ids))
+            .filter(chapter_ratings::user_id.eq(viewer))
+            .select((chapter_ratings::chapter_id, chapter_ratings::rating))
+            .load::<(Uuid, f64)>(db)
+            .await?
+            .into_iter()
+            .collect::<HashMap<Uuid, f64>>(),
+        None => HashMap::new(),
+    };
+
+    let mut counts_by_chapter = HashMap::<Uuid, Vec<(f64, i64)>>::new();
+    for (chapter_id, rating, times) in counts {
+        counts_by_chapter
+            .entry(chapter_id)
+            .or_default()
+            .push((rating, times));
+    }
+
+    Ok(chapter_ids
+        .iter()
+        .map(|chapter_id| {
+            let summary = rating_summary(
+                counts_by_chapter.remove(chapter_id).unwrap_or_default(),
+                user_ratings.get(chapter_id).copied(),
+                prior,
+            );
+
+            (*chapter_id, summary)
+        })
+        .collect())
+}
@@ -1,7 +1,7 @@
 use axum::{http::StatusCode, response::IntoResponse};
 use diesel::result::DatabaseErrorKind;
 
-use crate::ErrorResponse;
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
 
 pub mod models;
 pub mod routes;
@@ -13,6 +13,9 @@ pub enum ComicCommentsError {
 
     #[error(transparent)]
     PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
 }
 
 impl IntoResponse for ComicCommentsError {
@@ -52,6 +55,7 @@ impl IntoResponse for ComicCommentsError {
                 }
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
+            ComicCommentsError::InvalidCursor(err) => err.into_response(),
             _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
         }
     }
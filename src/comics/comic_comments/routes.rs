@@ -1,22 +1,25 @@
 use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     routing::{delete, get, post},
     Json, Router,
 };
 use chrono::Utc;
 use diesel::BelongingToDsl;
-use diesel::GroupedBy;
-use diesel::{ExpressionMethods, QueryDsl};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
 use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
-use itertools::multizip;
 use uuid::Uuid;
 
 use crate::{
     auth::AuthExtractor,
     comics::comic_comments::models::{ComicComment, ComicCommentResponse, CreateComicComment},
     comics::models::Comic,
+    common::pagination::{Paginated, PaginationParams},
+    notifications::{
+        models::{Notification, NotificationKind},
+        utils::notify,
+    },
     schema::{comic_comments, comic_comments_mapping, comics, users},
     users::models::{User, UserResponseBrief, UserRole},
     AppState, InnerAppState,
@@ -34,9 +37,12 @@ pub fn comic_comments_router() -> Router<AppState> {
 #[utoipa::path(
     get,
     path = "/api/v1/comics/:comic_id/comments",
+    params(
+        PaginationParams,
+    ),
     responses(
-        (status = 200, description = "Caller authorized, returned comics comments", body = [ComicCommentResponse]),
-        (status = StatusCode::BAD_REQUEST, description = "Invalid Comic ID", body = ErrorResponse),
+        (status = 200, description = "Caller authorized, returned comics comments", body = PaginatedComicComments),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid Comic ID or cursor", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Comic Comments API",
@@ -46,47 +52,68 @@ pub async fn get_comments(
     _auth: AuthExtractor<{ UserRole::User as u32 }>,
     State(state): State<Arc<InnerAppState>>,
     Path(comic_id): Path<Uuid>,
-) -> Result<Json<Vec<ComicCommentResponse>>, ComicCommentsError> {
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<ComicCommentResponse>>, ComicCommentsError> {
+    let cursor = pagination.cursor::<Uuid>()?;
     let mut db = state.pool.get().await?;
 
     let comic = comics::table
         .filter(comics::id.eq(comic_id))
+        .select(Comic::as_select())
         .get_result::<Comic>(&mut db)
         .await?;
 
-    let (comments, users): (Vec<ComicComment>, Vec<User>) = ComicComment::belonging_to(&comic)
+    let mut query = ComicComment::belonging_to(&comic)
         .inner_join(users::table)
-        .load::<(ComicComment, User)>(&mut db)
-        .await?
-        .into_iter()
-        .unzip();
+        .into_boxed();
 
-    let comment_mappings = ComicCommentMapping::belonging_to(&comments)
-        .load::<ComicCommentMapping>(&mut db)
+    // comment ids are v7 uuids, ordering by them lists the oldest comments first
+    if let Some(prev_id) = cursor {
+        query = query.filter(comic_comments::id.gt(prev_id));
+    }
+
+    let mut rows = query
+        .order(comic_comments::id.asc())
+        .limit(pagination.limit() + 1)
+        .select((ComicComment::as_select(), User::as_select()))
+        .load::<(ComicComment, User)>(&mut db)
         .await?;
 
-    let comment_mappings_by_parents = comment_mappings.clone().grouped_by(&comments); // 1 parent -> children
+    let next_cursor = pagination.page(&mut rows, |(comment, _)| comment.id);
+
+    let (comments, users): (Vec<ComicComment>, Vec<User>) = rows.into_iter().unzip();
 
-    let id_indices: HashMap<_, _> = comments
+    let comment_ids = comments
         .iter()
-        .enumerate()
-        .map(|(i, u)| (u.id, i))
-        .collect();
+        .map(|comment| comment.id)
+        .collect::<Vec<Uuid>>();
 
-    let mut comment_mappings_by_children = comments.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    // parents and replies of the comments in this page can be in other pages
+    let comment_mappings = comic_comments_mapping::table
+        .filter(
+            comic_comments_mapping::parent_comment_id
+                .eq_any(&comment_ids)
+                .or(comic_comments_mapping::child_comment_id.eq_any(&comment_ids)),
+        )
+        .select(ComicCommentMapping::as_select())
+        .load::<ComicCommentMapping>(&mut db)
+        .await?;
+
+    let mut parent_ids: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut children_ids: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
 
-    for child in comment_mappings {
-        comment_mappings_by_children[id_indices[&child.child_comment_id]].push(child);
+    for mapping in comment_mappings {
+        parent_ids.insert(mapping.child_comment_id, mapping.parent_comment_id);
+        children_ids
+            .entry(mapping.parent_comment_id)
+            .or_default()
+            .push(mapping.child_comment_id);
     }
 
-    let comments = multizip((
-        comments,
-        users,
-        comment_mappings_by_parents,
-        comment_mappings_by_children,
-    ))
-    .map(
-        |(comment, user, comment_parent_mapping, comment_children_mapping)| ComicCommentResponse {
+    let comments = comments
+        .into_iter()
+        .zip(users)
+        .map(|(comment, user)| ComicCommentResponse {
             id: comment.id,
             comic_id: comic.id,
             content: comment.content,
@@ -97,17 +124,13 @@ pub async fn get_comments(
                 email: user.email,
                 role: user.role,
             },
-            parent_comment: comment_children_mapping.get(0).map(|m| m.parent_comment_id),
-            child_comments_ids: comment_parent_mapping
-                .iter()
-                .map(|m| m.child_comment_id)
-                .collect(),
+            parent_comment: parent_ids.get(&comment.id).copied(),
+            child_comments_ids: children_ids.remove(&comment.id).unwrap_or_default(),
             child_comments: vec![],
-        },
-    )
-    .collect();
+        })
+        .collect();
 
-    Ok(Json(comments))
+    Ok(Json(Paginated::new(comments, next_cursor)))
 }
 
 // #[utoipa::path(
@@ -166,6 +189,8 @@ pub async fn create_comment(
 ) -> Result<Json<ComicCommentResponse>, ComicCommentsError> {
     let mut db = state.pool.get().await?;
 
+    let user_id = auth.current_user.id;
+
     let comment = db
         .transaction::<_, ComicCommentsError, _>(|transaction| {
             async move {
@@ -175,7 +200,7 @@ pub async fn create_comment(
                     created_at: Utc::now(),
                     updated_at: None,
                     comic_id,
-                    user_id: auth.current_user.id,
+                    user_id,
                 };
 
                 let comment = diesel::insert_into(comic_comments::table)
@@ -193,16 +218,54 @@ pub async fn create_comment(
                     child_comments: vec![],
                 };
 
-                if let Some(parent_comment_id) = payload.parent_comment_id {
-                    diesel::insert_into(comic_comments_mapping::table)
-                        .values((
-                            comic_comments_mapping::parent_comment_id.eq(parent_comment_id),
-                            comic_comments_mapping::child_comment_id.eq(comment.id),
-                        ))
-                        .execute(transaction)
-                        .await?;
+                let mut new_notifications = vec![];
+
+                let parent_author_id = match payload.parent_comment_id {
+                    Some(parent_comment_id) => {
+                        diesel::insert_into(comic_comments_mapping::table)
+                            .values((
+                                comic_comments_mapping::parent_comment_id.eq(parent_comment_id),
+                                comic_comments_mapping::child_comment_id.eq(comment.id),
+                            ))
+                            .execute(transaction)
+                            .await?;
+
+                        let parent_author_id = comic_comments::table
+                            .find(parent_comment_id)
+                            .select(comic_comments::user_id)
+                            .first::<Uuid>(transaction)
+                            .await?;
+
+                        new_notifications.push(
+                            Notification::new(parent_author_id, NotificationKind::CommentReply)
+                                .actor(user_id)
+                                .comic(comic_id)
+                                .comment(comment.id),
+                        );
+
+                        Some(parent_author_id)
+                    }
+                    None => None,
+                };
+
+                let author_id = comics::table
+                    .find(comic_id)
+                    .select(comics::user_id)
+                    .first::<Uuid>(transaction)
+                    .await?;
+
+                // authors replied to on their own comic only get the reply
+                if parent_author_id != Some(author_id) {
+                    new_notifications.push(
+                        Notification::new(author_id, NotificationKind::ComicComment)
+                            .actor(user_id)
+                            .comic(comic_id)
+                            .comment(comment.id),
+                    );
                 }
 
+                notify(transaction, new_notifications).await?;
+
                 Ok(commnet_response)
             }
             .scope_boxed()
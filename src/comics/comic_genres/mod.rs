@@ -1,10 +1,12 @@
 use axum::{http::StatusCode, response::IntoResponse};
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
 use diesel_async::pooled_connection::deadpool::PoolError;
 
 use crate::ErrorResponse;
 
 pub mod models;
 pub mod routes;
+pub mod utils;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ComicGenresError {
@@ -19,6 +21,9 @@ pub enum ComicGenresError {
 
     #[error("invalid genre")]
     InvalidGenre,
+
+    #[error("parent genre has to be an existing top level genre, and genres with sub-genres can't have a parent")]
+    InvalidParent,
 }
 
 impl IntoResponse for ComicGenresError {
@@ -27,7 +32,7 @@ impl IntoResponse for ComicGenresError {
 
         match self {
             ComicGenresError::PlaceHolder => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
-            ComicGenresError::InvalidGenre => (
+            ComicGenresError::InvalidGenre | ComicGenresError::InvalidParent => (
                 StatusCode::BAD_REQUEST,
                 ErrorResponse {
                     error: self.to_string(),
@@ -36,6 +41,29 @@ impl IntoResponse for ComicGenresError {
             )
                 .into_response(),
             ComicGenresError::PoolError(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            ComicGenresError::Diesel(DatabaseError(DatabaseErrorKind::UniqueViolation, info))
+                if info.constraint_name() == Some("comic_genres_slug_key") =>
+            {
+                (
+                    StatusCode::CONFLICT,
+                    ErrorResponse {
+                        error: String::from("slug is taken by another genre"),
+                        ..Default::default()
+                    },
+                )
+                    .into_response()
+            }
+            ComicGenresError::Diesel(DatabaseError(
+                DatabaseErrorKind::ForeignKeyViolation,
+                info,
+            )) if info.constraint_name() == Some("comic_genre_translations_genre_id_fkey") => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    error: String::from("genre not found"),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
             ComicGenresError::Diesel(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
         }
     }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::DateTime;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -7,7 +9,9 @@ use uuid::Uuid;
 
 use crate::{
     comics::models::Comic,
-    schema::{comic_genres, comic_genres_mapping},
+    common::locale::Locale,
+    schema::{comic_genre_translations, comic_genres, comic_genres_mapping},
+    utils::double_option,
 };
 
 #[derive(Queryable, Selectable, Identifiable, Debug, ToSchema, PartialEq)]
@@ -17,6 +21,21 @@ pub struct Genre {
     pub id: i32,
     pub name: String,
     pub created_at: DateTime<chrono::Utc>,
+    pub slug: String,
+    pub parent_id: Option<i32>,
+}
+
+impl Genre {
+    /// Genre with its name in the locale of the translations, or its default name
+    /// if it isn't translated
+    pub fn into_localized(self, translations: &HashMap<i32, String>) -> ComicGenre {
+        ComicGenre {
+            name: translations.get(&self.id).cloned().unwrap_or(self.name),
+            id: self.id,
+            slug: self.slug,
+            parent_id: self.parent_id,
+        }
+    }
 }
 
 #[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
@@ -30,16 +49,36 @@ pub struct GenreMapping {
     pub genre_id: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, TS, PartialEq)]
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(Genre))]
+#[diesel(table_name = comic_genre_translations)]
+#[diesel(primary_key(genre_id, locale))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GenreTranslation {
+    pub genre_id: i32,
+    pub locale: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS, PartialEq)]
 #[ts(export)]
 pub struct ComicGenre {
     pub id: i32,
+    /// localized name
     pub name: String,
+    pub slug: String,
+    pub parent_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, TS, PartialEq)]
 pub struct CreateComicGenre {
+    /// default name, it's used for locales without a translation
     pub name: String,
+    /// generated from the name if it's not given
+    pub slug: Option<String>,
+    pub parent_id: Option<i32>,
+    #[serde(default)]
+    pub translations: HashMap<Locale, String>,
 }
 
 #[derive(AsChangeset, Debug, Serialize, Deserialize, ToSchema, TS, PartialEq)]
@@ -48,6 +87,17 @@ pub struct CreateComicGenre {
 pub struct UpdateComicGenre {
     pub name: Option<String>,
     pub created_at: Option<DateTime<chrono::Utc>>,
+    pub slug: Option<String>,
+    /// `null` makes the genre a top level genre
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<i32>)]
+    #[ts(type = "number | null")]
+    pub parent_id: Option<Option<i32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS, PartialEq)]
+pub struct SetGenreTranslation {
+    pub name: String,
 }
 
 #[derive(Insertable, Debug, PartialEq)]
@@ -55,5 +105,7 @@ pub struct UpdateComicGenre {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct ComicGenreInsert {
     pub name: String,
+    pub slug: String,
+    pub parent_id: Option<i32>,
     pub created_at: DateTime<chrono::Utc>,
 }
@@ -1,15 +1,24 @@
 use std::sync::Arc;
 
 use crate::{
-    auth::AuthExtractor, comics::comic_genres::models::ComicGenre, schema::comic_genres,
-    users::models::UserRole, AppState, InnerAppState,
+    auth::AuthExtractor,
+    comics::{comic_genres::models::ComicGenre, utils::slugify},
+    common::locale::{Locale, LocaleParams},
+    schema::{comic_genre_translations, comic_genres},
+    users::models::UserRole,
+    AppState, InnerAppState,
 };
 use chrono::Utc;
-use diesel::ExpressionMethods;
+use diesel::{dsl::exists, ExpressionMethods, OptionalExtension};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection};
 use futures_util::TryStreamExt;
 
 use super::{
-    models::{ComicGenreInsert, CreateComicGenre, Genre, UpdateComicGenre},
+    models::{
+        ComicGenreInsert, CreateComicGenre, Genre, GenreTranslation, SetGenreTranslation,
+        UpdateComicGenre,
+    },
+    utils::genre_translations,
     ComicGenresError,
 };
 use axum::{
@@ -26,11 +35,60 @@ pub fn comic_genres_router() -> Router<AppState> {
         .route("/genres", post(create_genre))
         .route("/genres/:genre_id", put(update_genre))
         .route("/genres/:genre_id", delete(delete_genre))
+        .route(
+            "/genres/:genre_id/translations/:locale",
+            put(set_genre_translation),
+        )
+        .route(
+            "/genres/:genre_id/translations/:locale",
+            delete(delete_genre_translation),
+        )
+}
+
+/// Sub-genres can only be added to top level genres,
+/// and genres that have sub-genres can't become sub-genres themselves
+async fn check_parent(
+    db: &mut AsyncPgConnection,
+    genre_id: Option<i32>,
+    parent_id: i32,
+) -> Result<(), ComicGenresError> {
+    if genre_id == Some(parent_id) {
+        return Err(ComicGenresError::InvalidParent);
+    }
+
+    let grandparent_id = comic_genres::table
+        .filter(comic_genres::id.eq(parent_id))
+        .select(comic_genres::parent_id)
+        .first::<Option<i32>>(db)
+        .await
+        .optional()?
+        .ok_or(ComicGenresError::InvalidParent)?;
+
+    if grandparent_id.is_some() {
+        return Err(ComicGenresError::InvalidParent);
+    }
+
+    if let Some(genre_id) = genre_id {
+        let has_subgenres = diesel::select(exists(
+            comic_genres::table.filter(comic_genres::parent_id.eq(genre_id)),
+        ))
+        .get_result::<bool>(db)
+        .await?;
+
+        if has_subgenres {
+            return Err(ComicGenresError::InvalidParent);
+        }
+    }
+
+    Ok(())
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/comics/genres",
+    params(
+        LocaleParams,
+    ),
     responses(
         (status = StatusCode::OK, body = [ComicGenre]),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
@@ -40,19 +98,20 @@ pub fn comic_genres_router() -> Router<AppState> {
 #[axum::debug_handler(state = AppState)]
 pub async fn get_genres(
     // _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
     State(state): State<Arc<InnerAppState>>,
 ) -> Result<Json<Vec<ComicGenre>>, ComicGenresError> {
     let mut db = state.pool.get().await?;
 
+    let translations = genre_translations(&mut db, locale).await?;
+
     let genres = comic_genres::table
+        .order(comic_genres::id.asc())
         .select(Genre::as_select())
         .load_stream::<Genre>(&mut db)
         .await?
         .try_fold(Vec::new(), |mut acc, item| {
-            acc.push(ComicGenre {
-                id: item.id,
-                name: item.name,
-            });
+            acc.push(item.into_localized(&translations));
             futures::future::ready(Ok(acc))
         })
         .await?;
@@ -63,8 +122,11 @@ pub async fn get_genres(
 #[utoipa::path(
     post,
     path = "/api/v1/comics/genres",
+    request_body(content = CreateComicGenre, content_type = "application/json"),
     responses(
         (status = StatusCode::OK),
+        (status = StatusCode::BAD_REQUEST, description = "Parent genre doesn't exist or is a sub-genre", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Slug is taken by another genre", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Comic Genres API"
@@ -77,22 +139,59 @@ pub async fn create_genre(
 ) -> Result<(), ComicGenresError> {
     let mut db = state.pool.get().await?;
 
-    diesel::insert_into(comic_genres::table)
-        .values(ComicGenreInsert {
-            name: payload.name,
-            created_at: Utc::now(),
-        })
-        .execute(&mut db)
-        .await?;
+    if let Some(parent_id) = payload.parent_id {
+        check_parent(&mut db, None, parent_id).await?;
+    }
 
-    Ok(())
+    let slug = slugify(payload.slug.as_deref().unwrap_or(&payload.name));
+
+    if slug.is_empty() {
+        return Err(ComicGenresError::InvalidGenre);
+    }
+
+    db.transaction::<_, ComicGenresError, _>(|transaction| {
+        async move {
+            let genre_id = diesel::insert_into(comic_genres::table)
+                .values(ComicGenreInsert {
+                    name: payload.name,
+                    slug,
+                    parent_id: payload.parent_id,
+                    created_at: Utc::now(),
+                })
+                .returning(comic_genres::id)
+                .get_result::<i32>(transaction)
+                .await?;
+
+            let translations = payload
+                .translations
+                .into_iter()
+                .map(|(locale, name)| GenreTranslation {
+                    genre_id,
+                    locale: locale.as_str().to_string(),
+                    name,
+                })
+                .collect::<Vec<GenreTranslation>>();
+
+            diesel::insert_into(comic_genre_translations::table)
+                .values(&translations)
+                .execute(transaction)
+                .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
 }
 
 #[utoipa::path(
     put,
     path = "/api/v1/comics/genres/:genre_id",
+    request_body(content = UpdateComicGenre, content_type = "application/json"),
     responses(
         (status = StatusCode::OK),
+        (status = StatusCode::BAD_REQUEST, description = "Parent genre doesn't exist, is a sub-genre, or the genre has sub-genres", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Slug is taken by another genre", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Comic Genres API"
@@ -102,10 +201,24 @@ pub async fn update_genre(
     _auth: AuthExtractor<{ UserRole::Admin as u32 }>,
     State(state): State<Arc<InnerAppState>>,
     Path(genre_id): Path<i32>,
-    Json(payload): Json<UpdateComicGenre>,
+    Json(mut payload): Json<UpdateComicGenre>,
 ) -> Result<(), ComicGenresError> {
     let mut db = state.pool.get().await?;
 
+    if let Some(Some(parent_id)) = payload.parent_id {
+        check_parent(&mut db, Some(genre_id), parent_id).await?;
+    }
+
+    if let Some(slug) = payload.slug.as_deref() {
+        let slug = slugify(slug);
+
+        if slug.is_empty() {
+            return Err(ComicGenresError::InvalidGenre);
+        }
+
+        payload.slug = Some(slug);
+    }
+
     diesel::update(comic_genres::table.filter(comic_genres::id.eq(genre_id)))
         .set(payload)
         .execute(&mut db)
@@ -137,3 +250,71 @@ pub async fn delete_genre(
 
     Ok(())
 }
+
+/// Set the genre's name in a locale
+#[utoipa::path(
+    put,
+    path = "/api/v1/comics/genres/:genre_id/translations/:locale",
+    request_body(content = SetGenreTranslation, content_type = "application/json"),
+    responses(
+        (status = StatusCode::OK),
+        (status = StatusCode::NOT_FOUND, description = "Genre not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Comic Genres API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn set_genre_translation(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path((genre_id, locale)): Path<(i32, Locale)>,
+    Json(payload): Json<SetGenreTranslation>,
+) -> Result<(), ComicGenresError> {
+    let mut db = state.pool.get().await?;
+
+    diesel::insert_into(comic_genre_translations::table)
+        .values(GenreTranslation {
+            genre_id,
+            locale: locale.as_str().to_string(),
+            name: payload.name.clone(),
+        })
+        .on_conflict((
+            comic_genre_translations::genre_id,
+            comic_genre_translations::locale,
+        ))
+        .do_update()
+        .set(comic_genre_translations::name.eq(payload.name))
+        .execute(&mut db)
+        .await?;
+
+    Ok(())
+}
+
+/// Remove the genre's name in a locale, its default name is used instead
+#[utoipa::path(
+    delete,
+    path = "/api/v1/comics/genres/:genre_id/translations/:locale",
+    responses(
+        (status = StatusCode::OK),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Comic Genres API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn delete_genre_translation(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path((genre_id, locale)): Path<(i32, Locale)>,
+) -> Result<(), ComicGenresError> {
+    let mut db = state.pool.get().await?;
+
+    diesel::delete(
+        comic_genre_translations::table
+            .filter(comic_genre_translations::genre_id.eq(genre_id))
+            .filter(comic_genre_translations::locale.eq(locale.as_str())),
+    )
+    .execute(&mut db)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use diesel::{ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::{common::locale::Locale, schema::comic_genre_translations, schema::comic_genres};
+
+/// Names of the genres translated to the locale, by genre id
+///
+/// genres are a short list, so all of their translations are loaded at once
+pub async fn genre_translations(
+    db: &mut AsyncPgConnection,
+    locale: Locale,
+) -> QueryResult<HashMap<i32, String>> {
+    let translations = comic_genre_translations::table
+        .filter(comic_genre_translations::locale.eq(locale.as_str()))
+        .select((
+            comic_genre_translations::genre_id,
+            comic_genre_translations::name,
+        ))
+        .load::<(i32, String)>(db)
+        .await?;
+
+    Ok(translations.into_iter().collect())
+}
+
+/// Each genre along with its sub-genres, filtering by a genre matches its sub-genres too
+pub async fn with_subgenres(
+    db: &mut AsyncPgConnection,
+    genre_ids: &[i32],
+) -> QueryResult<Vec<Vec<i32>>> {
+    let subgenres = comic_genres::table
+        .filter(comic_genres::parent_id.eq_any(genre_ids))
+        .select((comic_genres::id, comic_genres::parent_id))
+        .load::<(i32, Option<i32>)>(db)
+        .await?;
+
+    Ok(genre_ids
+        .iter()
+        .map(|genre_id| {
+            std::iter::once(*genre_id)
+                .chain(
+                    subgenres
+                        .iter()
+                        .filter(|(_, parent_id)| *parent_id == Some(*genre_id))
+                        .map(|(id, _)| *id),
+                )
+                .collect()
+        })
+        .collect())
+}
@@ -0,0 +1,70 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use diesel_async::pooled_connection::deadpool::PoolError;
+
+use crate::ErrorResponse;
+
+pub mod models;
+pub mod routes;
+pub mod utils;
+
+pub const MAX_TAGS_PER_COMIC: usize = 10;
+
+pub const AUTOCOMPLETE_DEFAULT_LIMIT: i64 = 10;
+pub const AUTOCOMPLETE_MAX_LIMIT: i64 = 25;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ComicTagsError {
+    #[error("comic not found")]
+    ComicNotFound,
+
+    #[error("tag not found")]
+    TagNotFound,
+
+    #[error("invalid tag: {0}")]
+    InvalidTag(String),
+
+    #[error("tag is banned: {0}")]
+    BannedTag(String),
+
+    #[error("comics can't have more than {} tags", MAX_TAGS_PER_COMIC)]
+    TooManyTags,
+
+    #[error("tags can only be merged into other tags that aren't merged or banned")]
+    InvalidMerge,
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] PoolError),
+}
+
+impl IntoResponse for ComicTagsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        match self {
+            ComicTagsError::ComicNotFound | ComicTagsError::TagNotFound => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
+            ComicTagsError::InvalidTag(_)
+            | ComicTagsError::BannedTag(_)
+            | ComicTagsError::TooManyTags
+            | ComicTagsError::InvalidMerge => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
+            ComicTagsError::Diesel(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            ComicTagsError::PoolError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    comics::models::Comic,
+    schema::{comic_tags, comic_tags_mapping},
+};
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Debug, Clone)]
+#[diesel(table_name = comic_tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ComicTag {
+    pub id: Uuid,
+    pub name: String,
+    pub merged_into: Option<Uuid>,
+    pub banned_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ComicTag {
+    pub fn into_response(self) -> ComicTagResponse {
+        ComicTagResponse {
+            id: self.id,
+            name: self.name,
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(Comic))]
+#[diesel(belongs_to(ComicTag, foreign_key = tag_id))]
+#[diesel(table_name = comic_tags_mapping)]
+#[diesel(primary_key(comic_id, tag_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ComicTagMapping {
+    pub comic_id: Uuid,
+    pub tag_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS, PartialEq)]
+#[ts(export)]
+pub struct ComicTagResponse {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct TagSuggestion {
+    pub id: Uuid,
+    pub name: String,
+    pub comics_count: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TagsAutocompleteParams {
+    /// beginning of the tag name
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct SetComicTags {
+    /// replaces the comic's tags, names are normalized and new tags are created
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct MergeComicTag {
+    /// the tag that the merged tag's comics are moved to
+    pub target_id: Uuid,
+}
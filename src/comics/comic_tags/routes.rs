@@ -0,0 +1,434 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use diesel::{dsl::count, prelude::*};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    notifications::{
+        models::{Notification, NotificationKind},
+        utils::notify,
+    },
+    schema::{comic_tags, comic_tags_mapping, comics},
+    users::models::UserRole,
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{
+        ComicTag, ComicTagMapping, ComicTagResponse, MergeComicTag, SetComicTags, TagSuggestion,
+        TagsAutocompleteParams,
+    },
+    utils::{normalize_tag, normalize_tag_name},
+    ComicTagsError, AUTOCOMPLETE_DEFAULT_LIMIT, AUTOCOMPLETE_MAX_LIMIT, MAX_TAGS_PER_COMIC,
+};
+
+pub fn comic_tags_router() -> Router<AppState> {
+    Router::new()
+        .route("/tags", get(autocomplete_tags))
+        .route("/tags/:tag_id/merge", post(merge_tag))
+        .route("/tags/:tag_id/ban", post(ban_tag))
+        .route("/tags/:tag_id/unban", post(unban_tag))
+        .route("/:comic_id/tags", put(set_comic_tags))
+}
+
+/// Suggest tags that start with the query, most used first
+#[utoipa::path(
+    get,
+    path = "/api/v1/comics/tags",
+    params(
+        TagsAutocompleteParams,
+    ),
+    responses(
+        (status = StatusCode::OK, body = [TagSuggestion]),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Comic Tags API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn autocomplete_tags(
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<TagsAutocompleteParams>,
+) -> Result<Json<Vec<TagSuggestion>>, ComicTagsError> {
+    let prefix = normalize_tag_name(&params.q);
+
+    if prefix.is_empty() {
+        return Ok(Json(vec![]));
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(AUTOCOMPLETE_DEFAULT_LIMIT)
+        .clamp(1, AUTOCOMPLETE_MAX_LIMIT);
+
+    let mut db = state.pool.get().await?;
+
+    // normalized names can't have `%` or `_` in them, so the prefix doesn't need escaping
+    let suggestions = comic_tags::table
+        .left_join(comic_tags_mapping::table)
+        .filter(comic_tags::name.like(format!("{}%", prefix)))
+        .filter(comic_tags::merged_into.is_null())
+        .filter(comic_tags::banned_at.is_null())
+        .group_by(comic_tags::id)
+        .select((
+            comic_tags::id,
+            comic_tags::name,
+            count(comic_tags_mapping::comic_id.nullable()),
+        ))
+        .order((
+            count(comic_tags_mapping::comic_id.nullable()).desc(),
+            comic_tags::name.asc(),
+        ))
+        .limit(limit)
+        .load::<(Uuid, String, i64)>(&mut db)
+        .await?
+        .into_iter()
+        .map(|(id, name, comics_count)| TagSuggestion {
+            id,
+            name,
+            comics_count,
+        })
+        .collect();
+
+    Ok(Json(suggestions))
+}
+
+/// Replace a comic's tags, tags that don't exist yet are created
+#[utoipa::path(
+    put,
+    path = "/api/v1/comics/:comic_id/tags",
+    request_body(content = SetComicTags, description = "Validation:\n- up to 10 tags\n- names: 2-40 letters, numbers, spaces or dashes", content_type = "application/json"),
+    responses(
+        (status = StatusCode::OK, description = "Comic's tags after merged tags are resolved", body = [ComicTagResponse]),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid, banned or too many tags", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Comic not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Comic Tags API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn set_comic_tags(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(comic_id): Path<Uuid>,
+    Json(payload): Json<SetComicTags>,
+) -> Result<Json<Vec<ComicTagResponse>>, ComicTagsError> {
+    let mut names: Vec<String> = Vec::with_capacity(payload.tags.len());
+
+    for tag in payload.tags {
+        let name = normalize_tag(&tag).ok_or(ComicTagsError::InvalidTag(tag))?;
+
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    if names.len() > MAX_TAGS_PER_COMIC {
+        return Err(ComicTagsError::TooManyTags);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    let tags = db
+        .transaction::<_, ComicTagsError, _>(|transaction| {
+            async move {
+                comics::table
+                    .filter(comics::id.eq(comic_id))
+                    .filter(comics::user_id.eq(auth.current_user.id))
+                    .select(comics::id)
+                    .first::<Uuid>(transaction)
+                    .await
+                    .optional()?
+                    .ok_or(ComicTagsError::ComicNotFound)?;
+
+                let new_tags = names
+                    .iter()
+                    .map(|name| ComicTag {
+                        id: Uuid::now_v7(),
+                        name: name.clone(),
+                        merged_into: None,
+                        banned_at: None,
+                        created_at: Utc::now(),
+                    })
+                    .collect::<Vec<ComicTag>>();
+
+                diesel::insert_into(comic_tags::table)
+                    .values(&new_tags)
+                    .on_conflict(comic_tags::name)
+                    .do_nothing()
+                    .execute(transaction)
+                    .await?;
+
+                let tags = comic_tags::table
+                    .filter(comic_tags::name.eq_any(&names))
+                    .select(ComicTag::as_select())
+                    .load::<ComicTag>(transaction)
+                    .await?;
+
+                // merged tags are swapped for the tags they were merged into
+                let mut tag_ids = tags
+                    .iter()
+                    .map(|tag| tag.merged_into.unwrap_or(tag.id))
+                    .collect::<Vec<Uuid>>();
+                tag_ids.sort_unstable();
+                tag_ids.dedup();
+
+                let tags = comic_tags::table
+                    .filter(comic_tags::id.eq_any(&tag_ids))
+                    .order(comic_tags::name.asc())
+                    .select(ComicTag::as_select())
+                    .load::<ComicTag>(transaction)
+                    .await?;
+
+                if let Some(banned) = tags.iter().find(|tag| tag.banned_at.is_some()) {
+                    return Err(ComicTagsError::BannedTag(banned.name.clone()));
+                }
+
+                diesel::delete(
+                    comic_tags_mapping::table
+                        .filter(comic_tags_mapping::comic_id.eq(comic_id))
+                        .filter(comic_tags_mapping::tag_id.ne_all(&tag_ids)),
+                )
+                .execute(transaction)
+                .await?;
+
+                let mappings = tag_ids
+                    .iter()
+                    .map(|tag_id| ComicTagMapping {
+                        comic_id,
+                        tag_id: *tag_id,
+                        created_at: Utc::now(),
+                    })
+                    .collect::<Vec<ComicTagMapping>>();
+
+                diesel::insert_into(comic_tags_mapping::table)
+                    .values(&mappings)
+                    .on_conflict_do_nothing()
+                    .execute(transaction)
+                    .await?;
+
+                Ok(tags)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(Json(
+        tags.into_iter().map(ComicTag::into_response).collect(),
+    ))
+}
+
+/// Merge a tag into another one, the merged tag's comics are moved to the target
+#[utoipa::path(
+    post,
+    path = "/api/v1/comics/tags/:tag_id/merge",
+    request_body(content = MergeComicTag, content_type = "application/json"),
+    responses(
+        (status = StatusCode::OK, description = "The tag the comics were moved to", body = ComicTagResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Target is the same tag, merged or banned", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Tag not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Comic Tags API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn merge_tag(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(tag_id): Path<Uuid>,
+    Json(payload): Json<MergeComicTag>,
+) -> Result<Json<ComicTagResponse>, ComicTagsError> {
+    if tag_id == payload.target_id {
+        return Err(ComicTagsError::InvalidMerge);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    let target = db
+        .transaction::<_, ComicTagsError, _>(|transaction| {
+            async move {
+                let source = comic_tags::table
+                    .find(tag_id)
+                    .for_update()
+                    .select(ComicTag::as_select())
+                    .first::<ComicTag>(transaction)
+                    .await
+                    .optional()?
+                    .ok_or(ComicTagsError::TagNotFound)?;
+
+                let target = comic_tags::table
+                    .find(payload.target_id)
+                    .for_update()
+                    .select(ComicTag::as_select())
+                    .first::<ComicTag>(transaction)
+                    .await
+                    .optional()?
+                    .ok_or(ComicTagsError::TagNotFound)?;
+
+                if target.merged_into.is_some() || target.banned_at.is_some() {
+                    return Err(ComicTagsError::InvalidMerge);
+                }
+
+                let comic_ids = comic_tags_mapping::table
+                    .filter(comic_tags_mapping::tag_id.eq(source.id))
+                    .select(comic_tags_mapping::comic_id)
+                    .load::<Uuid>(transaction)
+                    .await?;
+
+                // comics that already have the target keep their mapping,
+                // so no comic ends up with more tags than it had
+                let mappings = comic_ids
+                    .into_iter()
+                    .map(|comic_id| ComicTagMapping {
+                        comic_id,
+                        tag_id: target.id,
+                        created_at: Utc::now(),
+                    })
+                    .collect::<Vec<ComicTagMapping>>();
+
+                diesel::insert_into(comic_tags_mapping::table)
+                    .values(&mappings)
+                    .on_conflict_do_nothing()
+                    .execute(transaction)
+                    .await?;
+
+                diesel::delete(
+                    comic_tags_mapping::table.filter(comic_tags_mapping::tag_id.eq(source.id)),
+                )
+                .execute(transaction)
+                .await?;
+
+                // tags merged into the source point straight to the target, so merges are
+                // never more than one level deep
+                diesel::update(
+                    comic_tags::table.filter(
+                        comic_tags::merged_into
+                            .eq(source.id)
+                            .or(comic_tags::id.eq(source.id)),
+                    ),
+                )
+                .set(comic_tags::merged_into.eq(target.id))
+                .execute(transaction)
+                .await?;
+
+                Ok(target)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(Json(target.into_response()))
+}
+
+/// Ban a tag, it's removed from every comic and can't be added again
+#[utoipa::path(
+    post,
+    path = "/api/v1/comics/tags/:tag_id/ban",
+    responses(
+        (status = StatusCode::OK, description = "Tag has been banned"),
+        (status = StatusCode::NOT_FOUND, description = "Tag not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Comic Tags API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn ban_tag(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(tag_id): Path<Uuid>,
+) -> Result<(), ComicTagsError> {
+    let mut db = state.pool.get().await?;
+
+    db.transaction::<_, ComicTagsError, _>(|transaction| {
+        async move {
+            let name = diesel::update(comic_tags::table.find(tag_id))
+                .set(comic_tags::banned_at.eq(Some(Utc::now())))
+                .returning(comic_tags::name)
+                .get_result::<String>(transaction)
+                .await
+                .optional()?
+                .ok_or(ComicTagsError::TagNotFound)?;
+
+            let tagged_comics = comic_tags_mapping::table
+                .inner_join(comics::table)
+                .filter(comic_tags_mapping::tag_id.eq(tag_id))
+                .select((comics::id, comics::user_id))
+                .load::<(Uuid, Uuid)>(transaction)
+                .await?;
+
+            diesel::delete(comic_tags_mapping::table.filter(comic_tags_mapping::tag_id.eq(tag_id)))
+                .execute(transaction)
+                .await?;
+
+            // authors get to know why the tag is gone from their comics
+            notify(
+                transaction,
+                tagged_comics
+                    .into_iter()
+                    .map(|(comic_id, author_id)| {
+                        Notification::new(author_id, NotificationKind::Moderation)
+                            .comic(comic_id)
+                            .details(format!(
+                                "the tag \"{name}\" was banned and removed from the comic"
+                            ))
+                    })
+                    .collect(),
+            )
+            .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Unban a tag, comics it was removed from don't get it back
+#[utoipa::path(
+    post,
+    path = "/api/v1/comics/tags/:tag_id/unban",
+    responses(
+        (status = StatusCode::OK, description = "Tag has been unbanned"),
+        (status = StatusCode::NOT_FOUND, description = "Tag not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Comic Tags API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn unban_tag(
+    _auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(tag_id): Path<Uuid>,
+) -> Result<(), ComicTagsError> {
+    let mut db = state.pool.get().await?;
+
+    let updated = diesel::update(comic_tags::table.find(tag_id))
+        .set(comic_tags::banned_at.eq(None::<DateTime<Utc>>))
+        .execute(&mut db)
+        .await?;
+
+    if updated == 0 {
+        return Err(ComicTagsError::TagNotFound);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use regex::Regex;
+
+pub const TAG_MIN_LENGTH: usize = 2;
+pub const TAG_MAX_LENGTH: usize = 40;
+
+/// Normalize a tag name so the different spellings of a tag end up as the same tag
+///
+/// tashkeel and tatweel are removed and the letter forms are unified
+/// the same way `normalize_arabic` does it in the database,
+/// only letters, numbers, spaces and dashes are kept
+pub fn normalize_tag_name(name: &str) -> String {
+    let re = Regex::new(r"[\u{064B}-\u{065F}\u{0670}\u{06D6}-\u{06ED}\u{0640}]")
+        .expect("valid regex for tashkeel and tatweel");
+    let name = re.replace_all(name, "");
+
+    let name = name
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'أ' | 'إ' | 'آ' | 'ٱ' => 'ا',
+            'ؤ' => 'و',
+            'ئ' | 'ى' => 'ي',
+            'ة' => 'ه',
+            c => c,
+        })
+        .collect::<String>();
+
+    let re = Regex::new(r"[^\p{L}\p{N}\s-]")
+        .expect("valid regex for characters that are not letters, numbers, whitespaces or dashes");
+    let name = re.replace_all(&name, "");
+
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Normalized tag name, or `None` if it's too short or too long to be a tag
+pub fn normalize_tag(name: &str) -> Option<String> {
+    let name = normalize_tag_name(name);
+
+    (TAG_MIN_LENGTH..=TAG_MAX_LENGTH)
+        .contains(&name.chars().count())
+        .then_some(name)
+}
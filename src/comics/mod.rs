@@ -2,14 +2,17 @@ use axum::{http::StatusCode, response::IntoResponse};
 use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
 use diesel_async::pooled_connection::deadpool::PoolError;
 
-use crate::ErrorResponse;
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
 
 use self::chapters::routes::FILE_SIZE_LIMIT_MB;
 
+pub mod aggregates;
 pub mod chapters;
 pub mod comic_comments;
 pub mod comic_genres;
+pub mod comic_tags;
 pub mod models;
+pub mod rankings;
 pub mod routes;
 mod utils;
 
@@ -21,6 +24,9 @@ pub enum ComicsError {
     #[error("comic not found")]
     ComicNotFound,
 
+    #[error("mature content is only available to verified adults")]
+    MatureContent,
+
     #[error("bad request")]
     BadRequest,
 
@@ -38,6 +44,9 @@ pub enum ComicsError {
 
     #[error(transparent)]
     ComicGenresErrors(#[from] crate::comics::comic_genres::ComicGenresError),
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
 }
 
 impl IntoResponse for ComicsError {
@@ -46,6 +55,14 @@ impl IntoResponse for ComicsError {
 
         match self {
             ComicsError::ComicNotFound => StatusCode::NOT_FOUND.into_response(),
+            ComicsError::MatureContent => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
             ComicsError::BadRequest => StatusCode::BAD_REQUEST.into_response(),
             ComicsError::ImageTooLarge => StatusCode::BAD_REQUEST.into_response(),
             ComicsError::Diesel(diesel_error) => {
@@ -118,6 +135,7 @@ impl IntoResponse for ComicsError {
             ComicsError::PoolError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
             ComicsError::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
             ComicsError::ComicGenresErrors(_) => StatusCode::BAD_REQUEST.into_response(),
+            ComicsError::InvalidCursor(err) => err.into_response(),
             ComicsError::Validator(errors) => (
                 StatusCode::BAD_REQUEST,
                 ErrorResponse {
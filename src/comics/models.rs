@@ -1,5 +1,13 @@
+use std::io::Write;
+
 use chrono::{DateTime, Utc};
-use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgValue},
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    AsExpression, FromSqlRow,
+};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use utoipa::{IntoParams, ToSchema};
@@ -8,15 +16,163 @@ use uuid::Uuid;
 use crate::{
     comics::chapters::models::ChapterResponseBrief,
     comics::comic_genres::models::ComicGenre,
+    comics::comic_tags::models::{ComicTag, ComicTagResponse},
+    common::models::RatingSummaryResponse,
+    library::models::LibraryStatus,
+    s3::signing::ImageSigner,
     schema::{comic_ratings, comics},
     users::models::{User, UserResponseBrief},
-    Rating, SortingOrder,
+    utils::{average_rating, comma_separated},
 };
 
-use super::{
-    chapters::models::{Chapter, ChapterPage},
-    comic_genres::models::Genre,
-};
+use super::chapters::models::{Chapter, ChapterPage};
+
+/// Comics are in arabic unless their author says otherwise
+pub const DEFAULT_ORIGINAL_LANGUAGE: &str = "ar";
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    AsExpression,
+    FromSqlRow,
+    TS,
+    Copy,
+    Clone,
+    ToSchema,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Comicstatus)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ComicStatus {
+    #[default]
+    Ongoing,
+    Completed,
+    Hiatus,
+    Cancelled,
+}
+
+impl ToSql<crate::schema::sql_types::Comicstatus, Pg> for ComicStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ComicStatus::Ongoing => out.write_all(b"ongoing"),
+            ComicStatus::Completed => out.write_all(b"completed"),
+            ComicStatus::Hiatus => out.write_all(b"hiatus"),
+            ComicStatus::Cancelled => out.write_all(b"cancelled"),
+        }?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::Comicstatus, Pg> for ComicStatus {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"ongoing" => Ok(ComicStatus::Ongoing),
+            b"completed" => Ok(ComicStatus::Completed),
+            b"hiatus" => Ok(ComicStatus::Hiatus),
+            b"cancelled" => Ok(ComicStatus::Cancelled),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    AsExpression,
+    FromSqlRow,
+    TS,
+    Copy,
+    Clone,
+    ToSchema,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Contentrating)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ContentRating {
+    #[default]
+    AllAges,
+    Teen,
+    /// only visible to verified adults
+    Mature,
+}
+
+impl ToSql<crate::schema::sql_types::Contentrating, Pg> for ContentRating {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ContentRating::AllAges => out.write_all(b"all_ages"),
+            ContentRating::Teen => out.write_all(b"teen"),
+            ContentRating::Mature => out.write_all(b"mature"),
+        }?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::Contentrating, Pg> for ContentRating {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"all_ages" => Ok(ContentRating::AllAges),
+            b"teen" => Ok(ContentRating::Teen),
+            b"mature" => Ok(ContentRating::Mature),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    AsExpression,
+    FromSqlRow,
+    TS,
+    Copy,
+    Clone,
+    ToSchema,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Readingdirection)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ReadingDirection {
+    /// right to left, like arabic manga-style works
+    #[default]
+    Rtl,
+    Ltr,
+    /// vertical scrolling, like webtoons
+    Vertical,
+}
+
+impl ToSql<crate::schema::sql_types::Readingdirection, Pg> for ReadingDirection {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ReadingDirection::Rtl => out.write_all(b"rtl"),
+            ReadingDirection::Ltr => out.write_all(b"ltr"),
+            ReadingDirection::Vertical => out.write_all(b"vertical"),
+        }?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::Readingdirection, Pg> for ReadingDirection {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"rtl" => Ok(ReadingDirection::Rtl),
+            b"ltr" => Ok(ReadingDirection::Ltr),
+            b"vertical" => Ok(ReadingDirection::Vertical),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
 
 #[derive(Insertable, Queryable, Selectable, Associations, Identifiable, Debug, Clone)]
 #[diesel(belongs_to(User))]
@@ -34,6 +190,13 @@ pub struct Comic {
     pub poster_path: Option<String>,
     pub poster_content_type: Option<String>,
     pub user_id: Uuid,
+    pub status: ComicStatus,
+    pub content_rating: ContentRating,
+    pub reading_direction: ReadingDirection,
+    pub original_language: String,
+    pub rating_sum: f64,
+    pub rating_count: i64,
+    pub chapters_count: i64,
 }
 
 #[derive(Serialize, ToSchema, TS)]
@@ -43,11 +206,36 @@ pub struct ComicResponse {
     pub title: String,
     pub slug: String,
     pub description: Option<String>,
+    pub status: ComicStatus,
+    pub content_rating: ContentRating,
+    pub reading_direction: ReadingDirection,
+    pub original_language: String,
     pub rating: f64,
+    pub ratings: RatingSummaryResponse,
     pub created_at: String,
     pub author: UserResponseBrief,
     pub chapters: Vec<ChapterResponseBrief>,
     pub genres: Vec<ComicGenre>,
+    pub tags: Vec<ComicTagResponse>,
+    /// the comic's status in the current user's library, `null` if it isn't in it
+    pub library_status: Option<LibraryStatus>,
+}
+
+impl ComicResponse {
+    pub fn with_tags(mut self, tags: Vec<ComicTag>) -> Self {
+        self.tags = tags.into_iter().map(ComicTag::into_response).collect();
+        self
+    }
+
+    pub fn with_library_status(mut self, library_status: Option<LibraryStatus>) -> Self {
+        self.library_status = library_status;
+        self
+    }
+
+    pub fn with_ratings(mut self, ratings: RatingSummaryResponse) -> Self {
+        self.ratings = ratings;
+        self
+    }
 }
 
 #[derive(Serialize, ToSchema, TS)]
@@ -57,103 +245,146 @@ pub struct ComicResponseBrief {
     pub title: String,
     pub slug: String,
     pub description: Option<String>,
+    pub status: ComicStatus,
+    pub content_rating: ContentRating,
+    pub reading_direction: ReadingDirection,
+    pub original_language: String,
     pub rating: f64,
     pub chapters_count: i64,
     pub created_at: String,
     pub genres: Vec<ComicGenre>,
 }
 
-#[derive(Debug, Deserialize, ToSchema, TS)]
-#[ts(export)]
-pub struct ComicsPagination {
-    #[serde(default = "Uuid::max")]
-    pub max_id: Uuid,
-    #[serde(default)]
-    pub order: Order,
-}
-
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct ComicsParams {
+    /// comma separated genre ids, e.g. `1,4,7`
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = Option<String>)]
+    pub genres: Vec<i32>,
+    /// whether comics need all of the genres or any of them
+    #[serde(default)]
+    pub genre_mode: GenreMode,
+    /// comma separated genre ids, comics with any of them are left out
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = Option<String>)]
+    pub exclude_genres: Vec<i32>,
+    /// comma separated tag names, comics need all of them
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = Option<String>)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub author_id: Option<Uuid>,
+    #[serde(default)]
+    pub status: Option<ComicStatus>,
+    /// mature comics are left out for users that can't see them
+    #[serde(default)]
+    pub content_rating: Option<ContentRating>,
+    #[serde(default)]
+    pub reading_direction: Option<ReadingDirection>,
+    /// ISO 639 language code, e.g. `ar`
     #[serde(default)]
-    pub genre: Option<i32>,
+    pub original_language: Option<String>,
     #[serde(default)]
-    pub sorting: Option<SortingOrder>,
+    pub min_rating: Option<f64>,
+    #[serde(default)]
+    pub min_chapters: Option<i64>,
+    #[serde(default)]
+    pub max_chapters: Option<i64>,
+    #[serde(default)]
+    pub created_after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub created_before: Option<DateTime<Utc>>,
+    /// comics that were never updated count as updated when they were created
+    #[serde(default)]
+    pub updated_after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub order: ComicsOrder,
 }
 
-#[derive(Debug, Deserialize, Serialize, ToSchema, TS)]
+#[derive(Debug, Deserialize, Default, Clone, Copy, ToSchema, TS)]
 #[serde(rename_all = "snake_case")]
 #[ts(export)]
-pub enum Order {
-    Latest(DateTime<chrono::Utc>),
-    Best(f64),
+pub enum ComicsOrder {
+    /// newest first
+    #[default]
+    Latest,
+    /// highest rated first, by the ratings' bayesian average so comics with only a few
+    /// ratings don't outrank well rated ones with a lot of them
+    Best,
+    /// most recently popular first, by the views, ratings, library adds and comments of the
+    /// last two weeks with the older ones weighing less
+    Trending,
+    /// most viewed of all time first
+    MostViewed,
 }
 
-impl Default for Order {
-    fn default() -> Self {
-        Self::Latest(Utc::now())
-    }
+#[derive(Debug, Deserialize, Default, ToSchema, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum GenreMode {
+    All,
+    #[default]
+    Any,
 }
 
-impl Default for ComicsPagination {
-    fn default() -> Self {
-        ComicsPagination {
-            max_id: Uuid::max(),
-            order: Order::default(),
-        }
-    }
+/// Sort key of the last comic of a page, it's encoded in the cursor with the comic's id
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    Latest(DateTime<chrono::Utc>),
+    Best(f64),
+    Trending(f64),
+    MostViewed(i64),
 }
 
 impl Comic {
     pub fn into_resonse(
         self,
         user: UserResponseBrief,
-        genres: Vec<Genre>,
+        genres: Vec<ComicGenre>,
         chapter_and_pages: Vec<(Chapter, Vec<ChapterPage>)>,
-        rating: f64,
+        image_signer: &ImageSigner,
+        viewer: Option<Uuid>,
     ) -> ComicResponse {
         ComicResponse {
             id: self.id,
             title: self.title,
             slug: self.slug,
             description: self.description,
+            status: self.status,
+            content_rating: self.content_rating,
+            reading_direction: self.reading_direction,
+            original_language: self.original_language,
             created_at: self.created_at.to_string(),
-            rating,
+            rating: average_rating(self.rating_sum, self.rating_count),
+            ratings: RatingSummaryResponse::default(),
             author: user,
             chapters: chapter_and_pages
                 .into_iter()
-                .map(|(chapter, pages)| chapter.into_response_brief(pages))
-                .collect(),
-            genres: genres
-                .into_iter()
-                .map(|genre| ComicGenre {
-                    id: genre.id,
-                    name: genre.name,
-                })
+                .map(|(chapter, pages)| chapter.into_response_brief(pages, image_signer, viewer))
                 .collect(),
+            genres,
+            tags: vec![],
+            library_status: None,
         }
     }
 
-    pub fn into_response_brief(
-        self,
-        genres: Vec<Genre>,
-        chapters_count: i64,
-        rating: f64,
-    ) -> ComicResponseBrief {
+    pub fn into_response_brief(self, genres: Vec<ComicGenre>) -> ComicResponseBrief {
         ComicResponseBrief {
             id: self.id,
             title: self.title,
             slug: self.slug,
             description: self.description,
-            rating,
-            chapters_count,
+            status: self.status,
+            content_rating: self.content_rating,
+            reading_direction: self.reading_direction,
+            original_language: self.original_language,
+            rating: average_rating(self.rating_sum, self.rating_count),
+            chapters_count: self.chapters_count,
             created_at: self.created_at.to_string(),
-            genres: genres
-                .into_iter()
-                .map(|genre| ComicGenre {
-                    id: genre.id,
-                    name: genre.name,
-                })
-                .collect(),
+            genres,
         }
     }
 }
@@ -172,27 +403,47 @@ pub struct ComicRating {
     pub comic_id: Uuid,
 }
 
-impl Rating for ComicRating {
-    fn rating(&self) -> f64 {
-        self.rating
-    }
-}
-
-#[derive(Deserialize, Serialize, ToSchema, TS)]
+#[derive(garde::Validate, Deserialize, Serialize, ToSchema, TS)]
 #[ts(export)]
 pub struct CreateComic {
+    #[garde(skip)]
     pub title: String,
+    #[garde(skip)]
     pub description: Option<String>,
+    #[garde(skip)]
     pub genres: Option<Vec<i32>>,
+    #[garde(skip)]
     pub is_visible: bool,
+    #[serde(default)]
+    #[garde(skip)]
+    pub status: ComicStatus,
+    #[serde(default)]
+    #[garde(skip)]
+    pub content_rating: ContentRating,
+    #[serde(default)]
+    #[garde(skip)]
+    pub reading_direction: ReadingDirection,
+    /// ISO 639 language code, arabic by default
+    #[garde(pattern("^[a-z]{2,3}$"))]
+    pub original_language: Option<String>,
 }
 
-#[derive(AsChangeset, Deserialize, ToSchema)]
+#[derive(garde::Validate, AsChangeset, Deserialize, ToSchema)]
 #[diesel(table_name = comics)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UpdateComic {
+    #[garde(skip)]
     pub title: Option<String>,
+    #[garde(skip)]
     pub description: Option<String>,
+    #[garde(skip)]
+    pub status: Option<ComicStatus>,
+    #[garde(skip)]
+    pub content_rating: Option<ContentRating>,
+    #[garde(skip)]
+    pub reading_direction: Option<ReadingDirection>,
+    #[garde(pattern("^[a-z]{2,3}$"))]
+    pub original_language: Option<String>,
 }
 
 #[derive(garde::Validate, Deserialize, Serialize, ToSchema, TS)]
@@ -0,0 +1,114 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use diesel::sql_types::{Double, Integer};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tokio::time::interval;
+
+use crate::InnerAppState;
+
+use super::ComicsError;
+
+const RANKINGS_INTERVAL_SECS: u64 = 10 * 60;
+
+/// A trending event loses half of its weight every this many days
+const TRENDING_HALF_LIFE_DAYS: f64 = 2.0;
+
+/// Events older than this don't count towards the trending score
+const TRENDING_WINDOW_DAYS: i32 = 14;
+
+// each event weighs its weight halved for every half-life that passed since it happened,
+// views are rolled up per day so a day's views are as old as the start of the day
+//
+// $1 is the half-life in days, $2 is the window in days
+const REFRESH_RANKINGS_QUERY: &str = "
+    INSERT INTO comic_rankings (comic_id, trending_score, views, computed_at)
+    SELECT
+        comics.id,
+        coalesce(trending.score, 0),
+        coalesce(total_views.views, 0),
+        now()
+    FROM comics
+    LEFT JOIN (
+        SELECT comic_id, sum(weight * power(0.5, age / $1)) AS score
+        FROM (
+            SELECT comic_id, views::float8 AS weight, ((now() AT TIME ZONE 'UTC')::date - day)::float8 AS age
+            FROM comic_daily_views
+            WHERE day > (now() AT TIME ZONE 'UTC')::date - $2
+
+            UNION ALL
+
+            SELECT comic_id, views::float8, ((now() AT TIME ZONE 'UTC')::date - day)::float8
+            FROM chapter_daily_views
+            WHERE day > (now() AT TIME ZONE 'UTC')::date - $2
+
+            UNION ALL
+
+            SELECT comic_id, 5, extract(epoch FROM now() - created_at) / 86400
+            FROM comic_ratings
+            WHERE created_at > now() - make_interval(days => $2)
+
+            UNION ALL
+
+            SELECT comic_id, 10, extract(epoch FROM now() - created_at) / 86400
+            FROM library_entries
+            WHERE created_at > now() - make_interval(days => $2)
+
+            UNION ALL
+
+            SELECT comic_id, 3, extract(epoch FROM now() - created_at) / 86400
+            FROM comic_comments
+            WHERE created_at > now() - make_interval(days => $2)
+
+            UNION ALL
+
+            SELECT comic_chapters.comic_id, 3, extract(epoch FROM now() - chapter_comments.created_at) / 86400
+            FROM chapter_comments
+            INNER JOIN comic_chapters ON comic_chapters.id = chapter_comments.chapter_id
+            WHERE chapter_comments.created_at > now() - make_interval(days => $2)
+        ) AS events
+        GROUP BY comic_id
+    ) AS trending ON trending.comic_id = comics.id
+    LEFT JOIN (
+        SELECT comic_id, sum(views)::bigint AS views
+        FROM (
+            SELECT comic_id, views FROM comic_daily_views
+            UNION ALL
+            SELECT comic_id, views FROM chapter_daily_views
+        ) AS daily_views
+        GROUP BY comic_id
+    ) AS total_views ON total_views.comic_id = comics.id
+    ON CONFLICT (comic_id) DO UPDATE SET
+        trending_score = excluded.trending_score,
+        views = excluded.views,
+        computed_at = excluded.computed_at
+";
+
+/// Recompute the trending scores and view counts of every comic
+///
+/// returns the number of ranked comics
+pub async fn refresh_rankings(db: &mut AsyncPgConnection) -> Result<usize, diesel::result::Error> {
+    diesel::sql_query(REFRESH_RANKINGS_QUERY)
+        .bind::<Double, _>(TRENDING_HALF_LIFE_DAYS)
+        .bind::<Integer, _>(TRENDING_WINDOW_DAYS)
+        .execute(db)
+        .await
+}
+
+/// Background task that keeps the comic rankings fresh
+pub async fn rankings_worker(state: Arc<InnerAppState>) {
+    let mut rankings_interval = interval(StdDuration::from_secs(RANKINGS_INTERVAL_SECS));
+
+    loop {
+        rankings_interval.tick().await;
+
+        let result = match state.pool.get().await {
+            Ok(mut db) => refresh_rankings(&mut db).await.map_err(ComicsError::from),
+            Err(err) => Err(err.into()),
+        };
+
+        match result {
+            Ok(ranked) => tracing::debug!("refreshed the rankings of {ranked} comics"),
+            Err(err) => tracing::error!("failed to refresh comic rankings: {:#?}", err),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
     extract::{Path, Query, State},
@@ -6,34 +6,62 @@ use axum::{
     Json, Router,
 };
 use chrono::Utc;
-use diesel::{dsl::avg, prelude::*};
+use diesel::{
+    dsl::{count, sql},
+    prelude::*,
+    sql_types::{Bool, Double},
+};
 use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use garde::Validate;
 use itertools::multizip;
-use itertools::Itertools;
 use uuid::Uuid;
 
 use crate::{
     auth::AuthExtractor,
     coalesce,
     comics::chapters::models::Chapter,
-    comics::models::{ComicsParams, NewComicRating, Order},
-    comics::{
-        comic_genres::models::{Genre, GenreMapping},
-        models::ComicsPagination,
+    comics::comic_genres::{
+        models::{Genre, GenreMapping},
+        utils::{genre_translations, with_subgenres},
+    },
+    comics::comic_tags::{
+        models::{ComicTag, ComicTagMapping},
+        utils::normalize_tag,
+    },
+    comics::models::{
+        ComicsOrder, ComicsParams, ContentRating, GenreMode, NewComicRating, Order,
+        DEFAULT_ORIGINAL_LANGUAGE,
+    },
+    common::{
+        locale::{Locale, LocaleParams},
+        pagination::{InvalidCursor, Paginated, PaginationParams},
+    },
+    library::{models::LibraryStatus, utils::library_statuses},
+    notifications::{
+        models::{Notification, NotificationKind},
+        utils::notify,
+        RATING_MILESTONES,
     },
-    schema::{comic_genres, comic_genres_mapping, comic_ratings, comics, users},
-    users::models::{User, UserRole},
-    utils::average_rating,
+    recommendations::routes::get_similar_comics,
+    schema::{
+        comic_genres, comic_genres_mapping, comic_rankings, comic_ratings, comic_tags,
+        comic_tags_mapping, comics, library_entries, users,
+    },
+    users::{
+        models::{User, UserRole},
+        utils::can_view_mature_content,
+    },
+    utils::rating_summary,
     AppState, InnerAppState,
 };
 
 use super::{
-    chapters::{models::ChapterPage, routes::chapters_router},
+    chapters::{entitlements::visible_chapters, models::ChapterPage, routes::chapters_router},
     comic_comments::routes::comic_comments_router,
     comic_genres::routes::comic_genres_router,
+    comic_tags::routes::comic_tags_router,
     models::{Comic, ComicRating, ComicResponse, CreateComic, UpdateComic},
-    utils::slugify,
+    utils::{comic_rating_summaries, slugify},
     ComicsError,
 };
 
@@ -46,7 +74,9 @@ pub fn comics_router() -> Router<AppState> {
         .route("/:comic_id", get(get_comic))
         .route("/by_slug/:slug/:username", get(get_comic_by_slug))
         .route("/:comic_id/rate", post(rate_comic))
+        .route("/:comic_id/similar", get(get_similar_comics))
         .nest("/", comic_genres_router())
+        .nest("/", comic_tags_router())
         .nest("/", comic_comments_router())
         .nest("/", chapters_router())
 }
@@ -55,6 +85,9 @@ pub fn comics_router() -> Router<AppState> {
 #[utoipa::path(
     post,
     path = "/api/v1/comics",
+    params(
+        LocaleParams,
+    ),
     request_body(content = CreateComic, content_type = "application/json"),
     responses(
         (status = 200, description = "Caller authorized. returned requested comic", body = ComicResponse),
@@ -68,12 +101,17 @@ pub fn comics_router() -> Router<AppState> {
 )]
 pub async fn create_comic(
     auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
     State(state): State<Arc<InnerAppState>>,
     Json(payload): Json<CreateComic>,
 ) -> Result<Json<ComicResponse>, ComicsError> {
+    payload.validate(&())?;
+
     // save comic to db
     let mut db = state.pool.get().await?;
 
+    let image_signer = &state.image_signer;
+
     let comic_response = db
         .transaction::<_, ComicsError, _>(|transaction| {
             async move {
@@ -87,8 +125,17 @@ pub async fn create_comic(
                     published_at: None,
                     poster_path: None,
                     poster_content_type: None,
+                    status: payload.status,
+                    content_rating: payload.content_rating,
+                    reading_direction: payload.reading_direction,
+                    original_language: payload
+                        .original_language
+                        .unwrap_or_else(|| String::from(DEFAULT_ORIGINAL_LANGUAGE)),
                     created_at: Utc::now(),
                     updated_at: None,
+                    rating_sum: 0.0,
+                    rating_count: 0,
+                    chapters_count: 0,
                 };
 
                 let comic = diesel::insert_into(comics::table)
@@ -97,7 +144,7 @@ pub async fn create_comic(
                     .get_result(transaction)
                     .await?;
 
-                let genres: Vec<Genre> = if let Some(genres) = payload.genres {
+                let genres = if let Some(genres) = payload.genres {
                     let db_genre_mappings: Vec<GenreMapping> = genres
                         .iter()
                         .map(|genre| GenreMapping {
@@ -111,21 +158,29 @@ pub async fn create_comic(
                         .execute(transaction)
                         .await?;
 
+                    let translations = genre_translations(transaction, locale).await?;
+
                     GenreMapping::belonging_to(&comic)
                         .inner_join(comic_genres::table)
                         .select(Genre::as_select())
                         .load::<Genre>(transaction)
                         .await?
+                        .into_iter()
+                        .map(|genre| genre.into_localized(&translations))
+                        .collect()
                 } else {
                     vec![]
                 };
 
-                Ok(comic.into_resonse(auth.current_user, genres, vec![], 0.0))
+                Ok(comic.into_resonse(auth.current_user, genres, vec![], image_signer, None))
             }
             .scope_boxed()
         })
         .await?;
 
+    let comic_response =
+        comic_response.with_ratings(rating_summary(vec![], None, state.rating_prior));
+
     Ok(Json(comic_response))
 }
 
@@ -133,9 +188,13 @@ pub async fn create_comic(
 #[utoipa::path(
     get,
     path = "/api/v1/comics/:comic_id",
+    params(
+        LocaleParams,
+    ),
     responses(
         (status = 200, description = "Caller authorized. returned requested comic", body = ComicResponse),
         (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse ),
+        (status = StatusCode::FORBIDDEN, description = "Comic is rated mature and caller isn't a verified adult", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     security(
@@ -145,7 +204,8 @@ pub async fn create_comic(
 )]
 #[axum::debug_handler(state = AppState)]
 pub async fn get_comic(
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
     State(state): State<Arc<InnerAppState>>,
     Path(comic_id): Path<Uuid>,
 ) -> Result<Json<ComicResponse>, ComicsError> {
@@ -158,10 +218,30 @@ pub async fn get_comic(
         .first::<(Comic, User)>(&mut db)
         .await?;
 
+    if comic.content_rating == ContentRating::Mature
+        && comic.user_id != auth.current_user.id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        return Err(ComicsError::MatureContent);
+    }
+
     let chapters = Chapter::belonging_to(&comic)
+        .select(Chapter::as_select())
         .load::<Chapter>(&mut db)
         .await?;
 
+    let visible = visible_chapters(
+        &mut db,
+        &chapters.iter().collect::<Vec<&Chapter>>(),
+        Some(&auth.current_user),
+    )
+    .await?;
+
+    let chapters = chapters
+        .into_iter()
+        .filter(|chapter| visible.contains(&chapter.id))
+        .collect::<Vec<Chapter>>();
+
     let chapter_pages = ChapterPage::belonging_to(&chapters)
         .select(ChapterPage::as_select())
         .load::<ChapterPage>(&mut db)
@@ -174,32 +254,67 @@ pub async fn get_comic(
         .map(|(p, c)| (c, p))
         .collect::<Vec<(Chapter, Vec<ChapterPage>)>>();
 
+    let translations = genre_translations(&mut db, locale).await?;
+
     let genres = GenreMapping::belonging_to(&comic)
         .inner_join(comic_genres::table)
         .select(Genre::as_select())
-        .load(&mut db)
-        .await?;
+        .load::<Genre>(&mut db)
+        .await?
+        .into_iter()
+        .map(|genre| genre.into_localized(&translations))
+        .collect();
 
-    let comic_ratings = ComicRating::belonging_to(&comic)
-        .select(ComicRating::as_select())
+    let tags = ComicTagMapping::belonging_to(&comic)
+        .inner_join(comic_tags::table)
+        .order(comic_tags::name.asc())
+        .select(ComicTag::as_select())
         .load(&mut db)
         .await?;
 
-    Ok(Json(comic.into_resonse(
-        user.into_response_brief(),
-        genres,
-        chapters_and_pages,
-        average_rating(comic_ratings),
-    )))
+    let ratings = comic_rating_summaries(
+        &mut db,
+        &[comic.id],
+        Some(auth.current_user.id),
+        state.rating_prior,
+    )
+    .await?
+    .remove(&comic.id)
+    .unwrap_or_default();
+
+    let library_status = library_entries::table
+        .find((auth.current_user.id, comic.id))
+        .select(library_entries::status)
+        .first::<LibraryStatus>(&mut db)
+        .await
+        .optional()?;
+
+    Ok(Json(
+        comic
+            .into_resonse(
+                user.into_response_brief(),
+                genres,
+                chapters_and_pages,
+                &state.image_signer,
+                Some(auth.current_user.id),
+            )
+            .with_tags(tags)
+            .with_library_status(library_status)
+            .with_ratings(ratings),
+    ))
 }
 
 /// Get comic by slug and username
 #[utoipa::path(
     get,
     path = "/api/v1/comics/by_slug/:slug/:username",
+    params(
+        LocaleParams,
+    ),
     responses(
         (status = 200, description = "Caller authorized. returned requested comic", body = ComicResponse),
         (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse ),
+        (status = StatusCode::FORBIDDEN, description = "Comic is rated mature and caller isn't a verified adult", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     security(
@@ -209,7 +324,8 @@ pub async fn get_comic(
 )]
 #[axum::debug_handler(state = AppState)]
 pub async fn get_comic_by_slug(
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
     State(state): State<Arc<InnerAppState>>,
     Path((slug, username)): Path<(String, String)>,
 ) -> Result<Json<ComicResponse>, ComicsError> {
@@ -223,10 +339,30 @@ pub async fn get_comic_by_slug(
         .first::<(Comic, User)>(&mut db)
         .await?;
 
+    if comic.content_rating == ContentRating::Mature
+        && comic.user_id != auth.current_user.id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        return Err(ComicsError::MatureContent);
+    }
+
     let chapters = Chapter::belonging_to(&comic)
+        .select(Chapter::as_select())
         .load::<Chapter>(&mut db)
         .await?;
 
+    let visible = visible_chapters(
+        &mut db,
+        &chapters.iter().collect::<Vec<&Chapter>>(),
+        Some(&auth.current_user),
+    )
+    .await?;
+
+    let chapters = chapters
+        .into_iter()
+        .filter(|chapter| visible.contains(&chapter.id))
+        .collect::<Vec<Chapter>>();
+
     let chapter_pages = ChapterPage::belonging_to(&chapters)
         .select(ChapterPage::as_select())
         .load::<ChapterPage>(&mut db)
@@ -239,107 +375,350 @@ pub async fn get_comic_by_slug(
         .map(|(p, c)| (c, p))
         .collect::<Vec<(Chapter, Vec<ChapterPage>)>>();
 
+    let translations = genre_translations(&mut db, locale).await?;
+
     let genres = GenreMapping::belonging_to(&comic)
         .inner_join(comic_genres::table)
         .select(Genre::as_select())
-        .load(&mut db)
-        .await?;
+        .load::<Genre>(&mut db)
+        .await?
+        .into_iter()
+        .map(|genre| genre.into_localized(&translations))
+        .collect();
 
-    let comic_ratings = ComicRating::belonging_to(&comic)
-        .select(ComicRating::as_select())
+    let tags = ComicTagMapping::belonging_to(&comic)
+        .inner_join(comic_tags::table)
+        .order(comic_tags::name.asc())
+        .select(ComicTag::as_select())
         .load(&mut db)
         .await?;
 
-    Ok(Json(comic.into_resonse(
-        user.into_response_brief(),
-        genres,
-        chapters_and_pages,
-        average_rating(comic_ratings),
-    )))
+    let ratings = comic_rating_summaries(
+        &mut db,
+        &[comic.id],
+        Some(auth.current_user.id),
+        state.rating_prior,
+    )
+    .await?
+    .remove(&comic.id)
+    .unwrap_or_default();
+
+    let library_status = library_entries::table
+        .find((auth.current_user.id, comic.id))
+        .select(library_entries::status)
+        .first::<LibraryStatus>(&mut db)
+        .await
+        .optional()?;
+
+    Ok(Json(
+        comic
+            .into_resonse(
+                user.into_response_brief(),
+                genres,
+                chapters_and_pages,
+                &state.image_signer,
+                Some(auth.current_user.id),
+            )
+            .with_tags(tags)
+            .with_library_status(library_status)
+            .with_ratings(ratings),
+    ))
 }
 
-/// Get comics with pagination and genre filtering
+/// Get comics with pagination and filtering
 #[utoipa::path(
     get,
     path = "/api/v1/comics",
-    request_body(content = ComicsPagination, content_type = "application/json"),
     params(
         ComicsParams,
+        PaginationParams,
+        LocaleParams,
     ),
     responses(
-        (status = 200, body = [ComicResponse]),
+        (status = 200, body = PaginatedComics),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
     tag = "Comics API"
 )]
 #[axum::debug_handler(state = AppState)]
 pub async fn get_comics(
+    auth: Option<AuthExtractor<{ UserRole::User as u32 }>>,
+    locale: Locale,
     State(state): State<Arc<InnerAppState>>,
     Query(filters): Query<ComicsParams>,
-    pagination: Option<Json<ComicsPagination>>,
-) -> Result<Json<Vec<ComicResponse>>, ComicsError> {
-    tracing::debug!("cursor: {:#?}", pagination);
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<ComicResponse>>, ComicsError> {
+    let cursor = pagination.cursor::<(Order, Uuid)>()?;
     let mut db = state.pool.get().await?;
-    let Json(pagination) = pagination.unwrap_or_default();
 
     // TODO: (possibly?) add ascending ordering, this requires finding someway to refactor this
     // TODO: change created_at to published_at when we have a frontend option to publish comics and
     // a way to not bypass this by publishing and unpublishing comics
-    let average_rating = coalesce(avg(comic_ratings::rating).nullable(), 0.0);
 
-    let query = comics::table
-        .left_join(comic_ratings::table)
+    // the best comics are ordered by the ratings' bayesian average, as if every comic had
+    // `weight` more ratings of `mean`
+    let prior = state.rating_prior;
+    let weighted = sql::<Double>(&format!(
+        "(({}::float8 + comics.rating_sum) / ({}::float8 + comics.rating_count))",
+        prior.mean * prior.weight,
+        prior.weight,
+    ));
+
+    // rankings are precomputed by the rankings worker, comics it didn't get to yet rank last
+    let trending_score = coalesce(comic_rankings::trending_score.nullable(), 0.0);
+    let views = coalesce(comic_rankings::views.nullable(), 0);
+
+    let mut query = comics::table
+        .left_join(comic_rankings::table)
         .inner_join(users::table)
-        .group_by((comics::id, users::id))
-        .limit(10)
-        .select((Comic::as_select(), User::as_select(), average_rating));
+        .limit(pagination.limit() + 1)
+        .select((
+            Comic::as_select(),
+            User::as_select(),
+            weighted.clone(),
+            trending_score,
+            views,
+        ))
+        .into_boxed();
+
+    // genres are matched with semi-joins so comics with several matching genres aren't
+    // returned more than once, and a genre matches its sub-genres too
+    if !filters.genres.is_empty() {
+        let mut genre_ids = filters.genres;
+        genre_ids.sort_unstable();
+        genre_ids.dedup();
+
+        let genre_groups = with_subgenres(&mut db, &genre_ids).await?;
+
+        match filters.genre_mode {
+            GenreMode::Any => {
+                query = query.filter(
+                    comics::id.eq_any(
+                        comic_genres_mapping::table
+                            .filter(comic_genres_mapping::genre_id.eq_any(genre_groups.concat()))
+                            .select(comic_genres_mapping::comic_id),
+                    ),
+                );
+            }
+            GenreMode::All => {
+                for genre_group in genre_groups {
+                    query = query.filter(
+                        comics::id.eq_any(
+                            comic_genres_mapping::table
+                                .filter(comic_genres_mapping::genre_id.eq_any(genre_group))
+                                .select(comic_genres_mapping::comic_id),
+                        ),
+                    );
+                }
+            }
+        }
+    }
 
-    let (comics, users, ratings): (Vec<Comic>, Vec<User>, Vec<f64>) = match pagination.order {
-        Order::Latest(prev_date) => {
-            let query = query
-                .filter(
-                    comics::created_at.lt(prev_date).or(comics::created_at
-                        .eq(prev_date)
-                        .and(comics::id.lt(pagination.max_id))),
-                )
-                .order((comics::created_at.desc(), comics::id.desc()));
+    if !filters.exclude_genres.is_empty() {
+        let genre_ids = with_subgenres(&mut db, &filters.exclude_genres)
+            .await?
+            .concat();
+
+        query = query.filter(
+            comics::id.ne_all(
+                comic_genres_mapping::table
+                    .filter(comic_genres_mapping::genre_id.eq_any(genre_ids))
+                    .select(comic_genres_mapping::comic_id),
+            ),
+        );
+    }
 
-            if let Some(genre_id) = filters.genre {
-                query
-                    .left_join(comic_genres_mapping::table.inner_join(comic_genres::table))
-                    .filter(comic_genres::id.eq(genre_id))
-                    .load::<(Comic, User, f64)>(&mut db)
-            } else {
-                query.load::<(Comic, User, f64)>(&mut db)
-            }
+    // tags are looked up by their normalized names and merged tags count as the tags they were
+    // merged into, names that aren't tags can't match any comic
+    if !filters.tags.is_empty() {
+        let Some(mut names) = filters
+            .tags
+            .iter()
+            .map(|tag| normalize_tag(tag))
+            .collect::<Option<Vec<String>>>()
+        else {
+            return Ok(Json(Paginated::new(vec![], None)));
+        };
+        names.sort_unstable();
+        names.dedup();
+
+        let tags = comic_tags::table
+            .filter(comic_tags::name.eq_any(&names))
+            .select((comic_tags::id, comic_tags::merged_into))
+            .load::<(Uuid, Option<Uuid>)>(&mut db)
+            .await?;
+
+        if tags.len() != names.len() {
+            return Ok(Json(Paginated::new(vec![], None)));
         }
-        Order::Best(prev_average) => {
-            let query = query
-                .having(
-                    average_rating.lt(prev_average).or(average_rating
-                        .eq(prev_average)
-                        .and(comics::id.lt(pagination.max_id))),
-                )
-                .order((average_rating.desc(), comics::id.desc()));
 
-            if let Some(genre_id) = filters.genre {
+        let mut tag_ids = tags
+            .into_iter()
+            .map(|(id, merged_into)| merged_into.unwrap_or(id))
+            .collect::<Vec<Uuid>>();
+        tag_ids.sort_unstable();
+        tag_ids.dedup();
+
+        let tags_count = tag_ids.len() as i64;
+
+        query = query.filter(
+            comics::id.eq_any(
+                comic_tags_mapping::table
+                    .filter(comic_tags_mapping::tag_id.eq_any(tag_ids))
+                    .group_by(comic_tags_mapping::comic_id)
+                    .having(count(comic_tags_mapping::tag_id).eq(tags_count))
+                    .select(comic_tags_mapping::comic_id),
+            ),
+        );
+    }
+
+    if let Some(author_id) = filters.author_id {
+        query = query.filter(comics::user_id.eq(author_id));
+    }
+
+    if let Some(status) = filters.status {
+        query = query.filter(comics::status.eq(status));
+    }
+
+    if let Some(content_rating) = filters.content_rating {
+        query = query.filter(comics::content_rating.eq(content_rating));
+    }
+
+    if !can_view_mature_content(&mut db, auth.as_ref().map(|auth| &auth.current_user)).await? {
+        query = query.filter(comics::content_rating.ne(ContentRating::Mature));
+    }
+
+    if let Some(reading_direction) = filters.reading_direction {
+        query = query.filter(comics::reading_direction.eq(reading_direction));
+    }
+
+    if let Some(original_language) = filters.original_language {
+        query = query.filter(comics::original_language.eq(original_language.to_lowercase()));
+    }
+
+    if let Some(min_chapters) = filters.min_chapters {
+        query = query.filter(comics::chapters_count.ge(min_chapters));
+    }
+
+    if let Some(max_chapters) = filters.max_chapters {
+        query = query.filter(comics::chapters_count.le(max_chapters));
+    }
+
+    if let Some(created_after) = filters.created_after {
+        query = query.filter(comics::created_at.ge(created_after));
+    }
+
+    if let Some(created_before) = filters.created_before {
+        query = query.filter(comics::created_at.lt(created_before));
+    }
+
+    let updated_at = coalesce(comics::updated_at, comics::created_at);
+
+    if let Some(updated_after) = filters.updated_after {
+        query = query.filter(updated_at.ge(updated_after));
+    }
+
+    if let Some(updated_before) = filters.updated_before {
+        query = query.filter(updated_at.lt(updated_before));
+    }
+
+    // comics without ratings average 0, the average is compared without dividing so the
+    // ratings' sum is compared to the minimum times their count
+    if let Some(min_rating) = filters.min_rating.filter(|min| *min > 0.0) {
+        query = query.filter(comics::rating_count.gt(0)).filter(
+            sql::<Bool>("comics.rating_sum >= ")
+                .bind::<Double, _>(min_rating)
+                .sql(" * comics.rating_count"),
+        );
+    }
+
+    let mut rows =
+        match (filters.order, cursor) {
+            (ComicsOrder::Latest, None) => query
+                .order((comics::created_at.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            (ComicsOrder::Latest, Some((Order::Latest(prev_date), prev_id))) => query
+                .filter(
+                    comics::created_at
+                        .lt(prev_date)
+                        .or(comics::created_at.eq(prev_date).and(comics::id.lt(prev_id))),
+                )
+                .order((comics::created_at.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            (ComicsOrder::Best, None) => {
                 query
-                    .left_join(comic_genres_mapping::table.inner_join(comic_genres::table))
-                    .filter(comic_genres::id.eq(genre_id))
-                    .load::<(Comic, User, f64)>(&mut db)
-            } else {
-                query.load::<(Comic, User, f64)>(&mut db)
+                    .order((weighted.desc(), comics::id.desc()))
+                    .load::<(Comic, User, f64, f64, i64)>(&mut db)
             }
+            (ComicsOrder::Best, Some((Order::Best(prev_rating), prev_id))) => query
+                .filter(
+                    weighted
+                        .clone()
+                        .lt(prev_rating)
+                        .or(weighted.clone().eq(prev_rating).and(comics::id.lt(prev_id))),
+                )
+                .order((weighted.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            (ComicsOrder::Trending, None) => query
+                .order((trending_score.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            (ComicsOrder::Trending, Some((Order::Trending(prev_score), prev_id))) => query
+                .filter(
+                    trending_score
+                        .lt(prev_score)
+                        .or(trending_score.eq(prev_score).and(comics::id.lt(prev_id))),
+                )
+                .order((trending_score.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            (ComicsOrder::MostViewed, None) => query
+                .order((views.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            (ComicsOrder::MostViewed, Some((Order::MostViewed(prev_views), prev_id))) => query
+                .filter(
+                    views
+                        .lt(prev_views)
+                        .or(views.eq(prev_views).and(comics::id.lt(prev_id))),
+                )
+                .order((views.desc(), comics::id.desc()))
+                .load::<(Comic, User, f64, f64, i64)>(&mut db),
+            // the cursor belongs to a differently ordered list
+            _ => return Err(InvalidCursor.into()),
         }
-    }
-    .await?
-    .into_iter()
-    .multiunzip();
+        .await?;
+
+    let next_cursor = pagination.page(
+        &mut rows,
+        |(comic, _, weighted_rating, trending_score, views)| {
+            let key = match filters.order {
+                ComicsOrder::Latest => Order::Latest(comic.created_at),
+                ComicsOrder::Best => Order::Best(*weighted_rating),
+                ComicsOrder::Trending => Order::Trending(*trending_score),
+                ComicsOrder::MostViewed => Order::MostViewed(*views),
+            };
+
+            (key, comic.id)
+        },
+    );
+
+    let (comics, users): (Vec<Comic>, Vec<User>) = rows
+        .into_iter()
+        .map(|(comic, user, _, _, _)| (comic, user))
+        .unzip();
 
     let chapters = Chapter::belonging_to(&comics)
+        .select(Chapter::as_select())
         .load::<Chapter>(&mut db)
         .await?;
 
+    let visible =
+        visible_chapters(&mut db, &chapters.iter().collect::<Vec<&Chapter>>(), None).await?;
+
+    let chapters = chapters
+        .into_iter()
+        .filter(|chapter| visible.contains(&chapter.id))
+        .collect::<Vec<Chapter>>();
+
     let chapter_pages = ChapterPage::belonging_to(&chapters)
         .select(ChapterPage::as_select())
         .load::<ChapterPage>(&mut db)
@@ -361,19 +740,56 @@ pub async fn get_comics(
 
     let genres = genres.grouped_by(&comics);
 
+    let translations = genre_translations(&mut db, locale).await?;
+
+    let tags: Vec<(ComicTagMapping, ComicTag)> = ComicTagMapping::belonging_to(&comics)
+        .inner_join(comic_tags::table)
+        .order(comic_tags::name.asc())
+        .select((ComicTagMapping::as_select(), ComicTag::as_select()))
+        .load::<(ComicTagMapping, ComicTag)>(&mut db)
+        .await?;
+
+    let tags = tags.grouped_by(&comics);
+
+    let comic_ids = comics.iter().map(|comic| comic.id).collect::<Vec<Uuid>>();
+
+    let library_statuses = match &auth {
+        Some(auth) => library_statuses(&mut db, auth.current_user.id, &comic_ids).await?,
+        None => HashMap::new(),
+    };
+
+    let mut rating_summaries = comic_rating_summaries(
+        &mut db,
+        &comic_ids,
+        auth.as_ref().map(|auth| auth.current_user.id),
+        prior,
+    )
+    .await?;
+
     let comics: Result<Vec<ComicResponse>, ComicsError> =
-        multizip((comics, users, genres, chapters_and_pages, ratings))
-            .map(|(comic, user, genres, chapter_and_pages, rating)| {
-                Ok(comic.into_resonse(
-                    user.into_response_brief(),
-                    genres.into_iter().map(|(_, genre)| genre).collect(),
-                    chapter_and_pages,
-                    rating,
-                ))
+        multizip((comics, users, genres, tags, chapters_and_pages))
+            .map(|(comic, user, genres, tags, chapter_and_pages)| {
+                let library_status = library_statuses.get(&comic.id).copied();
+                let rating_summary = rating_summaries.remove(&comic.id).unwrap_or_default();
+
+                Ok(comic
+                    .into_resonse(
+                        user.into_response_brief(),
+                        genres
+                            .into_iter()
+                            .map(|(_, genre)| genre.into_localized(&translations))
+                            .collect(),
+                        chapter_and_pages,
+                        &state.image_signer,
+                        None,
+                    )
+                    .with_tags(tags.into_iter().map(|(_, tag)| tag).collect())
+                    .with_library_status(library_status)
+                    .with_ratings(rating_summary))
             })
             .collect();
 
-    Ok(Json(comics?))
+    Ok(Json(Paginated::new(comics?, next_cursor)))
 }
 
 /// Update comic
@@ -394,6 +810,8 @@ pub async fn update_comic(
     Path(comic_id): Path<Uuid>,
     Json(payload): Json<UpdateComic>,
 ) -> Result<Json<Uuid>, ComicsError> {
+    payload.validate(&())?;
+
     let mut db = state.pool.get().await?;
 
     let updated_comic = diesel::update(
@@ -401,7 +819,7 @@ pub async fn update_comic(
             .filter(comics::id.eq(comic_id))
             .filter(comics::user_id.eq(auth.current_user.id)),
     )
-    .set(payload)
+    .set((payload, comics::updated_at.eq(Utc::now())))
     .returning(Comic::as_returning())
     .get_result(&mut db)
     .await
@@ -468,36 +886,82 @@ pub async fn rate_comic(
 
     let mut db = state.pool.get().await?;
 
-    match diesel::update(
-        comic_ratings::table
-            .filter(comic_ratings::user_id.eq(auth.current_user.id))
-            .filter(comic_ratings::comic_id.eq(comic_id)),
-    )
-    .set((
-        comic_ratings::updated_at.eq(Some(Utc::now())),
-        comic_ratings::rating.eq(payload.rating as f64),
-    ))
-    .get_result::<ComicRating>(&mut db)
-    .await
-    {
-        Err(diesel::result::Error::NotFound) => {
-            let comic_rating = ComicRating {
-                id: Uuid::now_v7(),
-                rating: payload.rating as f64,
-                created_at: Utc::now(),
-                updated_at: None,
-                user_id: auth.current_user.id,
-                comic_id,
-            };
+    let user_id = auth.current_user.id;
+    let rating = payload.rating as f64;
+
+    // the comic's rating sum and count change with its ratings so listing comics doesn't need
+    // to load them
+    db.transaction::<_, ComicsError, _>(|transaction| {
+        async move {
+            let previous_rating = comic_ratings::table
+                .filter(comic_ratings::user_id.eq(user_id))
+                .filter(comic_ratings::comic_id.eq(comic_id))
+                .select(comic_ratings::rating)
+                .for_update()
+                .first::<f64>(transaction)
+                .await
+                .optional()?;
+
+            match previous_rating {
+                Some(previous_rating) => {
+                    diesel::update(
+                        comic_ratings::table
+                            .filter(comic_ratings::user_id.eq(user_id))
+                            .filter(comic_ratings::comic_id.eq(comic_id)),
+                    )
+                    .set((
+                        comic_ratings::updated_at.eq(Some(Utc::now())),
+                        comic_ratings::rating.eq(rating),
+                    ))
+                    .execute(transaction)
+                    .await?;
 
-            diesel::insert_into(comic_ratings::table)
-                .values(comic_rating)
-                .execute(&mut db)
-                .await?;
+                    diesel::update(comics::table.find(comic_id))
+                        .set(comics::rating_sum.eq(comics::rating_sum + (rating - previous_rating)))
+                        .execute(transaction)
+                        .await?;
+                }
+                None => {
+                    let comic_rating = ComicRating {
+                        id: Uuid::now_v7(),
+                        rating,
+                        created_at: Utc::now(),
+                        updated_at: None,
+                        user_id,
+                        comic_id,
+                    };
+
+                    diesel::insert_into(comic_ratings::table)
+                        .values(comic_rating)
+                        .execute(transaction)
+                        .await?;
+
+                    let (author_id, rating_count) = diesel::update(comics::table.find(comic_id))
+                        .set((
+                            comics::rating_sum.eq(comics::rating_sum + rating),
+                            comics::rating_count.eq(comics::rating_count + 1),
+                        ))
+                        .returning((comics::user_id, comics::rating_count))
+                        .get_result::<(Uuid, i64)>(transaction)
+                        .await?;
+
+                    if RATING_MILESTONES.contains(&rating_count) {
+                        notify(
+                            transaction,
+                            vec![
+                                Notification::new(author_id, NotificationKind::RatingMilestone)
+                                    .comic(comic_id)
+                                    .milestone(rating_count),
+                            ],
+                        )
+                        .await?;
+                    }
+                }
+            }
 
             Ok(())
         }
-        Err(e) => Err(e.into()),
-        Ok(_) => Ok(()),
-    }
+        .scope_boxed()
+    })
+    .await
 }
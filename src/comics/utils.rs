@@ -1,4 +1,15 @@
+use std::collections::HashMap;
+
+use diesel::{dsl::count, prelude::*};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use regex::Regex;
+use uuid::Uuid;
+
+use crate::{
+    common::models::RatingSummaryResponse,
+    schema::comic_ratings,
+    utils::{rating_summary, RatingPrior},
+};
 
 pub fn slugify(title: &str) -> String {
     let re = Regex::new(r"[-/]").expect("valid regex for dash and slash");
@@ -14,3 +25,57 @@ pub fn slugify(title: &str) -> String {
 
     title.to_lowercase()
 }
+
+/// Rating summaries of the comics, with `viewer`'s own ratings if any
+///
+/// ratings are counted in the database so the comics' ratings aren't all loaded
+pub async fn comic_rating_summaries(
+    db: &mut AsyncPgConnection,
+    comic_ids: &[Uuid],
+    viewer: Option<Uuid>,
+    prior: RatingPrior,
+) -> QueryResult<HashMap<Uuid, RatingSummaryResponse>> {
+    let counts = comic_ratings::table
+        .filter(comic_ratings::comic_id.eq_any(comic_ids))
+        .group_by((comic_ratings::comic_id, comic_ratings::rating))
+        .select((
+            comic_ratings::comic_id,
+            comic_ratings::rating,
+            count(comic_ratings::id),
+        ))
+        .load::<(Uuid, f64, i64)>(db)
+        .await?;
+
+    let user_ratings = match viewer {
+        Some(viewer) => comic_ratings::table
+            .filter(comic_ratings::comic_id.eq_any(comic_ids))
+            .filter(comic_ratings::user_id.eq(viewer))
+            .select((comic_ratings::comic_id, comic_ratings::rating))
+            .load::<(Uuid, f64)>(db)
+            .await?
+            .into_iter()
+            .collect::<HashMap<Uuid, f64>>(),
+        None => HashMap::new(),
+    };
+
+    let mut counts_by_comic = HashMap::<Uuid, Vec<(f64, i64)>>::new();
+    for (comic_id, rating, times) in counts {
+        counts_by_comic
+            .entry(comic_id)
+            .or_default()
+            .push((rating, times));
+    }
+
+    Ok(comic_ids
+        .iter()
+        .map(|comic_id| {
+            let summary = rating_summary(
+                counts_by_comic.remove(comic_id).unwrap_or_default(),
+                user_ratings.get(comic_id).copied(),
+                prior,
+            );
+
+            (*comic_id, summary)
+        })
+        .collect())
+}
@@ -0,0 +1,99 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{header::ACCEPT_LANGUAGE, request::Parts},
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(
+    Serialize, Deserialize, ToSchema, TS, Debug, Clone, Copy, PartialEq, Eq, Hash, Default,
+)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum Locale {
+    #[default]
+    Ar,
+    En,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::Ar => "ar",
+            Locale::En => "en",
+        }
+    }
+
+    /// Only the primary subtag matters, `ar-EG` and `ar` are both arabic
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+
+        match primary.to_lowercase().as_str() {
+            "ar" => Some(Locale::Ar),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// Supported locale the client prefers the most in an `Accept-Language` header
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        header
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let locale = Locale::parse(parts.next()?)?;
+
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+
+                (quality > 0.0).then_some((locale, quality))
+            })
+            // the first of the equally preferred locales wins
+            .fold(
+                None,
+                |best: Option<(Locale, f32)>, (locale, quality)| match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((locale, quality)),
+                },
+            )
+            .map(|(locale, _)| locale)
+    }
+}
+
+#[derive(Deserialize, IntoParams, Debug, Default)]
+pub struct LocaleParams {
+    /// `ar` or `en`, takes precedence over the `Accept-Language` header
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+/// Locale of the response, from the `lang` query param, then the `Accept-Language` header,
+/// and arabic otherwise
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let from_param = Query::<LocaleParams>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(params)| params.lang)
+            .and_then(|lang| Locale::parse(&lang));
+
+        let locale = from_param
+            .or_else(|| {
+                parts
+                    .headers
+                    .get(ACCEPT_LANGUAGE)
+                    .and_then(|header| header.to_str().ok())
+                    .and_then(Locale::from_accept_language)
+            })
+            .unwrap_or_default();
+
+        Ok(locale)
+    }
+}
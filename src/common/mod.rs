@@ -1,4 +1,6 @@
+pub mod locale;
 pub mod models;
+pub mod pagination;
 
 // TODO: add common error type
 
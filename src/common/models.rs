@@ -3,9 +3,23 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use utoipa::ToSchema;
 
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug, Default)]
+#[ts(export)]
+pub struct RatingSummaryResponse {
+    /// bayesian average of the ratings, it's what the best comics are ordered by
+    pub weighted_rating: f64,
+    pub count: i64,
+    /// how many times each rating from 0 to 5 was given
+    pub histogram: Vec<i64>,
+    /// the current user's own rating, `null` if they haven't rated it
+    pub user_rating: Option<f64>,
+}
+
 #[derive(Queryable, Debug, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export)]
 pub struct ImageMetadataResponse {
     pub content_type: String,
     pub path: String,
+    /// signed url to get the image from, it expires after a few hours
+    pub url: String,
 }
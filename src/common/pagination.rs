@@ -0,0 +1,119 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    comics::{
+        chapters::{chapter_comments::models::ChapterCommentResponse, models::ChapterResponse},
+        comic_comments::models::ComicCommentResponse,
+        models::{ComicResponse, ComicResponseBrief},
+    },
+    library::models::{ContinueReadingResponse, LibraryEntryResponse},
+    notifications::models::NotificationResponse,
+    users::follows::models::FeedItemResponse,
+    ErrorResponse,
+};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 10;
+pub const MAX_PAGE_SIZE: i64 = 50;
+
+#[derive(thiserror::Error, Debug)]
+#[error("invalid cursor")]
+pub struct InvalidCursor;
+
+impl IntoResponse for InvalidCursor {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::BAD_REQUEST,
+            ErrorResponse {
+                error: self.to_string(),
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
+
+#[derive(Deserialize, IntoParams, Debug, Default)]
+pub struct PaginationParams {
+    /// `next_cursor` of the previous page, the first page is returned without it
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// number of items in a page, up to 50
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+impl PaginationParams {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// Decode the key of the last item of the previous page
+    pub fn cursor<K: DeserializeOwned>(&self) -> Result<Option<K>, InvalidCursor> {
+        self.cursor.as_deref().map(decode_cursor).transpose()
+    }
+
+    /// Cut the rows down to a page, returns the cursor of the next page if there is one
+    ///
+    /// rows have to be loaded with `limit() + 1`, the extra row tells whether there are more
+    pub fn page<R, K: Serialize>(
+        &self,
+        rows: &mut Vec<R>,
+        key: impl Fn(&R) -> K,
+    ) -> Option<String> {
+        let limit = self.limit() as usize;
+
+        if rows.len() <= limit {
+            return None;
+        }
+
+        rows.truncate(limit);
+        rows.last().map(|row| encode_cursor(&key(row)))
+    }
+}
+
+/// Cursors are opaque to clients, they only hand back what they got
+pub fn encode_cursor<K: Serialize>(key: &K) -> String {
+    let json = serde_json::to_vec(key).expect("cursor keys serialize to json");
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+pub fn decode_cursor<K: DeserializeOwned>(cursor: &str) -> Result<K, InvalidCursor> {
+    let json = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| InvalidCursor)?;
+    serde_json::from_slice(&json).map_err(|_| InvalidCursor)
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[aliases(
+    PaginatedComics = Paginated<ComicResponse>,
+    PaginatedComicsBrief = Paginated<ComicResponseBrief>,
+    PaginatedChapters = Paginated<ChapterResponse>,
+    PaginatedComicComments = Paginated<ComicCommentResponse>,
+    PaginatedChapterComments = Paginated<ChapterCommentResponse>,
+    PaginatedLibrary = Paginated<LibraryEntryResponse>,
+    PaginatedContinueReading = Paginated<ContinueReadingResponse>,
+    PaginatedFeed = Paginated<FeedItemResponse>,
+    PaginatedNotifications = Paginated<NotificationResponse>,
+)]
+#[ts(export)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    /// pass it as the `cursor` to get the next page
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>) -> Self {
+        Paginated {
+            items,
+            has_more: next_cursor.is_some(),
+            next_cursor,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::response::sse::Event;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events a stream can fall behind by before it's closed
+const HUB_CAPACITY: usize = 1024;
+
+/// What the database published
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HubEvent {
+    Notification { id: Uuid, user_id: Uuid },
+    ChapterComment { id: Uuid, chapter_id: Uuid },
+}
+
+/// An item serialized once, every stream sends the same data
+#[derive(Debug, Clone)]
+pub struct EventItem {
+    pub id: Uuid,
+    /// `notification` or `comment`
+    pub kind: &'static str,
+    pub data: Arc<str>,
+}
+
+impl EventItem {
+    pub fn to_event(&self) -> Event {
+        Event::default()
+            .event(self.kind)
+            .id(self.id.to_string())
+            .data(self.data.as_ref())
+    }
+}
+
+/// What the hub sends to the streams
+#[derive(Debug, Clone)]
+pub enum HubMessage {
+    /// an item committed on any instance, loaded by the listener of this one
+    Item(HubEvent, EventItem),
+    /// the listener reconnected and could have missed events
+    Resync,
+}
+
+/// Number of open streams of each user and of each chapter
+#[derive(Default, Debug)]
+struct Interests {
+    users: HashMap<Uuid, usize>,
+    chapters: HashMap<Uuid, usize>,
+}
+
+fn add_interest(counts: &mut HashMap<Uuid, usize>, id: Uuid) {
+    *counts.entry(id).or_default() += 1;
+}
+
+fn remove_interest(counts: &mut HashMap<Uuid, usize>, id: Uuid) {
+    if let Some(count) = counts.get_mut(&id) {
+        *count -= 1;
+
+        if *count == 0 {
+            counts.remove(&id);
+        }
+    }
+}
+
+/// Fans the events of every instance out to the open streams of this one
+#[derive(Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<HubMessage>,
+    interests: Arc<Mutex<Interests>>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(HUB_CAPACITY);
+
+        EventHub {
+            sender,
+            interests: Arc::default(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<HubMessage> {
+        self.sender.subscribe()
+    }
+
+    /// Record that a stream of `user_id` opened, along with the chapter whose comments it wants
+    pub fn add_stream(&self, user_id: Uuid, chapter_id: Option<Uuid>) {
+        let mut interests = self.interests.lock().expect("interests lock poisoned");
+
+        add_interest(&mut interests.users, user_id);
+
+        if let Some(chapter_id) = chapter_id {
+            add_interest(&mut interests.chapters, chapter_id);
+        }
+    }
+
+    /// Undo `add_stream` once the stream is closed
+    pub fn remove_stream(&self, user_id: Uuid, chapter_id: Option<Uuid>) {
+        let mut interests = self.interests.lock().expect("interests lock poisoned");
+
+        remove_interest(&mut interests.users, user_id);
+
+        if let Some(chapter_id) = chapter_id {
+            remove_interest(&mut interests.chapters, chapter_id);
+        }
+    }
+
+    /// Whether a stream of this instance wants the event, the others aren't loaded at all
+    pub fn wants(&self, event: HubEvent) -> bool {
+        let interests = self.interests.lock().expect("interests lock poisoned");
+
+        match event {
+            HubEvent::Notification { user_id, .. } => interests.users.contains_key(&user_id),
+            HubEvent::ChapterComment { chapter_id, .. } => {
+                interests.chapters.contains_key(&chapter_id)
+            }
+        }
+    }
+
+    /// Returns the number of streams the message was sent to
+    pub fn publish(&self, message: HubMessage) -> usize {
+        // no open streams isn't an error
+        self.sender.send(message).unwrap_or(0)
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
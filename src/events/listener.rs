@@ -0,0 +1,97 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use futures::{stream, StreamExt};
+use tokio::{sync::mpsc, time::sleep};
+use tokio_postgres::{AsyncMessage, NoTls};
+
+use crate::InnerAppState;
+
+use super::{
+    hub::{HubEvent, HubMessage},
+    utils::load_event,
+    EVENTS_CHANNEL,
+};
+
+const RECONNECT_DELAY_SECS: u64 = 5;
+
+/// Publish the events of the events channel to the hub until the connection closes
+///
+/// each item is loaded and serialized once here, the streams only filter what they get
+async fn listen(database_url: &str, state: &InnerAppState) -> Result<(), tokio_postgres::Error> {
+    let hub = &state.events;
+
+    let (client, mut connection) = tokio_postgres::connect(database_url, NoTls).await?;
+
+    // the connection does the actual work and has to be polled for the client to get anywhere
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let connection = tokio::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        while let Some(message) = messages.next().await {
+            if let AsyncMessage::Notification(notification) = message? {
+                // the receiver is only dropped when listening failed
+                let _ = sender.send(notification);
+            }
+        }
+
+        Ok::<(), tokio_postgres::Error>(())
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {EVENTS_CHANNEL}"))
+        .await?;
+
+    tracing::info!("listening for events on {EVENTS_CHANNEL}");
+
+    // streams opened while the listener was away reconnect and replay what they missed
+    hub.publish(HubMessage::Resync);
+
+    while let Some(notification) = receiver.recv().await {
+        let event = match serde_json::from_str::<HubEvent>(notification.payload()) {
+            Ok(event) => event,
+            Err(err) => {
+                tracing::error!(
+                    "invalid event payload {:?}: {:#?}",
+                    notification.payload(),
+                    err
+                );
+                continue;
+            }
+        };
+
+        // most events are for users and chapters nobody streams on this instance
+        if !hub.wants(event) {
+            continue;
+        }
+
+        // deleted items are skipped
+        match load_event(&state.pool, event).await {
+            Ok(Some(item)) => {
+                hub.publish(HubMessage::Item(event, item.serialize()));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::error!("failed to load event {:?}: {:#?}", event, err);
+                // the streams replay what they missed when they reconnect
+                hub.publish(HubMessage::Resync);
+            }
+        }
+    }
+
+    connection.await.unwrap_or_else(|err| {
+        tracing::error!("events connection task failed: {:#?}", err);
+        Ok(())
+    })
+}
+
+/// Background task that publishes the events of every instance to the streams of this one
+pub async fn events_listener(database_url: String, state: Arc<InnerAppState>) {
+    loop {
+        match listen(&database_url, &state).await {
+            Ok(()) => tracing::warn!("events connection closed, reconnecting"),
+            Err(err) => tracing::error!("events connection failed: {:#?}", err),
+        }
+
+        sleep(StdDuration::from_secs(RECONNECT_DELAY_SECS)).await;
+    }
+}
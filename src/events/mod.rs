@@ -0,0 +1,59 @@
+use axum::{
+    http::{HeaderName, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::ErrorResponse;
+
+pub mod hub;
+pub mod listener;
+pub mod models;
+pub mod routes;
+pub mod utils;
+
+/// Sent by EventSource clients when they reconnect, with the id of the last event they got
+pub const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+/// Postgres channel the triggers of new notifications and chapter comments publish to
+pub const EVENTS_CHANNEL: &str = "musawarah_events";
+
+/// Items replayed to a reconnecting client, older ones can be read from the paginated endpoints
+pub const REPLAY_LIMIT: i64 = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EventsError {
+    #[error("chapter not found")]
+    ChapterNotFound,
+
+    #[error("invalid Last-Event-ID")]
+    InvalidLastEventId,
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for EventsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let (status, error) = match &self {
+            EventsError::ChapterNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            EventsError::InvalidLastEventId => (StatusCode::BAD_REQUEST, self.to_string()),
+            EventsError::Diesel(_) | EventsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error,
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
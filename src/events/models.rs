@@ -0,0 +1,9 @@
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub struct EventsParams {
+    /// also stream the new comments of this chapter
+    pub chapter_id: Option<Uuid>,
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashSet, convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    comics::chapters::{entitlements::is_chapter_visible, models::Chapter},
+    schema::comic_chapters,
+    users::models::UserRole,
+    AppState, InnerAppState,
+};
+
+use super::{
+    hub::{EventHub, EventItem, HubEvent, HubMessage},
+    models::EventsParams,
+    utils::{load_chapter_comments, load_notifications, EventRange, StreamItem},
+    EventsError, LAST_EVENT_ID, REPLAY_LIMIT,
+};
+
+pub fn events_router() -> Router<AppState> {
+    Router::new().route("/", get(get_events))
+}
+
+/// The hub messages one stream is interested in
+struct Subscription {
+    hub: EventHub,
+    receiver: Receiver<HubMessage>,
+    user_id: Uuid,
+    chapter_id: Option<Uuid>,
+    /// items that were replayed can be published again after subscribing
+    replayed_ids: HashSet<Uuid>,
+}
+
+impl Subscription {
+    fn new(hub: &EventHub, user_id: Uuid, chapter_id: Option<Uuid>) -> Self {
+        hub.add_stream(user_id, chapter_id);
+
+        Subscription {
+            hub: hub.clone(),
+            receiver: hub.subscribe(),
+            user_id,
+            chapter_id,
+            replayed_ids: HashSet::new(),
+        }
+    }
+
+    fn wants(&self, event: HubEvent) -> bool {
+        match event {
+            HubEvent::Notification { id, user_id } => {
+                user_id == self.user_id && !self.replayed_ids.contains(&id)
+            }
+            HubEvent::ChapterComment { id, chapter_id } => {
+                Some(chapter_id) == self.chapter_id && !self.replayed_ids.contains(&id)
+            }
+        }
+    }
+
+    /// The next item to send, the stream ends when `None` is returned
+    ///
+    /// ending the stream when events could have been missed makes the client reconnect with
+    /// `Last-Event-ID` and get them replayed
+    async fn next(&mut self) -> Option<EventItem> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(HubMessage::Item(event, item)) if self.wants(event) => return Some(item),
+                Ok(HubMessage::Item(..)) => continue,
+                Ok(HubMessage::Resync) => return None,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("event stream fell behind by {skipped} events");
+                    return None;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.hub.remove_stream(self.user_id, self.chapter_id);
+    }
+}
+
+/// Stream the current user's new notifications, and the new comments of a chapter if one is given
+///
+/// `notification` events carry a notification and `comment` events a chapter comment, both are
+/// sent once they're committed on any instance, clients that reconnect with `Last-Event-ID` get
+/// what they missed replayed first
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    params(
+        EventsParams,
+        ("Last-Event-ID" = Option<Uuid>, Header, description = "id of the last event the client got"),
+    ),
+    responses(
+        (status = 200, description = "Stream of server-sent events", content_type = "text/event-stream", body = String),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid Last-Event-ID", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Chapter not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Events API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_events(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<EventsParams>,
+    headers: HeaderMap,
+) -> Result<Sse<BoxStream<'static, Result<Event, Infallible>>>, EventsError> {
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| Uuid::parse_str(value).ok())
+                .ok_or(EventsError::InvalidLastEventId)
+        })
+        .transpose()?;
+
+    let user_id = auth.current_user.id;
+    let mut db = state.pool.get().await?;
+
+    if let Some(chapter_id) = params.chapter_id {
+        let chapter = comic_chapters::table
+            .find(chapter_id)
+            .select(Chapter::as_select())
+            .first::<Chapter>(&mut db)
+            .await
+            .optional()?
+            .ok_or(EventsError::ChapterNotFound)?;
+
+        if !is_chapter_visible(&mut db, &chapter, Some(&auth.current_user)).await? {
+            return Err(EventsError::ChapterNotFound);
+        }
+    }
+
+    // subscribing before replaying doesn't lose what's committed in between
+    let mut subscription = Subscription::new(&state.events, user_id, params.chapter_id);
+
+    let mut replayed = vec![];
+    let mut replay_cut = false;
+
+    if let Some(last_event_id) = last_event_id {
+        let range = EventRange::After(last_event_id);
+
+        let notifications = load_notifications(&mut db, user_id, range).await?;
+
+        let comments = match params.chapter_id {
+            Some(chapter_id) => load_chapter_comments(&mut db, chapter_id, range).await?,
+            None => vec![],
+        };
+
+        // a kind that hit the limit can have more items after its last loaded one, the replay
+        // stops there so it never skips over items the client didn't get
+        let cutoff = [
+            notifications
+                .last()
+                .filter(|_| notifications.len() as i64 == REPLAY_LIMIT)
+                .map(|notification| notification.id),
+            comments
+                .last()
+                .filter(|_| comments.len() as i64 == REPLAY_LIMIT)
+                .map(|comment| comment.id),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+
+        replayed.extend(notifications.into_iter().map(StreamItem::Notification));
+        replayed.extend(comments.into_iter().map(StreamItem::Comment));
+
+        replayed.sort_by_key(StreamItem::id);
+        replayed.retain(|item| cutoff.map_or(true, |cutoff| item.id() <= cutoff));
+
+        replay_cut = cutoff.is_some() || replayed.len() > REPLAY_LIMIT as usize;
+        replayed.truncate(REPLAY_LIMIT as usize);
+    }
+
+    // streams stay open for a long time, they don't hold on to a connection
+    drop(db);
+
+    subscription.replayed_ids = replayed.iter().map(StreamItem::id).collect();
+
+    let replay = stream::iter(replayed).map(|item| Ok(item.serialize().to_event()));
+
+    // live items would move the client's Last-Event-ID past what wasn't replayed, the stream
+    // ends instead so the client reconnects and gets the next batch
+    if replay_cut {
+        return Ok(Sse::new(replay.boxed()).keep_alive(KeepAlive::default()));
+    }
+
+    let live = stream::unfold(subscription, |mut subscription| async move {
+        let item = subscription.next().await?;
+        Some((Ok(item.to_event()), subscription))
+    });
+
+    Ok(Sse::new(replay.chain(live).boxed()).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel_async::{pooled_connection::deadpool::Pool, AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    comics::chapters::chapter_comments::models::{
+        ChapterComment, ChapterCommentMapping, ChapterCommentResponse,
+    },
+    notifications::models::{Notification, NotificationResponse},
+    schema::{chapter_comments, chapter_comments_mapping, notifications, users},
+    users::models::User,
+};
+
+use super::{
+    hub::{EventItem, HubEvent},
+    EventsError, REPLAY_LIMIT,
+};
+
+/// An item sent down a stream
+pub enum StreamItem {
+    Notification(NotificationResponse),
+    Comment(ChapterCommentResponse),
+}
+
+impl StreamItem {
+    pub fn id(&self) -> Uuid {
+        match self {
+            StreamItem::Notification(notification) => notification.id,
+            StreamItem::Comment(comment) => comment.id,
+        }
+    }
+
+    pub fn serialize(self) -> EventItem {
+        let id = self.id();
+
+        let (kind, data) = match self {
+            StreamItem::Notification(notification) => {
+                ("notification", serde_json::to_string(&notification))
+            }
+            StreamItem::Comment(comment) => ("comment", serde_json::to_string(&comment)),
+        };
+
+        EventItem {
+            id,
+            kind,
+            data: data.expect("stream items serialize to json").into(),
+        }
+    }
+}
+
+/// Load the item of a live event
+pub async fn load_event(
+    pool: &Pool<AsyncPgConnection>,
+    event: HubEvent,
+) -> Result<Option<StreamItem>, EventsError> {
+    let mut db = pool.get().await?;
+
+    let item = match event {
+        HubEvent::Notification { id, user_id } => {
+            load_notifications(&mut db, user_id, EventRange::Only(id))
+                .await?
+                .pop()
+                .map(StreamItem::Notification)
+        }
+        HubEvent::ChapterComment { id, chapter_id } => {
+            load_chapter_comments(&mut db, chapter_id, EventRange::Only(id))
+                .await?
+                .pop()
+                .map(StreamItem::Comment)
+        }
+    };
+
+    Ok(item)
+}
+
+/// Which items of a stream to load
+#[derive(Debug, Clone, Copy)]
+pub enum EventRange {
+    /// the item of a live event
+    Only(Uuid),
+    /// what a reconnecting client missed, ids are time ordered
+    After(Uuid),
+}
+
+/// Load notifications of `user_id`, oldest first
+pub async fn load_notifications(
+    db: &mut AsyncPgConnection,
+    user_id: Uuid,
+    range: EventRange,
+) -> QueryResult<Vec<NotificationResponse>> {
+    let query = notifications::table
+        .left_join(users::table.on(users::id.nullable().eq(notifications::actor_id)))
+        .filter(notifications::user_id.eq(user_id))
+        .select((Notification::as_select(), User::as_select().nullable()))
+        .into_boxed();
+
+    let query = match range {
+        EventRange::Only(id) => query.filter(notifications::id.eq(id)),
+        EventRange::After(id) => query.filter(notifications::id.gt(id)),
+    };
+
+    let rows = query
+        .order(notifications::id.asc())
+        .limit(REPLAY_LIMIT)
+        .load::<(Notification, Option<User>)>(db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(notification, actor)| {
+            notification.into_response(actor.map(User::into_response_brief))
+        })
+        .collect())
+}
+
+/// Load comments of `chapter_id`, oldest first
+pub async fn load_chapter_comments(
+    db: &mut AsyncPgConnection,
+    chapter_id: Uuid,
+    range: EventRange,
+) -> QueryResult<Vec<ChapterCommentResponse>> {
+    let query = chapter_comments::table
+        .inner_join(users::table)
+        .filter(chapter_comments::chapter_id.eq(chapter_id))
+        .select((ChapterComment::as_select(), User::as_select()))
+        .into_boxed();
+
+    let query = match range {
+        EventRange::Only(id) => query.filter(chapter_comments::id.eq(id)),
+        EventRange::After(id) => query.filter(chapter_comments::id.gt(id)),
+    };
+
+    let rows = query
+        .order(chapter_comments::id.asc())
+        .limit(REPLAY_LIMIT)
+        .load::<(ChapterComment, User)>(db)
+        .await?;
+
+    if rows.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let comment_ids = rows
+        .iter()
+        .map(|(comment, _)| comment.id)
+        .collect::<Vec<Uuid>>();
+
+    // replayed comments can have replies already
+    let comment_mappings = chapter_comments_mapping::table
+        .filter(
+            chapter_comments_mapping::parent_comment_id
+                .eq_any(&comment_ids)
+                .or(chapter_comments_mapping::child_comment_id.eq_any(&comment_ids)),
+        )
+        .select(ChapterCommentMapping::as_select())
+        .load::<ChapterCommentMapping>(db)
+        .await?;
+
+    let mut parent_ids: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut children_ids: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+
+    for mapping in comment_mappings {
+        parent_ids.insert(mapping.child_comment_id, mapping.parent_comment_id);
+        children_ids
+            .entry(mapping.parent_comment_id)
+            .or_default()
+            .push(mapping.child_comment_id);
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|(comment, user)| ChapterCommentResponse {
+            id: comment.id,
+            chapter_id: comment.chapter_id,
+            content: comment.content,
+            user: user.into_response_brief(),
+            parent_comment: parent_ids.get(&comment.id).copied(),
+            child_comments_ids: children_ids.remove(&comment.id).unwrap_or_default(),
+            child_comments: vec![],
+        })
+        .collect())
+}
@@ -1,59 +1,47 @@
-use std::{fmt::Display, fs, sync::Arc};
+use std::{fmt::Display, fs, net::IpAddr, sync::Arc};
 
 use axum::{extract::FromRef, response::IntoResponse};
 use diesel::{
-    allow_columns_to_appear_in_same_group_by_clause, sql_function,
+    sql_function,
     sql_types::{Nullable, SingleValue},
 };
 use diesel_async::{pooled_connection::deadpool::Pool, AsyncPgConnection};
-use s3::interface::Storage;
+use events::hub::EventHub;
+use s3::{
+    interface::Storage,
+    signing::{ImageSigner, ImageSigningKey},
+};
 use serde::{Deserialize, Serialize};
 use tower_cookies::cookie::Key;
 use ts_rs::TS;
+use utils::RatingPrior;
 use utoipa::{
     openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
     Modify, OpenApi, ToSchema,
 };
+use wallets::payments::{PaymentProvider, PaymentProviderKind};
 
+pub mod analytics;
 pub mod auth;
 pub mod comics;
 pub mod common;
+pub mod events;
+pub mod library;
 pub mod migrations;
+pub mod notifications;
+pub mod recommendations;
 pub mod s3;
 pub mod schema;
+pub mod search;
 pub mod sessions;
+pub mod subscriptions;
+pub mod tus;
 pub mod users;
 pub mod utils;
+pub mod wallets;
 
 sql_function! { fn coalesce<T: SingleValue>(x: Nullable<T>, y: T) -> T; }
 
-allow_columns_to_appear_in_same_group_by_clause!(
-    crate::schema::comics::id,
-    crate::schema::comics::title,
-    crate::schema::comics::slug,
-    crate::schema::comics::description,
-    crate::schema::comics::created_at,
-    crate::schema::comics::updated_at,
-    crate::schema::comics::is_visible,
-    crate::schema::comics::published_at,
-    crate::schema::comics::poster_path,
-    crate::schema::comics::poster_content_type,
-    crate::schema::comics::user_id,
-    crate::schema::users::id,
-    crate::schema::users::first_name,
-    crate::schema::users::last_name,
-    crate::schema::users::username,
-    crate::schema::users::displayname,
-    crate::schema::users::email,
-    crate::schema::users::phone_number,
-    crate::schema::users::bio,
-    crate::schema::users::password,
-    crate::schema::users::role,
-    crate::schema::users::created_at,
-    crate::schema::users::updated_at,
-    crate::schema::users::last_login,
-);
-
 #[derive(thiserror::Error, Debug)]
 pub enum ConfigError {
     #[error(transparent)]
@@ -69,7 +57,17 @@ pub struct Config {
     pub email_username: String,
     pub email_password: String,
     pub email_smtp_server: String,
-    pub s3_referer: Option<String>,
+    /// the first key signs image urls, the rest are only used to verify them
+    #[serde(default)]
+    pub image_signing_keys: Vec<ImageSigningKey>,
+    #[serde(default)]
+    pub rating_prior: RatingPrior,
+    /// payments are disabled until a provider is configured
+    #[serde(default)]
+    pub payment_provider: PaymentProviderKind,
+    /// addresses of the reverse proxies whose `X-Forwarded-For` headers are believed
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl Config {
@@ -87,7 +85,11 @@ pub struct InnerAppState {
     pub email_username: String,
     pub email_password: String,
     pub email_smtp_server: String,
-    pub s3_referer: String,
+    pub image_signer: ImageSigner,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub rating_prior: RatingPrior,
+    pub trusted_proxies: Vec<IpAddr>,
+    pub events: EventHub,
 }
 
 #[derive(Clone, FromRef)]
@@ -104,6 +106,10 @@ pub struct AppState {
         users::routes::get_user_comics,
         users::routes::get_user,
         users::routes::me,
+        users::routes::set_birth_date,
+        users::follows::routes::follow_user,
+        users::follows::routes::unfollow_user,
+        users::follows::routes::get_feed,
         comics::routes::create_comic,
         comics::routes::update_comic,
         comics::routes::delete_comic,
@@ -114,9 +120,13 @@ pub struct AppState {
         comics::chapters::routes::get_chapters,
         comics::chapters::routes::get_chapter,
         comics::chapters::routes::delete_chapter,
+        comics::chapters::routes::create_chapter_entitlement,
+        comics::chapters::routes::delete_chapter_entitlement,
         comics::chapters::routes::update_chapter,
         comics::chapters::routes::rate_chapter,
         comics::chapters::routes::create_chapter_page,
+        comics::chapters::routes::create_chapter_page_upload,
+        comics::chapters::routes::complete_chapter_page_upload,
         comics::chapters::routes::update_chapter_page,
         comics::chapters::routes::delete_chapter_page,
         comics::chapters::chapter_comments::routes::get_comments,
@@ -126,36 +136,156 @@ pub struct AppState {
         comics::comic_genres::routes::create_genre,
         comics::comic_genres::routes::update_genre,
         comics::comic_genres::routes::delete_genre,
+        comics::comic_genres::routes::set_genre_translation,
+        comics::comic_genres::routes::delete_genre_translation,
+        comics::comic_tags::routes::autocomplete_tags,
+        comics::comic_tags::routes::set_comic_tags,
+        comics::comic_tags::routes::merge_tag,
+        comics::comic_tags::routes::ban_tag,
+        comics::comic_tags::routes::unban_tag,
         comics::comic_comments::routes::get_comments,
         comics::comic_comments::routes::create_comment,
         comics::comic_comments::routes::delete_comment,
         s3::routes::get_image,
+        s3::routes::reconcile_images,
+        tus::routes::tus_options,
+        tus::routes::create_upload,
+        tus::routes::get_upload_offset,
+        tus::routes::patch_upload,
+        tus::routes::delete_upload,
+        search::routes::search,
+        library::routes::get_library,
+        library::routes::set_library_entry,
+        library::routes::remove_from_library,
+        library::routes::get_shelves,
+        library::routes::create_shelf,
+        library::routes::update_shelf,
+        library::routes::delete_shelf,
+        library::routes::add_to_shelf,
+        library::routes::remove_from_shelf,
+        library::routes::set_reading_progress,
+        library::routes::mark_chapters_read,
+        library::routes::get_continue_reading,
+        analytics::routes::record_view,
+        analytics::routes::get_comic_analytics,
+        recommendations::routes::get_similar_comics,
+        recommendations::routes::get_recommendations,
+        notifications::routes::get_notifications,
+        notifications::routes::get_unread_count,
+        notifications::routes::mark_read,
+        notifications::routes::mark_all_read,
+        notifications::routes::get_preferences,
+        notifications::routes::update_preferences,
+        events::routes::get_events,
+        subscriptions::routes::create_tier,
+        subscriptions::routes::update_tier,
+        subscriptions::routes::get_author_tiers,
+        subscriptions::routes::subscribe,
+        subscriptions::routes::cancel_subscription,
+        subscriptions::routes::get_my_subscriptions,
+        wallets::routes::get_wallet,
+        wallets::routes::create_top_up,
+        wallets::routes::unlock_chapter,
+        wallets::routes::tip_author,
+        wallets::routes::refund_transaction,
+        wallets::routes::request_payout,
+        wallets::routes::approve_payout,
+        wallets::routes::reject_payout,
+        wallets::routes::get_statement,
+        wallets::routes::get_earnings,
     ),
     components(
         schemas(common::models::ImageMetadataResponse),
+        schemas(common::models::RatingSummaryResponse),
         schemas(comics::models::CreateComic),
         schemas(comics::models::UpdateComic),
         schemas(comics::models::ComicResponse),
         schemas(comics::models::ComicResponseBrief),
         schemas(comics::models::NewComicRating),
-        schemas(comics::models::ComicsPagination),
-        schemas(comics::models::Order),
+        schemas(comics::models::ComicsOrder),
+        schemas(comics::models::ComicStatus),
+        schemas(comics::models::ContentRating),
+        schemas(comics::models::ReadingDirection),
+        schemas(comics::models::GenreMode),
+        schemas(common::pagination::PaginatedComics),
+        schemas(common::pagination::PaginatedComicsBrief),
+        schemas(common::pagination::PaginatedChapters),
+        schemas(common::pagination::PaginatedComicComments),
+        schemas(common::pagination::PaginatedChapterComments),
+        schemas(common::pagination::PaginatedLibrary),
+        schemas(common::pagination::PaginatedContinueReading),
+        schemas(common::pagination::PaginatedFeed),
+        schemas(common::pagination::PaginatedNotifications),
         schemas(comics::comic_genres::models::ComicGenre),
+        schemas(comics::comic_genres::models::CreateComicGenre),
+        schemas(comics::comic_genres::models::UpdateComicGenre),
+        schemas(comics::comic_genres::models::SetGenreTranslation),
+        schemas(common::locale::Locale),
+        schemas(comics::comic_tags::models::ComicTagResponse),
+        schemas(comics::comic_tags::models::TagSuggestion),
+        schemas(comics::comic_tags::models::SetComicTags),
+        schemas(comics::comic_tags::models::MergeComicTag),
         schemas(comics::chapters::models::CreateChapter),
         schemas(comics::chapters::models::UpdateChapter),
         schemas(comics::chapters::models::CreateChapterPage),
+        schemas(comics::chapters::models::CreateChapterPageUpload),
+        schemas(comics::chapters::models::CreateChapterEntitlement),
+        schemas(comics::chapters::models::ChapterPageUploadResponse),
         schemas(comics::chapters::models::ChapterResponse),
         schemas(comics::chapters::models::ChapterResponseBrief),
         schemas(comics::chapters::models::ChapterPageResponse),
         schemas(comics::chapters::models::NewChapterRating),
         schemas(comics::comic_comments::models::ComicCommentResponse),
+        schemas(s3::models::ReconcileReport),
         schemas(users::models::UserRole),
         schemas(users::models::UserResponseBrief),
         schemas(users::models::UserResponse),
         schemas(users::models::UserClaims),
         schemas(users::models::CreateUser),
         schemas(users::models::UserLogin),
+        schemas(users::models::SetBirthDate),
         schemas(users::models::UserToken),
+        schemas(users::follows::models::FeedItemKind),
+        schemas(users::follows::models::FeedItemResponse),
+        schemas(search::models::SearchResponse),
+        schemas(search::models::ComicSearchResult),
+        schemas(search::models::ChapterSearchResult),
+        schemas(search::models::AuthorSearchResult),
+        schemas(library::models::LibraryStatus),
+        schemas(library::models::SetLibraryEntry),
+        schemas(library::models::CreateLibraryShelf),
+        schemas(library::models::UpdateLibraryShelf),
+        schemas(library::models::LibraryEntryResponse),
+        schemas(library::models::LibraryShelfResponse),
+        schemas(library::models::SetReadingProgress),
+        schemas(library::models::MarkChaptersRead),
+        schemas(library::models::ReadingProgressResponse),
+        schemas(library::models::ContinueReadingResponse),
+        schemas(analytics::models::RecordView),
+        schemas(analytics::models::RecordViewResponse),
+        schemas(analytics::models::DailyCount),
+        schemas(analytics::models::ChapterAnalyticsResponse),
+        schemas(analytics::models::ComicAnalyticsResponse),
+        schemas(notifications::models::NotificationKind),
+        schemas(notifications::models::NotificationResponse),
+        schemas(notifications::models::UnreadNotificationsResponse),
+        schemas(notifications::models::NotificationPreferences),
+        schemas(notifications::models::UpdateNotificationPreferences),
+        schemas(subscriptions::models::CreateSubscriptionTier),
+        schemas(subscriptions::models::UpdateSubscriptionTier),
+        schemas(subscriptions::models::SubscriptionTierResponse),
+        schemas(subscriptions::models::SubscriptionResponse),
+        schemas(wallets::models::TransactionKind),
+        schemas(wallets::models::PayoutStatus),
+        schemas(wallets::models::CreateTopUp),
+        schemas(wallets::models::CreateUnlock),
+        schemas(wallets::models::CreateTip),
+        schemas(wallets::models::CreatePayoutRequest),
+        schemas(wallets::models::WalletResponse),
+        schemas(wallets::models::LedgerTransactionResponse),
+        schemas(wallets::models::StatementEntryResponse),
+        schemas(wallets::models::PayoutRequestResponse),
+        schemas(wallets::models::EarningsResponse),
         schemas(ErrorResponse),
         schemas(SortingOrder),
     ),
@@ -166,8 +296,18 @@ pub struct AppState {
         (name = "Chapter Comments API"),
         (name = "Comics API"),
         (name = "Comic Genres API"),
+        (name = "Comic Tags API"),
         (name = "Comic Comments API"),
         (name = "Images API"),
+        (name = "Uploads API"),
+        (name = "Wallets API"),
+        (name = "Subscriptions API"),
+        (name = "Search API"),
+        (name = "Library API"),
+        (name = "Analytics API"),
+        (name = "Recommendations API"),
+        (name = "Notifications API"),
+        (name = "Events API"),
     )
 )]
 pub struct ApiDoc;
@@ -214,10 +354,6 @@ impl Modify for SecurityAddon {
     }
 }
 
-pub trait Rating {
-    fn rating(&self) -> f64;
-}
-
 #[derive(Debug, Deserialize, ToSchema)]
 pub enum SortingOrder {
     #[serde(rename = "desc")]
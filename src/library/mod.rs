@@ -0,0 +1,118 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
+
+pub mod models;
+pub mod routes;
+pub mod utils;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LibraryError {
+    #[error("comic not found")]
+    ComicNotFound,
+
+    #[error("comic isn't in the library")]
+    NotInLibrary,
+
+    #[error("shelf not found")]
+    ShelfNotFound,
+
+    #[error("chapter not found")]
+    ChapterNotFound,
+
+    #[error("chapter is locked")]
+    ChapterLocked,
+
+    #[error("page is out of the chapter's range")]
+    PageOutOfRange,
+
+    #[error("mature content is only available to verified adults")]
+    MatureContent,
+
+    #[error(transparent)]
+    Validator(#[from] garde::Errors),
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for LibraryError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let (status, error) = match &self {
+            LibraryError::ComicNotFound
+            | LibraryError::ShelfNotFound
+            | LibraryError::ChapterNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            LibraryError::NotInLibrary | LibraryError::PageOutOfRange => {
+                (StatusCode::BAD_REQUEST, self.to_string())
+            }
+            LibraryError::MatureContent | LibraryError::ChapterLocked => {
+                (StatusCode::FORBIDDEN, self.to_string())
+            }
+            LibraryError::Validator(errors) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    ErrorResponse {
+                        error: String::from("invalid input"),
+                        details: Some(
+                            errors
+                                .flatten()
+                                .iter()
+                                .map(|(path, error)| format!("{path}: {error}"))
+                                .collect::<Vec<String>>(),
+                        ),
+                    },
+                )
+                    .into_response();
+            }
+            LibraryError::InvalidCursor(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            LibraryError::Diesel(diesel::result::Error::NotFound) => {
+                (StatusCode::NOT_FOUND, String::from("not found"))
+            }
+            LibraryError::Diesel(DatabaseError(DatabaseErrorKind::UniqueViolation, message))
+                if message.constraint_name() == Some("library_shelves_user_id_name_key") =>
+            {
+                (
+                    StatusCode::CONFLICT,
+                    String::from("a shelf with the same name already exists"),
+                )
+            }
+            LibraryError::Diesel(DatabaseError(
+                DatabaseErrorKind::ForeignKeyViolation,
+                message,
+            )) => match message.constraint_name() {
+                Some("library_entries_comic_id_fkey") => {
+                    (StatusCode::NOT_FOUND, String::from("comic not found"))
+                }
+                Some("library_shelf_comics_shelf_id_user_id_fkey") => {
+                    (StatusCode::NOT_FOUND, String::from("shelf not found"))
+                }
+                Some("library_shelf_comics_user_id_comic_id_fkey") => (
+                    StatusCode::BAD_REQUEST,
+                    LibraryError::NotInLibrary.to_string(),
+                ),
+                _ => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            },
+            LibraryError::Diesel(_) | LibraryError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error,
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
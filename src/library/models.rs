@@ -0,0 +1,276 @@
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgValue},
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    AsExpression, FromSqlRow,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    comics::{
+        chapters::models::Chapter,
+        models::{Comic, ComicResponseBrief},
+    },
+    schema::{
+        chapter_reads, library_entries, library_shelf_comics, library_shelves, reading_progress,
+    },
+    users::models::User,
+};
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    AsExpression,
+    FromSqlRow,
+    TS,
+    Copy,
+    Clone,
+    ToSchema,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Librarystatus)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum LibraryStatus {
+    Reading,
+    #[default]
+    PlanToRead,
+    Completed,
+    Dropped,
+}
+
+impl ToSql<crate::schema::sql_types::Librarystatus, Pg> for LibraryStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            LibraryStatus::Reading => out.write_all(b"reading"),
+            LibraryStatus::PlanToRead => out.write_all(b"plan_to_read"),
+            LibraryStatus::Completed => out.write_all(b"completed"),
+            LibraryStatus::Dropped => out.write_all(b"dropped"),
+        }?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::Librarystatus, Pg> for LibraryStatus {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"reading" => Ok(LibraryStatus::Reading),
+            b"plan_to_read" => Ok(LibraryStatus::PlanToRead),
+            b"completed" => Ok(LibraryStatus::Completed),
+            b"dropped" => Ok(LibraryStatus::Dropped),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(Comic))]
+#[diesel(table_name = library_entries)]
+#[diesel(primary_key(user_id, comic_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LibraryEntry {
+    pub user_id: Uuid,
+    pub comic_id: Uuid,
+    pub status: LibraryStatus,
+    /// when the user last made progress in the comic, `None` if they haven't read it yet
+    pub last_read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LibraryEntry {
+    pub fn into_response(
+        self,
+        comic: ComicResponseBrief,
+        shelves: Vec<Uuid>,
+        unread_chapters: i64,
+    ) -> LibraryEntryResponse {
+        LibraryEntryResponse {
+            comic,
+            status: self.status,
+            shelves,
+            unread_chapters,
+            last_read_at: self.last_read_at.map(|date| date.to_string()),
+            added_at: self.created_at.to_string(),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = library_shelves)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LibraryShelf {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl LibraryShelf {
+    pub fn into_response(self, comics_count: i64) -> LibraryShelfResponse {
+        LibraryShelfResponse {
+            id: self.id,
+            name: self.name,
+            comics_count,
+            created_at: self.created_at.to_string(),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(LibraryShelf, foreign_key = shelf_id))]
+#[diesel(table_name = library_shelf_comics)]
+#[diesel(primary_key(shelf_id, comic_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LibraryShelfComic {
+    pub shelf_id: Uuid,
+    pub user_id: Uuid,
+    pub comic_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(Chapter))]
+#[diesel(table_name = chapter_reads)]
+#[diesel(primary_key(user_id, chapter_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterRead {
+    pub user_id: Uuid,
+    pub chapter_id: Uuid,
+    pub comic_id: Uuid,
+    pub read_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug, Clone)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(Comic))]
+#[diesel(table_name = reading_progress)]
+#[diesel(primary_key(user_id, comic_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ReadingProgress {
+    pub user_id: Uuid,
+    pub comic_id: Uuid,
+    pub chapter_id: Uuid,
+    pub page: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ReadingProgress {
+    pub fn into_response(self, chapter_number: i32, finished: bool) -> ReadingProgressResponse {
+        ReadingProgressResponse {
+            comic_id: self.comic_id,
+            chapter_id: self.chapter_id,
+            chapter_number,
+            page: self.page,
+            finished,
+            updated_at: self.updated_at.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct SetLibraryEntry {
+    pub status: LibraryStatus,
+}
+
+#[derive(Validate, Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateLibraryShelf {
+    #[garde(length(min = 1, max = 50))]
+    pub name: String,
+}
+
+#[derive(Validate, AsChangeset, Deserialize, ToSchema, Debug, TS)]
+#[diesel(table_name = library_shelves)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[ts(export)]
+pub struct UpdateLibraryShelf {
+    #[garde(length(min = 1, max = 50))]
+    pub name: String,
+}
+
+#[derive(Validate, Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct SetReadingProgress {
+    #[garde(skip)]
+    pub chapter_id: Uuid,
+    /// number of the page the user is on, reaching the last page marks the chapter as read
+    #[garde(range(min = 1))]
+    pub page: i32,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct MarkChaptersRead {
+    /// every chapter numbered up to this one (inclusive) is marked as read
+    pub up_to: i32,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub struct LibraryParams {
+    /// only comics with this reading status
+    pub status: Option<LibraryStatus>,
+    /// only comics on this shelf
+    pub shelf_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct LibraryEntryResponse {
+    pub comic: ComicResponseBrief,
+    pub status: LibraryStatus,
+    /// ids of the custom shelves the comic is on
+    pub shelves: Vec<Uuid>,
+    /// released chapters the user hasn't read yet
+    pub unread_chapters: i64,
+    pub last_read_at: Option<String>,
+    pub added_at: String,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct LibraryShelfResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub comics_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct ReadingProgressResponse {
+    pub comic_id: Uuid,
+    pub chapter_id: Uuid,
+    pub chapter_number: i32,
+    pub page: i32,
+    /// the chapter was read to its last page
+    pub finished: bool,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ContinueReadingResponse {
+    pub comic: ComicResponseBrief,
+    pub chapter_id: Uuid,
+    pub chapter_number: i32,
+    pub chapter_title: String,
+    pub page: i32,
+    pub updated_at: String,
+}
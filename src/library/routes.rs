@@ -0,0 +1,835 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use diesel::{dsl::count, prelude::*, result::Error::NotFound};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use garde::Validate;
+use itertools::{multizip, Itertools};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    comics::{
+        chapters::{
+            entitlements::{has_chapter_access, is_chapter_visible, visible_chapters},
+            models::Chapter,
+        },
+        comic_genres::{
+            models::{Genre, GenreMapping},
+            utils::genre_translations,
+        },
+        models::{Comic, ContentRating},
+    },
+    common::{
+        locale::{Locale, LocaleParams},
+        pagination::{Paginated, PaginationParams},
+    },
+    schema::{
+        chapter_pages, chapter_reads, comic_chapters, comic_genres, comics, library_entries,
+        library_shelf_comics, library_shelves, reading_progress,
+    },
+    users::{models::UserRole, utils::can_view_mature_content},
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{
+        ChapterRead, ContinueReadingResponse, CreateLibraryShelf, LibraryEntry,
+        LibraryEntryResponse, LibraryParams, LibraryShelf, LibraryShelfComic, LibraryShelfResponse,
+        MarkChaptersRead, ReadingProgress, ReadingProgressResponse, SetLibraryEntry,
+        SetReadingProgress, UpdateLibraryShelf,
+    },
+    utils::{mark_read, unread_chapters},
+    LibraryError,
+};
+
+pub fn library_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_library))
+        .route("/comics/:comic_id", put(set_library_entry))
+        .route("/comics/:comic_id", delete(remove_from_library))
+        .route("/comics/:comic_id/read", post(mark_chapters_read))
+        .route("/progress", put(set_reading_progress))
+        .route("/continue", get(get_continue_reading))
+        .route("/shelves", get(get_shelves))
+        .route("/shelves", post(create_shelf))
+        .route("/shelves/:shelf_id", put(update_shelf))
+        .route("/shelves/:shelf_id", delete(delete_shelf))
+        .route("/shelves/:shelf_id/comics/:comic_id", put(add_to_shelf))
+        .route(
+            "/shelves/:shelf_id/comics/:comic_id",
+            delete(remove_from_shelf),
+        )
+}
+
+/// Get the comics in the current user's library, recently read or updated first
+#[utoipa::path(
+    get,
+    path = "/api/v1/library",
+    params(
+        LibraryParams,
+        PaginationParams,
+        LocaleParams,
+    ),
+    responses(
+        (status = 200, description = "Comics in the library with their unread chapters", body = PaginatedLibrary),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_library(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<LibraryParams>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<LibraryEntryResponse>>, LibraryError> {
+    let cursor = pagination.cursor::<(DateTime<Utc>, Uuid)>()?;
+    let mut db = state.pool.get().await?;
+
+    let user_id = auth.current_user.id;
+
+    let mut query = library_entries::table
+        .inner_join(comics::table)
+        .filter(library_entries::user_id.eq(user_id))
+        .select((LibraryEntry::as_select(), Comic::as_select()))
+        .into_boxed();
+
+    if let Some(status) = params.status {
+        query = query.filter(library_entries::status.eq(status));
+    }
+
+    if let Some(shelf_id) = params.shelf_id {
+        query = query.filter(
+            library_entries::comic_id.eq_any(
+                library_shelf_comics::table
+                    .filter(library_shelf_comics::shelf_id.eq(shelf_id))
+                    .filter(library_shelf_comics::user_id.eq(user_id))
+                    .select(library_shelf_comics::comic_id),
+            ),
+        );
+    }
+
+    if let Some((prev_date, prev_id)) = cursor {
+        query = query.filter(
+            library_entries::updated_at
+                .lt(prev_date)
+                .or(library_entries::updated_at
+                    .eq(prev_date)
+                    .and(library_entries::comic_id.lt(prev_id))),
+        );
+    }
+
+    let mut rows = query
+        .order((
+            library_entries::updated_at.desc(),
+            library_entries::comic_id.desc(),
+        ))
+        .limit(pagination.limit() + 1)
+        .load::<(LibraryEntry, Comic)>(&mut db)
+        .await?;
+
+    let next_cursor = pagination.page(&mut rows, |(entry, _)| (entry.updated_at, entry.comic_id));
+
+    let (entries, comics): (Vec<LibraryEntry>, Vec<Comic>) = rows.into_iter().unzip();
+    let comic_ids = comics.iter().map(|comic| comic.id).collect::<Vec<Uuid>>();
+
+    let unread = unread_chapters(&mut db, user_id, &comic_ids).await?;
+
+    let mut shelves = library_shelf_comics::table
+        .filter(library_shelf_comics::user_id.eq(user_id))
+        .filter(library_shelf_comics::comic_id.eq_any(&comic_ids))
+        .select((
+            library_shelf_comics::comic_id,
+            library_shelf_comics::shelf_id,
+        ))
+        .load::<(Uuid, Uuid)>(&mut db)
+        .await?
+        .into_iter()
+        .into_group_map();
+
+    let genres = GenreMapping::belonging_to(&comics)
+        .inner_join(comic_genres::table)
+        .select((GenreMapping::as_select(), Genre::as_select()))
+        .load::<(GenreMapping, Genre)>(&mut db)
+        .await?
+        .grouped_by(&comics);
+
+    let translations = genre_translations(&mut db, locale).await?;
+
+    let entries = multizip((entries, comics, genres))
+        .map(|(entry, comic, genres)| {
+            let comic_id = comic.id;
+
+            let comic = comic.into_response_brief(
+                genres
+                    .into_iter()
+                    .map(|(_, genre)| genre.into_localized(&translations))
+                    .collect(),
+            );
+
+            entry.into_response(
+                comic,
+                shelves.remove(&comic_id).unwrap_or_default(),
+                unread.get(&comic_id).copied().unwrap_or(0),
+            )
+        })
+        .collect();
+
+    Ok(Json(Paginated::new(entries, next_cursor)))
+}
+
+/// Add a comic to the current user's library or change its reading status
+#[utoipa::path(
+    put,
+    path = "/api/v1/library/comics/:comic_id",
+    request_body(content = SetLibraryEntry, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Comic is in the library with the given status"),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::FORBIDDEN, description = "Comic is rated mature and caller isn't a verified adult", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Comic not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn set_library_entry(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(comic_id): Path<Uuid>,
+    Json(payload): Json<SetLibraryEntry>,
+) -> Result<(), LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    let (author_id, content_rating) = comics::table
+        .find(comic_id)
+        .select((comics::user_id, comics::content_rating))
+        .first::<(Uuid, ContentRating)>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => LibraryError::ComicNotFound,
+            e => e.into(),
+        })?;
+
+    if content_rating == ContentRating::Mature
+        && author_id != auth.current_user.id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        return Err(LibraryError::MatureContent);
+    }
+
+    let now = Utc::now();
+
+    diesel::insert_into(library_entries::table)
+        .values(&LibraryEntry {
+            user_id: auth.current_user.id,
+            comic_id,
+            status: payload.status,
+            last_read_at: None,
+            created_at: now,
+            updated_at: now,
+        })
+        .on_conflict((library_entries::user_id, library_entries::comic_id))
+        .do_update()
+        .set((
+            library_entries::status.eq(payload.status),
+            library_entries::updated_at.eq(now),
+        ))
+        .execute(&mut db)
+        .await?;
+
+    Ok(())
+}
+
+/// Remove a comic from the current user's library and all of its shelves
+#[utoipa::path(
+    delete,
+    path = "/api/v1/library/comics/:comic_id",
+    responses(
+        (status = 200, description = "Comic has been removed from the library"),
+        (status = StatusCode::BAD_REQUEST, description = "Comic isn't in the library", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn remove_from_library(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(comic_id): Path<Uuid>,
+) -> Result<(), LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    let deleted = diesel::delete(
+        library_entries::table
+            .filter(library_entries::user_id.eq(auth.current_user.id))
+            .filter(library_entries::comic_id.eq(comic_id)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    if deleted == 0 {
+        return Err(LibraryError::NotInLibrary);
+    }
+
+    Ok(())
+}
+
+/// Get the current user's custom shelves
+#[utoipa::path(
+    get,
+    path = "/api/v1/library/shelves",
+    responses(
+        (status = 200, description = "Shelves, oldest first", body = [LibraryShelfResponse]),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_shelves(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<Vec<LibraryShelfResponse>>, LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    let shelves = library_shelves::table
+        .filter(library_shelves::user_id.eq(auth.current_user.id))
+        .order((library_shelves::created_at.asc(), library_shelves::id.asc()))
+        .select(LibraryShelf::as_select())
+        .load::<LibraryShelf>(&mut db)
+        .await?;
+
+    let comics_counts = library_shelf_comics::table
+        .filter(library_shelf_comics::user_id.eq(auth.current_user.id))
+        .group_by(library_shelf_comics::shelf_id)
+        .select((
+            library_shelf_comics::shelf_id,
+            count(library_shelf_comics::comic_id),
+        ))
+        .load::<(Uuid, i64)>(&mut db)
+        .await?
+        .into_iter()
+        .collect::<HashMap<Uuid, i64>>();
+
+    Ok(Json(
+        shelves
+            .into_iter()
+            .map(|shelf| {
+                let comics_count = comics_counts.get(&shelf.id).copied().unwrap_or(0);
+                shelf.into_response(comics_count)
+            })
+            .collect(),
+    ))
+}
+
+/// Create a custom shelf
+#[utoipa::path(
+    post,
+    path = "/api/v1/library/shelves",
+    request_body(content = CreateLibraryShelf, description = "Validation:\n- name: 1-50 characters", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Shelf has been created", body = LibraryShelfResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Fields validation error", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "A shelf with the same name already exists", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn create_shelf(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Json(payload): Json<CreateLibraryShelf>,
+) -> Result<Json<LibraryShelfResponse>, LibraryError> {
+    payload.validate(&())?;
+
+    let mut db = state.pool.get().await?;
+
+    let shelf = diesel::insert_into(library_shelves::table)
+        .values(&LibraryShelf {
+            id: Uuid::now_v7(),
+            user_id: auth.current_user.id,
+            name: payload.name,
+            created_at: Utc::now(),
+            updated_at: None,
+        })
+        .returning(LibraryShelf::as_returning())
+        .get_result::<LibraryShelf>(&mut db)
+        .await?;
+
+    Ok(Json(shelf.into_response(0)))
+}
+
+/// Rename a custom shelf
+#[utoipa::path(
+    put,
+    path = "/api/v1/library/shelves/:shelf_id",
+    request_body(content = UpdateLibraryShelf, description = "Validation:\n- name: 1-50 characters", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Shelf has been renamed", body = LibraryShelfResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Fields validation error", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Shelf not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "A shelf with the same name already exists", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn update_shelf(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(shelf_id): Path<Uuid>,
+    Json(payload): Json<UpdateLibraryShelf>,
+) -> Result<Json<LibraryShelfResponse>, LibraryError> {
+    payload.validate(&())?;
+
+    let mut db = state.pool.get().await?;
+
+    let shelf = diesel::update(
+        library_shelves::table
+            .filter(library_shelves::id.eq(shelf_id))
+            .filter(library_shelves::user_id.eq(auth.current_user.id)),
+    )
+    .set((&payload, library_shelves::updated_at.eq(Utc::now())))
+    .returning(LibraryShelf::as_returning())
+    .get_result::<LibraryShelf>(&mut db)
+    .await
+    .map_err(|e| match e {
+        NotFound => LibraryError::ShelfNotFound,
+        e => e.into(),
+    })?;
+
+    let comics_count = library_shelf_comics::table
+        .filter(library_shelf_comics::shelf_id.eq(shelf.id))
+        .count()
+        .get_result::<i64>(&mut db)
+        .await?;
+
+    Ok(Json(shelf.into_response(comics_count)))
+}
+
+/// Delete a custom shelf, its comics stay in the library
+#[utoipa::path(
+    delete,
+    path = "/api/v1/library/shelves/:shelf_id",
+    responses(
+        (status = 200, description = "Shelf has been deleted"),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Shelf not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn delete_shelf(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(shelf_id): Path<Uuid>,
+) -> Result<(), LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    let deleted = diesel::delete(
+        library_shelves::table
+            .filter(library_shelves::id.eq(shelf_id))
+            .filter(library_shelves::user_id.eq(auth.current_user.id)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    if deleted == 0 {
+        return Err(LibraryError::ShelfNotFound);
+    }
+
+    Ok(())
+}
+
+/// Put a comic from the current user's library on a custom shelf
+#[utoipa::path(
+    put,
+    path = "/api/v1/library/shelves/:shelf_id/comics/:comic_id",
+    responses(
+        (status = 200, description = "Comic is on the shelf"),
+        (status = StatusCode::BAD_REQUEST, description = "Comic isn't in the library", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Shelf not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn add_to_shelf(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path((shelf_id, comic_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    // the foreign keys make sure the shelf and the comic's library entry belong to the user
+    diesel::insert_into(library_shelf_comics::table)
+        .values(&LibraryShelfComic {
+            shelf_id,
+            user_id: auth.current_user.id,
+            comic_id,
+            created_at: Utc::now(),
+        })
+        .on_conflict_do_nothing()
+        .execute(&mut db)
+        .await?;
+
+    Ok(())
+}
+
+/// Take a comic off a custom shelf, it stays in the library
+#[utoipa::path(
+    delete,
+    path = "/api/v1/library/shelves/:shelf_id/comics/:comic_id",
+    responses(
+        (status = 200, description = "Comic isn't on the shelf anymore"),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn remove_from_shelf(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path((shelf_id, comic_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    diesel::delete(
+        library_shelf_comics::table
+            .filter(library_shelf_comics::shelf_id.eq(shelf_id))
+            .filter(library_shelf_comics::comic_id.eq(comic_id))
+            .filter(library_shelf_comics::user_id.eq(auth.current_user.id)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    Ok(())
+}
+
+/// Record the chapter and page the current user is on, called by the reader as the user reads
+#[utoipa::path(
+    put,
+    path = "/api/v1/library/progress",
+    request_body(content = SetReadingProgress, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Reading progress has been recorded", body = ReadingProgressResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid page", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::FORBIDDEN, description = "Chapter is locked", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Chapter not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn set_reading_progress(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Json(payload): Json<SetReadingProgress>,
+) -> Result<Json<ReadingProgressResponse>, LibraryError> {
+    payload.validate(&())?;
+
+    let mut db = state.pool.get().await?;
+
+    let chapter = comic_chapters::table
+        .find(payload.chapter_id)
+        .select(Chapter::as_select())
+        .first::<Chapter>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => LibraryError::ChapterNotFound,
+            e => e.into(),
+        })?;
+
+    if !is_chapter_visible(&mut db, &chapter, Some(&auth.current_user)).await? {
+        return Err(LibraryError::ChapterNotFound);
+    }
+
+    if !has_chapter_access(&mut db, &chapter, Some(&auth.current_user)).await? {
+        return Err(LibraryError::ChapterLocked);
+    }
+
+    let pages_count = chapter_pages::table
+        .filter(chapter_pages::chapter_id.eq(chapter.id))
+        .count()
+        .get_result::<i64>(&mut db)
+        .await?;
+
+    if i64::from(payload.page) > pages_count {
+        return Err(LibraryError::PageOutOfRange);
+    }
+
+    let finished = i64::from(payload.page) == pages_count;
+
+    let user_id = auth.current_user.id;
+    let now = Utc::now();
+
+    let progress = ReadingProgress {
+        user_id,
+        comic_id: chapter.comic_id,
+        chapter_id: chapter.id,
+        page: payload.page,
+        updated_at: now,
+    };
+
+    let response = progress.clone().into_response(chapter.number, finished);
+
+    db.transaction::<_, LibraryError, _>(|conn| {
+        async move {
+            diesel::insert_into(reading_progress::table)
+                .values(&progress)
+                .on_conflict((reading_progress::user_id, reading_progress::comic_id))
+                .do_update()
+                .set((
+                    reading_progress::chapter_id.eq(progress.chapter_id),
+                    reading_progress::page.eq(progress.page),
+                    reading_progress::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await?;
+
+            if finished {
+                diesel::insert_into(chapter_reads::table)
+                    .values(&ChapterRead {
+                        user_id,
+                        chapter_id: progress.chapter_id,
+                        comic_id: progress.comic_id,
+                        read_at: now,
+                    })
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+            }
+
+            mark_read(conn, user_id, progress.comic_id).await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    Ok(Json(response))
+}
+
+/// Mark every chapter of a comic up to the given chapter number as read
+#[utoipa::path(
+    post,
+    path = "/api/v1/library/comics/:comic_id/read",
+    request_body(content = MarkChaptersRead, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Chapters have been marked as read"),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Comic not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn mark_chapters_read(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(comic_id): Path<Uuid>,
+    Json(payload): Json<MarkChaptersRead>,
+) -> Result<(), LibraryError> {
+    let mut db = state.pool.get().await?;
+
+    comics::table
+        .find(comic_id)
+        .select(comics::id)
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => LibraryError::ComicNotFound,
+            e => e.into(),
+        })?;
+
+    let chapters = comic_chapters::table
+        .filter(comic_chapters::comic_id.eq(comic_id))
+        .filter(comic_chapters::number.le(payload.up_to))
+        .select(Chapter::as_select())
+        .load::<Chapter>(&mut db)
+        .await?;
+
+    // chapters the user can't see yet stay unread
+    let visible = visible_chapters(
+        &mut db,
+        &chapters.iter().collect::<Vec<&Chapter>>(),
+        Some(&auth.current_user),
+    )
+    .await?;
+
+    let user_id = auth.current_user.id;
+    let now = Utc::now();
+
+    let reads = chapters
+        .into_iter()
+        .filter(|chapter| visible.contains(&chapter.id))
+        .map(|chapter| ChapterRead {
+            user_id,
+            chapter_id: chapter.id,
+            comic_id,
+            read_at: now,
+        })
+        .collect::<Vec<ChapterRead>>();
+
+    if reads.is_empty() {
+        return Ok(());
+    }
+
+    diesel::insert_into(chapter_reads::table)
+        .values(&reads)
+        .on_conflict_do_nothing()
+        .execute(&mut db)
+        .await?;
+
+    mark_read(&mut db, user_id, comic_id).await?;
+
+    Ok(())
+}
+
+/// Get the comics the current user is reading with where they left off, most recent first
+#[utoipa::path(
+    get,
+    path = "/api/v1/library/continue",
+    params(
+        PaginationParams,
+        LocaleParams,
+    ),
+    responses(
+        (status = 200, description = "Comics with the chapter and page the user is on", body = PaginatedContinueReading),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Library API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_continue_reading(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
+    State(state): State<Arc<InnerAppState>>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<ContinueReadingResponse>>, LibraryError> {
+    let cursor = pagination.cursor::<(DateTime<Utc>, Uuid)>()?;
+    let mut db = state.pool.get().await?;
+
+    let mut query = reading_progress::table
+        .inner_join(comics::table)
+        .inner_join(comic_chapters::table)
+        .filter(reading_progress::user_id.eq(auth.current_user.id))
+        .select((
+            ReadingProgress::as_select(),
+            Comic::as_select(),
+            (comic_chapters::number, comic_chapters::title),
+        ))
+        .into_boxed();
+
+    if let Some((prev_date, prev_id)) = cursor {
+        query = query.filter(
+            reading_progress::updated_at
+                .lt(prev_date)
+                .or(reading_progress::updated_at
+                    .eq(prev_date)
+                    .and(reading_progress::comic_id.lt(prev_id))),
+        );
+    }
+
+    let mut rows = query
+        .order((
+            reading_progress::updated_at.desc(),
+            reading_progress::comic_id.desc(),
+        ))
+        .limit(pagination.limit() + 1)
+        .load::<(ReadingProgress, Comic, (i32, String))>(&mut db)
+        .await?;
+
+    let next_cursor = pagination.page(&mut rows, |(progress, _, _)| {
+        (progress.updated_at, progress.comic_id)
+    });
+
+    let (progress, comics, chapters): (Vec<ReadingProgress>, Vec<Comic>, Vec<(i32, String)>) =
+        rows.into_iter().multiunzip();
+
+    let genres = GenreMapping::belonging_to(&comics)
+        .inner_join(comic_genres::table)
+        .select((GenreMapping::as_select(), Genre::as_select()))
+        .load::<(GenreMapping, Genre)>(&mut db)
+        .await?
+        .grouped_by(&comics);
+
+    let translations = genre_translations(&mut db, locale).await?;
+
+    let entries = multizip((progress, comics, chapters, genres))
+        .map(
+            |(progress, comic, (chapter_number, chapter_title), genres)| ContinueReadingResponse {
+                comic: comic.into_response_brief(
+                    genres
+                        .into_iter()
+                        .map(|(_, genre)| genre.into_localized(&translations))
+                        .collect(),
+                ),
+                chapter_id: progress.chapter_id,
+                chapter_number,
+                chapter_title,
+                page: progress.page,
+                updated_at: progress.updated_at.to_string(),
+            },
+        )
+        .collect();
+
+    Ok(Json(Paginated::new(entries, next_cursor)))
+}
@@ -0,0 +1,102 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use diesel::{
+    dsl::count, BoolExpressionMethods, ExpressionMethods, JoinOnDsl, QueryDsl, QueryResult,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::schema::{chapter_reads, comic_chapters, library_entries};
+
+use super::models::LibraryStatus;
+
+/// Library statuses of the comics that are in the user's library
+pub async fn library_statuses(
+    db: &mut AsyncPgConnection,
+    user_id: Uuid,
+    comic_ids: &[Uuid],
+) -> QueryResult<HashMap<Uuid, LibraryStatus>> {
+    let statuses = library_entries::table
+        .filter(library_entries::user_id.eq(user_id))
+        .filter(library_entries::comic_id.eq_any(comic_ids))
+        .select((library_entries::comic_id, library_entries::status))
+        .load::<(Uuid, LibraryStatus)>(db)
+        .await?;
+
+    Ok(statuses.into_iter().collect())
+}
+
+/// Number of released chapters of each comic in the user's library that the user hasn't read,
+/// comics without unread chapters are left out
+pub async fn unread_chapters(
+    db: &mut AsyncPgConnection,
+    user_id: Uuid,
+    comic_ids: &[Uuid],
+) -> QueryResult<HashMap<Uuid, i64>> {
+    let now = Utc::now();
+
+    let counts = comic_chapters::table
+        .inner_join(
+            library_entries::table.on(library_entries::comic_id
+                .eq(comic_chapters::comic_id)
+                .and(library_entries::user_id.eq(user_id))),
+        )
+        .left_join(
+            chapter_reads::table.on(chapter_reads::chapter_id
+                .eq(comic_chapters::id)
+                .and(chapter_reads::user_id.eq(user_id))),
+        )
+        .filter(comic_chapters::comic_id.eq_any(comic_ids))
+        .filter(
+            comic_chapters::published_at
+                .is_null()
+                .or(comic_chapters::published_at.le(now)),
+        )
+        .filter(chapter_reads::chapter_id.is_null())
+        .group_by(comic_chapters::comic_id)
+        .select((comic_chapters::comic_id, count(comic_chapters::id)))
+        .load::<(Uuid, i64)>(db)
+        .await?;
+
+    Ok(counts.into_iter().collect())
+}
+
+/// Ids of the given chapters that the user has read
+pub async fn read_chapters(
+    db: &mut AsyncPgConnection,
+    user_id: Uuid,
+    chapter_ids: &[Uuid],
+) -> QueryResult<HashSet<Uuid>> {
+    let chapters = chapter_reads::table
+        .filter(chapter_reads::user_id.eq(user_id))
+        .filter(chapter_reads::chapter_id.eq_any(chapter_ids))
+        .select(chapter_reads::chapter_id)
+        .load::<Uuid>(db)
+        .await?;
+
+    Ok(chapters.into_iter().collect())
+}
+
+/// Bumps when the user last read the comic, does nothing if it isn't in the user's library
+pub async fn mark_read(
+    db: &mut AsyncPgConnection,
+    user_id: Uuid,
+    comic_id: Uuid,
+) -> QueryResult<()> {
+    let now = Utc::now();
+
+    diesel::update(
+        library_entries::table
+            .filter(library_entries::user_id.eq(user_id))
+            .filter(library_entries::comic_id.eq(comic_id)),
+    )
+    .set((
+        library_entries::last_read_at.eq(now),
+        library_entries::updated_at.eq(now),
+    ))
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
@@ -1,21 +1,44 @@
 use axum::{
     http::{
-        header::{AUTHORIZATION, CONTENT_TYPE},
+        header::{AUTHORIZATION, CONTENT_TYPE, LOCATION},
         Method,
     },
     middleware,
     routing::get,
     Router,
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
 use diesel_async::pooled_connection::{deadpool::Pool, AsyncDieselConnectionManager};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations};
 use dotenvy::dotenv;
 use musawarah::{
-    comics::routes::comics_router,
+    analytics::{cleanup::view_windows_cleanup_worker, routes::analytics_router},
+    comics::{rankings::rankings_worker, routes::comics_router},
+    events::{hub::EventHub, listener::events_listener, routes::events_router, LAST_EVENT_ID},
+    library::routes::library_router,
     migrations::run_migrations,
-    s3::{helpers::setup_storage, routes::images_routes},
+    notifications::{releases::releases_worker, routes::notifications_router},
+    recommendations::worker::recommendations_worker,
+    s3::{
+        cleanup::storage_cleanup_worker,
+        helpers::setup_storage,
+        routes::images_routes,
+        signing::{ImageSigner, ImageSigningKey},
+    },
+    search::routes::search_router,
     sessions::refresh_session,
+    subscriptions::{renewals::subscription_renewal_worker, routes::subscriptions_router},
+    tus::{
+        cleanup::tus_cleanup_worker, routes::tus_router, TUS_RESUMABLE, UPLOAD_EXPIRES,
+        UPLOAD_LENGTH, UPLOAD_METADATA, UPLOAD_OFFSET,
+    },
     users::routes::users_router,
+    utils::RatingPrior,
+    wallets::{
+        payments::{DisabledPaymentProvider, PaymentProvider, PaymentProviderKind},
+        routes::wallets_router,
+        IDEMPOTENCY_KEY,
+    },
     ApiDoc, AppState, Config, ConfigError, InnerAppState,
 };
 use rand::Rng;
@@ -34,6 +57,7 @@ use tracing_subscriber::{
 };
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
+use uuid::Uuid;
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
 #[tokio::main]
@@ -50,7 +74,8 @@ async fn main() {
         .await
         .expect("Run migrations");
 
-    let config = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(database_url);
+    let config =
+        AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(database_url.clone());
     let pool = Pool::builder(config).build().expect("db connection pool");
 
     let config = match Config::load_config() {
@@ -64,8 +89,15 @@ async fn main() {
 
                 let secret = String::from_utf8_lossy(&secret).to_string();
 
+                let mut image_signing_secret = [0u8; 32];
+                rand::thread_rng().fill(&mut image_signing_secret);
+
                 let config = Config {
                     cookie_secret: secret,
+                    image_signing_keys: vec![ImageSigningKey {
+                        id: Uuid::now_v7().to_string(),
+                        secret: STANDARD.encode(image_signing_secret),
+                    }],
                     ..Default::default()
                 };
 
@@ -83,6 +115,45 @@ async fn main() {
         },
     };
 
+    let image_signing_keys = if config.image_signing_keys.is_empty() {
+        tracing::warn!(
+            "NO IMAGE SIGNING KEYS CONFIGURED, SIGNING IMAGE URLS WITH THE COOKIE SECRET"
+        );
+
+        vec![ImageSigningKey {
+            id: String::from("cookie"),
+            secret: STANDARD.encode(config.cookie_secret.as_bytes()),
+        }]
+    } else {
+        config.image_signing_keys
+    };
+
+    let rating_prior = if config.rating_prior.is_valid() {
+        config.rating_prior
+    } else {
+        tracing::warn!("INVALID RATING PRIOR CONFIGURED, USING THE DEFAULT ONE");
+
+        RatingPrior::default()
+    };
+
+    let payment_provider: Arc<dyn PaymentProvider> = match config.payment_provider {
+        PaymentProviderKind::Disabled => {
+            tracing::warn!("NO PAYMENT PROVIDER CONFIGURED, PAYMENTS ARE DISABLED");
+
+            Arc::new(DisabledPaymentProvider)
+        }
+        #[cfg(debug_assertions)]
+        PaymentProviderKind::Fake => {
+            tracing::warn!("USING THE FAKE PAYMENT PROVIDER, PAYMENTS DON'T MOVE ANY MONEY");
+
+            Arc::new(musawarah::wallets::payments::FakePaymentProvider)
+        }
+        #[cfg(not(debug_assertions))]
+        PaymentProviderKind::Fake => {
+            panic!("the fake payment provider can't be used in release builds")
+        }
+    };
+
     let app_state = AppState {
         inner: Arc::new(InnerAppState {
             pool,
@@ -91,15 +162,50 @@ async fn main() {
             email_username: config.email_username,
             email_password: config.email_password,
             email_smtp_server: config.email_smtp_server,
-            s3_referer: config
-                .s3_referer
-                .unwrap_or(String::from("http://localhost:5173/")),
+            image_signer: ImageSigner::new(image_signing_keys),
+            payment_provider,
+            rating_prior,
+            trusted_proxies: config.trusted_proxies,
+            events: EventHub::new(),
         }),
     };
 
+    tokio::spawn(storage_cleanup_worker(app_state.inner.clone()));
+    tokio::spawn(tus_cleanup_worker(app_state.inner.clone()));
+    tokio::spawn(subscription_renewal_worker(app_state.inner.clone()));
+    tokio::spawn(view_windows_cleanup_worker(app_state.inner.clone()));
+    tokio::spawn(rankings_worker(app_state.inner.clone()));
+    tokio::spawn(recommendations_worker(app_state.inner.clone()));
+    tokio::spawn(releases_worker(app_state.inner.clone()));
+    tokio::spawn(events_listener(database_url, app_state.inner.clone()));
+
     let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::PUT])
-        .allow_headers([AUTHORIZATION, CONTENT_TYPE])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::DELETE,
+            Method::PUT,
+            Method::PATCH,
+            Method::HEAD,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            TUS_RESUMABLE,
+            UPLOAD_LENGTH,
+            UPLOAD_OFFSET,
+            UPLOAD_METADATA,
+            IDEMPOTENCY_KEY,
+            LAST_EVENT_ID,
+        ])
+        .expose_headers([
+            LOCATION,
+            TUS_RESUMABLE,
+            UPLOAD_OFFSET,
+            UPLOAD_LENGTH,
+            UPLOAD_EXPIRES,
+        ])
         // FIXME: add proper allowed origins
         .allow_origin([
             "http://locahost:6060"
@@ -114,7 +220,15 @@ async fn main() {
     let v1_router = Router::new()
         .nest("/api/v1/users", users_router())
         .nest("/api/v1/comics", comics_router())
-        .nest("/api/v1/images", images_routes());
+        .nest("/api/v1/images", images_routes())
+        .nest("/api/v1/uploads", tus_router())
+        .nest("/api/v1/wallets", wallets_router())
+        .nest("/api/v1/subscriptions", subscriptions_router())
+        .nest("/api/v1/library", library_router())
+        .nest("/api/v1/analytics", analytics_router())
+        .nest("/api/v1/notifications", notifications_router())
+        .nest("/api/v1/events", events_router())
+        .nest("/api/v1/search", search_router());
 
     let app = Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
@@ -134,7 +248,7 @@ async fn main() {
     tracing::info!("listening on {}", addr);
 
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .expect("start server");
 }
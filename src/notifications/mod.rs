@@ -0,0 +1,49 @@
+use axum::{http::StatusCode, response::IntoResponse};
+
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
+
+pub mod models;
+pub mod releases;
+pub mod routes;
+pub mod utils;
+
+/// Authors are notified when their comic reaches each of these numbers of ratings
+pub const RATING_MILESTONES: [i64; 7] = [10, 50, 100, 500, 1000, 5000, 10000];
+
+#[derive(thiserror::Error, Debug)]
+pub enum NotificationsError {
+    #[error("notification not found")]
+    NotificationNotFound,
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for NotificationsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let (status, error) = match &self {
+            NotificationsError::NotificationNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            NotificationsError::InvalidCursor(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            NotificationsError::Diesel(_) | NotificationsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error,
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
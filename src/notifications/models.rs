@@ -0,0 +1,245 @@
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgValue},
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    AsExpression, FromSqlRow,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    schema::{notifications, users},
+    users::models::UserResponseBrief,
+};
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    AsExpression,
+    FromSqlRow,
+    TS,
+    Copy,
+    Clone,
+    ToSchema,
+    PartialEq,
+    Eq,
+)]
+#[diesel(sql_type = crate::schema::sql_types::Notificationkind)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum NotificationKind {
+    /// a comic in the user's library released a chapter
+    NewChapter,
+    /// someone replied to the user's comment
+    CommentReply,
+    /// someone commented on the user's comic or one of its chapters
+    ComicComment,
+    /// the user's comic reached a number of ratings
+    RatingMilestone,
+    /// moderators changed the user's content
+    Moderation,
+}
+
+impl ToSql<crate::schema::sql_types::Notificationkind, Pg> for NotificationKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            NotificationKind::NewChapter => out.write_all(b"new_chapter"),
+            NotificationKind::CommentReply => out.write_all(b"comment_reply"),
+            NotificationKind::ComicComment => out.write_all(b"comic_comment"),
+            NotificationKind::RatingMilestone => out.write_all(b"rating_milestone"),
+            NotificationKind::Moderation => out.write_all(b"moderation"),
+        }?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::Notificationkind, Pg> for NotificationKind {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"new_chapter" => Ok(NotificationKind::NewChapter),
+            b"comment_reply" => Ok(NotificationKind::CommentReply),
+            b"comic_comment" => Ok(NotificationKind::ComicComment),
+            b"rating_milestone" => Ok(NotificationKind::RatingMilestone),
+            b"moderation" => Ok(NotificationKind::Moderation),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Debug, Clone)]
+#[diesel(table_name = notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: NotificationKind,
+    pub actor_id: Option<Uuid>,
+    pub comic_id: Option<Uuid>,
+    pub chapter_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub milestone: Option<i64>,
+    pub details: Option<String>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    /// An unread notification of `kind` for `user_id`
+    pub fn new(user_id: Uuid, kind: NotificationKind) -> Self {
+        Notification {
+            id: Uuid::now_v7(),
+            user_id,
+            kind,
+            actor_id: None,
+            comic_id: None,
+            chapter_id: None,
+            comment_id: None,
+            milestone: None,
+            details: None,
+            read_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn actor(mut self, actor_id: Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn comic(mut self, comic_id: Uuid) -> Self {
+        self.comic_id = Some(comic_id);
+        self
+    }
+
+    pub fn chapter(mut self, chapter_id: Uuid) -> Self {
+        self.chapter_id = Some(chapter_id);
+        self
+    }
+
+    pub fn comment(mut self, comment_id: Uuid) -> Self {
+        self.comment_id = Some(comment_id);
+        self
+    }
+
+    pub fn milestone(mut self, milestone: i64) -> Self {
+        self.milestone = Some(milestone);
+        self
+    }
+
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn into_response(self, actor: Option<UserResponseBrief>) -> NotificationResponse {
+        NotificationResponse {
+            id: self.id,
+            kind: self.kind,
+            actor,
+            comic_id: self.comic_id,
+            chapter_id: self.chapter_id,
+            comment_id: self.comment_id,
+            milestone: self.milestone,
+            details: self.details,
+            read: self.read_at.is_some(),
+            created_at: self.created_at.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct NotificationResponse {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    /// the user who caused the notification, if there is one
+    pub actor: Option<UserResponseBrief>,
+    pub comic_id: Option<Uuid>,
+    pub chapter_id: Option<Uuid>,
+    /// a chapter comment if `chapter_id` is set, otherwise a comic comment
+    pub comment_id: Option<Uuid>,
+    /// number of ratings reached, for rating milestones
+    pub milestone: Option<i64>,
+    /// what the moderators did, for moderation notifications
+    pub details: Option<String>,
+    pub read: bool,
+    pub created_at: String,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct UnreadNotificationsResponse {
+    pub count: i64,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub struct NotificationsParams {
+    /// only unread notifications
+    #[serde(default)]
+    pub unread: bool,
+}
+
+/// Which kinds of notifications the user gets, every kind is on by default
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug, Clone, Copy, TS)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[ts(export)]
+pub struct NotificationPreferences {
+    #[diesel(column_name = notify_new_chapters)]
+    pub new_chapters: bool,
+    #[diesel(column_name = notify_comment_replies)]
+    pub comment_replies: bool,
+    #[diesel(column_name = notify_comic_comments)]
+    pub comic_comments: bool,
+    #[diesel(column_name = notify_rating_milestones)]
+    pub rating_milestones: bool,
+    #[diesel(column_name = notify_moderation)]
+    pub moderation: bool,
+}
+
+impl NotificationPreferences {
+    pub fn allows(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::NewChapter => self.new_chapters,
+            NotificationKind::CommentReply => self.comment_replies,
+            NotificationKind::ComicComment => self.comic_comments,
+            NotificationKind::RatingMilestone => self.rating_milestones,
+            NotificationKind::Moderation => self.moderation,
+        }
+    }
+}
+
+/// Only the given kinds are changed
+#[derive(AsChangeset, Deserialize, ToSchema, Debug, TS)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[ts(export)]
+pub struct UpdateNotificationPreferences {
+    #[diesel(column_name = notify_new_chapters)]
+    pub new_chapters: Option<bool>,
+    #[diesel(column_name = notify_comment_replies)]
+    pub comment_replies: Option<bool>,
+    #[diesel(column_name = notify_comic_comments)]
+    pub comic_comments: Option<bool>,
+    #[diesel(column_name = notify_rating_milestones)]
+    pub rating_milestones: Option<bool>,
+    #[diesel(column_name = notify_moderation)]
+    pub moderation: Option<bool>,
+}
+
+impl UpdateNotificationPreferences {
+    pub fn is_empty(&self) -> bool {
+        self.new_chapters.is_none()
+            && self.comment_replies.is_none()
+            && self.comic_comments.is_none()
+            && self.rating_milestones.is_none()
+            && self.moderation.is_none()
+    }
+}
@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc, time::Duration as StdDuration};
+
+use chrono::Utc;
+use diesel::{dsl::count_star, prelude::*};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::{
+    coalesce,
+    library::models::LibraryStatus,
+    schema::{comic_chapters, library_entries},
+    InnerAppState,
+};
+
+use super::{
+    models::{Notification, NotificationKind},
+    utils::notify,
+    NotificationsError,
+};
+
+const RELEASES_INTERVAL_SECS: u64 = 60;
+
+/// Chapters looked at in one go
+const RELEASES_BATCH_SIZE: i64 = 100;
+
+/// Notifications created in one go, chapters whose readers don't fit wait for the next tick
+const RELEASES_MAX_NOTIFICATIONS: i64 = 10_000;
+
+/// Notify the readers of released chapters that weren't notified about yet
+///
+/// chapters are notified when they're released to everyone, so scheduled chapters don't show up
+/// before their publish date, returns the number of notified chapters
+pub async fn notify_released_chapters(
+    db: &mut AsyncPgConnection,
+) -> Result<usize, diesel::result::Error> {
+    db.transaction::<_, diesel::result::Error, _>(|transaction| {
+        async move {
+            let now = Utc::now();
+
+            let released_at = coalesce(comic_chapters::published_at, comic_chapters::created_at);
+
+            let released = comic_chapters::table
+                .filter(comic_chapters::notified_at.is_null())
+                .filter(released_at.le(now))
+                .order(released_at.asc())
+                .select((
+                    comic_chapters::id,
+                    comic_chapters::comic_id,
+                    comic_chapters::user_id,
+                ))
+                .limit(RELEASES_BATCH_SIZE)
+                .for_update()
+                .skip_locked()
+                .load::<(Uuid, Uuid, Uuid)>(transaction)
+                .await?;
+
+            if released.is_empty() {
+                return Ok(0);
+            }
+
+            let comic_ids = released
+                .iter()
+                .map(|(_, comic_id, _)| *comic_id)
+                .collect::<Vec<Uuid>>();
+
+            // readers who dropped the comic aren't interested anymore
+            let reader_counts = library_entries::table
+                .filter(library_entries::comic_id.eq_any(&comic_ids))
+                .filter(library_entries::status.ne(LibraryStatus::Dropped))
+                .group_by(library_entries::comic_id)
+                .select((library_entries::comic_id, count_star()))
+                .load::<(Uuid, i64)>(transaction)
+                .await?
+                .into_iter()
+                .collect::<HashMap<Uuid, i64>>();
+
+            // the oldest chapter is always notified, even when it has more readers than the limit
+            let mut notifications_count = 0;
+            let released = released
+                .into_iter()
+                .take_while(|(_, comic_id, _)| {
+                    let readers = reader_counts.get(comic_id).copied().unwrap_or_default();
+                    let fits = notifications_count == 0
+                        || notifications_count + readers <= RELEASES_MAX_NOTIFICATIONS;
+                    notifications_count += readers;
+                    fits
+                })
+                .collect::<Vec<(Uuid, Uuid, Uuid)>>();
+
+            let comic_ids = released
+                .iter()
+                .map(|(_, comic_id, _)| *comic_id)
+                .collect::<Vec<Uuid>>();
+
+            let readers = library_entries::table
+                .filter(library_entries::comic_id.eq_any(&comic_ids))
+                .filter(library_entries::status.ne(LibraryStatus::Dropped))
+                .select((library_entries::comic_id, library_entries::user_id))
+                .load::<(Uuid, Uuid)>(transaction)
+                .await?;
+
+            let new_notifications = released
+                .iter()
+                .flat_map(|(chapter_id, comic_id, author_id)| {
+                    readers
+                        .iter()
+                        .filter(move |(reader_comic_id, _)| reader_comic_id == comic_id)
+                        .map(move |(_, reader_id)| {
+                            Notification::new(*reader_id, NotificationKind::NewChapter)
+                                .actor(*author_id)
+                                .comic(*comic_id)
+                                .chapter(*chapter_id)
+                        })
+                })
+                .collect::<Vec<Notification>>();
+
+            notify(transaction, new_notifications).await?;
+
+            let chapter_ids = released
+                .iter()
+                .map(|(chapter_id, _, _)| *chapter_id)
+                .collect::<Vec<Uuid>>();
+
+            diesel::update(comic_chapters::table.filter(comic_chapters::id.eq_any(&chapter_ids)))
+                .set(comic_chapters::notified_at.eq(Some(now)))
+                .execute(transaction)
+                .await?;
+
+            Ok(chapter_ids.len())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Background task that notifies readers about newly released chapters
+pub async fn releases_worker(state: Arc<InnerAppState>) {
+    let mut releases_interval = interval(StdDuration::from_secs(RELEASES_INTERVAL_SECS));
+
+    loop {
+        releases_interval.tick().await;
+
+        let result = match state.pool.get().await {
+            Ok(mut db) => notify_released_chapters(&mut db)
+                .await
+                .map_err(NotificationsError::from),
+            Err(err) => Err(err.into()),
+        };
+
+        match result {
+            Ok(notified) => tracing::debug!("notified the readers of {notified} released chapters"),
+            Err(err) => tracing::error!("failed to notify about released chapters: {:#?}", err),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    common::pagination::{Paginated, PaginationParams},
+    schema::{notifications, users},
+    users::models::{User, UserRole},
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{
+        Notification, NotificationPreferences, NotificationResponse, NotificationsParams,
+        UnreadNotificationsResponse, UpdateNotificationPreferences,
+    },
+    NotificationsError,
+};
+
+pub fn notifications_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_notifications))
+        .route("/unread_count", get(get_unread_count))
+        .route("/read_all", post(mark_all_read))
+        .route("/:notification_id/read", post(mark_read))
+        .route("/preferences", get(get_preferences))
+        .route("/preferences", put(update_preferences))
+}
+
+/// Get the current user's notifications, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications",
+    params(
+        NotificationsParams,
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Notifications of the current user", body = PaginatedNotifications),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Notifications API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_notifications(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<NotificationsParams>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<NotificationResponse>>, NotificationsError> {
+    let cursor = pagination.cursor::<Uuid>()?;
+    let mut db = state.pool.get().await?;
+
+    let mut query = notifications::table
+        .left_join(users::table.on(users::id.nullable().eq(notifications::actor_id)))
+        .filter(notifications::user_id.eq(auth.current_user.id))
+        .select((Notification::as_select(), User::as_select().nullable()))
+        .into_boxed();
+
+    if params.unread {
+        query = query.filter(notifications::read_at.is_null());
+    }
+
+    // ids are time ordered
+    if let Some(prev_id) = cursor {
+        query = query.filter(notifications::id.lt(prev_id));
+    }
+
+    let mut rows = query
+        .order(notifications::id.desc())
+        .limit(pagination.limit() + 1)
+        .load::<(Notification, Option<User>)>(&mut db)
+        .await?;
+
+    let next_cursor = pagination.page(&mut rows, |(notification, _)| notification.id);
+
+    let items = rows
+        .into_iter()
+        .map(|(notification, actor)| {
+            notification.into_response(actor.map(User::into_response_brief))
+        })
+        .collect();
+
+    Ok(Json(Paginated::new(items, next_cursor)))
+}
+
+/// Get the number of unread notifications of the current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/unread_count",
+    responses(
+        (status = 200, description = "Number of unread notifications", body = UnreadNotificationsResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Notifications API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_unread_count(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<UnreadNotificationsResponse>, NotificationsError> {
+    let mut db = state.pool.get().await?;
+
+    let count = notifications::table
+        .filter(notifications::user_id.eq(auth.current_user.id))
+        .filter(notifications::read_at.is_null())
+        .count()
+        .get_result::<i64>(&mut db)
+        .await?;
+
+    Ok(Json(UnreadNotificationsResponse { count }))
+}
+
+/// Mark a notification as read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/:notification_id/read",
+    responses(
+        (status = 200, description = "Notification is read"),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Notification not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Notifications API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn mark_read(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(notification_id): Path<Uuid>,
+) -> Result<(), NotificationsError> {
+    let mut db = state.pool.get().await?;
+
+    let updated = diesel::update(
+        notifications::table
+            .filter(notifications::id.eq(notification_id))
+            .filter(notifications::user_id.eq(auth.current_user.id))
+            .filter(notifications::read_at.is_null()),
+    )
+    .set(notifications::read_at.eq(Some(Utc::now())))
+    .execute(&mut db)
+    .await?;
+
+    // reading a notification twice is fine
+    if updated == 0 {
+        notifications::table
+            .filter(notifications::id.eq(notification_id))
+            .filter(notifications::user_id.eq(auth.current_user.id))
+            .select(notifications::id)
+            .first::<Uuid>(&mut db)
+            .await
+            .optional()?
+            .ok_or(NotificationsError::NotificationNotFound)?;
+    }
+
+    Ok(())
+}
+
+/// Mark every notification of the current user as read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/read_all",
+    responses(
+        (status = 200, description = "Number of notifications that were marked as read", body = usize),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Notifications API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn mark_all_read(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<usize>, NotificationsError> {
+    let mut db = state.pool.get().await?;
+
+    let updated = diesel::update(
+        notifications::table
+            .filter(notifications::user_id.eq(auth.current_user.id))
+            .filter(notifications::read_at.is_null()),
+    )
+    .set(notifications::read_at.eq(Some(Utc::now())))
+    .execute(&mut db)
+    .await?;
+
+    Ok(Json(updated))
+}
+
+/// Get which kinds of notifications the current user gets
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/preferences",
+    responses(
+        (status = 200, description = "Notification preferences of the current user", body = NotificationPreferences),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Notifications API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_preferences(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<NotificationPreferences>, NotificationsError> {
+    let mut db = state.pool.get().await?;
+
+    let preferences = users::table
+        .find(auth.current_user.id)
+        .select(NotificationPreferences::as_select())
+        .first::<NotificationPreferences>(&mut db)
+        .await?;
+
+    Ok(Json(preferences))
+}
+
+/// Turn kinds of notifications on or off for the current user
+#[utoipa::path(
+    put,
+    path = "/api/v1/notifications/preferences",
+    request_body(content = UpdateNotificationPreferences, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Updated notification preferences", body = NotificationPreferences),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Notifications API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn update_preferences(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Json(payload): Json<UpdateNotificationPreferences>,
+) -> Result<Json<NotificationPreferences>, NotificationsError> {
+    let mut db = state.pool.get().await?;
+
+    // an empty changeset isn't a valid update
+    if payload.is_empty() {
+        let preferences = users::table
+            .find(auth.current_user.id)
+            .select(NotificationPreferences::as_select())
+            .first::<NotificationPreferences>(&mut db)
+            .await?;
+
+        return Ok(Json(preferences));
+    }
+
+    let preferences = diesel::update(users::table.find(auth.current_user.id))
+        .set(&payload)
+        .returning(NotificationPreferences::as_returning())
+        .get_result::<NotificationPreferences>(&mut db)
+        .await?;
+
+    Ok(Json(preferences))
+}
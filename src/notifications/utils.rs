@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, SelectableHelper};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use itertools::Itertools;
+use uuid::Uuid;
+
+use crate::schema::{notifications, users};
+
+use super::models::{Notification, NotificationPreferences};
+
+/// Notifications stored by one insert, postgres can't bind more than 65535 parameters
+const NOTIFY_CHUNK_SIZE: usize = 1000;
+
+/// Store the notifications whose users didn't turn their kind off
+///
+/// users aren't notified about what they did themselves,
+/// returns the number of stored notifications
+pub async fn notify(
+    db: &mut AsyncPgConnection,
+    new_notifications: Vec<Notification>,
+) -> QueryResult<usize> {
+    let new_notifications = new_notifications
+        .into_iter()
+        .filter(|notification| notification.actor_id != Some(notification.user_id))
+        .collect::<Vec<Notification>>();
+
+    if new_notifications.is_empty() {
+        return Ok(0);
+    }
+
+    let user_ids = new_notifications
+        .iter()
+        .map(|notification| notification.user_id)
+        .unique()
+        .collect::<Vec<Uuid>>();
+
+    let preferences = users::table
+        .filter(users::id.eq_any(&user_ids))
+        .select((users::id, NotificationPreferences::as_select()))
+        .load::<(Uuid, NotificationPreferences)>(db)
+        .await?
+        .into_iter()
+        .collect::<HashMap<Uuid, NotificationPreferences>>();
+
+    let new_notifications = new_notifications
+        .into_iter()
+        .filter(|notification| {
+            preferences
+                .get(&notification.user_id)
+                .is_some_and(|preferences| preferences.allows(notification.kind))
+        })
+        .collect::<Vec<Notification>>();
+
+    if new_notifications.is_empty() {
+        return Ok(0);
+    }
+
+    let mut stored = 0;
+
+    for chunk in new_notifications.chunks(NOTIFY_CHUNK_SIZE) {
+        // rating milestones are only notified once per comic
+        stored += diesel::insert_into(notifications::table)
+            .values(chunk)
+            .on_conflict_do_nothing()
+            .execute(db)
+            .await?;
+    }
+
+    Ok(stored)
+}
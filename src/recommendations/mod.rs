@@ -0,0 +1,50 @@
+use axum::{http::StatusCode, response::IntoResponse};
+
+use crate::ErrorResponse;
+
+pub mod routes;
+pub mod worker;
+
+/// Similar comics kept for each comic
+pub const SIMILAR_COMICS_LIMIT: i64 = 20;
+
+/// Recommendations kept for each user
+pub const RECOMMENDATIONS_LIMIT: i64 = 50;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RecommendationsError {
+    #[error("comic not found")]
+    ComicNotFound,
+
+    #[error("mature content is only available to verified adults")]
+    MatureContent,
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for RecommendationsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let (status, error) = match &self {
+            RecommendationsError::ComicNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            RecommendationsError::MatureContent => (StatusCode::FORBIDDEN, self.to_string()),
+            RecommendationsError::Diesel(_) | RecommendationsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error,
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
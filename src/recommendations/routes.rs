@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use diesel::{dsl::not, prelude::*, result::Error::NotFound};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    comics::{
+        comic_genres::{
+            models::{Genre, GenreMapping},
+            utils::genre_translations,
+        },
+        models::{Comic, ComicResponseBrief, ContentRating},
+    },
+    common::locale::{Locale, LocaleParams},
+    schema::{
+        comic_genres, comic_rankings, comic_similarities, comics, library_entries,
+        user_recommendations,
+    },
+    users::{models::UserRole, utils::can_view_mature_content},
+    AppState, InnerAppState,
+};
+
+use super::{RecommendationsError, RECOMMENDATIONS_LIMIT, SIMILAR_COMICS_LIMIT};
+
+/// Get the comics that readers of a comic also liked, most similar first
+#[utoipa::path(
+    get,
+    path = "/api/v1/comics/:comic_id/similar",
+    params(
+        LocaleParams,
+    ),
+    responses(
+        (status = 200, description = "Similar comics", body = [ComicResponseBrief]),
+        (status = StatusCode::NOT_FOUND, description = "Comic not found", body = ErrorResponse),
+        (status = StatusCode::FORBIDDEN, description = "Comic is rated mature and caller isn't a verified adult", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Recommendations API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_similar_comics(
+    auth: Option<AuthExtractor<{ UserRole::User as u32 }>>,
+    locale: Locale,
+    State(state): State<Arc<InnerAppState>>,
+    Path(comic_id): Path<Uuid>,
+) -> Result<Json<Vec<ComicResponseBrief>>, RecommendationsError> {
+    let mut db = state.pool.get().await?;
+
+    let (author_id, content_rating) = comics::table
+        .find(comic_id)
+        .select((comics::user_id, comics::content_rating))
+        .first::<(Uuid, ContentRating)>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => RecommendationsError::ComicNotFound,
+            e => e.into(),
+        })?;
+
+    let viewer = auth.as_ref().map(|auth| &auth.current_user);
+    let can_view_mature = can_view_mature_content(&mut db, viewer).await?;
+
+    if content_rating == ContentRating::Mature
+        && viewer.map(|viewer| viewer.id) != Some(author_id)
+        && !can_view_mature
+    {
+        return Err(RecommendationsError::MatureContent);
+    }
+
+    let mut query = comic_similarities::table
+        .inner_join(comics::table.on(comics::id.eq(comic_similarities::similar_comic_id)))
+        .filter(comic_similarities::comic_id.eq(comic_id))
+        .select(Comic::as_select())
+        .into_boxed();
+
+    if !can_view_mature {
+        query = query.filter(comics::content_rating.ne(ContentRating::Mature));
+    }
+
+    let similar = query
+        .order((comic_similarities::score.desc(), comics::id))
+        .limit(SIMILAR_COMICS_LIMIT)
+        .load::<Comic>(&mut db)
+        .await?;
+
+    Ok(Json(comic_briefs(&mut db, similar, locale).await?))
+}
+
+/// Get the comics recommended to the current user, best match first
+///
+/// users without recommendations yet get the trending comics instead
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/recommendations",
+    params(
+        LocaleParams,
+    ),
+    responses(
+        (status = 200, description = "Recommended comics", body = [ComicResponseBrief]),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Recommendations API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_recommendations(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<Vec<ComicResponseBrief>>, RecommendationsError> {
+    let mut db = state.pool.get().await?;
+
+    let user_id = auth.current_user.id;
+    let can_view_mature = can_view_mature_content(&mut db, Some(&auth.current_user)).await?;
+
+    let mut query = user_recommendations::table
+        .inner_join(comics::table)
+        .filter(user_recommendations::user_id.eq(user_id))
+        .select(Comic::as_select())
+        .into_boxed();
+
+    if !can_view_mature {
+        query = query.filter(comics::content_rating.ne(ContentRating::Mature));
+    }
+
+    let mut recommended = query
+        .order((user_recommendations::score.desc(), comics::id))
+        .limit(RECOMMENDATIONS_LIMIT)
+        .load::<Comic>(&mut db)
+        .await?;
+
+    // nothing to go on yet, new users get what's trending
+    if recommended.is_empty() {
+        let mut query = comics::table
+            .inner_join(comic_rankings::table)
+            .filter(comics::user_id.ne(user_id))
+            .filter(not(comics::id.eq_any(
+                library_entries::table
+                    .filter(library_entries::user_id.eq(user_id))
+                    .select(library_entries::comic_id),
+            )))
+            .select(Comic::as_select())
+            .into_boxed();
+
+        if !can_view_mature {
+            query = query.filter(comics::content_rating.ne(ContentRating::Mature));
+        }
+
+        recommended = query
+            .order((comic_rankings::trending_score.desc(), comics::id))
+            .limit(RECOMMENDATIONS_LIMIT)
+            .load::<Comic>(&mut db)
+            .await?;
+    }
+
+    Ok(Json(comic_briefs(&mut db, recommended, locale).await?))
+}
+
+async fn comic_briefs(
+    db: &mut AsyncPgConnection,
+    comics: Vec<Comic>,
+    locale: Locale,
+) -> QueryResult<Vec<ComicResponseBrief>> {
+    let genres = GenreMapping::belonging_to(&comics)
+        .inner_join(comic_genres::table)
+        .select((GenreMapping::as_select(), Genre::as_select()))
+        .load::<(GenreMapping, Genre)>(db)
+        .await?
+        .grouped_by(&comics);
+
+    let translations = genre_translations(db, locale).await?;
+
+    Ok(comics
+        .into_iter()
+        .zip(genres)
+        .map(|(comic, genres)| {
+            comic.into_response_brief(
+                genres
+                    .into_iter()
+                    .map(|(_, genre)| genre.into_localized(&translations))
+                    .collect(),
+            )
+        })
+        .collect())
+}
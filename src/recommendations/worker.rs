@@ -0,0 +1,171 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use diesel::sql_types::{BigInt, Double};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use tokio::time::interval;
+
+use crate::{
+    schema::{comic_similarities, user_recommendations},
+    InnerAppState,
+};
+
+use super::{RecommendationsError, RECOMMENDATIONS_LIMIT, SIMILAR_COMICS_LIMIT};
+
+const RECOMMENDATIONS_INTERVAL_SECS: u64 = 60 * 60;
+
+/// How much sharing genres and tags counts compared to sharing readers,
+/// comics nobody reads together yet are only similar by their content
+const CONTENT_SIMILARITY_WEIGHT: f64 = 0.1;
+
+// two comics are as similar as the cosine similarity of their readers' interactions, plus the
+// jaccard index of their genres and tags scaled down by the content weight
+//
+// $1 is the content weight, $2 is the number of similar comics kept for each comic
+const REFRESH_SIMILARITIES_QUERY: &str = "
+    WITH norms AS (
+        SELECT comic_id, sqrt(sum(weight * weight)) AS norm
+        FROM comic_interactions
+        GROUP BY comic_id
+    ), features AS (
+        SELECT comic_id, 'genre:' || genre_id AS feature
+        FROM comic_genres_mapping
+
+        UNION
+
+        SELECT comic_tags_mapping.comic_id, 'tag:' || comic_tags_mapping.tag_id
+        FROM comic_tags_mapping
+        INNER JOIN comic_tags ON comic_tags.id = comic_tags_mapping.tag_id
+        WHERE comic_tags.banned_at IS NULL
+    ), feature_counts AS (
+        SELECT comic_id, count(*) AS features
+        FROM features
+        GROUP BY comic_id
+    )
+    INSERT INTO comic_similarities (comic_id, similar_comic_id, score, computed_at)
+    SELECT comic_id, similar_comic_id, score, now()
+    FROM (
+        SELECT
+            comic_id,
+            similar_comic_id,
+            sum(score) AS score,
+            row_number() OVER (
+                PARTITION BY comic_id
+                ORDER BY sum(score) DESC, similar_comic_id
+            ) AS rank
+        FROM (
+            SELECT a.comic_id, b.comic_id AS similar_comic_id, sum(a.weight * b.weight) / (norms_a.norm * norms_b.norm) AS score
+            FROM comic_interactions AS a
+            INNER JOIN comic_interactions AS b ON b.user_id = a.user_id AND b.comic_id <> a.comic_id
+            INNER JOIN norms AS norms_a ON norms_a.comic_id = a.comic_id
+            INNER JOIN norms AS norms_b ON norms_b.comic_id = b.comic_id
+            GROUP BY a.comic_id, b.comic_id, norms_a.norm, norms_b.norm
+
+            UNION ALL
+
+            SELECT a.comic_id, b.comic_id, $1 * count(*) / (counts_a.features + counts_b.features - count(*))
+            FROM features AS a
+            INNER JOIN features AS b ON b.feature = a.feature AND b.comic_id <> a.comic_id
+            INNER JOIN feature_counts AS counts_a ON counts_a.comic_id = a.comic_id
+            INNER JOIN feature_counts AS counts_b ON counts_b.comic_id = b.comic_id
+            GROUP BY a.comic_id, b.comic_id, counts_a.features, counts_b.features
+        ) AS scores
+        GROUP BY comic_id, similar_comic_id
+    ) AS ranked
+    WHERE rank <= $2
+";
+
+// a user is recommended the comics similar to the ones they interacted with, weighted by how
+// much they're into them, leaving out comics they already know of and their own comics
+//
+// $1 is the number of recommendations kept for each user
+const REFRESH_RECOMMENDATIONS_QUERY: &str = "
+    INSERT INTO user_recommendations (user_id, comic_id, score, computed_at)
+    SELECT user_id, comic_id, score, now()
+    FROM (
+        SELECT
+            interactions.user_id,
+            comic_similarities.similar_comic_id AS comic_id,
+            sum(interactions.weight * comic_similarities.score) AS score,
+            row_number() OVER (
+                PARTITION BY interactions.user_id
+                ORDER BY sum(interactions.weight * comic_similarities.score) DESC, comic_similarities.similar_comic_id
+            ) AS rank
+        FROM comic_interactions AS interactions
+        INNER JOIN comic_similarities ON comic_similarities.comic_id = interactions.comic_id
+        INNER JOIN comics ON comics.id = comic_similarities.similar_comic_id
+        WHERE comics.user_id <> interactions.user_id
+            AND NOT EXISTS (
+                SELECT 1 FROM comic_interactions AS seen
+                WHERE seen.user_id = interactions.user_id
+                    AND seen.comic_id = comic_similarities.similar_comic_id
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM library_entries
+                WHERE library_entries.user_id = interactions.user_id
+                    AND library_entries.comic_id = comic_similarities.similar_comic_id
+            )
+        GROUP BY interactions.user_id, comic_similarities.similar_comic_id
+    ) AS ranked
+    WHERE rank <= $1
+";
+
+/// Recompute the similar comics of every comic and the recommendations of every user
+///
+/// the old results are replaced in a single transaction so readers never see them half done,
+/// returns the number of similarities and recommendations
+pub async fn refresh_recommendations(
+    db: &mut AsyncPgConnection,
+) -> Result<(usize, usize), diesel::result::Error> {
+    db.transaction::<_, diesel::result::Error, _>(|transaction| {
+        async move {
+            diesel::delete(user_recommendations::table)
+                .execute(transaction)
+                .await?;
+
+            diesel::delete(comic_similarities::table)
+                .execute(transaction)
+                .await?;
+
+            let similarities = diesel::sql_query(REFRESH_SIMILARITIES_QUERY)
+                .bind::<Double, _>(CONTENT_SIMILARITY_WEIGHT)
+                .bind::<BigInt, _>(SIMILAR_COMICS_LIMIT)
+                .execute(transaction)
+                .await?;
+
+            let recommendations = diesel::sql_query(REFRESH_RECOMMENDATIONS_QUERY)
+                .bind::<BigInt, _>(RECOMMENDATIONS_LIMIT)
+                .execute(transaction)
+                .await?;
+
+            Ok((similarities, recommendations))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Background task that keeps the similar comics and recommendations fresh
+pub async fn recommendations_worker(state: Arc<InnerAppState>) {
+    let mut recommendations_interval =
+        interval(StdDuration::from_secs(RECOMMENDATIONS_INTERVAL_SECS));
+
+    loop {
+        recommendations_interval.tick().await;
+
+        let result = match state.pool.get().await {
+            Ok(mut db) => refresh_recommendations(&mut db)
+                .await
+                .map_err(RecommendationsError::from),
+            Err(err) => Err(err.into()),
+        };
+
+        match result {
+            Ok((similarities, recommendations)) => tracing::debug!(
+                "refreshed {similarities} comic similarities and {recommendations} recommendations"
+            ),
+            Err(err) => tracing::error!("failed to refresh recommendations: {:#?}", err),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::HashSet, sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tokio::time::{interval, interval_at, Instant};
+
+use crate::{
+    schema::{chapter_pages, comics, profile_images, storage_deletions, storage_uploads},
+    users::DEFAULT_PROFILE_IMAGE_PATH,
+    InnerAppState,
+};
+
+use super::{
+    models::{ReconcileReport, StorageDeletion},
+    uploads::process_storage_uploads,
+    ImagesError,
+};
+
+/// Unreferenced objects younger than this are left alone by the reconciler,
+/// they might belong to an upload that hasn't been committed yet
+pub const ORPHAN_GRACE_PERIOD_HOURS: i64 = 24;
+
+const DELETIONS_BATCH_SIZE: i64 = 100;
+
+const MAX_DELETION_ATTEMPTS: i32 = 10;
+
+const DELETIONS_INTERVAL_SECS: u64 = 60;
+
+const RECONCILE_INTERVAL_SECS: u64 = 60 * 60 * 24;
+
+/// Objects shared between many rows, these are never deleted
+const PROTECTED_PATHS: [&str; 1] = [DEFAULT_PROFILE_IMAGE_PATH];
+
+/// Returns the subset of `paths` that is still referenced by the database
+async fn referenced_paths(
+    db: &mut AsyncPgConnection,
+    paths: &[String],
+) -> Result<HashSet<String>, ImagesError> {
+    let mut referenced = paths
+        .iter()
+        .filter(|path| PROTECTED_PATHS.contains(&path.as_str()))
+        .cloned()
+        .collect::<HashSet<String>>();
+
+    referenced.extend(
+        chapter_pages::table
+            .filter(chapter_pages::path.eq_any(paths))
+            .select(chapter_pages::path)
+            .load::<String>(db)
+            .await?,
+    );
+
+    referenced.extend(
+        profile_images::table
+            .filter(profile_images::path.eq_any(paths))
+            .select(profile_images::path)
+            .load::<String>(db)
+            .await?,
+    );
+
+    // staged objects are owned by their upload until it gets promoted or discarded
+    referenced.extend(
+        storage_uploads::table
+            .filter(storage_uploads::staging_path.eq_any(paths))
+            .select(storage_uploads::staging_path)
+            .load::<String>(db)
+            .await?,
+    );
+
+    referenced.extend(
+        comics::table
+            .filter(comics::poster_path.eq_any(paths))
+            .select(comics::poster_path)
+            .load::<Option<String>>(db)
+            .await?
+            .into_iter()
+            .flatten(),
+    );
+
+    Ok(referenced)
+}
+
+/// Delete the objects queued by the storage deletion triggers
+///
+/// returns the number of deleted objects
+pub async fn process_storage_deletions(state: &InnerAppState) -> Result<usize, ImagesError> {
+    let mut db = state.pool.get().await?;
+
+    // claim a batch by removing it from the queue, this way no transaction is kept open
+    // while talking to s3 and other instances skip the rows we are working on
+    let deletions = diesel::delete(
+        storage_deletions::table.filter(
+            storage_deletions::id.eq_any(
+                storage_deletions::table
+                    .select(storage_deletions::id)
+                    .order(storage_deletions::id.asc())
+                    .limit(DELETIONS_BATCH_SIZE)
+                    .for_update()
+                    .skip_locked(),
+            ),
+        ),
+    )
+    .returning(StorageDeletion::as_returning())
+    .get_results::<StorageDeletion>(&mut db)
+    .await?;
+
+    if deletions.is_empty() {
+        return Ok(0);
+    }
+
+    let paths = deletions
+        .iter()
+        .map(|deletion| deletion.path.clone())
+        .collect::<Vec<String>>();
+
+    let referenced = referenced_paths(&mut db, &paths).await?;
+
+    let mut deleted = 0;
+
+    for deletion in deletions {
+        if referenced.contains(&deletion.path) {
+            tracing::debug!("{} is still referenced, skipping deletion", deletion.path);
+            continue;
+        }
+
+        if let Err(err) = state.storage.delete(&deletion.path).await {
+            tracing::error!(
+                "failed to delete {} from storage: {:#?}",
+                deletion.path,
+                err
+            );
+
+            // give up after a while, the reconciler will pick it up eventually
+            if deletion.attempts + 1 < MAX_DELETION_ATTEMPTS {
+                diesel::insert_into(storage_deletions::table)
+                    .values((
+                        storage_deletions::path.eq(&deletion.path),
+                        storage_deletions::attempts.eq(deletion.attempts + 1),
+                        storage_deletions::created_at.eq(deletion.created_at),
+                    ))
+                    .execute(&mut db)
+                    .await?;
+            }
+
+            continue;
+        }
+
+        deleted += 1;
+    }
+
+    Ok(deleted)
+}
+
+/// Compare the bucket keys against the paths referenced by the database
+/// and delete the unreferenced objects older than `grace_period`
+///
+/// nothing is deleted when `dry_run` is set, the report lists what would be deleted
+pub async fn reconcile_storage(
+    state: &InnerAppState,
+    grace_period: Duration,
+    dry_run: bool,
+) -> Result<ReconcileReport, ImagesError> {
+    let mut db = state.pool.get().await?;
+
+    let cutoff = Utc::now() - grace_period;
+
+    let mut report = ReconcileReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let mut continuation_token = None;
+
+    loop {
+        let page = state
+            .storage
+            .list(continuation_token)
+            .await
+            .map_err(ImagesError::Storage)?;
+
+        let keys = page
+            .objects
+            .iter()
+            .map(|object| object.key.clone())
+            .collect::<Vec<String>>();
+
+        let referenced = referenced_paths(&mut db, &keys).await?;
+
+        for object in page.objects {
+            report.scanned += 1;
+
+            if referenced.contains(&object.key) {
+                report.referenced += 1;
+                continue;
+            }
+
+            // objects without a modification date are treated as recent
+            if object
+                .last_modified
+                .map_or(true, |last_modified| last_modified > cutoff)
+            {
+                report.recent += 1;
+                continue;
+            }
+
+            if !dry_run {
+                if let Err(err) = state.storage.delete(&object.key).await {
+                    tracing::error!("failed to delete orphan {}: {:#?}", object.key, err);
+                    report.failed.push(object.key);
+                    continue;
+                }
+            }
+
+            report.orphaned.push(object.key);
+        }
+
+        match page.next_continuation_token {
+            Some(token) => continuation_token = Some(token),
+            None => break,
+        }
+    }
+
+    Ok(report)
+}
+
+/// Background task that drains the storage deletion queue, promotes or discards
+/// staged uploads and periodically reconciles the bucket with the database
+pub async fn storage_cleanup_worker(state: Arc<InnerAppState>) {
+    let mut deletions_interval = interval(StdDuration::from_secs(DELETIONS_INTERVAL_SECS));
+
+    let reconcile_period = StdDuration::from_secs(RECONCILE_INTERVAL_SECS);
+    let mut reconcile_interval = interval_at(Instant::now() + reconcile_period, reconcile_period);
+
+    loop {
+        tokio::select! {
+            _ = deletions_interval.tick() => {
+                match process_storage_deletions(&state).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("deleted {deleted} objects from storage"),
+                    Err(err) => tracing::error!("failed to process storage deletions: {:#?}", err),
+                }
+
+                match process_storage_uploads(&state).await {
+                    Ok((0, 0)) => {}
+                    Ok((promoted, discarded)) => tracing::info!(
+                        "promoted {promoted} and discarded {discarded} staged uploads"
+                    ),
+                    Err(err) => tracing::error!("failed to process staged uploads: {:#?}", err),
+                }
+            }
+            _ = reconcile_interval.tick() => {
+                match reconcile_storage(&state, Duration::hours(ORPHAN_GRACE_PERIOD_HOURS), false).await {
+                    Ok(report) => tracing::info!(
+                        "storage reconciled: scanned {}, deleted {} orphans, {} failed",
+                        report.scanned,
+                        report.orphaned.len(),
+                        report.failed.len()
+                    ),
+                    Err(err) => tracing::error!("failed to reconcile storage: {:#?}", err),
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,74 @@
-use aws_sdk_s3::{types::ByteStream, Client, Config};
+use aws_sdk_s3::{
+    error::HeadObjectErrorKind,
+    presigning::config::PresigningConfig,
+    types::{ByteStream, DateTime as AwsDateTime, SdkError},
+    Client, Config,
+};
 use aws_smithy_http::body::{BoxBody, SdkBody};
 use axum::http::HeaderMap;
 use axum::BoxError;
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
 use futures_util::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
 use http_body::Body;
 use pin_project_lite::pin_project;
 use std::{
     pin::Pin,
     task::{self, Poll},
+    time::Duration,
 };
 use sync_wrapper::SyncWrapper;
 
 use super::ImagesError;
 
+/// A single object returned when listing the bucket
+#[derive(Debug)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Metadata of an object, as reported by s3
+#[derive(Debug)]
+pub struct ObjectMetadata {
+    pub content_length: i64,
+    pub content_type: Option<String>,
+}
+
+/// Range and conditions of a get request
+#[derive(Debug, Default)]
+pub struct GetObjectOptions {
+    /// value of the `Range` header, e.g. `bytes=0-1023`
+    pub range: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<DateTime<Utc>>,
+}
+
+/// Body of an object along with the metadata needed to serve it
+pub struct ObjectStream {
+    /// length of the returned body, not of the whole object for range requests
+    pub content_length: i64,
+    pub content_type: Option<String>,
+    /// set when only a range of the object is returned
+    pub content_range: Option<String>,
+    pub e_tag: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub body: BoxStream<'static, super::Result<Bytes>>,
+}
+
+pub enum GetObjectResponse {
+    Object(ObjectStream),
+    NotModified { e_tag: Option<String> },
+    RangeNotSatisfiable,
+}
+
+/// One page of a bucket listing
+#[derive(Debug)]
+pub struct ObjectsPage {
+    pub objects: Vec<StoredObject>,
+    pub next_continuation_token: Option<String>,
+}
+
 pin_project! {
     struct StreamBody<S> {
         #[pin]
@@ -75,40 +130,157 @@ impl Storage {
         Ok(())
     }
 
-    pub async fn get_stream(
+    /// Get the metadata of the object at `path`, `None` if it doesn't exist
+    pub async fn head(&self, path: &str) -> super::Result<Option<ObjectMetadata>> {
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(path)
+            .send()
+            .await
+        {
+            Ok(response) => Ok(Some(ObjectMetadata {
+                content_length: response.content_length(),
+                content_type: response.content_type().map(String::from),
+            })),
+            Err(SdkError::ServiceError(service_error))
+                if matches!(service_error.err().kind, HeadObjectErrorKind::NotFound(_)) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Create a presigned PUT url for `path`
+    ///
+    /// the content type and length are part of the signature,
+    /// so the client has to upload exactly what it declared
+    pub async fn presign_put(
         &self,
         path: &str,
-    ) -> super::Result<BoxStream<'static, super::Result<Bytes>>> {
-        let response = self
+        content_length: i64,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> super::Result<String> {
+        let presigned_request = self
             .client
-            .get_object()
+            .put_object()
             .bucket(&self.bucket_name)
             .key(path)
+            .content_length(content_length)
+            .content_type(content_type)
+            .presigned(PresigningConfig::expires_in(expires_in)?)
+            .await?;
+
+        Ok(presigned_request.uri().to_string())
+    }
+
+    /// Copy the object at `from` to `to` inside the bucket
+    ///
+    /// `from` is used as is in the copy source, so it must not need url encoding
+    pub async fn copy(&self, from: &str, to: &str) -> super::Result<()> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .copy_source(format!("{}/{}", self.bucket_name, from))
+            .key(to)
             .send()
             .await?;
 
-        Ok(response.body.map_err(Into::into).boxed())
+        Ok(())
     }
 
-    pub async fn get_bytes(&self, path: &str) -> Result<Bytes, ImagesError> {
-        let response: BoxStream<'static, Result<Bytes, ImagesError>> = self
+    /// List at most 1000 objects of the bucket, starting from `continuation_token`
+    pub async fn list(&self, continuation_token: Option<String>) -> super::Result<ObjectsPage> {
+        let response = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket_name)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+
+        let objects = response
+            .contents()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|object| {
+                Some(StoredObject {
+                    key: object.key()?.to_string(),
+                    last_modified: object
+                        .last_modified()
+                        .and_then(|date| Utc.timestamp_opt(date.secs(), 0).single()),
+                })
+            })
+            .collect();
+
+        Ok(ObjectsPage {
+            objects,
+            next_continuation_token: response
+                .is_truncated()
+                .then(|| response.next_continuation_token().map(String::from))
+                .flatten(),
+        })
+    }
+
+    /// Get the object at `path` as a stream along with its metadata
+    ///
+    /// the range and conditions are forwarded to s3, which does the matching
+    pub async fn get_stream(
+        &self,
+        path: &str,
+        options: GetObjectOptions,
+    ) -> Result<GetObjectResponse, ImagesError> {
+        let response = match self
             .client
             .get_object()
             .bucket(&self.bucket_name)
             .key(path)
+            .set_range(options.range)
+            .set_if_none_match(options.if_none_match)
+            .set_if_modified_since(
+                options
+                    .if_modified_since
+                    .map(|date| AwsDateTime::from_secs(date.timestamp())),
+            )
             .send()
-            .await?
-            .body
-            .map_err(Into::into)
-            .boxed();
-
-        Ok(response
-            .try_fold(BytesMut::new(), |mut acc, chunk| {
-                acc.put(chunk);
-                futures_util::future::ok(acc)
-            })
-            .await?
-            .freeze())
+            .await
+        {
+            Ok(response) => response,
+            // s3 reports these as errors since they have no body
+            Err(SdkError::ServiceError(service_error))
+                if service_error.raw().http().status().as_u16() == 304 =>
+            {
+                return Ok(GetObjectResponse::NotModified {
+                    e_tag: service_error
+                        .raw()
+                        .http()
+                        .headers()
+                        .get("etag")
+                        .and_then(|e_tag| e_tag.to_str().ok())
+                        .map(String::from),
+                });
+            }
+            Err(SdkError::ServiceError(service_error))
+                if service_error.raw().http().status().as_u16() == 416 =>
+            {
+                return Ok(GetObjectResponse::RangeNotSatisfiable);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(GetObjectResponse::Object(ObjectStream {
+            content_length: response.content_length(),
+            content_type: response.content_type().map(String::from),
+            content_range: response.content_range().map(String::from),
+            e_tag: response.e_tag().map(String::from),
+            last_modified: response
+                .last_modified()
+                .and_then(|date| Utc.timestamp_opt(date.secs(), 0).single()),
+            body: response.body.map_err(Into::into).boxed(),
+        }))
     }
 
     pub async fn put(
@@ -4,10 +4,13 @@ use serde::Deserialize;
 
 use crate::ErrorResponse;
 
+pub mod cleanup;
 pub mod helpers;
 pub mod interface;
 pub mod models;
 pub mod routes;
+pub mod signing;
+pub mod uploads;
 
 pub type Result<T, E = BoxError> = std::result::Result<T, E>;
 
@@ -34,6 +37,18 @@ pub enum ImagesError {
     #[error("bad request")]
     BadRequest,
 
+    #[error("invalid image url signature")]
+    InvalidSignature,
+
+    #[error("image url has expired")]
+    UrlExpired,
+
+    #[error("chapter is locked")]
+    ChapterLocked,
+
+    #[error("mature content is only available to verified adults")]
+    MatureContent,
+
     #[error(transparent)]
     AWSGetError(#[from] aws_smithy_http::result::SdkError<aws_sdk_s3::error::GetObjectError>),
 
@@ -42,6 +57,15 @@ pub enum ImagesError {
 
     #[error(transparent)]
     AWSStreamError(#[from] aws_smithy_http::byte_stream::error::Error),
+
+    #[error(transparent)]
+    Storage(BoxError),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
 }
 
 impl IntoResponse for ImagesError {
@@ -57,6 +81,17 @@ impl IntoResponse for ImagesError {
                 },
             )
                 .into_response(),
+            ImagesError::InvalidSignature
+            | ImagesError::UrlExpired
+            | ImagesError::ChapterLocked
+            | ImagesError::MatureContent => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
             ImagesError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
 
             ImagesError::AWSGetError(e) => match e {
@@ -72,6 +107,9 @@ impl IntoResponse for ImagesError {
             },
             ImagesError::AWSPutError(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
             ImagesError::AWSStreamError(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            ImagesError::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            ImagesError::Diesel(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            ImagesError::PoolError(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
         }
     }
 }
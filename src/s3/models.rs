@@ -1,5 +1,14 @@
+use chrono::{DateTime, Utc};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::schema::{storage_deletions, storage_uploads};
+
+use super::uploads::STAGING_PREFIX;
+
 #[allow(dead_code)]
 pub struct Image {
     id: Uuid,
@@ -7,3 +16,73 @@ pub struct Image {
     post_id: Uuid,
     path: String,
 }
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = storage_deletions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct StorageDeletion {
+    pub id: i64,
+    pub path: String,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An object uploaded to a staging key, waiting to be promoted to `path`
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = storage_uploads)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct StorageUpload {
+    pub id: Uuid,
+    pub staging_path: String,
+    pub path: String,
+    pub content_type: String,
+    pub committed: bool,
+    pub claimed_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub user_id: Uuid,
+}
+
+impl StorageUpload {
+    pub fn new(user_id: Uuid, path: String, content_type: String) -> Self {
+        let id = Uuid::now_v7();
+
+        Self {
+            id,
+            staging_path: format!("{STAGING_PREFIX}{id}"),
+            path,
+            content_type,
+            committed: false,
+            claimed_until: None,
+            created_at: Utc::now(),
+            user_id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ReconcileParams {
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub grace_period_hours: Option<i64>,
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ReconcileReport {
+    pub dry_run: bool,
+    /// number of objects found in the bucket
+    pub scanned: usize,
+    /// objects that are still referenced by the database
+    pub referenced: usize,
+    /// unreferenced objects that are younger than the grace period
+    pub recent: usize,
+    /// unreferenced objects that were (or would be, in dry-run mode) deleted
+    pub orphaned: Vec<String>,
+    /// orphaned objects that failed to be deleted
+    pub failed: Vec<String>,
+}
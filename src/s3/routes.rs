@@ -1,27 +1,67 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::HeaderMap,
-    routing::get,
-    Router,
+    body::{boxed, Empty, StreamBody},
+    extract::{Path, Query, State},
+    http::{
+        header::{
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+            IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE,
+        },
+        HeaderMap, HeaderName, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
 };
+use chrono::{DateTime, Duration, Utc};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
 
-use crate::{auth::AuthExtractor, users::models::UserRole, AppState, InnerAppState};
+use crate::{
+    auth::AuthExtractor,
+    comics::{
+        chapters::{entitlements::has_chapter_access, models::Chapter},
+        models::ContentRating,
+    },
+    schema::{chapter_pages, comic_chapters, comics},
+    users::{models::UserRole, utils::can_view_mature_content},
+    utils::http_date,
+    AppState, InnerAppState,
+};
 
-use super::ImagesError;
+use super::{
+    cleanup::{reconcile_storage, ORPHAN_GRACE_PERIOD_HOURS},
+    interface::{GetObjectOptions, GetObjectResponse},
+    models::{ReconcileParams, ReconcileReport},
+    signing::SignedImageParams,
+    ImagesError,
+};
 
 pub fn images_routes() -> Router<AppState> {
-    Router::new().route("/:image_path", get(get_image))
+    Router::new()
+        .route("/:image_path", get(get_image))
+        .route("/reconcile", post(reconcile_images))
 }
 
 /// Get an image
+///
+/// Image urls are signed, use the `url` returned along with the image metadata.
+/// Supports conditional requests (`If-None-Match`, `If-Modified-Since`) and single byte ranges
 #[utoipa::path(
     get,
     path = "/api/v1/images/:image_path",
+    params(
+        SignedImageParams,
+    ),
     responses(
         (status = 200, description = "Image found", content_type = "application/octet-stream"),
+        (status = StatusCode::PARTIAL_CONTENT, description = "Requested range of the image", content_type = "application/octet-stream"),
+        (status = StatusCode::NOT_MODIFIED, description = "Cached image is still valid"),
         (status = StatusCode::BAD_REQUEST, description = "Image not found"),
+        (status = StatusCode::FORBIDDEN, description = "Invalid or expired image url, or the image belongs to a locked chapter or a mature comic", body = ErrorResponse),
+        (status = StatusCode::RANGE_NOT_SATISFIABLE, description = "Requested range is outside of the image"),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong"),
     ),
     tag = "Images API"
@@ -29,19 +69,171 @@ pub fn images_routes() -> Router<AppState> {
 #[axum::debug_handler(state = AppState)]
 pub async fn get_image(
     State(state): State<Arc<InnerAppState>>,
-    // TODO: check if authorized to view the image (paid for the chapter that contains this image/page)
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
+    auth: Option<AuthExtractor<{ UserRole::User as u32 }>>,
     headers: HeaderMap,
     Path(image_path): Path<String>,
-) -> Result<bytes::Bytes, ImagesError> {
-    let referer = headers.get("referer").ok_or(ImagesError::BadRequest)?;
+    Query(params): Query<SignedImageParams>,
+) -> Result<Response, ImagesError> {
+    let current_user = auth.map(|auth| auth.current_user);
+
+    state.image_signer.verify(
+        &image_path,
+        &params,
+        current_user.as_ref().map(|user| user.id),
+    )?;
+
+    {
+        let mut db = state.pool.get().await?;
+
+        // pages of locked chapters are only served to users who unlocked the chapter,
+        // pages of mature comics to verified adults
+        let chapter = chapter_pages::table
+            .inner_join(comic_chapters::table)
+            .filter(chapter_pages::path.eq(&image_path))
+            .select(Chapter::as_select())
+            .first::<Chapter>(&mut db)
+            .await
+            .optional()?;
+
+        if let Some(chapter) = chapter {
+            if !has_chapter_access(&mut db, &chapter, current_user.as_ref()).await? {
+                return Err(ImagesError::ChapterLocked);
+            }
+
+            let content_rating = comics::table
+                .find(chapter.comic_id)
+                .select(comics::content_rating)
+                .first::<ContentRating>(&mut db)
+                .await?;
+
+            if content_rating == ContentRating::Mature
+                && current_user.as_ref().map(|user| user.id) != Some(chapter.user_id)
+                && !can_view_mature_content(&mut db, current_user.as_ref()).await?
+            {
+                return Err(ImagesError::MatureContent);
+            }
+        }
+    }
+
+    let header_string = |name: HeaderName| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    };
+
+    let options = GetObjectOptions {
+        range: header_string(RANGE),
+        if_none_match: header_string(IF_NONE_MATCH),
+        if_modified_since: header_string(IF_MODIFIED_SINCE)
+            .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+            .map(|date| date.with_timezone(&Utc)),
+    };
+
+    let cache_control = cache_control(&image_path);
+
+    let object = match state.storage.get_stream(&image_path, options).await? {
+        GetObjectResponse::Object(object) => object,
+        GetObjectResponse::NotModified { e_tag } => {
+            let mut response = Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(CACHE_CONTROL, cache_control);
+
+            if let Some(e_tag) = e_tag {
+                response = response.header(ETAG, e_tag);
+            }
+
+            return response
+                .body(boxed(Empty::new()))
+                .map_err(|_| ImagesError::InternalServerError);
+        }
+        GetObjectResponse::RangeNotSatisfiable => {
+            return Ok(StatusCode::RANGE_NOT_SATISFIABLE.into_response());
+        }
+    };
+
+    let mut response = Response::builder()
+        .status(if object.content_range.is_some() {
+            StatusCode::PARTIAL_CONTENT
+        } else {
+            StatusCode::OK
+        })
+        .header(
+            CONTENT_TYPE,
+            object
+                .content_type
+                .unwrap_or_else(|| String::from("application/octet-stream")),
+        )
+        .header(CONTENT_LENGTH, object.content_length)
+        .header(ACCEPT_RANGES, "bytes")
+        .header(CACHE_CONTROL, cache_control);
+
+    if let Some(content_range) = object.content_range {
+        response = response.header(CONTENT_RANGE, content_range);
+    }
 
-    if referer != &state.s3_referer {
-        tracing::debug!("referer: {referer:?}");
-        return Err(ImagesError::BadRequest);
+    if let Some(e_tag) = object.e_tag {
+        response = response.header(ETAG, e_tag);
     }
 
-    let bytes = state.storage.get_bytes(&image_path).await?;
+    if let Some(last_modified) = object.last_modified {
+        response = response.header(LAST_MODIFIED, http_date(last_modified));
+    }
+
+    response
+        .body(boxed(StreamBody::new(object.body)))
+        .map_err(|_| ImagesError::InternalServerError)
+}
+
+/// Uploaded images get a unique `{uuid}_` prefixed key and are never overwritten,
+/// so they can be cached forever. Anything else has to be revalidated
+fn cache_control(image_path: &str) -> &'static str {
+    let content_addressed = image_path
+        .split_once('_')
+        .map_or(false, |(prefix, _)| Uuid::try_parse(prefix).is_ok());
+
+    // images are only served to logged in users, shared caches must not keep them
+    if content_addressed {
+        "private, max-age=31536000, immutable"
+    } else {
+        "private, no-cache"
+    }
+}
+
+/// Reconcile storage objects with the database
+///
+/// Deletes objects that are not referenced by any chapter page, profile image or comic poster
+/// and are older than the grace period. Defaults to a dry run that only reports the orphans.
+#[utoipa::path(
+    post,
+    path = "/api/v1/images/reconcile",
+    params(
+        ReconcileParams,
+    ),
+    responses(
+        (status = 200, description = "Storage reconciled", body = ReconcileReport),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong"),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Images API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn reconcile_images(
+    _auth: AuthExtractor<{ UserRole::Admin as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<ReconcileParams>,
+) -> Result<Json<ReconcileReport>, ImagesError> {
+    let grace_period = Duration::hours(
+        params
+            .grace_period_hours
+            .unwrap_or(ORPHAN_GRACE_PERIOD_HOURS)
+            .max(0),
+    );
+
+    let report = reconcile_storage(&state, grace_period, params.dry_run).await?;
 
-    Ok(bytes)
+    Ok(Json(report))
 }
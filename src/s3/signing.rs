@@ -0,0 +1,177 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::common::models::ImageMetadataResponse;
+
+use super::ImagesError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Minimum lifetime of an issued image url
+pub const IMAGE_URL_TTL_HOURS: i64 = 6;
+
+/// Decoded signing secrets must be at least this long, the size of the HMAC-SHA256 output
+const MIN_SIGNING_SECRET_BYTES: usize = 32;
+
+/// Image urls expire on a multiple of this, so the same url is issued
+/// for a while and browsers can reuse their cached copy
+const IMAGE_URL_EXPIRY_STEP_SECS: i64 = 60 * 60;
+
+/// A key used to sign image urls, `id` ends up in the url so the right key is used to verify it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImageSigningKey {
+    pub id: String,
+    /// base64 encoded
+    pub secret: String,
+}
+
+/// An `ImageSigningKey` with its secret decoded
+struct DecodedSigningKey {
+    id: String,
+    secret: Vec<u8>,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct SignedImageParams {
+    /// unix timestamp after which the url is no longer valid
+    pub expires: i64,
+    /// id of the key that signed the url
+    pub kid: String,
+    /// only this user can use the url
+    pub viewer: Option<Uuid>,
+    pub signature: String,
+}
+
+/// Signs and verifies image urls
+///
+/// The first key signs new urls, all of them are accepted when verifying.
+/// To rotate keys, add the new key first and remove the old one once the urls it signed have expired
+pub struct ImageSigner {
+    keys: Vec<DecodedSigningKey>,
+}
+
+impl ImageSigner {
+    pub fn new(keys: Vec<ImageSigningKey>) -> Self {
+        assert!(
+            !keys.is_empty(),
+            "at least one image signing key is required"
+        );
+
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                let secret = STANDARD
+                    .decode(&key.secret)
+                    .unwrap_or_else(|_| panic!("image signing key {} isn't valid base64", key.id));
+
+                assert!(
+                    secret.len() >= MIN_SIGNING_SECRET_BYTES,
+                    "image signing key {} must be at least {MIN_SIGNING_SECRET_BYTES} bytes",
+                    key.id
+                );
+
+                DecodedSigningKey { id: key.id, secret }
+            })
+            .collect();
+
+        Self { keys }
+    }
+
+    fn mac(secret: &[u8], path: &str, expires: i64, viewer: Option<Uuid>) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(secret).expect("hmac accepts keys of any size");
+
+        mac.update(path.as_bytes());
+        mac.update(b"\n");
+        mac.update(expires.to_string().as_bytes());
+        mac.update(b"\n");
+        if let Some(viewer) = viewer {
+            mac.update(viewer.as_bytes());
+        }
+
+        mac
+    }
+
+    /// Create a url for the image at `path`, limited to `viewer` if specified
+    pub fn sign_url(&self, path: &str, viewer: Option<Uuid>) -> String {
+        let key = &self.keys[0];
+
+        let expires = url_expiry(Utc::now());
+
+        let signature = URL_SAFE_NO_PAD.encode(
+            Self::mac(&key.secret, path, expires, viewer)
+                .finalize()
+                .into_bytes(),
+        );
+
+        let mut url = format!(
+            "/api/v1/images/{}?expires={expires}&kid={}&signature={signature}",
+            utf8_percent_encode(path, NON_ALPHANUMERIC),
+            utf8_percent_encode(&key.id, NON_ALPHANUMERIC),
+        );
+
+        if let Some(viewer) = viewer {
+            url.push_str(&format!("&viewer={viewer}"));
+        }
+
+        url
+    }
+
+    pub fn image_metadata(
+        &self,
+        path: String,
+        content_type: String,
+        viewer: Option<Uuid>,
+    ) -> ImageMetadataResponse {
+        ImageMetadataResponse {
+            url: self.sign_url(&path, viewer),
+            content_type,
+            path,
+        }
+    }
+
+    /// Verify the signature of an image url, `current_user` is the id of the logged in user if any
+    pub fn verify(
+        &self,
+        path: &str,
+        params: &SignedImageParams,
+        current_user: Option<Uuid>,
+    ) -> Result<(), ImagesError> {
+        if params.expires < Utc::now().timestamp() {
+            return Err(ImagesError::UrlExpired);
+        }
+
+        if params.viewer.is_some() && params.viewer != current_user {
+            return Err(ImagesError::InvalidSignature);
+        }
+
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.id == params.kid)
+            .ok_or(ImagesError::InvalidSignature)?;
+
+        let signature = URL_SAFE_NO_PAD
+            .decode(&params.signature)
+            .map_err(|_| ImagesError::InvalidSignature)?;
+
+        Self::mac(&key.secret, path, params.expires, params.viewer)
+            .verify_slice(&signature)
+            .map_err(|_| ImagesError::InvalidSignature)
+    }
+}
+
+fn url_expiry(now: DateTime<Utc>) -> i64 {
+    let expires = (now + Duration::hours(IMAGE_URL_TTL_HOURS)).timestamp();
+
+    expires - expires.rem_euclid(IMAGE_URL_EXPIRY_STEP_SECS) + IMAGE_URL_EXPIRY_STEP_SECS
+}
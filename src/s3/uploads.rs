@@ -0,0 +1,288 @@
+use bytes::Bytes;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{dsl::exists, prelude::*};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use futures_util::Stream;
+use uuid::Uuid;
+
+use crate::{
+    schema::{chapter_pages, storage_deletions, storage_uploads},
+    InnerAppState,
+};
+
+use super::{models::StorageUpload, ImagesError};
+
+/// Key prefix of objects that were uploaded but not committed yet
+pub const STAGING_PREFIX: &str = "staging/";
+
+/// Uncommitted staged uploads older than this are considered abandoned
+pub const STAGED_UPLOAD_TTL_MINUTES: i64 = 60;
+
+/// How long promoting or discarding an upload can take before someone else can claim it
+const UPLOAD_CLAIM_LEASE_MINUTES: i64 = 5;
+
+const UPLOADS_BATCH_SIZE: i64 = 100;
+
+/// Record an upload whose object will be put under its staging key later,
+/// e.g. by a client using a presigned url
+pub async fn reserve_upload(
+    db: &mut AsyncPgConnection,
+    upload: &StorageUpload,
+) -> Result<StorageUpload, diesel::result::Error> {
+    diesel::insert_into(storage_uploads::table)
+        .values(upload)
+        .returning(StorageUpload::as_returning())
+        .get_result::<StorageUpload>(db)
+        .await
+}
+
+/// Record the upload and put the object under its staging key
+///
+/// Must not be called inside a transaction, the row has to exist
+/// before the object does so a crash never leaks the staged object
+pub async fn stage_upload(
+    state: &InnerAppState,
+    upload: StorageUpload,
+    input_stream: impl Stream<Item = super::Result<Bytes>> + 'static + Send,
+    content_length: i64,
+) -> Result<StorageUpload, ImagesError> {
+    let mut db = state.pool.get().await?;
+
+    let upload = reserve_upload(&mut db, &upload).await?;
+
+    if let Err(err) = state
+        .storage
+        .put(
+            &upload.staging_path,
+            input_stream,
+            content_length,
+            &upload.content_type,
+        )
+        .await
+    {
+        diesel::delete(storage_uploads::table.find(upload.id))
+            .execute(&mut db)
+            .await?;
+
+        return Err(ImagesError::Storage(err));
+    }
+
+    Ok(upload)
+}
+
+/// Mark the upload as committed
+///
+/// Call this inside the transaction that inserts the rows referencing the upload's staging key,
+/// the object gets promoted only if that transaction commits
+pub async fn commit_upload(
+    transaction: &mut AsyncPgConnection,
+    upload_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    let now = Utc::now();
+
+    let updated = diesel::update(
+        storage_uploads::table.find(upload_id).filter(
+            storage_uploads::claimed_until
+                .is_null()
+                .or(storage_uploads::claimed_until.lt(now)),
+        ),
+    )
+    .set(storage_uploads::committed.eq(true))
+    .execute(transaction)
+    .await?;
+
+    // the upload is being discarded or was discarded in the meantime, its staged object is gone
+    if updated == 0 {
+        return Err(diesel::result::Error::NotFound);
+    }
+
+    Ok(())
+}
+
+/// Claim the upload for promoting or discarding it, returns the lease if it was claimed
+///
+/// the claim is committed right away, no transaction is held while the object is moved
+async fn claim_upload(
+    db: &mut AsyncPgConnection,
+    upload_id: Uuid,
+    committed: bool,
+) -> Result<Option<DateTime<Utc>>, diesel::result::Error> {
+    let now = Utc::now();
+
+    diesel::update(
+        storage_uploads::table
+            .find(upload_id)
+            .filter(storage_uploads::committed.eq(committed))
+            .filter(
+                storage_uploads::claimed_until
+                    .is_null()
+                    .or(storage_uploads::claimed_until.lt(now)),
+            ),
+    )
+    .set(storage_uploads::claimed_until.eq(now + Duration::minutes(UPLOAD_CLAIM_LEASE_MINUTES)))
+    .returning(storage_uploads::claimed_until)
+    .get_result::<Option<DateTime<Utc>>>(db)
+    .await
+    .optional()
+    .map(Option::flatten)
+}
+
+/// Give up a claim so the upload can be retried without waiting for the lease to pass
+async fn release_upload(db: &mut AsyncPgConnection, upload_id: Uuid, lease: DateTime<Utc>) {
+    if let Err(err) = diesel::update(
+        storage_uploads::table
+            .find(upload_id)
+            .filter(storage_uploads::claimed_until.eq(lease)),
+    )
+    .set(storage_uploads::claimed_until.eq(None::<DateTime<Utc>>))
+    .execute(db)
+    .await
+    {
+        tracing::error!("failed to release upload {}: {:#?}", upload_id, err);
+    }
+}
+
+/// Move a committed upload from its staging key to its final path
+///
+/// The upload is claimed with a lease so concurrent promoters don't race, the object is copied
+/// with no transaction open and chapter pages are only pointed at the final path once it's there.
+/// Returns whether this call promoted the upload
+pub async fn promote_upload(
+    state: &InnerAppState,
+    upload: &StorageUpload,
+) -> Result<bool, ImagesError> {
+    let mut db = state.pool.get().await?;
+
+    // someone else is promoting it, or it's already promoted
+    let Some(lease) = claim_upload(&mut db, upload.id, true).await? else {
+        return Ok(false);
+    };
+
+    let referenced = diesel::select(exists(
+        chapter_pages::table.filter(chapter_pages::path.eq(&upload.staging_path)),
+    ))
+    .get_result::<bool>(&mut db)
+    .await?;
+
+    // pages deleted before the promotion already queued the staged object
+    if referenced {
+        if let Err(err) = state.storage.copy(&upload.staging_path, &upload.path).await {
+            release_upload(&mut db, upload.id, lease).await;
+            return Err(ImagesError::Storage(err));
+        }
+    }
+
+    db.transaction::<_, ImagesError, _>(|transaction| {
+        async move {
+            // the lease passed and someone else took over
+            let deleted = diesel::delete(
+                storage_uploads::table
+                    .find(upload.id)
+                    .filter(storage_uploads::claimed_until.eq(lease)),
+            )
+            .execute(transaction)
+            .await?;
+
+            if deleted == 0 {
+                return Ok(false);
+            }
+
+            // replacing the staging key queues the staged object for deletion
+            let switched = diesel::update(
+                chapter_pages::table.filter(chapter_pages::path.eq(&upload.staging_path)),
+            )
+            .set(chapter_pages::path.eq(&upload.path))
+            .execute(transaction)
+            .await?;
+
+            // the pages were deleted while the object was copied
+            if referenced && switched == 0 {
+                diesel::insert_into(storage_deletions::table)
+                    .values(storage_deletions::path.eq(&upload.path))
+                    .execute(transaction)
+                    .await?;
+            }
+
+            Ok(true)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Delete an upload that will never be committed
+///
+/// The upload is claimed with a lease, which makes a concurrent commit_upload fail.
+/// Returns whether this call discarded the upload
+pub async fn discard_upload(
+    state: &InnerAppState,
+    upload: &StorageUpload,
+) -> Result<bool, ImagesError> {
+    let mut db = state.pool.get().await?;
+
+    let Some(lease) = claim_upload(&mut db, upload.id, false).await? else {
+        return Ok(false);
+    };
+
+    if let Err(err) = state.storage.delete(&upload.staging_path).await {
+        release_upload(&mut db, upload.id, lease).await;
+        return Err(ImagesError::Storage(err));
+    }
+
+    let deleted = diesel::delete(
+        storage_uploads::table
+            .find(upload.id)
+            .filter(storage_uploads::claimed_until.eq(lease)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    Ok(deleted > 0)
+}
+
+/// Promote committed uploads that weren't promoted yet (e.g. the server crashed right after commit)
+/// and delete staged objects that were never committed
+///
+/// returns the number of promoted and discarded uploads
+pub async fn process_storage_uploads(state: &InnerAppState) -> Result<(usize, usize), ImagesError> {
+    let mut db = state.pool.get().await?;
+
+    let stale_before = Utc::now() - Duration::minutes(STAGED_UPLOAD_TTL_MINUTES);
+
+    let uploads = storage_uploads::table
+        .filter(
+            storage_uploads::committed
+                .eq(true)
+                .or(storage_uploads::created_at.lt(stale_before)),
+        )
+        .order(storage_uploads::created_at.asc())
+        .limit(UPLOADS_BATCH_SIZE)
+        .select(StorageUpload::as_select())
+        .load::<StorageUpload>(&mut db)
+        .await?;
+
+    drop(db);
+
+    let mut promoted = 0;
+    let mut discarded = 0;
+
+    for upload in uploads {
+        if upload.committed {
+            match promote_upload(state, &upload).await {
+                Ok(true) => promoted += 1,
+                Ok(false) => {}
+                Err(err) => tracing::error!("failed to promote upload {}: {:#?}", upload.id, err),
+            }
+        } else {
+            match discard_upload(state, &upload).await {
+                Ok(true) => discarded += 1,
+                Ok(false) => {}
+                Err(err) => tracing::error!("failed to discard upload {}: {:#?}", upload.id, err),
+            }
+        }
+    }
+
+    Ok((promoted, discarded))
+}
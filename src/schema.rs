@@ -1,6 +1,30 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "comicstatus"))]
+    pub struct Comicstatus;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "contentrating"))]
+    pub struct Contentrating;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "librarystatus"))]
+    pub struct Librarystatus;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "notificationkind"))]
+    pub struct Notificationkind;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "readingdirection"))]
+    pub struct Readingdirection;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "userrole"))]
     pub struct Userrole;
@@ -24,6 +48,39 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    chapter_daily_views (chapter_id, day) {
+        chapter_id -> Uuid,
+        comic_id -> Uuid,
+        day -> Date,
+        views -> Int8,
+    }
+}
+
+diesel::table! {
+    chapter_entitlements (user_id, chapter_id) {
+        user_id -> Uuid,
+        chapter_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    chapter_page_uploads (id) {
+        id -> Uuid,
+        number -> Int4,
+        description -> Nullable<Text>,
+        content_type -> Text,
+        content_length -> Int8,
+        comic_id -> Uuid,
+        chapter_id -> Uuid,
+        user_id -> Uuid,
+        storage_upload_id -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     chapter_pages (id) {
         id -> Uuid,
@@ -51,6 +108,26 @@ diesel::table! {
 }
 
 diesel::table! {
+    chapter_reads (user_id, chapter_id) {
+        user_id -> Uuid,
+        chapter_id -> Uuid,
+        comic_id -> Uuid,
+        read_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    chapter_view_windows (chapter_id, viewer, window_start) {
+        chapter_id -> Uuid,
+        viewer -> Text,
+        window_start -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
     comic_chapters (id) {
         id -> Uuid,
         title -> Text,
@@ -62,6 +139,14 @@ diesel::table! {
         is_visible -> Bool,
         user_id -> Uuid,
         comic_id -> Uuid,
+        price -> Int4,
+        unlocks_at -> Nullable<Timestamptz>,
+        early_access_at -> Nullable<Timestamptz>,
+        early_access_level -> Nullable<Int4>,
+        search_vector -> Tsvector,
+        rating_sum -> Float8,
+        rating_count -> Int8,
+        notified_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -83,11 +168,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    comic_daily_views (comic_id, day) {
+        comic_id -> Uuid,
+        day -> Date,
+        views -> Int8,
+    }
+}
+
+diesel::table! {
+    comic_genre_translations (genre_id, locale) {
+        genre_id -> Int4,
+        locale -> Text,
+        name -> Text,
+    }
+}
+
 diesel::table! {
     comic_genres (id) {
         id -> Int4,
         name -> Text,
         created_at -> Timestamptz,
+        slug -> Text,
+        parent_id -> Nullable<Int4>,
     }
 }
 
@@ -98,6 +201,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    comic_rankings (comic_id) {
+        comic_id -> Uuid,
+        trending_score -> Float8,
+        views -> Int8,
+        computed_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     comic_ratings (id) {
         id -> Uuid,
@@ -110,6 +222,47 @@ diesel::table! {
 }
 
 diesel::table! {
+    comic_similarities (comic_id, similar_comic_id) {
+        comic_id -> Uuid,
+        similar_comic_id -> Uuid,
+        score -> Float8,
+        computed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    comic_tags (id) {
+        id -> Uuid,
+        name -> Text,
+        merged_into -> Nullable<Uuid>,
+        banned_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    comic_tags_mapping (comic_id, tag_id) {
+        comic_id -> Uuid,
+        tag_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    comic_view_windows (comic_id, viewer, window_start) {
+        comic_id -> Uuid,
+        viewer -> Text,
+        window_start -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+    use super::sql_types::Comicstatus;
+    use super::sql_types::Contentrating;
+    use super::sql_types::Readingdirection;
+
     comics (id) {
         id -> Uuid,
         title -> Text,
@@ -122,6 +275,14 @@ diesel::table! {
         poster_path -> Nullable<Text>,
         poster_content_type -> Nullable<Text>,
         user_id -> Uuid,
+        search_vector -> Tsvector,
+        status -> Comicstatus,
+        content_rating -> Contentrating,
+        reading_direction -> Readingdirection,
+        original_language -> Text,
+        rating_sum -> Float8,
+        rating_count -> Int8,
+        chapters_count -> Int8,
     }
 }
 
@@ -135,6 +296,113 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    follows (follower_id, followed_id) {
+        follower_id -> Uuid,
+        followed_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    ledger_entries (id) {
+        id -> Int8,
+        transaction_id -> Uuid,
+        wallet_id -> Uuid,
+        amount -> Int8,
+        balance_after -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    ledger_transactions (id) {
+        id -> Uuid,
+        kind -> Text,
+        idempotency_key -> Text,
+        user_id -> Uuid,
+        chapter_id -> Nullable<Uuid>,
+        reverses_id -> Nullable<Uuid>,
+        provider_reference -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Librarystatus;
+
+    library_entries (user_id, comic_id) {
+        user_id -> Uuid,
+        comic_id -> Uuid,
+        status -> Librarystatus,
+        last_read_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    library_shelf_comics (shelf_id, comic_id) {
+        shelf_id -> Uuid,
+        user_id -> Uuid,
+        comic_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    library_shelves (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Notificationkind;
+
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        kind -> Notificationkind,
+        actor_id -> Nullable<Uuid>,
+        comic_id -> Nullable<Uuid>,
+        chapter_id -> Nullable<Uuid>,
+        comment_id -> Nullable<Uuid>,
+        milestone -> Nullable<Int8>,
+        details -> Nullable<Text>,
+        read_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    payout_requests (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        amount -> Int8,
+        status -> Text,
+        request_transaction_id -> Uuid,
+        resolution_transaction_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    provider_refunds (transaction_id) {
+        transaction_id -> Uuid,
+        status -> Text,
+        provider_reference -> Nullable<Text>,
+        created_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     profile_images (id) {
         id -> Uuid,
@@ -145,6 +413,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    reading_progress (user_id, comic_id) {
+        user_id -> Uuid,
+        comic_id -> Uuid,
+        chapter_id -> Uuid,
+        page -> Int4,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     sessions (id) {
         id -> Uuid,
@@ -154,6 +432,89 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    storage_deletions (id) {
+        id -> Int8,
+        path -> Text,
+        attempts -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    storage_uploads (id) {
+        id -> Uuid,
+        staging_path -> Text,
+        path -> Text,
+        content_type -> Text,
+        committed -> Bool,
+        claimed_until -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        user_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    subscription_payments (id) {
+        id -> Uuid,
+        subscription_id -> Uuid,
+        tier_id -> Uuid,
+        amount -> Int8,
+        period_start -> Timestamptz,
+        period_end -> Timestamptz,
+        provider_reference -> Text,
+        idempotency_key -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    subscription_tiers (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        description -> Nullable<Text>,
+        price -> Int8,
+        level -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    subscriptions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        author_id -> Uuid,
+        tier_id -> Uuid,
+        next_tier_id -> Nullable<Uuid>,
+        current_period_start -> Timestamptz,
+        current_period_end -> Timestamptz,
+        canceled_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    tus_uploads (id) {
+        id -> Uuid,
+        upload_length -> Int8,
+        upload_offset -> Int8,
+        target -> Text,
+        file_name -> Text,
+        content_type -> Text,
+        number -> Int4,
+        description -> Nullable<Text>,
+        comic_id -> Uuid,
+        chapter_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        locked_until -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     user_links (id) {
         id -> Uuid,
@@ -163,8 +524,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_recommendations (user_id, comic_id) {
+        user_id -> Uuid,
+        comic_id -> Uuid,
+        score -> Float8,
+        computed_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
     use super::sql_types::Userrole;
 
     users (id) {
@@ -181,44 +552,133 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Nullable<Timestamptz>,
         last_login -> Nullable<Timestamptz>,
+        search_vector -> Tsvector,
+        birth_date -> Nullable<Date>,
+        notify_new_chapters -> Bool,
+        notify_comment_replies -> Bool,
+        notify_comic_comments -> Bool,
+        notify_rating_milestones -> Bool,
+        notify_moderation -> Bool,
+    }
+}
+
+diesel::table! {
+    wallets (id) {
+        id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        kind -> Text,
+        balance -> Int8,
+        created_at -> Timestamptz,
     }
 }
 
 diesel::joinable!(chapter_comments -> comic_chapters (chapter_id));
 diesel::joinable!(chapter_comments -> users (user_id));
+diesel::joinable!(chapter_daily_views -> comic_chapters (chapter_id));
+diesel::joinable!(chapter_daily_views -> comics (comic_id));
+diesel::joinable!(chapter_entitlements -> comic_chapters (chapter_id));
+diesel::joinable!(chapter_entitlements -> users (user_id));
+diesel::joinable!(chapter_page_uploads -> comic_chapters (chapter_id));
+diesel::joinable!(chapter_page_uploads -> comics (comic_id));
+diesel::joinable!(chapter_page_uploads -> storage_uploads (storage_upload_id));
+diesel::joinable!(chapter_page_uploads -> users (user_id));
 diesel::joinable!(chapter_pages -> comic_chapters (chapter_id));
 diesel::joinable!(chapter_pages -> comics (comic_id));
 diesel::joinable!(chapter_pages -> users (user_id));
 diesel::joinable!(chapter_ratings -> comic_chapters (chapter_id));
 diesel::joinable!(chapter_ratings -> users (user_id));
+diesel::joinable!(chapter_reads -> comic_chapters (chapter_id));
+diesel::joinable!(chapter_reads -> comics (comic_id));
+diesel::joinable!(chapter_reads -> users (user_id));
+diesel::joinable!(chapter_view_windows -> comic_chapters (chapter_id));
 diesel::joinable!(comic_chapters -> comics (comic_id));
 diesel::joinable!(comic_chapters -> users (user_id));
 diesel::joinable!(comic_comments -> comics (comic_id));
 diesel::joinable!(comic_comments -> users (user_id));
+diesel::joinable!(comic_daily_views -> comics (comic_id));
+diesel::joinable!(comic_genre_translations -> comic_genres (genre_id));
 diesel::joinable!(comic_genres_mapping -> comic_genres (genre_id));
 diesel::joinable!(comic_genres_mapping -> comics (comic_id));
+diesel::joinable!(comic_rankings -> comics (comic_id));
 diesel::joinable!(comic_ratings -> comics (comic_id));
 diesel::joinable!(comic_ratings -> users (user_id));
+diesel::joinable!(comic_tags_mapping -> comic_tags (tag_id));
+diesel::joinable!(comic_tags_mapping -> comics (comic_id));
+diesel::joinable!(comic_view_windows -> comics (comic_id));
 diesel::joinable!(comics -> users (user_id));
 diesel::joinable!(email_verifications -> users (user_id));
+diesel::joinable!(ledger_entries -> ledger_transactions (transaction_id));
+diesel::joinable!(ledger_entries -> wallets (wallet_id));
+diesel::joinable!(ledger_transactions -> users (user_id));
+diesel::joinable!(library_entries -> comics (comic_id));
+diesel::joinable!(library_entries -> users (user_id));
+diesel::joinable!(library_shelves -> users (user_id));
+diesel::joinable!(notifications -> comic_chapters (chapter_id));
+diesel::joinable!(notifications -> comics (comic_id));
+diesel::joinable!(payout_requests -> users (user_id));
 diesel::joinable!(profile_images -> users (user_id));
+diesel::joinable!(provider_refunds -> ledger_transactions (transaction_id));
+diesel::joinable!(reading_progress -> comic_chapters (chapter_id));
+diesel::joinable!(reading_progress -> comics (comic_id));
+diesel::joinable!(reading_progress -> users (user_id));
 diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(storage_uploads -> users (user_id));
+diesel::joinable!(subscription_payments -> subscription_tiers (tier_id));
+diesel::joinable!(subscription_payments -> subscriptions (subscription_id));
+diesel::joinable!(subscription_tiers -> users (user_id));
+diesel::joinable!(subscriptions -> subscription_tiers (tier_id));
+diesel::joinable!(tus_uploads -> comic_chapters (chapter_id));
+diesel::joinable!(tus_uploads -> comics (comic_id));
+diesel::joinable!(tus_uploads -> users (user_id));
+diesel::joinable!(user_recommendations -> comics (comic_id));
+diesel::joinable!(user_recommendations -> users (user_id));
+diesel::joinable!(wallets -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     chapter_comments,
     chapter_comments_mapping,
+    chapter_daily_views,
+    chapter_entitlements,
+    chapter_page_uploads,
     chapter_pages,
     chapter_ratings,
+    chapter_reads,
+    chapter_view_windows,
     comic_chapters,
     comic_comments,
     comic_comments_mapping,
+    comic_daily_views,
+    comic_genre_translations,
     comic_genres,
     comic_genres_mapping,
+    comic_rankings,
     comic_ratings,
+    comic_similarities,
+    comic_tags,
+    comic_tags_mapping,
+    comic_view_windows,
     comics,
     email_verifications,
+    follows,
+    ledger_entries,
+    ledger_transactions,
+    library_entries,
+    library_shelf_comics,
+    library_shelves,
+    notifications,
+    payout_requests,
     profile_images,
+    provider_refunds,
+    reading_progress,
     sessions,
+    storage_deletions,
+    storage_uploads,
+    subscription_payments,
+    subscription_tiers,
+    subscriptions,
+    tus_uploads,
     user_links,
+    user_recommendations,
     users,
+    wallets,
 );
@@ -0,0 +1,45 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use diesel_async::pooled_connection::deadpool::PoolError;
+
+use crate::ErrorResponse;
+
+pub mod models;
+pub mod routes;
+
+pub const SEARCH_DEFAULT_LIMIT: i64 = 10;
+
+pub const SEARCH_MAX_LIMIT: i64 = 50;
+
+pub const MAX_QUERY_LENGTH: usize = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("search query must be between 1 and {MAX_QUERY_LENGTH} characters")]
+    InvalidQuery,
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] PoolError),
+}
+
+impl IntoResponse for SearchError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        match self {
+            SearchError::InvalidQuery => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    error: self.to_string(),
+                    ..Default::default()
+                },
+            )
+                .into_response(),
+            SearchError::Diesel(_) | SearchError::PoolError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
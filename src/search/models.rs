@@ -0,0 +1,84 @@
+use diesel::{
+    sql_types::{Float4, Int4, Text, Uuid as SqlUuid},
+    QueryableByName,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub struct SearchParams {
+    /// search query, supports "quoted phrases", `or` and -excluded words
+    pub q: String,
+    /// maximum number of results of each kind
+    pub limit: Option<i64>,
+}
+
+#[derive(QueryableByName, Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct ComicSearchResult {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Text)]
+    pub slug: String,
+    #[diesel(sql_type = Text)]
+    pub author_username: String,
+    /// html escaped normalized title with the matches wrapped in `<mark>`
+    #[diesel(sql_type = Text)]
+    pub title_highlight: String,
+    #[diesel(sql_type = Text)]
+    pub description_highlight: String,
+    #[diesel(sql_type = Float4)]
+    pub rank: f32,
+}
+
+#[derive(QueryableByName, Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct ChapterSearchResult {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Int4)]
+    pub number: i32,
+    #[diesel(sql_type = SqlUuid)]
+    pub comic_id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub comic_title: String,
+    #[diesel(sql_type = Text)]
+    pub comic_slug: String,
+    #[diesel(sql_type = Text)]
+    pub author_username: String,
+    #[diesel(sql_type = Text)]
+    pub title_highlight: String,
+    #[diesel(sql_type = Float4)]
+    pub rank: f32,
+}
+
+#[derive(QueryableByName, Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct AuthorSearchResult {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub username: String,
+    #[diesel(sql_type = Text)]
+    pub displayname: String,
+    #[diesel(sql_type = Text)]
+    pub username_highlight: String,
+    #[diesel(sql_type = Text)]
+    pub displayname_highlight: String,
+    #[diesel(sql_type = Float4)]
+    pub rank: f32,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct SearchResponse {
+    pub comics: Vec<ComicSearchResult>,
+    pub chapters: Vec<ChapterSearchResult>,
+    pub authors: Vec<AuthorSearchResult>,
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel_async::RunQueryDsl;
+
+use crate::{
+    auth::AuthExtractor,
+    users::{models::UserRole, utils::can_view_mature_content},
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{
+        AuthorSearchResult, ChapterSearchResult, ComicSearchResult, SearchParams, SearchResponse,
+    },
+    SearchError, MAX_QUERY_LENGTH, SEARCH_DEFAULT_LIMIT, SEARCH_MAX_LIMIT,
+};
+
+pub fn search_router() -> Router<AppState> {
+    Router::new().route("/", get(search))
+}
+
+// the query is normalized the same way as the search vectors,
+// so it matches regardless of tashkeel, tatweel and letter forms
+//
+// $3 is whether the caller can see mature comics
+const COMICS_QUERY: &str = "
+    SELECT
+        comics.id,
+        comics.title,
+        comics.slug,
+        users.username AS author_username,
+        search_headline(comics.title, query) AS title_highlight,
+        search_headline(coalesce(comics.description, ''), query) AS description_highlight,
+        ts_rank(comics.search_vector, query) AS rank
+    FROM comics
+    INNER JOIN users ON users.id = comics.user_id,
+    websearch_to_tsquery('simple', normalize_arabic($1)) AS query
+    WHERE comics.search_vector @@ query
+        AND ($3 OR comics.content_rating <> 'mature')
+    ORDER BY rank DESC, comics.id DESC
+    LIMIT $2
+";
+
+// unreleased chapters are left out, they're only visible to some users
+const CHAPTERS_QUERY: &str = "
+    SELECT
+        comic_chapters.id,
+        comic_chapters.title,
+        comic_chapters.number,
+        comics.id AS comic_id,
+        comics.title AS comic_title,
+        comics.slug AS comic_slug,
+        users.username AS author_username,
+        search_headline(comic_chapters.title, query) AS title_highlight,
+        ts_rank(comic_chapters.search_vector, query) AS rank
+    FROM comic_chapters
+    INNER JOIN comics ON comics.id = comic_chapters.comic_id
+    INNER JOIN users ON users.id = comics.user_id,
+    websearch_to_tsquery('simple', normalize_arabic($1)) AS query
+    WHERE comic_chapters.search_vector @@ query
+        AND (comic_chapters.published_at IS NULL OR comic_chapters.published_at <= now())
+        AND ($3 OR comics.content_rating <> 'mature')
+    ORDER BY rank DESC, comic_chapters.id DESC
+    LIMIT $2
+";
+
+const AUTHORS_QUERY: &str = "
+    SELECT
+        users.id,
+        users.username,
+        users.displayname,
+        search_headline(users.username, query) AS username_highlight,
+        search_headline(users.displayname, query) AS displayname_highlight,
+        ts_rank(users.search_vector, query) AS rank
+    FROM users,
+    websearch_to_tsquery('simple', normalize_arabic($1)) AS query
+    WHERE users.search_vector @@ query
+        AND EXISTS (SELECT 1 FROM comics WHERE comics.user_id = users.id)
+    ORDER BY rank DESC, users.id DESC
+    LIMIT $2
+";
+
+/// Search comics, chapters and authors
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    params(
+        SearchParams,
+    ),
+    responses(
+        (status = 200, description = "Best matches of each kind", body = SearchResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Empty or too long query", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Search API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn search(
+    auth: Option<AuthExtractor<{ UserRole::User as u32 }>>,
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResponse>, SearchError> {
+    let q = params.q.trim();
+
+    if q.is_empty() || q.chars().count() > MAX_QUERY_LENGTH {
+        return Err(SearchError::InvalidQuery);
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(SEARCH_DEFAULT_LIMIT)
+        .clamp(1, SEARCH_MAX_LIMIT);
+
+    let mut db = state.pool.get().await?;
+
+    let mature =
+        can_view_mature_content(&mut db, auth.as_ref().map(|auth| &auth.current_user)).await?;
+
+    let comics = diesel::sql_query(COMICS_QUERY)
+        .bind::<Text, _>(q)
+        .bind::<BigInt, _>(limit)
+        .bind::<Bool, _>(mature)
+        .load::<ComicSearchResult>(&mut db)
+        .await?;
+
+    let chapters = diesel::sql_query(CHAPTERS_QUERY)
+        .bind::<Text, _>(q)
+        .bind::<BigInt, _>(limit)
+        .bind::<Bool, _>(mature)
+        .load::<ChapterSearchResult>(&mut db)
+        .await?;
+
+    let authors = diesel::sql_query(AUTHORS_QUERY)
+        .bind::<Text, _>(q)
+        .bind::<BigInt, _>(limit)
+        .load::<AuthorSearchResult>(&mut db)
+        .await?;
+
+    Ok(Json(SearchResponse {
+        comics,
+        chapters,
+        authors,
+    }))
+}
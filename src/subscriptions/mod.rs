@@ -0,0 +1,99 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+
+use crate::{wallets::payments::PaymentError, ErrorResponse};
+
+pub mod models;
+pub mod renewals;
+pub mod routes;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SubscriptionsError {
+    #[error("internal server error")]
+    InternalServerError,
+
+    #[error("bad request")]
+    BadRequest,
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("subscription tier not found")]
+    TierNotFound,
+
+    #[error("subscription not found")]
+    SubscriptionNotFound,
+
+    #[error(transparent)]
+    Payment(#[from] PaymentError),
+
+    #[error(transparent)]
+    Validator(#[from] garde::Errors),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for SubscriptionsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let status = match &self {
+            SubscriptionsError::BadRequest => StatusCode::BAD_REQUEST,
+            SubscriptionsError::Conflict(_) => StatusCode::CONFLICT,
+            SubscriptionsError::TierNotFound | SubscriptionsError::SubscriptionNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            SubscriptionsError::Payment(PaymentError::Declined(_)) => StatusCode::PAYMENT_REQUIRED,
+            SubscriptionsError::Payment(PaymentError::Disabled) => StatusCode::SERVICE_UNAVAILABLE,
+            SubscriptionsError::Payment(_) => StatusCode::BAD_GATEWAY,
+            SubscriptionsError::Validator(errors) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    ErrorResponse {
+                        error: String::from("invalid input"),
+                        details: Some(
+                            errors
+                                .flatten()
+                                .iter()
+                                .map(|(path, error)| format!("{path}: {error}"))
+                                .collect::<Vec<String>>(),
+                        ),
+                    },
+                )
+                    .into_response();
+            }
+            SubscriptionsError::Diesel(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+            SubscriptionsError::Diesel(DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                message,
+            )) if message.constraint_name() == Some("subscription_tiers_user_id_level_key") => {
+                return (
+                    StatusCode::CONFLICT,
+                    ErrorResponse {
+                        error: String::from("a tier with the same level already exists"),
+                        ..Default::default()
+                    },
+                )
+                    .into_response();
+            }
+            SubscriptionsError::InternalServerError
+            | SubscriptionsError::Diesel(_)
+            | SubscriptionsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error: self.to_string(),
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
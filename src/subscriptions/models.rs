@@ -0,0 +1,165 @@
+use chrono::{DateTime, Months, Utc};
+use diesel::prelude::*;
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    schema::{subscription_payments, subscription_tiers, subscriptions},
+    users::models::User,
+};
+
+/// End of a billing period starting at `start`
+pub fn period_end(start: DateTime<Utc>) -> DateTime<Utc> {
+    start
+        .checked_add_months(Months::new(1))
+        .expect("subscription period end out of range")
+}
+
+/// Price difference charged when upgrading from `from` to `to` at `now`,
+/// only the rest of the current period is charged
+pub fn upgrade_price(
+    from: &SubscriptionTier,
+    to: &SubscriptionTier,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> i64 {
+    let remaining = (period_end - now).num_seconds().max(0);
+    let length = (period_end - period_start).num_seconds().max(1);
+
+    (i128::from(to.price - from.price) * i128::from(remaining) / i128::from(length)) as i64
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = subscription_tiers)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubscriptionTier {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: i64,
+    pub level: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl SubscriptionTier {
+    pub fn into_response(self) -> SubscriptionTierResponse {
+        SubscriptionTierResponse {
+            id: self.id,
+            author_id: self.user_id,
+            name: self.name,
+            description: self.description,
+            price: self.price,
+            level: self.level,
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(SubscriptionTier, foreign_key = tier_id))]
+#[diesel(table_name = subscriptions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Subscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub author_id: Uuid,
+    pub tier_id: Uuid,
+    pub next_tier_id: Option<Uuid>,
+    pub current_period_start: DateTime<Utc>,
+    pub current_period_end: DateTime<Utc>,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Subscription {
+    pub fn into_response(self, tier: SubscriptionTier) -> SubscriptionResponse {
+        SubscriptionResponse {
+            id: self.id,
+            tier: tier.into_response(),
+            next_tier_id: self.next_tier_id,
+            current_period_start: self.current_period_start,
+            current_period_end: self.current_period_end,
+            active: self.current_period_end > Utc::now(),
+            renews: self.canceled_at.is_none(),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Subscription))]
+#[diesel(belongs_to(SubscriptionTier, foreign_key = tier_id))]
+#[diesel(table_name = subscription_payments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SubscriptionPayment {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub tier_id: Uuid,
+    pub amount: i64,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub provider_reference: String,
+    pub idempotency_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Validate, Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateSubscriptionTier {
+    #[garde(length(min = 1, max = 100))]
+    pub name: String,
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// charged every month
+    #[garde(range(min = 1))]
+    pub price: i64,
+    /// subscribers get the early access of chapters up to this level
+    #[garde(range(min = 1))]
+    pub level: i32,
+}
+
+#[derive(Validate, AsChangeset, Deserialize, ToSchema, Debug, TS)]
+#[diesel(table_name = subscription_tiers)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[ts(export)]
+pub struct UpdateSubscriptionTier {
+    #[garde(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// takes effect when subscriptions are renewed
+    #[garde(range(min = 1))]
+    pub price: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct SubscriptionTierResponse {
+    pub id: Uuid,
+    pub author_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: i64,
+    pub level: i32,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct SubscriptionResponse {
+    pub id: Uuid,
+    pub tier: SubscriptionTierResponse,
+    /// cheaper tier the subscription switches to at the next renewal
+    pub next_tier_id: Option<Uuid>,
+    pub current_period_start: DateTime<Utc>,
+    pub current_period_end: DateTime<Utc>,
+    /// the subscription gives early access until the end of the current period
+    pub active: bool,
+    /// whether the subscription is renewed at the end of the current period
+    pub renews: bool,
+}
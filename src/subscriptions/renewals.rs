@@ -0,0 +1,245 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::{
+    schema::{subscription_payments, subscription_tiers, subscriptions},
+    wallets::payments::{PaymentError, PaymentProvider},
+    InnerAppState,
+};
+
+use super::{
+    models::{period_end, Subscription, SubscriptionPayment, SubscriptionTier},
+    SubscriptionsError,
+};
+
+const RENEWAL_INTERVAL_SECS: u64 = 10 * 60;
+
+/// Subscriptions whose period ended longer ago than this are canceled instead of renewed,
+/// the subscriber is never charged for periods they couldn't use
+const RENEWAL_GRACE_HOURS: i64 = 24;
+
+const RENEWAL_BATCH_SIZE: i64 = 100;
+
+/// Charge a billing period of a subscription through the payment provider
+///
+/// the provider dedupes charges by `idempotency_key`, charging the same period again with the
+/// same key never bills the subscriber twice
+pub async fn charge_period(
+    payment_provider: &dyn PaymentProvider,
+    subscription_id: Uuid,
+    subscriber_id: Uuid,
+    tier: &SubscriptionTier,
+    period_start: DateTime<Utc>,
+    idempotency_key: String,
+) -> Result<SubscriptionPayment, PaymentError> {
+    let provider_reference = payment_provider
+        .charge(subscriber_id, tier.price, &idempotency_key)
+        .await?;
+
+    Ok(SubscriptionPayment {
+        id: Uuid::now_v7(),
+        subscription_id,
+        tier_id: tier.id,
+        amount: tier.price,
+        period_start,
+        period_end: period_end(period_start),
+        provider_reference,
+        idempotency_key,
+        created_at: Utc::now(),
+    })
+}
+
+/// Charge the price difference of an upgrade through the payment provider
+///
+/// the payment covers the rest of the subscription's current period with the new tier
+pub async fn charge_upgrade(
+    payment_provider: &dyn PaymentProvider,
+    subscription: &Subscription,
+    tier: &SubscriptionTier,
+    amount: i64,
+    now: DateTime<Utc>,
+    idempotency_key: String,
+) -> Result<SubscriptionPayment, PaymentError> {
+    let provider_reference = payment_provider
+        .charge(subscription.user_id, amount, &idempotency_key)
+        .await?;
+
+    Ok(SubscriptionPayment {
+        id: Uuid::now_v7(),
+        subscription_id: subscription.id,
+        tier_id: tier.id,
+        amount,
+        period_start: now,
+        period_end: subscription.current_period_end,
+        provider_reference,
+        idempotency_key,
+        created_at: Utc::now(),
+    })
+}
+
+/// Renew a subscription whose current period ended
+///
+/// a downgrade scheduled during the period takes effect with the renewal
+async fn renew_subscription(
+    db: &mut AsyncPgConnection,
+    payment_provider: &dyn PaymentProvider,
+    subscription: Subscription,
+    tier: SubscriptionTier,
+) -> Result<(), SubscriptionsError> {
+    let now = Utc::now();
+
+    if subscription.current_period_end < now - Duration::hours(RENEWAL_GRACE_HOURS) {
+        tracing::warn!(
+            "subscription {} lapsed before it could be renewed",
+            subscription.id
+        );
+
+        diesel::update(subscriptions::table.find(subscription.id))
+            .set((
+                subscriptions::canceled_at.eq(now),
+                subscriptions::updated_at.eq(now),
+            ))
+            .execute(db)
+            .await?;
+
+        return Ok(());
+    }
+
+    // the scheduled tier is gone when it was deleted, the subscription keeps its tier then
+    let tier = match subscription.next_tier_id {
+        Some(next_tier_id) => subscription_tiers::table
+            .find(next_tier_id)
+            .select(SubscriptionTier::as_select())
+            .first::<SubscriptionTier>(db)
+            .await
+            .optional()?
+            .unwrap_or(tier),
+        None => tier,
+    };
+
+    let payment = match charge_period(
+        payment_provider,
+        subscription.id,
+        subscription.user_id,
+        &tier,
+        subscription.current_period_end,
+        // the next period starts where the current one ends, so the key is the same on every retry
+        format!(
+            "renewal:{}:{}",
+            subscription.id,
+            subscription.current_period_end.timestamp()
+        ),
+    )
+    .await
+    {
+        Ok(payment) => payment,
+        Err(PaymentError::Declined(reason)) => {
+            // the subscription ends with the current period, the user has to subscribe again
+            tracing::info!(
+                "renewal of subscription {} declined: {reason}",
+                subscription.id
+            );
+
+            diesel::update(subscriptions::table.find(subscription.id))
+                .set((
+                    subscriptions::canceled_at.eq(now),
+                    subscriptions::updated_at.eq(now),
+                ))
+                .execute(db)
+                .await?;
+
+            return Ok(());
+        }
+        // retried on the next run
+        Err(err) => return Err(err.into()),
+    };
+
+    db.transaction::<_, SubscriptionsError, _>(|transaction| {
+        async move {
+            diesel::insert_into(subscription_payments::table)
+                .values(&payment)
+                .on_conflict((
+                    subscription_payments::subscription_id,
+                    subscription_payments::period_start,
+                ))
+                .do_nothing()
+                .execute(transaction)
+                .await?;
+
+            // only moves the period forward once if the same period is renewed concurrently
+            diesel::update(
+                subscriptions::table
+                    .filter(subscriptions::id.eq(payment.subscription_id))
+                    .filter(subscriptions::current_period_end.eq(payment.period_start)),
+            )
+            .set((
+                subscriptions::tier_id.eq(payment.tier_id),
+                subscriptions::next_tier_id.eq(None::<Uuid>),
+                subscriptions::current_period_start.eq(payment.period_start),
+                subscriptions::current_period_end.eq(payment.period_end),
+                subscriptions::updated_at.eq(Utc::now()),
+            ))
+            .execute(transaction)
+            .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Renew the subscriptions whose current period ended
+///
+/// returns the number of processed subscriptions
+pub async fn process_subscription_renewals(
+    state: &InnerAppState,
+) -> Result<usize, SubscriptionsError> {
+    let mut db = state.pool.get().await?;
+
+    let due = subscriptions::table
+        .inner_join(subscription_tiers::table)
+        .filter(subscriptions::canceled_at.is_null())
+        .filter(subscriptions::current_period_end.le(Utc::now()))
+        .order(subscriptions::current_period_end.asc())
+        .limit(RENEWAL_BATCH_SIZE)
+        .select((Subscription::as_select(), SubscriptionTier::as_select()))
+        .load::<(Subscription, SubscriptionTier)>(&mut db)
+        .await?;
+
+    let processed = due.len();
+
+    for (subscription, tier) in due {
+        let subscription_id = subscription.id;
+
+        if let Err(err) =
+            renew_subscription(&mut db, state.payment_provider.as_ref(), subscription, tier).await
+        {
+            tracing::error!("failed to renew subscription {subscription_id}: {:#?}", err);
+        }
+    }
+
+    Ok(processed)
+}
+
+/// Background task that bills subscriptions at the end of their period
+pub async fn subscription_renewal_worker(state: Arc<InnerAppState>) {
+    let mut renewal_interval = interval(StdDuration::from_secs(RENEWAL_INTERVAL_SECS));
+
+    loop {
+        renewal_interval.tick().await;
+
+        match process_subscription_renewals(&state).await {
+            Ok(0) => {}
+            Ok(processed) => tracing::info!("processed {processed} subscription renewals"),
+            Err(err) => tracing::error!("failed to renew subscriptions: {:#?}", err),
+        }
+    }
+}
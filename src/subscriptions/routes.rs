@@ -0,0 +1,522 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use chrono::Utc;
+use diesel::{
+    result::{DatabaseErrorKind, Error::DatabaseError, Error::NotFound},
+    ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use garde::Validate;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    schema::{subscription_payments, subscription_tiers, subscriptions},
+    users::models::UserRole,
+    wallets::IdempotencyKey,
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{
+        upgrade_price, CreateSubscriptionTier, Subscription, SubscriptionPayment,
+        SubscriptionResponse, SubscriptionTier, SubscriptionTierResponse, UpdateSubscriptionTier,
+    },
+    renewals::{charge_period, charge_upgrade},
+    SubscriptionsError,
+};
+
+pub fn subscriptions_router() -> Router<AppState> {
+    Router::new()
+        .route("/tiers", post(create_tier))
+        .route("/tiers/:tier_id", put(update_tier))
+        .route("/tiers/:tier_id/subscribe", post(subscribe))
+        .route("/authors/:author_id/tiers", get(get_author_tiers))
+        .route("/me", get(get_my_subscriptions))
+        .route("/:subscription_id", delete(cancel_subscription))
+}
+
+/// Create a subscription tier
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions/tiers",
+    request_body(content = CreateSubscriptionTier, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Subscription tier successfully created", body = SubscriptionTierResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Fields validation error", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "A tier with the same level already exists", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Subscriptions API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn create_tier(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Json(payload): Json<CreateSubscriptionTier>,
+) -> Result<Json<SubscriptionTierResponse>, SubscriptionsError> {
+    payload.validate(&())?;
+
+    let mut db = state.pool.get().await?;
+
+    let tier = diesel::insert_into(subscription_tiers::table)
+        .values(&SubscriptionTier {
+            id: Uuid::now_v7(),
+            user_id: auth.current_user.id,
+            name: payload.name,
+            description: payload.description,
+            price: payload.price,
+            level: payload.level,
+            created_at: Utc::now(),
+            updated_at: None,
+        })
+        .returning(SubscriptionTier::as_returning())
+        .get_result::<SubscriptionTier>(&mut db)
+        .await?;
+
+    Ok(Json(tier.into_response()))
+}
+
+/// Update a subscription tier
+#[utoipa::path(
+    put,
+    path = "/api/v1/subscriptions/tiers/:tier_id",
+    request_body(content = UpdateSubscriptionTier, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Subscription tier successfully updated", body = SubscriptionTierResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Fields validation error", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified tier was not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Subscriptions API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn update_tier(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(tier_id): Path<Uuid>,
+    Json(payload): Json<UpdateSubscriptionTier>,
+) -> Result<Json<SubscriptionTierResponse>, SubscriptionsError> {
+    payload.validate(&())?;
+
+    let mut db = state.pool.get().await?;
+
+    let tier = diesel::update(
+        subscription_tiers::table
+            .filter(subscription_tiers::id.eq(tier_id))
+            .filter(subscription_tiers::user_id.eq(auth.current_user.id)),
+    )
+    .set((&payload, subscription_tiers::updated_at.eq(Utc::now())))
+    .returning(SubscriptionTier::as_returning())
+    .get_result::<SubscriptionTier>(&mut db)
+    .await
+    .map_err(|e| match e {
+        NotFound => SubscriptionsError::TierNotFound,
+        e => e.into(),
+    })?;
+
+    Ok(Json(tier.into_response()))
+}
+
+/// Get the subscription tiers of an author
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscriptions/authors/:author_id/tiers",
+    responses(
+        (status = 200, description = "Author's tiers, from the lowest level", body = [SubscriptionTierResponse]),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    tag = "Subscriptions API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_author_tiers(
+    State(state): State<Arc<InnerAppState>>,
+    Path(author_id): Path<Uuid>,
+) -> Result<Json<Vec<SubscriptionTierResponse>>, SubscriptionsError> {
+    let mut db = state.pool.get().await?;
+
+    let tiers = subscription_tiers::table
+        .filter(subscription_tiers::user_id.eq(author_id))
+        .order(subscription_tiers::level.asc())
+        .select(SubscriptionTier::as_select())
+        .load::<SubscriptionTier>(&mut db)
+        .await?;
+
+    Ok(Json(
+        tiers.into_iter().map(|tier| tier.into_response()).collect(),
+    ))
+}
+
+/// Get the subscription paid by the charge made with this idempotency key
+async fn replay(
+    db: &mut AsyncPgConnection,
+    idempotency_key: &str,
+) -> Result<Option<(Subscription, SubscriptionTier)>, SubscriptionsError> {
+    Ok(subscription_payments::table
+        .inner_join(subscriptions::table.inner_join(subscription_tiers::table))
+        .filter(subscription_payments::idempotency_key.eq(idempotency_key))
+        .select((Subscription::as_select(), SubscriptionTier::as_select()))
+        .first::<(Subscription, SubscriptionTier)>(db)
+        .await
+        .optional()?)
+}
+
+/// Unwrap the result of recording a subscription payment
+///
+/// when the same request was sent concurrently, the subscription paid by the other request is returned
+async fn recover_duplicate(
+    db: &mut AsyncPgConnection,
+    result: Result<(Subscription, SubscriptionTier), SubscriptionsError>,
+    idempotency_key: &str,
+) -> Result<(Subscription, SubscriptionTier), SubscriptionsError> {
+    match result {
+        Err(SubscriptionsError::Diesel(DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            info,
+        ))) if info.constraint_name() == Some("subscription_payments_idempotency_key_key") => {
+            replay(db, idempotency_key)
+                .await?
+                .ok_or(SubscriptionsError::InternalServerError)
+        }
+        result => result,
+    }
+}
+
+/// Subscribe to an author's tier
+///
+/// the first period is charged right away. switching to a more expensive tier of the same author
+/// charges the price difference for the rest of the current period, switching to a cheaper one
+/// takes effect at the next renewal
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions/tiers/:tier_id/subscribe",
+    params(
+        ("Idempotency-Key" = String, Header, description = "Unique key of the request, retries must reuse it"),
+    ),
+    responses(
+        (status = 200, description = "Subscribed to the tier, or switching to it at the next renewal", body = SubscriptionResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Can't subscribe to your own tier or invalid idempotency key", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::PAYMENT_REQUIRED, description = "The payment was declined", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified tier was not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Already subscribed to the tier", body = ErrorResponse),
+        (status = StatusCode::BAD_GATEWAY, description = "The payment failed", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Subscriptions API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn subscribe(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    IdempotencyKey(key): IdempotencyKey,
+    Path(tier_id): Path<Uuid>,
+) -> Result<Json<SubscriptionResponse>, SubscriptionsError> {
+    let user_id = auth.current_user.id;
+
+    let mut db = state.pool.get().await?;
+
+    // keys only have to be unique per user
+    let idempotency_key = format!("subscribe:{user_id}:{key}");
+
+    if let Some((subscription, tier)) = replay(&mut db, &idempotency_key).await? {
+        return Ok(Json(subscription.into_response(tier)));
+    }
+
+    let tier = subscription_tiers::table
+        .find(tier_id)
+        .select(SubscriptionTier::as_select())
+        .first::<SubscriptionTier>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => SubscriptionsError::TierNotFound,
+            e => e.into(),
+        })?;
+
+    if tier.user_id == user_id {
+        return Err(SubscriptionsError::BadRequest);
+    }
+
+    let now = Utc::now();
+
+    let existing = subscriptions::table
+        .filter(subscriptions::user_id.eq(user_id))
+        .filter(subscriptions::author_id.eq(tier.user_id))
+        .select(Subscription::as_select())
+        .first::<Subscription>(&mut db)
+        .await
+        .optional()?;
+
+    if let Some(existing) = existing.as_ref().filter(|s| s.current_period_end > now) {
+        let current_tier = subscription_tiers::table
+            .find(existing.tier_id)
+            .select(SubscriptionTier::as_select())
+            .first::<SubscriptionTier>(&mut db)
+            .await?;
+
+        if current_tier.id == tier.id {
+            if existing.canceled_at.is_none() && existing.next_tier_id.is_none() {
+                return Err(SubscriptionsError::Conflict(String::from(
+                    "already subscribed to this tier",
+                )));
+            }
+
+            // resuming a canceled subscription or dropping a scheduled downgrade,
+            // the current period is already paid
+            let subscription = diesel::update(subscriptions::table.find(existing.id))
+                .set((
+                    subscriptions::next_tier_id.eq(None::<Uuid>),
+                    subscriptions::canceled_at.eq(None::<chrono::DateTime<Utc>>),
+                    subscriptions::updated_at.eq(now),
+                ))
+                .returning(Subscription::as_returning())
+                .get_result::<Subscription>(&mut db)
+                .await?;
+
+            return Ok(Json(subscription.into_response(tier)));
+        }
+
+        let amount = upgrade_price(
+            &current_tier,
+            &tier,
+            existing.current_period_start,
+            existing.current_period_end,
+            now,
+        );
+
+        // downgrades take effect at the next renewal, the current period is already paid
+        if amount <= 0 && tier.price < current_tier.price {
+            let subscription = diesel::update(subscriptions::table.find(existing.id))
+                .set((
+                    subscriptions::next_tier_id.eq(tier.id),
+                    subscriptions::canceled_at.eq(None::<chrono::DateTime<Utc>>),
+                    subscriptions::updated_at.eq(now),
+                ))
+                .returning(Subscription::as_returning())
+                .get_result::<Subscription>(&mut db)
+                .await?;
+
+            return Ok(Json(subscription.into_response(current_tier)));
+        }
+
+        // upgrades take effect right away, the rest of the period is charged at the new price
+        let payment = if amount > 0 {
+            Some(
+                charge_upgrade(
+                    state.payment_provider.as_ref(),
+                    existing,
+                    &tier,
+                    amount,
+                    now,
+                    idempotency_key.clone(),
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+
+        let subscription_id = existing.id;
+
+        let result = db
+            .transaction::<_, SubscriptionsError, _>(|transaction| {
+                async move {
+                    let subscription = diesel::update(subscriptions::table.find(subscription_id))
+                        .set((
+                            subscriptions::tier_id.eq(tier.id),
+                            subscriptions::next_tier_id.eq(None::<Uuid>),
+                            subscriptions::canceled_at.eq(None::<chrono::DateTime<Utc>>),
+                            subscriptions::updated_at.eq(now),
+                        ))
+                        .returning(Subscription::as_returning())
+                        .get_result::<Subscription>(transaction)
+                        .await?;
+
+                    if let Some(payment) = payment {
+                        diesel::insert_into(subscription_payments::table)
+                            .values(&payment)
+                            .execute(transaction)
+                            .await?;
+                    }
+
+                    Ok((subscription, tier))
+                }
+                .scope_boxed()
+            })
+            .await;
+
+        let (subscription, tier) = recover_duplicate(&mut db, result, &idempotency_key).await?;
+
+        return Ok(Json(subscription.into_response(tier)));
+    }
+
+    let subscription_id = existing
+        .as_ref()
+        .map(|subscription| subscription.id)
+        .unwrap_or_else(Uuid::now_v7);
+
+    let payment = charge_period(
+        state.payment_provider.as_ref(),
+        subscription_id,
+        user_id,
+        &tier,
+        now,
+        idempotency_key.clone(),
+    )
+    .await?;
+
+    let result = db
+        .transaction::<_, SubscriptionsError, _>(|transaction| {
+            async move {
+                let subscription = diesel::insert_into(subscriptions::table)
+                    .values(&Subscription {
+                        id: payment.subscription_id,
+                        user_id,
+                        author_id: tier.user_id,
+                        tier_id: tier.id,
+                        next_tier_id: None,
+                        current_period_start: payment.period_start,
+                        current_period_end: payment.period_end,
+                        canceled_at: None,
+                        created_at: now,
+                        updated_at: None,
+                    })
+                    .on_conflict((subscriptions::user_id, subscriptions::author_id))
+                    .do_update()
+                    .set((
+                        subscriptions::tier_id.eq(tier.id),
+                        subscriptions::next_tier_id.eq(None::<Uuid>),
+                        subscriptions::current_period_start.eq(payment.period_start),
+                        subscriptions::current_period_end.eq(payment.period_end),
+                        subscriptions::canceled_at.eq(None::<chrono::DateTime<Utc>>),
+                        subscriptions::updated_at.eq(now),
+                    ))
+                    .returning(Subscription::as_returning())
+                    .get_result::<Subscription>(transaction)
+                    .await?;
+
+                diesel::insert_into(subscription_payments::table)
+                    .values(&SubscriptionPayment {
+                        // a concurrent request may have created the subscription first
+                        subscription_id: subscription.id,
+                        ..payment
+                    })
+                    .execute(transaction)
+                    .await?;
+
+                Ok((subscription, tier))
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    let (subscription, tier) = recover_duplicate(&mut db, result, &idempotency_key).await?;
+
+    Ok(Json(subscription.into_response(tier)))
+}
+
+/// Cancel a subscription, it stays active until the end of the current period
+#[utoipa::path(
+    delete,
+    path = "/api/v1/subscriptions/:subscription_id",
+    responses(
+        (status = 200, description = "Subscription won't be renewed", body = SubscriptionResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified subscription was not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Subscriptions API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn cancel_subscription(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<Json<SubscriptionResponse>, SubscriptionsError> {
+    let mut db = state.pool.get().await?;
+
+    let now = Utc::now();
+
+    let subscription = diesel::update(
+        subscriptions::table
+            .filter(subscriptions::id.eq(subscription_id))
+            .filter(subscriptions::user_id.eq(auth.current_user.id)),
+    )
+    .set((
+        subscriptions::canceled_at.eq(now),
+        subscriptions::updated_at.eq(now),
+    ))
+    .returning(Subscription::as_returning())
+    .get_result::<Subscription>(&mut db)
+    .await
+    .map_err(|e| match e {
+        NotFound => SubscriptionsError::SubscriptionNotFound,
+        e => e.into(),
+    })?;
+
+    let tier = subscription_tiers::table
+        .find(subscription.tier_id)
+        .select(SubscriptionTier::as_select())
+        .first::<SubscriptionTier>(&mut db)
+        .await?;
+
+    Ok(Json(subscription.into_response(tier)))
+}
+
+/// Get current user's subscriptions
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscriptions/me",
+    responses(
+        (status = 200, description = "Caller's subscriptions, including the ended ones", body = [SubscriptionResponse]),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Subscriptions API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_my_subscriptions(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<Vec<SubscriptionResponse>>, SubscriptionsError> {
+    let mut db = state.pool.get().await?;
+
+    let subscriptions = subscriptions::table
+        .inner_join(subscription_tiers::table)
+        .filter(subscriptions::user_id.eq(auth.current_user.id))
+        .order(subscriptions::current_period_end.desc())
+        .select((Subscription::as_select(), SubscriptionTier::as_select()))
+        .load::<(Subscription, SubscriptionTier)>(&mut db)
+        .await?;
+
+    Ok(Json(
+        subscriptions
+            .into_iter()
+            .map(|(subscription, tier)| subscription.into_response(tier))
+            .collect(),
+    ))
+}
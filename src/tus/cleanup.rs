@@ -0,0 +1,96 @@
+use std::{collections::HashSet, sync::Arc, time::Duration as StdDuration};
+
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::{schema::tus_uploads, InnerAppState};
+
+use super::{models::upload_file_path, TusError, TUS_UPLOADS_DIR, TUS_UPLOAD_EXPIRY_HOURS};
+
+const TUS_CLEANUP_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Delete expired uploads and the local files of uploads that no longer exist
+/// (e.g. their chapter was deleted)
+///
+/// returns the number of removed files
+pub async fn process_expired_tus_uploads(state: &InnerAppState) -> Result<usize, TusError> {
+    let mut db = state.pool.get().await?;
+
+    diesel::delete(tus_uploads::table.filter(tus_uploads::expires_at.lt(Utc::now())))
+        .execute(&mut db)
+        .await?;
+
+    let mut entries = match tokio::fs::read_dir(TUS_UPLOADS_DIR).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    // files are created right before their row is inserted,
+    // only look at files old enough to not belong to an upload being created
+    let created_before = std::time::SystemTime::now()
+        - StdDuration::from_secs(TUS_UPLOAD_EXPIRY_HOURS as u64 * 60 * 60);
+
+    let mut candidates = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let Some(upload_id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<Uuid>().ok())
+        else {
+            continue;
+        };
+
+        let modified = entry.metadata().await?.modified()?;
+
+        if modified < created_before {
+            candidates.push(upload_id);
+        }
+    }
+
+    if candidates.is_empty() {
+        return Ok(0);
+    }
+
+    let existing = tus_uploads::table
+        .filter(tus_uploads::id.eq_any(&candidates))
+        .select(tus_uploads::id)
+        .load::<Uuid>(&mut db)
+        .await?
+        .into_iter()
+        .collect::<HashSet<Uuid>>();
+
+    let mut removed = 0;
+
+    for upload_id in candidates {
+        if existing.contains(&upload_id) {
+            continue;
+        }
+
+        match tokio::fs::remove_file(upload_file_path(upload_id)).await {
+            Ok(()) => removed += 1,
+            Err(err) => tracing::error!("failed to remove tus upload file {upload_id}: {:#?}", err),
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Background task that removes abandoned resumable uploads
+pub async fn tus_cleanup_worker(state: Arc<InnerAppState>) {
+    let mut cleanup_interval = interval(StdDuration::from_secs(TUS_CLEANUP_INTERVAL_SECS));
+
+    loop {
+        cleanup_interval.tick().await;
+
+        match process_expired_tus_uploads(&state).await {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!("removed {removed} abandoned tus uploads"),
+            Err(err) => tracing::error!("failed to clean up tus uploads: {:#?}", err),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use axum::{
+    http::{HeaderName, StatusCode},
+    response::IntoResponse,
+};
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+
+use crate::ErrorResponse;
+
+pub mod cleanup;
+pub mod models;
+pub mod routes;
+
+pub const TUS_VERSION: &str = "1.0.0";
+
+pub const TUS_EXTENSIONS: &str = "creation,expiration,termination";
+
+/// Partially uploaded files are kept here until the upload completes
+pub const TUS_UPLOADS_DIR: &str = "./uploads/tus";
+
+pub const TUS_UPLOAD_EXPIRY_HOURS: i64 = 24;
+
+/// How long a request can write a chunk for, what's received after it is left for the client
+/// to resume
+pub const TUS_CHUNK_LEASE_MINUTES: i64 = 10;
+
+pub const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+pub const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+pub const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+pub const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+pub const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+pub const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+pub const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+pub const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+#[derive(thiserror::Error, Debug)]
+pub enum TusError {
+    #[error("internal server error")]
+    InternalServerError,
+
+    #[error("unsupported tus version")]
+    UnsupportedVersion,
+
+    #[error("missing or invalid {0} header")]
+    InvalidHeader(&'static str),
+
+    #[error("{0}")]
+    InvalidMetadata(String),
+
+    #[error("upload is larger than the maximum allowed size")]
+    TooLarge,
+
+    #[error("upload offset doesn't match the current offset")]
+    OffsetMismatch,
+
+    #[error("content type must be application/offset+octet-stream")]
+    UnsupportedContentType,
+
+    #[error("upload not found")]
+    UploadNotFound,
+
+    #[error("upload has expired")]
+    UploadExpired,
+
+    #[error("chapter not found")]
+    ChapterNotFound,
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+
+    #[error(transparent)]
+    Images(#[from] crate::s3::ImagesError),
+}
+
+impl IntoResponse for TusError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let status = match &self {
+            TusError::UnsupportedVersion => StatusCode::PRECONDITION_FAILED,
+            TusError::InvalidHeader(_) => StatusCode::BAD_REQUEST,
+            TusError::InvalidMetadata(_) => StatusCode::BAD_REQUEST,
+            TusError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            TusError::OffsetMismatch => StatusCode::CONFLICT,
+            TusError::UnsupportedContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            TusError::UploadNotFound => StatusCode::NOT_FOUND,
+            TusError::UploadExpired => StatusCode::GONE,
+            TusError::ChapterNotFound => StatusCode::NOT_FOUND,
+            TusError::Diesel(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+            TusError::Diesel(DatabaseError(DatabaseErrorKind::UniqueViolation, message))
+                if message.constraint_name() == Some("chapter_pages_chapter_id_number_key") =>
+            {
+                return (
+                    StatusCode::CONFLICT,
+                    [(TUS_RESUMABLE, TUS_VERSION)],
+                    ErrorResponse {
+                        error: String::from("chapter page with same number already exists"),
+                        ..Default::default()
+                    },
+                )
+                    .into_response();
+            }
+            TusError::InternalServerError
+            | TusError::Io(_)
+            | TusError::Diesel(_)
+            | TusError::PoolError(_)
+            | TusError::Images(_) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [(TUS_RESUMABLE, TUS_VERSION)],
+                )
+                    .into_response()
+            }
+        };
+
+        if let TusError::UnsupportedVersion = self {
+            return (
+                status,
+                [
+                    (TUS_RESUMABLE, TUS_VERSION),
+                    (TUS_VERSION_HEADER, TUS_VERSION),
+                ],
+            )
+                .into_response();
+        }
+
+        (
+            status,
+            [(TUS_RESUMABLE, TUS_VERSION)],
+            ErrorResponse {
+                error: self.to_string(),
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
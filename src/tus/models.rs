@@ -0,0 +1,106 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::tus_uploads;
+
+use super::{TusError, TUS_UPLOADS_DIR};
+
+/// What gets created once a tus upload is complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TusUploadTarget {
+    ChapterPage,
+}
+
+impl TusUploadTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TusUploadTarget::ChapterPage => "chapter_page",
+        }
+    }
+
+    pub fn parse(target: &str) -> Option<Self> {
+        match target {
+            "chapter_page" => Some(TusUploadTarget::ChapterPage),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = tus_uploads)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TusUpload {
+    pub id: Uuid,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub target: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub number: i32,
+    pub description: Option<String>,
+    pub comic_id: Uuid,
+    pub chapter_id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl TusUpload {
+    /// Local file holding the bytes received so far
+    pub fn file_path(&self) -> PathBuf {
+        upload_file_path(self.id)
+    }
+}
+
+pub fn upload_file_path(upload_id: Uuid) -> PathBuf {
+    PathBuf::from(TUS_UPLOADS_DIR).join(upload_id.to_string())
+}
+
+/// Decoded `Upload-Metadata` header
+///
+/// the header is a comma separated list of `key base64(value)` pairs
+#[derive(Debug, Default)]
+pub struct UploadMetadata(HashMap<String, String>);
+
+impl UploadMetadata {
+    pub fn parse(header: &str) -> Result<Self, TusError> {
+        let mut metadata = HashMap::new();
+
+        for pair in header
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = match pair.split_once(' ') {
+                Some((key, value)) => (key, value.trim()),
+                None => (pair, ""),
+            };
+
+            let value = STANDARD
+                .decode(value)
+                .ok()
+                .and_then(|value| String::from_utf8(value).ok())
+                .ok_or_else(|| {
+                    TusError::InvalidMetadata(format!("invalid metadata value for {key}"))
+                })?;
+
+            metadata.insert(key.to_string(), value);
+        }
+
+        Ok(Self(metadata))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn require(&self, key: &str) -> Result<&str, TusError> {
+        self.get(key)
+            .ok_or_else(|| TusError::InvalidMetadata(format!("missing {key} metadata")))
+    }
+}
@@ -0,0 +1,607 @@
+use std::{io::SeekFrom, sync::Arc};
+
+use axum::{
+    extract::{BodyStream, DefaultBodyLimit, Path, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, LOCATION},
+        HeaderMap, HeaderName, StatusCode,
+    },
+    response::IntoResponse,
+    routing::{delete, head, options, patch, post},
+    BoxError, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use diesel::{
+    result::Error::NotFound, BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
+    SelectableHelper,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use futures::{StreamExt, TryStreamExt};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    comics::chapters::{
+        models::ChapterPage,
+        routes::{ALLOWED_MIME_TYPES, FILE_SIZE_LIMIT},
+    },
+    s3::{
+        models::StorageUpload,
+        uploads::{commit_upload, discard_upload, promote_upload, stage_upload},
+    },
+    schema::{chapter_pages, comic_chapters, tus_uploads},
+    users::models::UserRole,
+    utils::http_date,
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{upload_file_path, TusUpload, TusUploadTarget, UploadMetadata},
+    TusError, TUS_CHUNK_LEASE_MINUTES, TUS_EXTENSION, TUS_EXTENSIONS, TUS_MAX_SIZE, TUS_RESUMABLE,
+    TUS_UPLOADS_DIR, TUS_UPLOAD_EXPIRY_HOURS, TUS_VERSION, TUS_VERSION_HEADER, UPLOAD_EXPIRES,
+    UPLOAD_LENGTH, UPLOAD_METADATA, UPLOAD_OFFSET,
+};
+
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+pub fn tus_router() -> Router<AppState> {
+    Router::new()
+        .route("/tus", options(tus_options))
+        .route("/tus", post(create_upload))
+        .route("/tus/:upload_id", head(get_upload_offset))
+        .route("/tus/:upload_id", patch(patch_upload))
+        .route("/tus/:upload_id", delete(delete_upload))
+        // chunks are streamed to disk, their size is checked against the upload length
+        .layer(DefaultBodyLimit::disable())
+}
+
+fn check_tus_resumable(headers: &HeaderMap) -> Result<(), TusError> {
+    match headers.get(TUS_RESUMABLE) {
+        Some(version) if version == TUS_VERSION => Ok(()),
+        _ => Err(TusError::UnsupportedVersion),
+    }
+}
+
+fn parse_header<T: std::str::FromStr>(
+    headers: &HeaderMap,
+    name: HeaderName,
+    display_name: &'static str,
+) -> Result<T, TusError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or(TusError::InvalidHeader(display_name))
+}
+
+/// Get the upload if it belongs to the user and hasn't expired
+async fn find_upload(
+    db: &mut AsyncPgConnection,
+    upload_id: Uuid,
+    user_id: Uuid,
+) -> Result<TusUpload, TusError> {
+    let upload = tus_uploads::table
+        .filter(tus_uploads::id.eq(upload_id))
+        .filter(tus_uploads::user_id.eq(user_id))
+        .select(TusUpload::as_select())
+        .first::<TusUpload>(db)
+        .await
+        .map_err(|e| match e {
+            NotFound => TusError::UploadNotFound,
+            e => e.into(),
+        })?;
+
+    if upload.expires_at < Utc::now() {
+        return Err(TusError::UploadExpired);
+    }
+
+    Ok(upload)
+}
+
+/// Get the server's tus configuration
+#[utoipa::path(
+    options,
+    path = "/api/v1/uploads/tus",
+    responses(
+        (status = StatusCode::NO_CONTENT, description = "Supported tus versions, extensions and max upload size"),
+    ),
+    tag = "Uploads API"
+)]
+pub async fn tus_options() -> impl IntoResponse {
+    (
+        StatusCode::NO_CONTENT,
+        [
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+            (TUS_VERSION_HEADER, TUS_VERSION.to_string()),
+            (TUS_EXTENSION, TUS_EXTENSIONS.to_string()),
+            (TUS_MAX_SIZE, FILE_SIZE_LIMIT.to_string()),
+        ],
+    )
+}
+
+/// Create a resumable upload
+///
+/// `Upload-Metadata` must contain `comic_id`, `chapter_id`, `number`, `filename` and `filetype`,
+/// `description` is optional. The chapter page is created once all the bytes are received
+#[utoipa::path(
+    post,
+    path = "/api/v1/uploads/tus",
+    responses(
+        (status = StatusCode::CREATED, description = "Upload created, its url is in the Location header"),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid headers or metadata", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified chapter not found", body = ErrorResponse),
+        (status = StatusCode::PRECONDITION_FAILED, description = "Unsupported tus version"),
+        (status = StatusCode::PAYLOAD_TOO_LARGE, description = "Upload is too large", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong"),
+    ),
+    tag = "Uploads API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn create_upload(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, TusError> {
+    check_tus_resumable(&headers)?;
+
+    let upload_length = parse_header::<i64>(&headers, UPLOAD_LENGTH, "Upload-Length")?;
+
+    if upload_length <= 0 {
+        return Err(TusError::InvalidHeader("Upload-Length"));
+    }
+
+    if upload_length > FILE_SIZE_LIMIT as i64 {
+        return Err(TusError::TooLarge);
+    }
+
+    let metadata = match headers.get(UPLOAD_METADATA) {
+        Some(metadata) => UploadMetadata::parse(
+            metadata
+                .to_str()
+                .map_err(|_| TusError::InvalidHeader("Upload-Metadata"))?,
+        )?,
+        None => UploadMetadata::default(),
+    };
+
+    let target = TusUploadTarget::parse(metadata.get("target").unwrap_or("chapter_page"))
+        .ok_or_else(|| TusError::InvalidMetadata(String::from("unsupported upload target")))?;
+
+    let content_type = metadata.require("filetype")?;
+
+    if !ALLOWED_MIME_TYPES.contains(&content_type) {
+        return Err(TusError::InvalidMetadata(String::from(
+            "unsupported file type",
+        )));
+    }
+
+    let comic_id = metadata
+        .require("comic_id")?
+        .parse::<Uuid>()
+        .map_err(|_| TusError::InvalidMetadata(String::from("invalid comic_id")))?;
+
+    let chapter_id = metadata
+        .require("chapter_id")?
+        .parse::<Uuid>()
+        .map_err(|_| TusError::InvalidMetadata(String::from("invalid chapter_id")))?;
+
+    let number = metadata
+        .require("number")?
+        .parse::<i32>()
+        .map_err(|_| TusError::InvalidMetadata(String::from("invalid number")))?;
+
+    let mut db = state.pool.get().await?;
+
+    // only the author can add pages to the chapter
+    comic_chapters::table
+        .filter(comic_chapters::id.eq(chapter_id))
+        .filter(comic_chapters::comic_id.eq(comic_id))
+        .filter(comic_chapters::user_id.eq(auth.current_user.id))
+        .select(comic_chapters::id)
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => TusError::ChapterNotFound,
+            e => e.into(),
+        })?;
+
+    let now = Utc::now();
+
+    let upload = TusUpload {
+        id: Uuid::now_v7(),
+        upload_length,
+        upload_offset: 0,
+        target: target.as_str().to_string(),
+        file_name: metadata.require("filename")?.replace('/', "_"),
+        content_type: content_type.to_string(),
+        number,
+        description: metadata.get("description").map(String::from),
+        comic_id,
+        chapter_id,
+        user_id: auth.current_user.id,
+        created_at: now,
+        expires_at: now + Duration::hours(TUS_UPLOAD_EXPIRY_HOURS),
+        locked_until: None,
+    };
+
+    tokio::fs::create_dir_all(TUS_UPLOADS_DIR).await?;
+    File::create(upload.file_path()).await?;
+
+    let upload = diesel::insert_into(tus_uploads::table)
+        .values(&upload)
+        .returning(TusUpload::as_returning())
+        .get_result::<TusUpload>(&mut db)
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        [
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+            (LOCATION, format!("/api/v1/uploads/tus/{}", upload.id)),
+            (UPLOAD_EXPIRES, http_date(upload.expires_at)),
+        ],
+    ))
+}
+
+/// Get the current offset of a resumable upload
+#[utoipa::path(
+    head,
+    path = "/api/v1/uploads/tus/:upload_id",
+    responses(
+        (status = 200, description = "Current offset is in the Upload-Offset header"),
+        (status = StatusCode::NOT_FOUND, description = "Specified upload not found"),
+        (status = StatusCode::GONE, description = "Upload has expired"),
+    ),
+    tag = "Uploads API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_upload_offset(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, TusError> {
+    check_tus_resumable(&headers)?;
+
+    let mut db = state.pool.get().await?;
+
+    let upload = find_upload(&mut db, upload_id, auth.current_user.id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+            (UPLOAD_OFFSET, upload.upload_offset.to_string()),
+            (UPLOAD_LENGTH, upload.upload_length.to_string()),
+            (UPLOAD_EXPIRES, http_date(upload.expires_at)),
+            (CACHE_CONTROL, String::from("no-store")),
+        ],
+    ))
+}
+
+/// Append a chunk to a resumable upload
+///
+/// The chapter page is created when the last chunk is received
+#[utoipa::path(
+    patch,
+    path = "/api/v1/uploads/tus/:upload_id",
+    request_body(content = Vec<u8>, content_type = "application/offset+octet-stream"),
+    responses(
+        (status = StatusCode::NO_CONTENT, description = "Chunk received, the new offset is in the Upload-Offset header"),
+        (status = StatusCode::NOT_FOUND, description = "Specified upload not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Upload-Offset doesn't match the current offset", body = ErrorResponse),
+        (status = StatusCode::GONE, description = "Upload has expired", body = ErrorResponse),
+        (status = StatusCode::PAYLOAD_TOO_LARGE, description = "Chunk exceeds the upload length", body = ErrorResponse),
+        (status = StatusCode::UNSUPPORTED_MEDIA_TYPE, description = "Wrong content type", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong"),
+    ),
+    tag = "Uploads API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn patch_upload(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+    mut body: BodyStream,
+) -> Result<impl IntoResponse, TusError> {
+    check_tus_resumable(&headers)?;
+
+    if headers
+        .get(CONTENT_TYPE)
+        .map_or(true, |content_type| content_type != OFFSET_OCTET_STREAM)
+    {
+        return Err(TusError::UnsupportedContentType);
+    }
+
+    let offset = parse_header::<i64>(&headers, UPLOAD_OFFSET, "Upload-Offset")?;
+
+    let upload = {
+        let mut db = state.pool.get().await?;
+        find_upload(&mut db, upload_id, auth.current_user.id).await?
+    };
+
+    if offset != upload.upload_offset {
+        return Err(TusError::OffsetMismatch);
+    }
+
+    let now = Utc::now();
+
+    // the upload is claimed with a lease rather than a row lock, so no connection is held while
+    // the chunk is received
+    let lease = {
+        let mut db = state.pool.get().await?;
+
+        diesel::update(
+            tus_uploads::table
+                .filter(tus_uploads::id.eq(upload_id))
+                .filter(tus_uploads::upload_offset.eq(offset))
+                .filter(
+                    tus_uploads::locked_until
+                        .is_null()
+                        .or(tus_uploads::locked_until.lt(now)),
+                ),
+        )
+        .set(tus_uploads::locked_until.eq(now + Duration::minutes(TUS_CHUNK_LEASE_MINUTES)))
+        .returning(tus_uploads::locked_until)
+        .get_result::<Option<DateTime<Utc>>>(&mut db)
+        .await
+        .optional()?
+        .flatten()
+        // another request is writing, or moved the offset since
+        .ok_or(TusError::OffsetMismatch)?
+    };
+
+    let written = write_chunk(&upload, offset, lease, &mut body).await;
+
+    let mut db = state.pool.get().await?;
+
+    let new_offset = match written {
+        Ok(new_offset) => new_offset,
+        Err(err) => {
+            // lets the client retry right away instead of waiting for the lease to pass
+            if let Err(err) = diesel::update(
+                tus_uploads::table
+                    .filter(tus_uploads::id.eq(upload_id))
+                    .filter(tus_uploads::locked_until.eq(lease)),
+            )
+            .set(tus_uploads::locked_until.eq(None::<DateTime<Utc>>))
+            .execute(&mut db)
+            .await
+            {
+                tracing::error!("failed to release tus upload {}: {:#?}", upload_id, err);
+            }
+
+            return Err(err);
+        }
+    };
+
+    // only moves the offset if no other request claimed the upload after the lease passed
+    let updated = diesel::update(
+        tus_uploads::table
+            .filter(tus_uploads::id.eq(upload_id))
+            .filter(tus_uploads::upload_offset.eq(offset))
+            .filter(tus_uploads::locked_until.eq(lease)),
+    )
+    .set((
+        tus_uploads::upload_offset.eq(new_offset),
+        tus_uploads::locked_until.eq(None::<DateTime<Utc>>),
+    ))
+    .execute(&mut db)
+    .await?;
+
+    drop(db);
+
+    if updated == 0 {
+        return Err(TusError::OffsetMismatch);
+    }
+
+    if new_offset == upload.upload_length {
+        match TusUploadTarget::parse(&upload.target) {
+            Some(TusUploadTarget::ChapterPage) => {
+                finalize_chapter_page(&state, upload).await?;
+            }
+            None => {
+                tracing::error!(
+                    "tus upload {} has unknown target {}",
+                    upload.id,
+                    upload.target
+                );
+                return Err(TusError::InternalServerError);
+            }
+        }
+    }
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+            (UPLOAD_OFFSET, new_offset.to_string()),
+        ],
+    ))
+}
+
+/// Write the request body to the upload's file from `offset`, returns the new offset
+///
+/// writing stops once the lease has passed, the client resumes from what was received
+async fn write_chunk(
+    upload: &TusUpload,
+    offset: i64,
+    lease: DateTime<Utc>,
+    body: &mut BodyStream,
+) -> Result<i64, TusError> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(upload.file_path())
+        .await?;
+
+    // drop whatever an interrupted request wrote past the recorded offset
+    file.set_len(offset as u64).await?;
+    file.seek(SeekFrom::Start(offset as u64)).await?;
+
+    let mut new_offset = offset;
+
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                // keep what was received so far, the client resumes from the new offset
+                tracing::debug!("tus upload {} interrupted: {:#?}", upload.id, err);
+                break;
+            }
+        };
+
+        if Utc::now() >= lease {
+            tracing::debug!("tus upload {} lease passed", upload.id);
+            break;
+        }
+
+        if new_offset + chunk.len() as i64 > upload.upload_length {
+            return Err(TusError::TooLarge);
+        }
+
+        file.write_all(&chunk).await?;
+        new_offset += chunk.len() as i64;
+    }
+
+    file.sync_all().await?;
+
+    Ok(new_offset)
+}
+
+/// Stage the received file and create the chapter page from it
+async fn finalize_chapter_page(
+    state: &InnerAppState,
+    upload: TusUpload,
+) -> Result<ChapterPage, TusError> {
+    let file = File::open(upload.file_path()).await?;
+
+    let staged_upload = stage_upload(
+        state,
+        StorageUpload::new(
+            upload.user_id,
+            format!("{}_{}", Uuid::now_v7(), upload.file_name),
+            upload.content_type.clone(),
+        ),
+        ReaderStream::new(file).map_err(BoxError::from),
+        upload.upload_length,
+    )
+    .await?;
+
+    let upload_id = upload.id;
+
+    let mut db = state.pool.get().await?;
+
+    let transaction_result = {
+        let staged_upload = staged_upload.clone();
+        db.transaction::<_, TusError, _>(|transaction| {
+            async move {
+                // makes sure concurrent requests finalize the upload only once
+                let deleted = diesel::delete(tus_uploads::table.find(upload_id))
+                    .execute(transaction)
+                    .await?;
+
+                if deleted == 0 {
+                    return Err(TusError::UploadNotFound);
+                }
+
+                let chapter_page = ChapterPage {
+                    id: Uuid::now_v7(),
+                    user_id: upload.user_id,
+                    comic_id: upload.comic_id,
+                    chapter_id: upload.chapter_id,
+                    number: upload.number,
+                    description: upload.description,
+                    path: staged_upload.staging_path,
+                    content_type: staged_upload.content_type,
+                    created_at: Utc::now(),
+                    updated_at: None,
+                };
+
+                diesel::insert_into(chapter_pages::table)
+                    .values(&chapter_page)
+                    .execute(transaction)
+                    .await?;
+
+                commit_upload(transaction, staged_upload.id).await?;
+
+                Ok(chapter_page)
+            }
+            .scope_boxed()
+        })
+        .await
+    };
+
+    let mut chapter_page = match transaction_result {
+        Ok(chapter_page) => chapter_page,
+        Err(err) => {
+            if let Err(err) = discard_upload(state, &staged_upload).await {
+                // the storage cleanup worker will discard it once it's stale
+                tracing::error!("failed to discard staged upload: {:#?}", err);
+            }
+            return Err(err);
+        }
+    };
+
+    // the page is served from the staging key until the upload is promoted
+    match promote_upload(state, &staged_upload).await {
+        Ok(true) => chapter_page.path = staged_upload.path.clone(),
+        Ok(false) => {}
+        // the upload is committed, the storage cleanup worker will retry promoting it
+        Err(err) => tracing::error!("failed to promote chapter page image: {:#?}", err),
+    }
+
+    if let Err(err) = tokio::fs::remove_file(upload_file_path(upload_id)).await {
+        tracing::error!("failed to remove tus upload file: {:#?}", err);
+    }
+
+    Ok(chapter_page)
+}
+
+/// Cancel a resumable upload
+#[utoipa::path(
+    delete,
+    path = "/api/v1/uploads/tus/:upload_id",
+    responses(
+        (status = StatusCode::NO_CONTENT, description = "Upload cancelled"),
+        (status = StatusCode::NOT_FOUND, description = "Specified upload not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong"),
+    ),
+    tag = "Uploads API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn delete_upload(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, TusError> {
+    check_tus_resumable(&headers)?;
+
+    let mut db = state.pool.get().await?;
+
+    let upload = diesel::delete(
+        tus_uploads::table
+            .filter(tus_uploads::id.eq(upload_id))
+            .filter(tus_uploads::user_id.eq(auth.current_user.id)),
+    )
+    .returning(TusUpload::as_returning())
+    .get_result::<TusUpload>(&mut db)
+    .await
+    .map_err(|e| match e {
+        NotFound => TusError::UploadNotFound,
+        e => e.into(),
+    })?;
+
+    if let Err(err) = tokio::fs::remove_file(upload.file_path()).await {
+        tracing::error!("failed to remove tus upload file: {:#?}", err);
+    }
+
+    Ok((StatusCode::NO_CONTENT, [(TUS_RESUMABLE, TUS_VERSION)]))
+}
@@ -0,0 +1,54 @@
+use axum::{http::StatusCode, response::IntoResponse};
+
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
+
+pub mod models;
+pub mod routes;
+pub mod utils;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FollowsError {
+    #[error("user not found")]
+    UserNotFound,
+
+    #[error("users can't follow themselves")]
+    SelfFollow,
+
+    #[error("user isn't followed")]
+    NotFollowing,
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for FollowsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let (status, error) = match &self {
+            FollowsError::UserNotFound | FollowsError::NotFollowing => {
+                (StatusCode::NOT_FOUND, self.to_string())
+            }
+            FollowsError::SelfFollow => (StatusCode::BAD_REQUEST, self.to_string()),
+            FollowsError::InvalidCursor(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            FollowsError::Diesel(_) | FollowsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error,
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
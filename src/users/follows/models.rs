@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    comics::models::ComicResponseBrief, schema::follows, users::models::UserResponseBrief,
+};
+
+#[derive(Insertable, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = follows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Follow {
+    pub follower_id: Uuid,
+    pub followed_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum FeedItemKind {
+    NewChapter,
+    NewComic,
+}
+
+#[derive(Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct FeedItemResponse {
+    pub kind: FeedItemKind,
+    pub comic: ComicResponseBrief,
+    pub author: UserResponseBrief,
+    /// only set for new chapters
+    pub chapter_id: Option<Uuid>,
+    pub chapter_number: Option<i32>,
+    pub chapter_title: Option<String>,
+    pub published_at: String,
+}
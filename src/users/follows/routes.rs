@@ -0,0 +1,333 @@
+use std::{cmp::Reverse, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, put},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error::NotFound};
+use diesel_async::RunQueryDsl;
+use itertools::Itertools;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    coalesce,
+    comics::{
+        chapters::models::Chapter,
+        comic_genres::{models::Genre, utils::genre_translations},
+        models::{Comic, ContentRating},
+    },
+    common::{
+        locale::{Locale, LocaleParams},
+        pagination::{Paginated, PaginationParams},
+    },
+    library::models::LibraryStatus,
+    schema::{
+        comic_chapters, comic_genres, comic_genres_mapping, comics, follows, library_entries, users,
+    },
+    users::{
+        models::{User, UserRole},
+        utils::can_view_mature_content,
+    },
+    AppState, InnerAppState,
+};
+
+use super::{
+    models::{FeedItemKind, FeedItemResponse, Follow},
+    FollowsError,
+};
+
+pub fn follows_router() -> Router<AppState> {
+    Router::new()
+        .route("/follows/:user_id", put(follow_user))
+        .route("/follows/:user_id", delete(unfollow_user))
+        .route("/me/feed", get(get_feed))
+}
+
+/// Follow a user to get their new comics and chapters in the feed
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/follows/:user_id",
+    responses(
+        (status = 200, description = "User is followed"),
+        (status = StatusCode::BAD_REQUEST, description = "Caller tried to follow themselves", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "User not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Users API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn follow_user(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<(), FollowsError> {
+    if user_id == auth.current_user.id {
+        return Err(FollowsError::SelfFollow);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    users::table
+        .find(user_id)
+        .select(users::id)
+        .first::<Uuid>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => FollowsError::UserNotFound,
+            e => e.into(),
+        })?;
+
+    diesel::insert_into(follows::table)
+        .values(&Follow {
+            follower_id: auth.current_user.id,
+            followed_id: user_id,
+            created_at: Utc::now(),
+        })
+        .on_conflict_do_nothing()
+        .execute(&mut db)
+        .await?;
+
+    Ok(())
+}
+
+/// Unfollow a user
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/follows/:user_id",
+    responses(
+        (status = 200, description = "User isn't followed anymore"),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "User isn't followed", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Users API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn unfollow_user(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<(), FollowsError> {
+    let mut db = state.pool.get().await?;
+
+    let deleted = diesel::delete(
+        follows::table
+            .filter(follows::follower_id.eq(auth.current_user.id))
+            .filter(follows::followed_id.eq(user_id)),
+    )
+    .execute(&mut db)
+    .await?;
+
+    if deleted == 0 {
+        return Err(FollowsError::NotFollowing);
+    }
+
+    Ok(())
+}
+
+/// A released chapter or comic, before it's turned into a feed item
+struct FeedRow {
+    released_at: DateTime<Utc>,
+    chapter: Option<Chapter>,
+    comic: Comic,
+    author: User,
+}
+
+impl FeedRow {
+    fn id(&self) -> Uuid {
+        self.chapter
+            .as_ref()
+            .map_or(self.comic.id, |chapter| chapter.id)
+    }
+}
+
+/// Get the newly released chapters and comics of followed authors and of the comics in the
+/// current user's library, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/feed",
+    params(
+        PaginationParams,
+        LocaleParams,
+    ),
+    responses(
+        (status = 200, description = "Feed of new chapters and comics", body = PaginatedFeed),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Users API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_feed(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    locale: Locale,
+    State(state): State<Arc<InnerAppState>>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<FeedItemResponse>>, FollowsError> {
+    let cursor = pagination.cursor::<(DateTime<Utc>, Uuid)>()?;
+    let mut db = state.pool.get().await?;
+
+    let user_id = auth.current_user.id;
+    let now = Utc::now();
+    let can_view_mature = can_view_mature_content(&mut db, Some(&auth.current_user)).await?;
+
+    // chapters and comics without a publish date are released when they're created
+    let chapter_released_at = coalesce(comic_chapters::published_at, comic_chapters::created_at);
+    let comic_released_at = coalesce(comics::published_at, comics::created_at);
+
+    // dropped comics don't show up in the feed, unless their author is followed
+    let mut chapters_query = comic_chapters::table
+        .inner_join(comics::table.inner_join(users::table))
+        .filter(chapter_released_at.le(now))
+        .filter(comics::user_id.ne(user_id))
+        .filter(
+            comics::user_id
+                .eq_any(
+                    follows::table
+                        .filter(follows::follower_id.eq(user_id))
+                        .select(follows::followed_id),
+                )
+                .or(comic_chapters::comic_id.eq_any(
+                    library_entries::table
+                        .filter(library_entries::user_id.eq(user_id))
+                        .filter(library_entries::status.ne(LibraryStatus::Dropped))
+                        .select(library_entries::comic_id),
+                )),
+        )
+        .select((
+            Chapter::as_select(),
+            Comic::as_select(),
+            User::as_select(),
+            chapter_released_at,
+        ))
+        .into_boxed();
+
+    let mut comics_query = comics::table
+        .inner_join(users::table)
+        .filter(comic_released_at.le(now))
+        .filter(
+            comics::user_id.eq_any(
+                follows::table
+                    .filter(follows::follower_id.eq(user_id))
+                    .select(follows::followed_id),
+            ),
+        )
+        .select((Comic::as_select(), User::as_select(), comic_released_at))
+        .into_boxed();
+
+    if !can_view_mature {
+        chapters_query = chapters_query.filter(comics::content_rating.ne(ContentRating::Mature));
+        comics_query = comics_query.filter(comics::content_rating.ne(ContentRating::Mature));
+    }
+
+    if let Some((prev_date, prev_id)) = cursor {
+        chapters_query = chapters_query.filter(
+            chapter_released_at.lt(prev_date).or(chapter_released_at
+                .eq(prev_date)
+                .and(comic_chapters::id.lt(prev_id))),
+        );
+        comics_query = comics_query.filter(
+            comic_released_at
+                .lt(prev_date)
+                .or(comic_released_at.eq(prev_date).and(comics::id.lt(prev_id))),
+        );
+    }
+
+    // a page of each is enough to fill a page of both
+    let chapters = chapters_query
+        .order((chapter_released_at.desc(), comic_chapters::id.desc()))
+        .limit(pagination.limit() + 1)
+        .load::<(Chapter, Comic, User, DateTime<Utc>)>(&mut db)
+        .await?;
+
+    let new_comics = comics_query
+        .order((comic_released_at.desc(), comics::id.desc()))
+        .limit(pagination.limit() + 1)
+        .load::<(Comic, User, DateTime<Utc>)>(&mut db)
+        .await?;
+
+    let mut rows = chapters
+        .into_iter()
+        .map(|(chapter, comic, author, released_at)| FeedRow {
+            released_at,
+            chapter: Some(chapter),
+            comic,
+            author,
+        })
+        .chain(
+            new_comics
+                .into_iter()
+                .map(|(comic, author, released_at)| FeedRow {
+                    released_at,
+                    chapter: None,
+                    comic,
+                    author,
+                }),
+        )
+        .sorted_by_key(|row| Reverse((row.released_at, row.id())))
+        .collect::<Vec<FeedRow>>();
+
+    let next_cursor = pagination.page(&mut rows, |row| (row.released_at, row.id()));
+
+    // several chapters of the same comic can be in a page
+    let comic_ids = rows
+        .iter()
+        .map(|row| row.comic.id)
+        .unique()
+        .collect::<Vec<Uuid>>();
+
+    let translations = genre_translations(&mut db, locale).await?;
+
+    let genres = comic_genres_mapping::table
+        .inner_join(comic_genres::table)
+        .filter(comic_genres_mapping::comic_id.eq_any(&comic_ids))
+        .select((comic_genres_mapping::comic_id, Genre::as_select()))
+        .load::<(Uuid, Genre)>(&mut db)
+        .await?
+        .into_iter()
+        .map(|(comic_id, genre)| (comic_id, genre.into_localized(&translations)))
+        .into_group_map();
+
+    let items = rows
+        .into_iter()
+        .map(|row| {
+            let (kind, chapter_id, chapter_number, chapter_title) = match row.chapter {
+                Some(chapter) => (
+                    FeedItemKind::NewChapter,
+                    Some(chapter.id),
+                    Some(chapter.number),
+                    Some(chapter.title),
+                ),
+                None => (FeedItemKind::NewComic, None, None, None),
+            };
+
+            let comic_genres = genres.get(&row.comic.id).cloned().unwrap_or_default();
+
+            FeedItemResponse {
+                kind,
+                comic: row.comic.into_response_brief(comic_genres),
+                author: row.author.into_response_brief(),
+                chapter_id,
+                chapter_number,
+                chapter_title,
+                published_at: row.released_at.to_string(),
+            }
+        })
+        .collect();
+
+    Ok(Json(Paginated::new(items, next_cursor)))
+}
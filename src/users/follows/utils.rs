@@ -0,0 +1,22 @@
+use diesel::{ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::schema::follows;
+
+/// Number of followers of the user and the number of users they follow
+pub async fn follow_counts(db: &mut AsyncPgConnection, user_id: Uuid) -> QueryResult<(i64, i64)> {
+    let followers = follows::table
+        .filter(follows::followed_id.eq(user_id))
+        .count()
+        .get_result::<i64>(db)
+        .await?;
+
+    let following = follows::table
+        .filter(follows::follower_id.eq(user_id))
+        .count()
+        .get_result::<i64>(db)
+        .await?;
+
+    Ok((followers, following))
+}
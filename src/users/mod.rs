@@ -1,11 +1,16 @@
 use axum::{http::StatusCode, response::IntoResponse};
 use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
 
-use crate::ErrorResponse;
+use crate::{common::pagination::InvalidCursor, ErrorResponse};
 
 pub mod email_verifications;
+pub mod follows;
 pub mod models;
 pub mod routes;
+pub mod utils;
+
+/// Profile image shared by every user that hasn't uploaded one
+pub const DEFAULT_PROFILE_IMAGE_PATH: &str = "ppL.webp";
 
 #[derive(thiserror::Error, Debug)]
 pub enum UsersError {
@@ -41,6 +46,9 @@ pub enum UsersError {
 
     #[error("{0}")]
     Conflict(String),
+
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
 }
 
 impl IntoResponse for UsersError {
@@ -162,6 +170,7 @@ impl IntoResponse for UsersError {
                     .into_response()
             }
             UsersError::PoolError(_) => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            UsersError::InvalidCursor(err) => err.into_response(),
         }
     }
 }
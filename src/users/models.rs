@@ -1,6 +1,6 @@
 use std::io::Write;
 
-use chrono::{DateTime, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use diesel::{
     deserialize::{self, FromSql},
     pg::{Pg, PgValue},
@@ -83,6 +83,7 @@ pub struct User {
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
     pub last_login: Option<NaiveDateTime>,
+    pub birth_date: Option<NaiveDate>,
 }
 
 impl User {
@@ -117,6 +118,15 @@ pub struct CreateUser {
     pub email: String,
     #[garde(length(min = 8))]
     pub password: String,
+    /// needed to see mature comics
+    #[garde(skip)]
+    pub birth_date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct SetBirthDate {
+    pub birth_date: NaiveDate,
 }
 
 #[derive(Deserialize, Serialize, ToSchema, TS)]
@@ -141,6 +151,8 @@ pub struct UserResponse {
     pub email: String,
     pub profile_image: ImageMetadataResponse,
     pub role: UserRole,
+    pub followers_count: i64,
+    pub following_count: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
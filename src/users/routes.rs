@@ -5,42 +5,50 @@ use argon2::{
     Argon2, PasswordHash,
 };
 use axum::{
-    extract::{Path, State},
-    routing::{get, post},
+    extract::{Path, Query, State},
+    routing::{get, post, put},
     Json, Router,
 };
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
 use diesel::GroupedBy;
-use diesel::{dsl::count, prelude::*};
 use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use garde::Validate;
-use itertools::multizip;
-use itertools::Itertools;
 use time::OffsetDateTime;
 use tower_cookies::{cookie::Cookie, Cookies};
 use uuid::Uuid;
 
 use crate::{
     auth::AuthExtractor,
-    coalesce,
-    comics::comic_genres::models::{Genre, GenreMapping},
-    comics::models::{Comic, ComicRating, ComicResponseBrief},
-    common::models::ImageMetadataResponse,
+    comics::comic_genres::{
+        models::{Genre, GenreMapping},
+        utils::genre_translations,
+    },
+    comics::models::{Comic, ComicResponseBrief, ContentRating},
+    common::{
+        locale::{Locale, LocaleParams},
+        pagination::{Paginated, PaginationParams},
+    },
+    recommendations::routes::get_recommendations,
     schema::comics,
-    schema::{comic_chapters, comic_genres, profile_images, sessions, users},
+    schema::{comic_genres, profile_images, sessions, users},
     sessions::{
         models::{CreateSession, Session},
         SESSION_COOKIE_NAME,
     },
     users::models::User,
-    utils::average_rating,
     AppState, InnerAppState,
 };
 
 use super::{
     email_verifications::routes::email_verification_router,
-    models::{CreateUser, ProfileImage, UserLogin, UserResponse, UserResponseBrief, UserRole},
-    UsersError,
+    follows::{routes::follows_router, utils::follow_counts},
+    models::{
+        CreateUser, ProfileImage, SetBirthDate, UserLogin, UserResponse, UserResponseBrief,
+        UserRole,
+    },
+    utils::can_view_mature_content,
+    UsersError, DEFAULT_PROFILE_IMAGE_PATH,
 };
 
 pub fn users_router() -> Router<AppState> {
@@ -51,7 +59,10 @@ pub fn users_router() -> Router<AppState> {
         .route("/", post(create_user))
         .route("/login", post(login))
         .route("/me", get(me))
+        .route("/me/birth_date", put(set_birth_date))
+        .route("/me/recommendations", get(get_recommendations))
         .nest("/", email_verification_router())
+        .nest("/", follows_router())
 }
 
 /// get user by cookie
@@ -68,6 +79,58 @@ pub async fn me(auth: AuthExtractor<{ UserRole::User as u32 }>) -> Json<UserResp
     Json(auth.current_user)
 }
 
+/// Set current user's birth date
+///
+/// it can only be set once, it decides whether the user can see mature comics
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/me/birth_date",
+    request_body(content = SetBirthDate, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Birth date set"),
+        (status = StatusCode::BAD_REQUEST, description = "Birth date is in the future", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Birth date was already set", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Users API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn set_birth_date(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Json(payload): Json<SetBirthDate>,
+) -> Result<(), UsersError> {
+    if payload.birth_date > Utc::now().date_naive() {
+        return Err(UsersError::BadRequest);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    let updated = diesel::update(
+        users::table
+            .find(auth.current_user.id)
+            .filter(users::birth_date.is_null()),
+    )
+    .set((
+        users::birth_date.eq(payload.birth_date),
+        users::updated_at.eq(Utc::now()),
+    ))
+    .execute(&mut db)
+    .await?;
+
+    if updated == 0 {
+        return Err(UsersError::Conflict(String::from(
+            "birth date was already set",
+        )));
+    }
+
+    Ok(())
+}
+
 /// Create User
 #[utoipa::path(
     post,
@@ -93,6 +156,13 @@ pub async fn create_user(
         return Err(UsersError::BadRequest);
     }
 
+    if payload
+        .birth_date
+        .is_some_and(|birth_date| birth_date > Utc::now().date_naive())
+    {
+        return Err(UsersError::BadRequest);
+    }
+
     let mut db = state.pool.get().await?;
 
     let salt = SaltString::generate(rand::thread_rng());
@@ -121,6 +191,7 @@ pub async fn create_user(
                     created_at: Utc::now().naive_utc(),
                     updated_at: None,
                     last_login: None,
+                    birth_date: payload.birth_date,
                 };
 
                 let user = diesel::insert_into(users::table)
@@ -132,7 +203,7 @@ pub async fn create_user(
                 let profile_image = ProfileImage {
                     id: Uuid::now_v7(),
                     user_id: user.id,
-                    path: String::from("ppL.webp"),
+                    path: String::from(DEFAULT_PROFILE_IMAGE_PATH),
                     content_type: String::from("image/webp"),
                     updated_at: None,
                 };
@@ -154,11 +225,14 @@ pub async fn create_user(
         displayname: user.displayname,
         username: user.username,
         email: user.email,
-        profile_image: ImageMetadataResponse {
-            path: profile_image.path,
-            content_type: profile_image.content_type,
-        },
+        profile_image: state.image_signer.image_metadata(
+            profile_image.path,
+            profile_image.content_type,
+            None,
+        ),
         role: user.role,
+        followers_count: 0,
+        following_count: 0,
     }))
 }
 
@@ -317,8 +391,13 @@ pub async fn logout(
 #[utoipa::path(
     get,
     path = "/api/v1/users/comics/:user_id",
+    params(
+        PaginationParams,
+        LocaleParams,
+    ),
     responses(
-        (status = 200, description = "Caller authorized. returned requested user's comics", body = [ComicResponseBrief]),
+        (status = 200, description = "Caller authorized. returned requested user's comics, newest first", body = PaginatedComicsBrief),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid cursor", body = ErrorResponse),
         (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
     ),
@@ -331,24 +410,41 @@ pub async fn logout(
 pub async fn get_user_comics(
     State(state): State<Arc<InnerAppState>>,
     Path(user_id): Path<Uuid>,
-    _auth: AuthExtractor<{ UserRole::User as u32 }>,
-) -> Result<Json<Vec<ComicResponseBrief>>, UsersError> {
+    Query(pagination): Query<PaginationParams>,
+    locale: Locale,
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+) -> Result<Json<Paginated<ComicResponseBrief>>, UsersError> {
     tracing::debug!("get {}'s comics", user_id);
 
+    let cursor = pagination.cursor::<(DateTime<Utc>, Uuid)>()?;
     let mut db = state.pool.get().await?;
 
-    let (comics, chapters_counts): (Vec<Comic>, Vec<i64>) = comics::table
+    let mut query = comics::table
         .filter(comics::user_id.eq(user_id))
-        .left_join(comic_chapters::table)
-        .group_by(comics::id)
-        .select((
-            Comic::as_select(),
-            coalesce(count(comic_chapters::id).nullable(), 0),
-        ))
-        .load::<(Comic, i64)>(&mut db)
-        .await?
-        .into_iter()
-        .multiunzip();
+        .select(Comic::as_select())
+        .into_boxed();
+
+    if auth.current_user.id != user_id
+        && !can_view_mature_content(&mut db, Some(&auth.current_user)).await?
+    {
+        query = query.filter(comics::content_rating.ne(ContentRating::Mature));
+    }
+
+    if let Some((prev_date, prev_id)) = cursor {
+        query = query.filter(
+            comics::created_at
+                .lt(prev_date)
+                .or(comics::created_at.eq(prev_date).and(comics::id.lt(prev_id))),
+        );
+    }
+
+    let mut comics = query
+        .order((comics::created_at.desc(), comics::id.desc()))
+        .limit(pagination.limit() + 1)
+        .load::<Comic>(&mut db)
+        .await?;
+
+    let next_cursor = pagination.page(&mut comics, |comic| (comic.created_at, comic.id));
 
     let genres = GenreMapping::belonging_to(&comics)
         .inner_join(comic_genres::table)
@@ -357,24 +453,22 @@ pub async fn get_user_comics(
         .await?
         .grouped_by(&comics);
 
-    let comics_ratings = ComicRating::belonging_to(&comics)
-        .select(ComicRating::as_select())
-        .load::<ComicRating>(&mut db)
-        .await?
-        .grouped_by(&comics);
+    let translations = genre_translations(&mut db, locale).await?;
+
+    let comics: Result<Vec<ComicResponseBrief>, UsersError> = comics
+        .into_iter()
+        .zip(genres)
+        .map(|(comic, genres)| {
+            Ok(comic.into_response_brief(
+                genres
+                    .into_iter()
+                    .map(|(_, genre)| genre.into_localized(&translations))
+                    .collect(),
+            ))
+        })
+        .collect();
 
-    let comics: Result<Vec<ComicResponseBrief>, UsersError> =
-        multizip((comics, genres, chapters_counts, comics_ratings))
-            .map(|(comic, genres, chapters_count, comic_ratings)| {
-                Ok(comic.into_response_brief(
-                    genres.into_iter().map(|(_, genre)| genre).collect(),
-                    chapters_count,
-                    average_rating(comic_ratings),
-                ))
-            })
-            .collect();
-
-    Ok(Json(comics?))
+    Ok(Json(Paginated::new(comics?, next_cursor)))
 }
 
 /// Get user by username
@@ -410,16 +504,21 @@ pub async fn get_user(
         .first(&mut db)
         .await?;
 
+    let (followers_count, following_count) = follow_counts(&mut db, user.id).await?;
+
     let user = UserResponse {
         id: user.id,
         displayname: user.displayname,
         username: user.username,
         email: user.email,
-        profile_image: ImageMetadataResponse {
-            content_type: profile_image.content_type,
-            path: profile_image.path,
-        },
+        profile_image: state.image_signer.image_metadata(
+            profile_image.path,
+            profile_image.content_type,
+            None,
+        ),
         role: user.role,
+        followers_count,
+        following_count,
     };
 
     Ok(Json(user))
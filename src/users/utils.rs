@@ -0,0 +1,43 @@
+use chrono::{NaiveDate, Utc};
+use diesel::{QueryDsl, QueryResult};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::schema::users;
+
+use super::models::{UserResponseBrief, UserRole};
+
+/// Users younger than this can't see mature comics
+pub const ADULT_AGE: u32 = 18;
+
+pub fn is_adult(birth_date: NaiveDate, today: NaiveDate) -> bool {
+    today
+        .years_since(birth_date)
+        .is_some_and(|age| age >= ADULT_AGE)
+}
+
+/// Whether the viewer can see comics rated mature
+///
+/// anonymous and unverified users can't, and neither can verified users
+/// that are underage or never set their birth date
+pub async fn can_view_mature_content(
+    db: &mut AsyncPgConnection,
+    viewer: Option<&UserResponseBrief>,
+) -> QueryResult<bool> {
+    let Some(viewer) = viewer else {
+        return Ok(false);
+    };
+
+    match viewer.role {
+        UserRole::Admin | UserRole::Staff => return Ok(true),
+        UserRole::User => return Ok(false),
+        UserRole::VerifiedUser => {}
+    }
+
+    let birth_date = users::table
+        .find(viewer.id)
+        .select(users::birth_date)
+        .first::<Option<NaiveDate>>(db)
+        .await?;
+
+    Ok(birth_date.is_some_and(|birth_date| is_adult(birth_date, Utc::now().date_naive())))
+}
@@ -1,9 +1,104 @@
-use crate::Rating;
+use std::str::FromStr;
 
-pub fn average_rating<T: Rating>(ratings: Vec<T>) -> f64 {
-    if ratings.is_empty() {
+use chrono::{DateTime, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::common::models::RatingSummaryResponse;
+
+/// Highest rating, ratings are bucketed by their rounded value from 0 up to this in histograms
+pub const MAX_RATING: usize = 5;
+
+/// Plain average of `count` ratings adding up to `sum`
+pub fn average_rating(sum: f64, count: i64) -> f64 {
+    if count == 0 {
         0.0
     } else {
-        ratings.iter().map(|r| r.rating()).sum::<f64>() / ratings.len() as f64
+        sum / count as f64
+    }
+}
+
+/// Prior of the weighted ratings, every comic and chapter counts as having `weight` more
+/// ratings averaging `mean` so a few ratings can't outrank a lot of slightly lower ones
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RatingPrior {
+    pub mean: f64,
+    pub weight: f64,
+}
+
+impl Default for RatingPrior {
+    fn default() -> Self {
+        Self {
+            mean: 3.0,
+            weight: 10.0,
+        }
+    }
+}
+
+impl RatingPrior {
+    pub fn is_valid(&self) -> bool {
+        (0.0..=MAX_RATING as f64).contains(&self.mean)
+            && self.weight.is_finite()
+            && self.weight > 0.0
+    }
+
+    /// Bayesian average of `count` ratings adding up to `sum`
+    pub fn weighted_rating(&self, sum: f64, count: i64) -> f64 {
+        (self.mean * self.weight + sum) / (self.weight + count as f64)
     }
 }
+
+/// Summarizes ratings given as how many times each rating was given
+pub fn rating_summary(
+    counts: impl IntoIterator<Item = (f64, i64)>,
+    user_rating: Option<f64>,
+    prior: RatingPrior,
+) -> RatingSummaryResponse {
+    let mut histogram = vec![0; MAX_RATING + 1];
+    let mut sum = 0.0;
+    let mut count = 0;
+
+    for (rating, times) in counts {
+        histogram[rating.round().clamp(0.0, MAX_RATING as f64) as usize] += times;
+        sum += rating * times as f64;
+        count += times;
+    }
+
+    RatingSummaryResponse {
+        weighted_rating: prior.weighted_rating(sum, count),
+        count,
+        histogram,
+        user_rating,
+    }
+}
+
+/// Format a date as an HTTP-date (RFC 7231), e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Deserialize a comma separated query parameter, e.g. `genres=1,4,7`
+pub fn comma_separated<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = String::deserialize(deserializer)?;
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().map_err(de::Error::custom))
+        .collect()
+}
+
+/// Deserialize a field that can be left out, set to `null` or set to a value,
+/// use with `#[serde(default)]` so a missing field is `None` and `null` is `Some(None)`
+pub fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,182 @@
+use chrono::Utc;
+use diesel::{
+    result::{DatabaseErrorKind, Error::DatabaseError},
+    ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::schema::{ledger_entries, ledger_transactions, wallets};
+
+use super::{
+    models::{
+        LedgerTransaction, LedgerTransactionResponse, NewLedgerEntry, TransactionKind, Wallet,
+    },
+    WalletsError,
+};
+
+/// Get the user's wallet, it's created on first use
+pub async fn user_wallet(
+    conn: &mut AsyncPgConnection,
+    user_id: Uuid,
+) -> Result<Wallet, WalletsError> {
+    diesel::insert_into(wallets::table)
+        .values(&Wallet {
+            id: Uuid::now_v7(),
+            user_id: Some(user_id),
+            kind: String::from("user"),
+            balance: 0,
+            created_at: Utc::now(),
+        })
+        .on_conflict(wallets::user_id)
+        .do_nothing()
+        .execute(conn)
+        .await
+        .map_err(|e| match e {
+            DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => WalletsError::UserNotFound,
+            e => e.into(),
+        })?;
+
+    Ok(wallets::table
+        .filter(wallets::user_id.eq(user_id))
+        .select(Wallet::as_select())
+        .first::<Wallet>(conn)
+        .await?)
+}
+
+/// Record a transaction and move the coins between wallets
+///
+/// `movements` are `(wallet_id, amount)` pairs that must add up to zero.
+/// Must be called inside a database transaction, the balances are updated along with the entries
+/// and a user wallet going below zero fails the whole transaction with `InsufficientFunds`
+pub async fn record_transaction(
+    conn: &mut AsyncPgConnection,
+    transaction: LedgerTransaction,
+    movements: &[(Uuid, i64)],
+) -> Result<LedgerTransaction, WalletsError> {
+    if movements.iter().map(|(_, amount)| amount).sum::<i64>() != 0
+        || movements.iter().any(|(_, amount)| *amount == 0)
+    {
+        tracing::error!("unbalanced ledger transaction: {:?}", movements);
+        return Err(WalletsError::InternalServerError);
+    }
+
+    let transaction = diesel::insert_into(ledger_transactions::table)
+        .values(&transaction)
+        .returning(LedgerTransaction::as_returning())
+        .get_result::<LedgerTransaction>(conn)
+        .await?;
+
+    // lock the wallets in the same order everywhere so concurrent transactions can't deadlock
+    let mut movements = movements.to_vec();
+    movements.sort_by_key(|(wallet_id, _)| *wallet_id);
+
+    for (wallet_id, amount) in movements {
+        let balance_after = diesel::update(wallets::table.find(wallet_id))
+            .set(wallets::balance.eq(wallets::balance + amount))
+            .returning(wallets::balance)
+            .get_result::<i64>(conn)
+            .await
+            .map_err(|e| match e {
+                DatabaseError(DatabaseErrorKind::CheckViolation, ref info)
+                    if info.constraint_name() == Some("wallets_balance_check") =>
+                {
+                    WalletsError::InsufficientFunds
+                }
+                e => e.into(),
+            })?;
+
+        diesel::insert_into(ledger_entries::table)
+            .values(&NewLedgerEntry {
+                transaction_id: transaction.id,
+                wallet_id,
+                amount,
+                balance_after,
+                created_at: transaction.created_at,
+            })
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(transaction)
+}
+
+/// Whether the error comes from recording a transaction whose idempotency key was already used,
+/// this happens when the same request is sent twice concurrently
+pub fn is_duplicate_idempotency_key(err: &WalletsError) -> bool {
+    matches!(
+        err,
+        WalletsError::Diesel(DatabaseError(DatabaseErrorKind::UniqueViolation, info))
+            if info.constraint_name() == Some("ledger_transactions_idempotency_key_key")
+    )
+}
+
+/// Find the transaction previously recorded with this idempotency key
+///
+/// fails if the key was used for another kind of transaction
+pub async fn find_transaction(
+    conn: &mut AsyncPgConnection,
+    kind: TransactionKind,
+    user_id: Uuid,
+    idempotency_key: &str,
+) -> Result<Option<LedgerTransaction>, WalletsError> {
+    let transaction = ledger_transactions::table
+        .filter(ledger_transactions::idempotency_key.eq(format!("{user_id}:{idempotency_key}")))
+        .select(LedgerTransaction::as_select())
+        .first::<LedgerTransaction>(conn)
+        .await
+        .optional()?;
+
+    match transaction {
+        Some(transaction) if transaction.kind != kind.as_str() => {
+            Err(WalletsError::IdempotencyKeyReused)
+        }
+        transaction => Ok(transaction),
+    }
+}
+
+/// Describe the transaction from the point of view of `wallet_id`
+pub async fn transaction_response(
+    conn: &mut AsyncPgConnection,
+    transaction: LedgerTransaction,
+    wallet_id: Uuid,
+) -> Result<LedgerTransactionResponse, WalletsError> {
+    let (amount, balance) = ledger_entries::table
+        .filter(ledger_entries::transaction_id.eq(transaction.id))
+        .filter(ledger_entries::wallet_id.eq(wallet_id))
+        .select((ledger_entries::amount, ledger_entries::balance_after))
+        .first::<(i64, i64)>(conn)
+        .await?;
+
+    Ok(LedgerTransactionResponse {
+        id: transaction.id,
+        kind: TransactionKind::parse(&transaction.kind).ok_or_else(|| {
+            tracing::error!("unknown transaction kind {}", transaction.kind);
+            WalletsError::InternalServerError
+        })?,
+        amount,
+        balance,
+        chapter_id: transaction.chapter_id,
+        created_at: transaction.created_at,
+    })
+}
+
+/// Unwrap the result of recording a transaction
+///
+/// when the same request was sent concurrently, the transaction recorded by the other request is returned
+pub async fn recover_duplicate(
+    conn: &mut AsyncPgConnection,
+    result: Result<LedgerTransaction, WalletsError>,
+    kind: TransactionKind,
+    user_id: Uuid,
+    idempotency_key: &str,
+) -> Result<LedgerTransaction, WalletsError> {
+    match result {
+        Err(err) if is_duplicate_idempotency_key(&err) => {
+            find_transaction(conn, kind, user_id, idempotency_key)
+                .await?
+                .ok_or(WalletsError::InternalServerError)
+        }
+        result => result,
+    }
+}
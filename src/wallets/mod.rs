@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderName, StatusCode},
+    response::IntoResponse,
+};
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+use uuid::Uuid;
+
+use crate::ErrorResponse;
+
+use self::payments::PaymentError;
+
+pub mod ledger;
+pub mod models;
+pub mod payments;
+pub mod routes;
+
+/// Stands for the money outside of the platform, top-ups come from it and payouts go to it
+pub const EXTERNAL_WALLET_ID: Uuid = Uuid::from_u128(1);
+
+/// Holds the coins of payout requests until they are paid or rejected
+pub const PENDING_PAYOUTS_WALLET_ID: Uuid = Uuid::from_u128(2);
+
+pub const MAX_TOP_UP_AMOUNT: i64 = 100_000;
+
+pub const MIN_PAYOUT_AMOUNT: i64 = 1_000;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WalletsError {
+    #[error("internal server error")]
+    InternalServerError,
+
+    #[error("bad request")]
+    BadRequest,
+
+    #[error("missing or invalid Idempotency-Key header")]
+    InvalidIdempotencyKey,
+
+    #[error("idempotency key was already used for another request")]
+    IdempotencyKeyReused,
+
+    #[error("insufficient funds")]
+    InsufficientFunds,
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("chapter not found")]
+    ChapterNotFound,
+
+    #[error("transaction not found")]
+    TransactionNotFound,
+
+    #[error("payout request not found")]
+    PayoutNotFound,
+
+    #[error("user not found")]
+    UserNotFound,
+
+    #[error(transparent)]
+    Payment(#[from] PaymentError),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+}
+
+impl IntoResponse for WalletsError {
+    fn into_response(self) -> axum::response::Response {
+        tracing::error!("{:#?}", self);
+
+        let status = match &self {
+            WalletsError::BadRequest | WalletsError::InvalidIdempotencyKey => {
+                StatusCode::BAD_REQUEST
+            }
+            WalletsError::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            WalletsError::InsufficientFunds => StatusCode::PAYMENT_REQUIRED,
+            WalletsError::Conflict(_) => StatusCode::CONFLICT,
+            WalletsError::ChapterNotFound
+            | WalletsError::TransactionNotFound
+            | WalletsError::PayoutNotFound
+            | WalletsError::UserNotFound => StatusCode::NOT_FOUND,
+            WalletsError::Payment(PaymentError::Disabled) => StatusCode::SERVICE_UNAVAILABLE,
+            WalletsError::Payment(_) => StatusCode::BAD_GATEWAY,
+            WalletsError::Diesel(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+            WalletsError::Diesel(DatabaseError(DatabaseErrorKind::CheckViolation, message))
+                if message.constraint_name() == Some("wallets_balance_check") =>
+            {
+                return (
+                    StatusCode::PAYMENT_REQUIRED,
+                    ErrorResponse {
+                        error: String::from("insufficient funds"),
+                        ..Default::default()
+                    },
+                )
+                    .into_response();
+            }
+            WalletsError::InternalServerError
+            | WalletsError::Diesel(_)
+            | WalletsError::PoolError(_) => {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+
+        (
+            status,
+            ErrorResponse {
+                error: self.to_string(),
+                ..Default::default()
+            },
+        )
+            .into_response()
+    }
+}
+
+/// Value of the `Idempotency-Key` header, retrying a request with the same key
+/// returns the result of the first one instead of moving coins again
+pub struct IdempotencyKey(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IdempotencyKey {
+    type Rejection = WalletsError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(IDEMPOTENCY_KEY)
+            .and_then(|key| key.to_str().ok())
+            .filter(|key| !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH)
+            .map(|key| IdempotencyKey(key.to_string()))
+            .ok_or(WalletsError::InvalidIdempotencyKey)
+    }
+}
@@ -0,0 +1,317 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::schema::{
+    ledger_entries, ledger_transactions, payout_requests, provider_refunds, wallets,
+};
+
+pub const STATEMENT_DEFAULT_LIMIT: i64 = 50;
+
+pub const STATEMENT_MAX_LIMIT: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum TransactionKind {
+    /// coins bought through the payment provider
+    TopUp,
+    /// coins spent to unlock a chapter
+    Unlock,
+    /// coins given to an author
+    Tip,
+    /// reversal of a top-up, unlock or tip
+    Refund,
+    /// coins set aside for a payout
+    PayoutRequest,
+    /// coins paid out to an author
+    Payout,
+    /// coins of a rejected payout returned to the author
+    PayoutRejection,
+    /// coins of a top-up refund returned after the payment provider declined it
+    RefundReversal,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::TopUp => "top_up",
+            TransactionKind::Unlock => "unlock",
+            TransactionKind::Tip => "tip",
+            TransactionKind::Refund => "refund",
+            TransactionKind::PayoutRequest => "payout_request",
+            TransactionKind::Payout => "payout",
+            TransactionKind::PayoutRejection => "payout_rejection",
+            TransactionKind::RefundReversal => "refund_reversal",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "top_up" => Some(TransactionKind::TopUp),
+            "unlock" => Some(TransactionKind::Unlock),
+            "tip" => Some(TransactionKind::Tip),
+            "refund" => Some(TransactionKind::Refund),
+            "payout_request" => Some(TransactionKind::PayoutRequest),
+            "payout" => Some(TransactionKind::Payout),
+            "payout_rejection" => Some(TransactionKind::PayoutRejection),
+            "refund_reversal" => Some(TransactionKind::RefundReversal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum PayoutStatus {
+    Pending,
+    /// the payment provider is paying it out
+    Processing,
+    Paid,
+    Rejected,
+}
+
+impl PayoutStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutStatus::Pending => "pending",
+            PayoutStatus::Processing => "processing",
+            PayoutStatus::Paid => "paid",
+            PayoutStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "pending" => Some(PayoutStatus::Pending),
+            "processing" => Some(PayoutStatus::Processing),
+            "paid" => Some(PayoutStatus::Paid),
+            "rejected" => Some(PayoutStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = wallets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Wallet {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub kind: String,
+    pub balance: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Debug, Clone)]
+#[diesel(table_name = ledger_transactions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LedgerTransaction {
+    pub id: Uuid,
+    pub kind: String,
+    pub idempotency_key: String,
+    pub user_id: Uuid,
+    pub chapter_id: Option<Uuid>,
+    pub reverses_id: Option<Uuid>,
+    pub provider_reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LedgerTransaction {
+    pub fn new(kind: TransactionKind, user_id: Uuid, idempotency_key: &str) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            kind: kind.as_str().to_string(),
+            // keys only have to be unique per user
+            idempotency_key: format!("{user_id}:{idempotency_key}"),
+            user_id,
+            chapter_id: None,
+            reverses_id: None,
+            provider_reference: None,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(LedgerTransaction, foreign_key = transaction_id))]
+#[diesel(belongs_to(Wallet))]
+#[diesel(table_name = ledger_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LedgerEntry {
+    pub id: i64,
+    pub transaction_id: Uuid,
+    pub wallet_id: Uuid,
+    pub amount: i64,
+    pub balance_after: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = ledger_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewLedgerEntry {
+    pub transaction_id: Uuid,
+    pub wallet_id: Uuid,
+    pub amount: i64,
+    pub balance_after: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = payout_requests)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PayoutRequest {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub amount: i64,
+    pub status: String,
+    pub request_transaction_id: Uuid,
+    pub resolution_transaction_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl PayoutRequest {
+    pub fn into_response(self) -> PayoutRequestResponse {
+        PayoutRequestResponse {
+            id: self.id,
+            user_id: self.user_id,
+            amount: self.amount,
+            status: PayoutStatus::parse(&self.status).unwrap_or(PayoutStatus::Pending),
+            created_at: self.created_at,
+            resolved_at: self.resolved_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderRefundStatus {
+    Pending,
+    Refunded,
+    Failed,
+}
+
+impl ProviderRefundStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderRefundStatus::Pending => "pending",
+            ProviderRefundStatus::Refunded => "refunded",
+            ProviderRefundStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "pending" => Some(ProviderRefundStatus::Pending),
+            "refunded" => Some(ProviderRefundStatus::Refunded),
+            "failed" => Some(ProviderRefundStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Money of a refunded top-up that has to be returned through the payment provider
+#[derive(Insertable, Queryable, Selectable, Debug)]
+#[diesel(table_name = provider_refunds)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProviderRefund {
+    pub transaction_id: Uuid,
+    pub status: String,
+    pub provider_reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateTopUp {
+    pub amount: i64,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateUnlock {
+    pub chapter_id: Uuid,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreateTip {
+    pub author_id: Uuid,
+    pub amount: i64,
+}
+
+#[derive(Deserialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct CreatePayoutRequest {
+    pub amount: i64,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub struct StatementParams {
+    /// only return entries older than this entry
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct WalletResponse {
+    pub balance: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct LedgerTransactionResponse {
+    pub id: Uuid,
+    pub kind: TransactionKind,
+    /// change of the caller's balance
+    pub amount: i64,
+    /// caller's balance right after the transaction
+    pub balance: i64,
+    pub chapter_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct StatementEntryResponse {
+    pub id: i64,
+    pub transaction_id: Uuid,
+    pub kind: TransactionKind,
+    pub amount: i64,
+    pub balance_after: i64,
+    pub chapter_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct PayoutRequestResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub amount: i64,
+    pub status: PayoutStatus,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema, Debug, TS)]
+#[ts(export)]
+pub struct EarningsResponse {
+    pub balance: i64,
+    /// coins received from chapter unlocks
+    pub unlocks_total: i64,
+    /// coins received from tips
+    pub tips_total: i64,
+    /// coins taken back by refunded unlocks and tips
+    pub refunds_total: i64,
+    pub pending_payouts_total: i64,
+    pub paid_out_total: i64,
+    pub payouts: Vec<PayoutRequestResponse>,
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PaymentError {
+    #[error("payment declined: {0}")]
+    Declined(String),
+
+    #[error("payment provider error: {0}")]
+    Provider(String),
+
+    #[error("payments are disabled")]
+    Disabled,
+}
+
+/// Payment provider picked in the config
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentProviderKind {
+    /// every payment is refused, coins can't be bought or paid out
+    #[default]
+    Disabled,
+    /// payments succeed without moving any money, only available in debug builds
+    Fake,
+}
+
+/// Moves real money in and out of the platform
+///
+/// `idempotency_key` is passed along so retrying a call never charges or pays twice,
+/// every method returns the provider's reference of the operation
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Charge the user for `amount` coins
+    async fn charge(
+        &self,
+        user_id: Uuid,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<String, PaymentError>;
+
+    /// Refund `amount` coins of the charge identified by `reference`
+    async fn refund(
+        &self,
+        reference: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<String, PaymentError>;
+
+    /// Pay `amount` coins out to the user
+    async fn payout(
+        &self,
+        user_id: Uuid,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<String, PaymentError>;
+}
+
+/// Provider used when none is configured, refuses every payment
+pub struct DisabledPaymentProvider;
+
+#[async_trait]
+impl PaymentProvider for DisabledPaymentProvider {
+    async fn charge(
+        &self,
+        _user_id: Uuid,
+        _amount: i64,
+        _idempotency_key: &str,
+    ) -> Result<String, PaymentError> {
+        Err(PaymentError::Disabled)
+    }
+
+    async fn refund(
+        &self,
+        _reference: &str,
+        _amount: i64,
+        _idempotency_key: &str,
+    ) -> Result<String, PaymentError> {
+        Err(PaymentError::Disabled)
+    }
+
+    async fn payout(
+        &self,
+        _user_id: Uuid,
+        _amount: i64,
+        _idempotency_key: &str,
+    ) -> Result<String, PaymentError> {
+        Err(PaymentError::Disabled)
+    }
+}
+
+/// Provider that accepts everything without moving any money, for development
+///
+/// anyone could buy coins for free with it, so release builds don't include it
+#[cfg(debug_assertions)]
+pub struct FakePaymentProvider;
+
+#[cfg(debug_assertions)]
+#[async_trait]
+impl PaymentProvider for FakePaymentProvider {
+    async fn charge(
+        &self,
+        user_id: Uuid,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<String, PaymentError> {
+        tracing::debug!("fake charge of {amount} for {user_id} ({idempotency_key})");
+        Ok(format!("fake_charge_{idempotency_key}"))
+    }
+
+    async fn refund(
+        &self,
+        reference: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<String, PaymentError> {
+        tracing::debug!("fake refund of {amount} for {reference} ({idempotency_key})");
+        Ok(format!("fake_refund_{idempotency_key}"))
+    }
+
+    async fn payout(
+        &self,
+        user_id: Uuid,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<String, PaymentError> {
+        tracing::debug!("fake payout of {amount} to {user_id} ({idempotency_key})");
+        Ok(format!("fake_payout_{idempotency_key}"))
+    }
+}
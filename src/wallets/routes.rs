@@ -0,0 +1,1097 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use diesel::{
+    dsl::sql,
+    result::{DatabaseErrorKind, Error::DatabaseError, Error::NotFound},
+    sql_types::BigInt,
+    ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthExtractor,
+    comics::chapters::models::{Chapter, ChapterEntitlement},
+    schema::{
+        chapter_entitlements, comic_chapters, ledger_entries, ledger_transactions, payout_requests,
+        provider_refunds,
+    },
+    users::models::UserRole,
+    AppState, InnerAppState,
+};
+
+use super::{
+    ledger::{
+        find_transaction, record_transaction, recover_duplicate, transaction_response, user_wallet,
+    },
+    models::{
+        CreatePayoutRequest, CreateTip, CreateTopUp, CreateUnlock, EarningsResponse,
+        LedgerTransaction, LedgerTransactionResponse, PayoutRequest, PayoutRequestResponse,
+        PayoutStatus, ProviderRefund, ProviderRefundStatus, StatementEntryResponse,
+        StatementParams, TransactionKind, WalletResponse, STATEMENT_DEFAULT_LIMIT,
+        STATEMENT_MAX_LIMIT,
+    },
+    payments::{PaymentError, PaymentProvider},
+    IdempotencyKey, WalletsError, EXTERNAL_WALLET_ID, MAX_TOP_UP_AMOUNT, MIN_PAYOUT_AMOUNT,
+    PENDING_PAYOUTS_WALLET_ID,
+};
+
+pub fn wallets_router() -> Router<AppState> {
+    Router::new()
+        .route("/me", get(get_wallet))
+        .route("/me/top-ups", post(create_top_up))
+        .route("/me/statement", get(get_statement))
+        .route("/me/earnings", get(get_earnings))
+        .route("/unlocks", post(unlock_chapter))
+        .route("/tips", post(tip_author))
+        .route(
+            "/transactions/:transaction_id/refund",
+            post(refund_transaction),
+        )
+        .route("/payouts", post(request_payout))
+        .route("/payouts/:payout_id/approve", post(approve_payout))
+        .route("/payouts/:payout_id/reject", post(reject_payout))
+}
+
+/// Returns the response of a transaction previously recorded with the same idempotency key
+async fn replay(
+    db: &mut diesel_async::AsyncPgConnection,
+    kind: TransactionKind,
+    user_id: Uuid,
+    idempotency_key: &str,
+) -> Result<Option<LedgerTransactionResponse>, WalletsError> {
+    let Some(transaction) = find_transaction(db, kind, user_id, idempotency_key).await? else {
+        return Ok(None);
+    };
+
+    let wallet = user_wallet(db, user_id).await?;
+
+    Ok(Some(
+        transaction_response(db, transaction, wallet.id).await?,
+    ))
+}
+
+/// Get current user's wallet
+#[utoipa::path(
+    get,
+    path = "/api/v1/wallets/me",
+    responses(
+        (status = 200, description = "Caller's wallet", body = WalletResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_wallet(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<WalletResponse>, WalletsError> {
+    let mut db = state.pool.get().await?;
+
+    let wallet = user_wallet(&mut db, auth.current_user.id).await?;
+
+    Ok(Json(WalletResponse {
+        balance: wallet.balance,
+    }))
+}
+
+/// Buy coins
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/me/top-ups",
+    request_body(content = CreateTopUp, content_type = "application/json"),
+    params(
+        ("Idempotency-Key" = String, Header, description = "Unique key of the request, retries must reuse it"),
+    ),
+    responses(
+        (status = 200, description = "Coins added to the caller's wallet", body = LedgerTransactionResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid amount or idempotency key", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::BAD_GATEWAY, description = "The payment failed", body = ErrorResponse),
+        (status = StatusCode::SERVICE_UNAVAILABLE, description = "Payments are disabled", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn create_top_up(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    IdempotencyKey(key): IdempotencyKey,
+    Json(payload): Json<CreateTopUp>,
+) -> Result<Json<LedgerTransactionResponse>, WalletsError> {
+    if payload.amount <= 0 || payload.amount > MAX_TOP_UP_AMOUNT {
+        return Err(WalletsError::BadRequest);
+    }
+
+    let user_id = auth.current_user.id;
+
+    let mut db = state.pool.get().await?;
+
+    if let Some(response) = replay(&mut db, TransactionKind::TopUp, user_id, &key).await? {
+        return Ok(Json(response));
+    }
+
+    // the provider dedupes charges by key, a retry after a failed commit isn't charged twice.
+    // keys come from clients, they're namespaced so users never share a charge
+    let reference = state
+        .payment_provider
+        .charge(user_id, payload.amount, &format!("topup:{user_id}:{key}"))
+        .await?;
+
+    let wallet = user_wallet(&mut db, user_id).await?;
+
+    let result = {
+        let mut transaction = LedgerTransaction::new(TransactionKind::TopUp, user_id, &key);
+        transaction.provider_reference = Some(reference);
+        let movements = [
+            (EXTERNAL_WALLET_ID, -payload.amount),
+            (wallet.id, payload.amount),
+        ];
+
+        db.transaction::<_, WalletsError, _>(|conn| {
+            async move { record_transaction(conn, transaction, &movements).await }.scope_boxed()
+        })
+        .await
+    };
+
+    let transaction =
+        recover_duplicate(&mut db, result, TransactionKind::TopUp, user_id, &key).await?;
+
+    Ok(Json(
+        transaction_response(&mut db, transaction, wallet.id).await?,
+    ))
+}
+
+/// Unlock a chapter with coins
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/unlocks",
+    request_body(content = CreateUnlock, content_type = "application/json"),
+    params(
+        ("Idempotency-Key" = String, Header, description = "Unique key of the request, retries must reuse it"),
+    ),
+    responses(
+        (status = 200, description = "Chapter unlocked", body = LedgerTransactionResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid idempotency key", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::PAYMENT_REQUIRED, description = "Not enough coins", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified chapter was not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Chapter isn't for sale or is already unlocked", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn unlock_chapter(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    IdempotencyKey(key): IdempotencyKey,
+    Json(payload): Json<CreateUnlock>,
+) -> Result<Json<LedgerTransactionResponse>, WalletsError> {
+    let user_id = auth.current_user.id;
+
+    let mut db = state.pool.get().await?;
+
+    if let Some(response) = replay(&mut db, TransactionKind::Unlock, user_id, &key).await? {
+        return Ok(Json(response));
+    }
+
+    let chapter = comic_chapters::table
+        .find(payload.chapter_id)
+        .select(Chapter::as_select())
+        .first::<Chapter>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => WalletsError::ChapterNotFound,
+            e => e.into(),
+        })?;
+
+    if !chapter.is_locked(Utc::now()) || chapter.price <= 0 {
+        return Err(WalletsError::Conflict(String::from(
+            "chapter isn't for sale",
+        )));
+    }
+
+    if chapter.user_id == user_id {
+        return Err(WalletsError::Conflict(String::from(
+            "can't unlock your own chapter",
+        )));
+    }
+
+    let buyer_wallet = user_wallet(&mut db, user_id).await?;
+    let author_wallet = user_wallet(&mut db, chapter.user_id).await?;
+
+    let result = {
+        let mut transaction = LedgerTransaction::new(TransactionKind::Unlock, user_id, &key);
+        transaction.chapter_id = Some(chapter.id);
+        let price = i64::from(chapter.price);
+        let movements = [(buyer_wallet.id, -price), (author_wallet.id, price)];
+
+        db.transaction::<_, WalletsError, _>(|conn| {
+            async move {
+                diesel::insert_into(chapter_entitlements::table)
+                    .values(&ChapterEntitlement {
+                        user_id,
+                        chapter_id: chapter.id,
+                        created_at: Utc::now(),
+                    })
+                    .execute(conn)
+                    .await
+                    .map_err(|e| match e {
+                        DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                            WalletsError::Conflict(String::from("chapter is already unlocked"))
+                        }
+                        e => e.into(),
+                    })?;
+
+                record_transaction(conn, transaction, &movements).await
+            }
+            .scope_boxed()
+        })
+        .await
+    };
+
+    let transaction =
+        recover_duplicate(&mut db, result, TransactionKind::Unlock, user_id, &key).await?;
+
+    Ok(Json(
+        transaction_response(&mut db, transaction, buyer_wallet.id).await?,
+    ))
+}
+
+/// Tip an author
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/tips",
+    request_body(content = CreateTip, content_type = "application/json"),
+    params(
+        ("Idempotency-Key" = String, Header, description = "Unique key of the request, retries must reuse it"),
+    ),
+    responses(
+        (status = 200, description = "Coins sent to the author", body = LedgerTransactionResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid amount or idempotency key", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::PAYMENT_REQUIRED, description = "Not enough coins", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified author was not found", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn tip_author(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    IdempotencyKey(key): IdempotencyKey,
+    Json(payload): Json<CreateTip>,
+) -> Result<Json<LedgerTransactionResponse>, WalletsError> {
+    let user_id = auth.current_user.id;
+
+    if payload.amount <= 0 || payload.author_id == user_id {
+        return Err(WalletsError::BadRequest);
+    }
+
+    let mut db = state.pool.get().await?;
+
+    if let Some(response) = replay(&mut db, TransactionKind::Tip, user_id, &key).await? {
+        return Ok(Json(response));
+    }
+
+    let sender_wallet = user_wallet(&mut db, user_id).await?;
+    let author_wallet = user_wallet(&mut db, payload.author_id).await?;
+
+    let result = {
+        let transaction = LedgerTransaction::new(TransactionKind::Tip, user_id, &key);
+        let movements = [
+            (sender_wallet.id, -payload.amount),
+            (author_wallet.id, payload.amount),
+        ];
+
+        db.transaction::<_, WalletsError, _>(|conn| {
+            async move { record_transaction(conn, transaction, &movements).await }.scope_boxed()
+        })
+        .await
+    };
+
+    let transaction =
+        recover_duplicate(&mut db, result, TransactionKind::Tip, user_id, &key).await?;
+
+    Ok(Json(
+        transaction_response(&mut db, transaction, sender_wallet.id).await?,
+    ))
+}
+
+/// Refund a top-up, unlock or tip
+///
+/// every coin movement of the transaction is reversed, refunding an unlock also revokes
+/// the access to the chapter and refunding a top-up gives the money back through the payment provider
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/transactions/:transaction_id/refund",
+    params(
+        ("Idempotency-Key" = String, Header, description = "Unique key of the request, retries must reuse it"),
+    ),
+    responses(
+        (status = 200, description = "Transaction refunded, described from the point of view of the user who made it", body = LedgerTransactionResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid idempotency key", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::PAYMENT_REQUIRED, description = "A wallet doesn't have enough coins left", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified transaction was not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Transaction can't be refunded or was already refunded", body = ErrorResponse),
+        (status = StatusCode::BAD_GATEWAY, description = "The payment provider refused the refund", body = ErrorResponse),
+        (status = StatusCode::SERVICE_UNAVAILABLE, description = "Payments are disabled", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn refund_transaction(
+    auth: AuthExtractor<{ UserRole::Staff as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    IdempotencyKey(key): IdempotencyKey,
+    Path(transaction_id): Path<Uuid>,
+) -> Result<Json<LedgerTransactionResponse>, WalletsError> {
+    let staff_id = auth.current_user.id;
+
+    let mut db = state.pool.get().await?;
+
+    let original = ledger_transactions::table
+        .find(transaction_id)
+        .select(LedgerTransaction::as_select())
+        .first::<LedgerTransaction>(&mut db)
+        .await
+        .map_err(|e| match e {
+            NotFound => WalletsError::TransactionNotFound,
+            e => e.into(),
+        })?;
+
+    let original_wallet_id = user_wallet(&mut db, original.user_id).await?.id;
+
+    if let Some(refund) = find_transaction(&mut db, TransactionKind::Refund, staff_id, &key).await?
+    {
+        settle_provider_refund(state.payment_provider.as_ref(), &mut db, &original, &refund)
+            .await?;
+
+        return Ok(Json(
+            transaction_response(&mut db, refund, original_wallet_id).await?,
+        ));
+    }
+
+    let kind = TransactionKind::parse(&original.kind);
+
+    if !matches!(
+        kind,
+        Some(TransactionKind::TopUp | TransactionKind::Unlock | TransactionKind::Tip)
+    ) {
+        return Err(WalletsError::Conflict(String::from(
+            "only top-ups, unlocks and tips can be refunded",
+        )));
+    }
+
+    if kind == Some(TransactionKind::TopUp) && original.provider_reference.is_none() {
+        tracing::error!("top-up {} has no provider reference", original.id);
+        return Err(WalletsError::InternalServerError);
+    }
+
+    let movements = ledger_entries::table
+        .filter(ledger_entries::transaction_id.eq(original.id))
+        .select((ledger_entries::wallet_id, ledger_entries::amount))
+        .load::<(Uuid, i64)>(&mut db)
+        .await?
+        .into_iter()
+        .map(|(wallet_id, amount)| (wallet_id, -amount))
+        .collect::<Vec<(Uuid, i64)>>();
+
+    let result = {
+        let key = key.clone();
+        let original = &original;
+
+        db.transaction::<_, WalletsError, _>(|conn| {
+            async move {
+                let mut refund = LedgerTransaction::new(TransactionKind::Refund, staff_id, &key);
+                refund.reverses_id = Some(original.id);
+                refund.chapter_id = original.chapter_id;
+
+                if let (Some(TransactionKind::Unlock), Some(chapter_id)) =
+                    (kind, original.chapter_id)
+                {
+                    diesel::delete(
+                        chapter_entitlements::table.find((original.user_id, chapter_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                }
+
+                // the user's wallet can't go below zero, coins already spent fail the refund
+                let refund =
+                    record_transaction(conn, refund, &movements)
+                        .await
+                        .map_err(|e| match e {
+                            WalletsError::Diesel(DatabaseError(
+                                DatabaseErrorKind::UniqueViolation,
+                                ref info,
+                            )) if info.constraint_name()
+                                == Some("ledger_transactions_reverses_id_key") =>
+                            {
+                                WalletsError::Conflict(String::from(
+                                    "transaction was already refunded",
+                                ))
+                            }
+                            e => e,
+                        })?;
+
+                // the money is returned through the payment provider once the coins are taken back
+                if kind == Some(TransactionKind::TopUp) {
+                    diesel::insert_into(provider_refunds::table)
+                        .values(&ProviderRefund {
+                            transaction_id: refund.id,
+                            status: ProviderRefundStatus::Pending.as_str().to_string(),
+                            provider_reference: None,
+                            created_at: refund.created_at,
+                            resolved_at: None,
+                        })
+                        .execute(conn)
+                        .await?;
+                }
+
+                Ok(refund)
+            }
+            .scope_boxed()
+        })
+        .await
+    };
+
+    let refund =
+        recover_duplicate(&mut db, result, TransactionKind::Refund, staff_id, &key).await?;
+
+    settle_provider_refund(state.payment_provider.as_ref(), &mut db, &original, &refund).await?;
+
+    Ok(Json(
+        transaction_response(&mut db, refund, original_wallet_id).await?,
+    ))
+}
+
+/// Return the money of a refunded top-up through the payment provider
+///
+/// the coins were taken back when the refund was recorded, the provider is called outside of any
+/// database transaction and dedupes refunds by the refund's idempotency key, so retrying the
+/// refund request with the same key retries a failed call.
+/// When the provider declines, the coins are given back and the refund is marked as failed
+async fn settle_provider_refund(
+    payment_provider: &dyn PaymentProvider,
+    db: &mut AsyncPgConnection,
+    original: &LedgerTransaction,
+    refund: &LedgerTransaction,
+) -> Result<(), WalletsError> {
+    // unlocks and tips don't involve the payment provider
+    let Some(provider_refund) = provider_refunds::table
+        .find(refund.id)
+        .select(ProviderRefund::as_select())
+        .first::<ProviderRefund>(db)
+        .await
+        .optional()?
+    else {
+        return Ok(());
+    };
+
+    match ProviderRefundStatus::parse(&provider_refund.status) {
+        Some(ProviderRefundStatus::Pending) => {}
+        Some(ProviderRefundStatus::Refunded) => return Ok(()),
+        Some(ProviderRefundStatus::Failed) => {
+            return Err(WalletsError::Conflict(String::from(
+                "the payment provider declined the refund",
+            )))
+        }
+        None => {
+            tracing::error!("unknown provider refund status {}", provider_refund.status);
+            return Err(WalletsError::InternalServerError);
+        }
+    }
+
+    let reference = original
+        .provider_reference
+        .as_deref()
+        .ok_or(WalletsError::InternalServerError)?;
+
+    let amount = ledger_entries::table
+        .filter(ledger_entries::transaction_id.eq(refund.id))
+        .filter(ledger_entries::wallet_id.eq(EXTERNAL_WALLET_ID))
+        .select(ledger_entries::amount)
+        .first::<i64>(db)
+        .await?;
+
+    match payment_provider
+        .refund(reference, amount, &refund.idempotency_key)
+        .await
+    {
+        Ok(provider_reference) => {
+            diesel::update(provider_refunds::table.find(refund.id))
+                .filter(provider_refunds::status.eq(ProviderRefundStatus::Pending.as_str()))
+                .set((
+                    provider_refunds::status.eq(ProviderRefundStatus::Refunded.as_str()),
+                    provider_refunds::provider_reference.eq(provider_reference),
+                    provider_refunds::resolved_at.eq(Utc::now()),
+                ))
+                .execute(db)
+                .await?;
+
+            Ok(())
+        }
+        Err(PaymentError::Declined(reason)) => {
+            db.transaction::<_, WalletsError, _>(|conn| {
+                async move {
+                    // concurrent retries of the same refund wait for each other
+                    let status = provider_refunds::table
+                        .find(refund.id)
+                        .select(provider_refunds::status)
+                        .for_update()
+                        .first::<String>(conn)
+                        .await?;
+
+                    if status != ProviderRefundStatus::Pending.as_str() {
+                        return Ok(());
+                    }
+
+                    let movements = ledger_entries::table
+                        .filter(ledger_entries::transaction_id.eq(refund.id))
+                        .select((ledger_entries::wallet_id, ledger_entries::amount))
+                        .load::<(Uuid, i64)>(conn)
+                        .await?
+                        .into_iter()
+                        .map(|(wallet_id, amount)| (wallet_id, -amount))
+                        .collect::<Vec<(Uuid, i64)>>();
+
+                    let mut reversal = LedgerTransaction::new(
+                        TransactionKind::RefundReversal,
+                        refund.user_id,
+                        &format!("reversal:{}", refund.id),
+                    );
+                    reversal.reverses_id = Some(refund.id);
+
+                    let reversal = record_transaction(conn, reversal, &movements).await?;
+
+                    diesel::update(provider_refunds::table.find(refund.id))
+                        .set((
+                            provider_refunds::status.eq(ProviderRefundStatus::Failed.as_str()),
+                            provider_refunds::resolved_at.eq(reversal.created_at),
+                        ))
+                        .execute(conn)
+                        .await?;
+
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+            Err(PaymentError::Declined(reason).into())
+        }
+        // the refund stays pending, retrying the request calls the provider again
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Request a payout of the current user's coins
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/payouts",
+    request_body(content = CreatePayoutRequest, content_type = "application/json"),
+    params(
+        ("Idempotency-Key" = String, Header, description = "Unique key of the request, retries must reuse it"),
+    ),
+    responses(
+        (status = 200, description = "Payout requested, the coins are held until it's resolved", body = PayoutRequestResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid amount or idempotency key", body = ErrorResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::PAYMENT_REQUIRED, description = "Not enough coins", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn request_payout(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    IdempotencyKey(key): IdempotencyKey,
+    Json(payload): Json<CreatePayoutRequest>,
+) -> Result<Json<PayoutRequestResponse>, WalletsError> {
+    if payload.amount < MIN_PAYOUT_AMOUNT {
+        return Err(WalletsError::BadRequest);
+    }
+
+    let user_id = auth.current_user.id;
+
+    let mut db = state.pool.get().await?;
+
+    let transaction = match find_transaction(&mut db, TransactionKind::PayoutRequest, user_id, &key)
+        .await?
+    {
+        Some(transaction) => transaction,
+        None => {
+            let wallet = user_wallet(&mut db, user_id).await?;
+
+            let transaction = LedgerTransaction::new(TransactionKind::PayoutRequest, user_id, &key);
+            let movements = [
+                (wallet.id, -payload.amount),
+                (PENDING_PAYOUTS_WALLET_ID, payload.amount),
+            ];
+
+            let result = db
+                .transaction::<_, WalletsError, _>(|conn| {
+                    async move {
+                        let transaction = record_transaction(conn, transaction, &movements).await?;
+
+                        diesel::insert_into(payout_requests::table)
+                            .values(&PayoutRequest {
+                                id: Uuid::now_v7(),
+                                user_id,
+                                amount: payload.amount,
+                                status: PayoutStatus::Pending.as_str().to_string(),
+                                request_transaction_id: transaction.id,
+                                resolution_transaction_id: None,
+                                created_at: transaction.created_at,
+                                resolved_at: None,
+                            })
+                            .execute(conn)
+                            .await?;
+
+                        Ok(transaction)
+                    }
+                    .scope_boxed()
+                })
+                .await;
+
+            recover_duplicate(
+                &mut db,
+                result,
+                TransactionKind::PayoutRequest,
+                user_id,
+                &key,
+            )
+            .await?
+        }
+    };
+
+    let payout = payout_requests::table
+        .filter(payout_requests::request_transaction_id.eq(transaction.id))
+        .select(PayoutRequest::as_select())
+        .first::<PayoutRequest>(&mut db)
+        .await?;
+
+    Ok(Json(payout.into_response()))
+}
+
+/// Lock the payout request until the end of the database transaction
+async fn lock_payout(
+    conn: &mut AsyncPgConnection,
+    payout_id: Uuid,
+) -> Result<PayoutRequest, WalletsError> {
+    payout_requests::table
+        .find(payout_id)
+        .select(PayoutRequest::as_select())
+        .for_update()
+        .first::<PayoutRequest>(conn)
+        .await
+        .map_err(|e| match e {
+            NotFound => WalletsError::PayoutNotFound,
+            e => e.into(),
+        })
+}
+
+/// Record the transaction resolving the payout request and mark it with its final status
+async fn settle_payout(
+    conn: &mut AsyncPgConnection,
+    payout_id: Uuid,
+    status: PayoutStatus,
+    transaction: LedgerTransaction,
+    movements: &[(Uuid, i64)],
+) -> Result<PayoutRequest, WalletsError> {
+    let transaction = record_transaction(conn, transaction, movements).await?;
+
+    Ok(diesel::update(payout_requests::table.find(payout_id))
+        .set((
+            payout_requests::status.eq(status.as_str()),
+            payout_requests::resolution_transaction_id.eq(transaction.id),
+            payout_requests::resolved_at.eq(transaction.created_at),
+        ))
+        .returning(PayoutRequest::as_returning())
+        .get_result::<PayoutRequest>(conn)
+        .await?)
+}
+
+/// Pay out a payout request through the payment provider
+///
+/// the request is marked as processing and committed before calling the provider, which happens
+/// outside of any database transaction and dedupes payouts by the request's id.
+/// A failed call leaves the request processing so approving it again retries the payout,
+/// a declined one puts it back to pending
+async fn pay_out(
+    state: Arc<InnerAppState>,
+    payout_id: Uuid,
+) -> Result<PayoutRequest, WalletsError> {
+    let mut db = state.pool.get().await?;
+
+    let payout = db
+        .transaction::<_, WalletsError, _>(|conn| {
+            async move {
+                // concurrent resolutions of the same payout wait for each other
+                let payout = lock_payout(conn, payout_id).await?;
+
+                match PayoutStatus::parse(&payout.status) {
+                    Some(PayoutStatus::Pending) => {}
+                    // a previous approval failed to reach the provider
+                    Some(PayoutStatus::Processing) => return Ok(payout),
+                    _ => {
+                        return Err(WalletsError::Conflict(String::from(
+                            "payout request was already resolved",
+                        )))
+                    }
+                }
+
+                Ok(diesel::update(payout_requests::table.find(payout.id))
+                    .set(payout_requests::status.eq(PayoutStatus::Processing.as_str()))
+                    .returning(PayoutRequest::as_returning())
+                    .get_result::<PayoutRequest>(conn)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    let idempotency_key = payout.id.to_string();
+
+    let reference = match state
+        .payment_provider
+        .payout(payout.user_id, payout.amount, &idempotency_key)
+        .await
+    {
+        Ok(reference) => reference,
+        Err(err @ PaymentError::Declined(_)) => {
+            diesel::update(payout_requests::table.find(payout.id))
+                .filter(payout_requests::status.eq(PayoutStatus::Processing.as_str()))
+                .set(payout_requests::status.eq(PayoutStatus::Pending.as_str()))
+                .execute(&mut db)
+                .await?;
+
+            return Err(err.into());
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    db.transaction::<_, WalletsError, _>(|conn| {
+        async move {
+            let payout = lock_payout(conn, payout_id).await?;
+
+            // a concurrent approval already recorded the payout
+            if payout.status != PayoutStatus::Processing.as_str() {
+                return Ok(payout);
+            }
+
+            let mut transaction =
+                LedgerTransaction::new(TransactionKind::Payout, payout.user_id, &idempotency_key);
+            transaction.provider_reference = Some(reference);
+
+            settle_payout(
+                conn,
+                payout.id,
+                PayoutStatus::Paid,
+                transaction,
+                &[
+                    (PENDING_PAYOUTS_WALLET_ID, -payout.amount),
+                    (EXTERNAL_WALLET_ID, payout.amount),
+                ],
+            )
+            .await
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Approve a payout request and pay it out
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/payouts/:payout_id/approve",
+    responses(
+        (status = 200, description = "Payout paid", body = PayoutRequestResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified payout request was not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Payout request was already resolved", body = ErrorResponse),
+        (status = StatusCode::BAD_GATEWAY, description = "The payment provider refused the payout", body = ErrorResponse),
+        (status = StatusCode::SERVICE_UNAVAILABLE, description = "Payments are disabled", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn approve_payout(
+    _auth: AuthExtractor<{ UserRole::Admin as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(payout_id): Path<Uuid>,
+) -> Result<Json<PayoutRequestResponse>, WalletsError> {
+    Ok(Json(pay_out(state, payout_id).await?.into_response()))
+}
+
+/// Reject a payout request and return the coins to the user
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallets/payouts/:payout_id/reject",
+    responses(
+        (status = 200, description = "Payout rejected", body = PayoutRequestResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::NOT_FOUND, description = "Specified payout request was not found", body = ErrorResponse),
+        (status = StatusCode::CONFLICT, description = "Payout request was already resolved or is being paid out", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn reject_payout(
+    _auth: AuthExtractor<{ UserRole::Admin as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Path(payout_id): Path<Uuid>,
+) -> Result<Json<PayoutRequestResponse>, WalletsError> {
+    let mut db = state.pool.get().await?;
+
+    let payout = db
+        .transaction::<_, WalletsError, _>(|conn| {
+            async move {
+                let payout = lock_payout(conn, payout_id).await?;
+
+                match PayoutStatus::parse(&payout.status) {
+                    Some(PayoutStatus::Pending) => {}
+                    Some(PayoutStatus::Processing) => {
+                        return Err(WalletsError::Conflict(String::from(
+                            "payout request is being paid out",
+                        )))
+                    }
+                    _ => {
+                        return Err(WalletsError::Conflict(String::from(
+                            "payout request was already resolved",
+                        )))
+                    }
+                }
+
+                let wallet = user_wallet(conn, payout.user_id).await?;
+
+                settle_payout(
+                    conn,
+                    payout.id,
+                    PayoutStatus::Rejected,
+                    LedgerTransaction::new(
+                        TransactionKind::PayoutRejection,
+                        payout.user_id,
+                        &payout.id.to_string(),
+                    ),
+                    &[
+                        (PENDING_PAYOUTS_WALLET_ID, -payout.amount),
+                        (wallet.id, payout.amount),
+                    ],
+                )
+                .await
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(Json(payout.into_response()))
+}
+
+/// Get current user's statement
+#[utoipa::path(
+    get,
+    path = "/api/v1/wallets/me/statement",
+    params(StatementParams),
+    responses(
+        (status = 200, description = "Caller's wallet entries, newest first", body = [StatementEntryResponse]),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_statement(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+    Query(params): Query<StatementParams>,
+) -> Result<Json<Vec<StatementEntryResponse>>, WalletsError> {
+    let mut db = state.pool.get().await?;
+
+    let wallet = user_wallet(&mut db, auth.current_user.id).await?;
+
+    let mut query = ledger_entries::table
+        .inner_join(ledger_transactions::table)
+        .filter(ledger_entries::wallet_id.eq(wallet.id))
+        .into_boxed();
+
+    if let Some(before) = params.before {
+        query = query.filter(ledger_entries::id.lt(before));
+    }
+
+    let entries = query
+        .order(ledger_entries::id.desc())
+        .limit(
+            params
+                .limit
+                .unwrap_or(STATEMENT_DEFAULT_LIMIT)
+                .clamp(1, STATEMENT_MAX_LIMIT),
+        )
+        .select((
+            ledger_entries::id,
+            ledger_entries::transaction_id,
+            ledger_transactions::kind,
+            ledger_entries::amount,
+            ledger_entries::balance_after,
+            ledger_transactions::chapter_id,
+            ledger_entries::created_at,
+        ))
+        .load::<(
+            i64,
+            Uuid,
+            String,
+            i64,
+            i64,
+            Option<Uuid>,
+            chrono::DateTime<Utc>,
+        )>(&mut db)
+        .await?;
+
+    Ok(Json(
+        entries
+            .into_iter()
+            .filter_map(
+                |(id, transaction_id, kind, amount, balance_after, chapter_id, created_at)| {
+                    Some(StatementEntryResponse {
+                        id,
+                        transaction_id,
+                        kind: TransactionKind::parse(&kind)?,
+                        amount,
+                        balance_after,
+                        chapter_id,
+                        created_at,
+                    })
+                },
+            )
+            .collect(),
+    ))
+}
+
+/// Get current user's earnings as an author
+#[utoipa::path(
+    get,
+    path = "/api/v1/wallets/me/earnings",
+    responses(
+        (status = 200, description = "Caller's earnings and payout requests", body = EarningsResponse),
+        (status = StatusCode::UNAUTHORIZED, description = "Caller unauthorized", body = ErrorResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Something went wrong", body = ErrorResponse),
+    ),
+    security(
+        ("auth" = [])
+    ),
+    tag = "Wallets API"
+)]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_earnings(
+    auth: AuthExtractor<{ UserRole::User as u32 }>,
+    State(state): State<Arc<InnerAppState>>,
+) -> Result<Json<EarningsResponse>, WalletsError> {
+    let mut db = state.pool.get().await?;
+
+    let wallet = user_wallet(&mut db, auth.current_user.id).await?;
+
+    // (kind, coins received, coins taken) of every kind of transaction on the wallet
+    let totals = ledger_entries::table
+        .inner_join(ledger_transactions::table)
+        .filter(ledger_entries::wallet_id.eq(wallet.id))
+        .group_by(ledger_transactions::kind)
+        .select((
+            ledger_transactions::kind,
+            sql::<BigInt>(
+                "COALESCE(SUM(CASE WHEN ledger_entries.amount > 0 THEN ledger_entries.amount ELSE 0 END), 0)::BIGINT",
+            ),
+            sql::<BigInt>(
+                "COALESCE(SUM(CASE WHEN ledger_entries.amount < 0 THEN -ledger_entries.amount ELSE 0 END), 0)::BIGINT",
+            ),
+        ))
+        .load::<(String, i64, i64)>(&mut db)
+        .await?;
+
+    let received = |kind: TransactionKind| {
+        totals
+            .iter()
+            .find(|(k, _, _)| k == kind.as_str())
+            .map(|(_, received, _)| *received)
+            .unwrap_or_default()
+    };
+
+    let refunds_total = totals
+        .iter()
+        .find(|(k, _, _)| k == TransactionKind::Refund.as_str())
+        .map(|(_, _, taken)| *taken)
+        .unwrap_or_default();
+
+    let payouts = payout_requests::table
+        .filter(payout_requests::user_id.eq(auth.current_user.id))
+        .order(payout_requests::created_at.desc())
+        .select(PayoutRequest::as_select())
+        .load::<PayoutRequest>(&mut db)
+        .await?
+        .into_iter()
+        .map(|payout| payout.into_response())
+        .collect::<Vec<PayoutRequestResponse>>();
+
+    let payouts_total = |status: PayoutStatus| {
+        payouts
+            .iter()
+            .filter(|payout| payout.status == status)
+            .map(|payout| payout.amount)
+            .sum::<i64>()
+    };
+
+    Ok(Json(EarningsResponse {
+        balance: wallet.balance,
+        unlocks_total: received(TransactionKind::Unlock),
+        tips_total: received(TransactionKind::Tip),
+        refunds_total,
+        pending_payouts_total: payouts_total(PayoutStatus::Pending)
+            + payouts_total(PayoutStatus::Processing),
+        paid_out_total: payouts_total(PayoutStatus::Paid),
+        payouts,
+    }))
+}